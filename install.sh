@@ -136,6 +136,10 @@ install_application() {
 create_service() {
     print_message "$BLUE" "🔧 Systemd servisi yaradılır..."
     
+    # Generate an API token for scripts; only its SHA-256 digest is stored
+    API_TOKEN=$(head -c 32 /dev/urandom | od -An -tx1 | tr -d ' \n')
+    API_TOKEN_HASH=$(printf '%s' "$API_TOKEN" | sha256sum | cut -d' ' -f1)
+    
     cat > "/etc/systemd/system/${SERVICE_NAME}.service" <<EOF
 [Unit]
 Description=Ubuntu Resource Monitor
@@ -146,6 +150,7 @@ Type=simple
 User=root
 WorkingDirectory=$INSTALL_DIR
 ExecStart=$INSTALL_DIR/$BINARY_NAME
+ExecReload=/bin/kill -HUP $MAINPID
 Restart=always
 RestartSec=10
 
@@ -154,6 +159,7 @@ Environment="SERVER_BIND_ADDRESS=$BIND_ADDRESS:$PORT"
 Environment="NGINX_SITES_AVAILABLE=$NGINX_AVAILABLE"
 Environment="NGINX_SITES_ENABLED=$NGINX_ENABLED"
 Environment="DOCKER_SOCKET_PATH=$DOCKER_SOCKET"
+Environment="AUTH_API_TOKENS=admin:admin:$API_TOKEN_HASH"
 Environment="RUST_LOG=info"
 
 # Security settings
@@ -201,6 +207,11 @@ print_success() {
     print_message "$BLUE" "🔄 Nginx Manager: http://$BIND_ADDRESS:$PORT/nginx"
     print_message "$BLUE" "🐳 Docker Manager: http://$BIND_ADDRESS:$PORT/docker"
     echo ""
+    print_message "$YELLOW" "🔑 API token (yalnız indi göstərilir, saxlayın):"
+    echo "  $API_TOKEN"
+    echo "  İstifadə: curl -H \"Authorization: Bearer <token>\" http://$BIND_ADDRESS:$PORT/api/system"
+    echo "  Dashboard girişi üçün servisə AUTH_USERS əlavə edin: istifadəçi:rol:\$(htpasswd -nbB x <parol> | cut -d: -f2)"
+    echo ""
     print_message "$YELLOW" "Faydalı əmrlər:"
     echo "  Servisi dayandır:    sudo systemctl stop $SERVICE_NAME"
     echo "  Servisi başlat:      sudo systemctl start $SERVICE_NAME"
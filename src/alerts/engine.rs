@@ -0,0 +1,422 @@
+// Alert rule storage and firing/resolved state tracking
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, RwLock};
+
+use log::info;
+
+use crate::alerts::models::{Alert, AlertRule, AlertState, Operator, Severity, Threshold};
+use crate::error::AppError;
+
+/// Number of resolved alerts kept for listing
+const RESOLVED_HISTORY: usize = 100;
+
+/// One observed value, optionally tied to a device label (mount point, container name)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Series {
+    pub metric: String,
+    pub label: Option<String>,
+    pub value: f64,
+}
+
+type AlertKey = (String, Option<String>);
+
+/// Holds the rules and evaluates them against observed series
+pub struct AlertEngine {
+    rules: RwLock<Vec<AlertRule>>,
+    active: Mutex<HashMap<AlertKey, Alert>>,
+    resolved: Mutex<VecDeque<Alert>>,
+    rules_path: Option<PathBuf>,
+}
+
+impl AlertEngine {
+    /// Create an engine with the given rules and no persistence
+    pub fn new(rules: Vec<AlertRule>) -> Self {
+        AlertEngine {
+            rules: RwLock::new(rules),
+            active: Mutex::new(HashMap::new()),
+            resolved: Mutex::new(VecDeque::new()),
+            rules_path: None,
+        }
+    }
+
+    /// Load rules from a JSON file, falling back to [`default_rules`] when it does not exist
+    ///
+    /// Rule changes made through the API are written back to the same file.
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let rules = if path.exists() {
+            let content = fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read alert rules {}: {}", path.display(), e))?;
+            let rules: Vec<AlertRule> = serde_json::from_str(&content)
+                .map_err(|e| format!("Invalid alert rules {}: {}", path.display(), e))?;
+            for rule in &rules {
+                rule.validate()
+                    .map_err(|e| format!("Invalid alert rule '{}': {}", rule.id, e))?;
+            }
+            info!("Loaded {} alert rules from {}", rules.len(), path.display());
+            rules
+        } else {
+            info!("No alert rules at {}, using defaults", path.display());
+            default_rules()
+        };
+
+        let mut engine = Self::new(rules);
+        engine.rules_path = Some(path.to_path_buf());
+        Ok(engine)
+    }
+
+    pub fn rules(&self) -> Vec<AlertRule> {
+        self.rules.read().unwrap().clone()
+    }
+
+    pub fn add_rule(&self, rule: AlertRule) -> Result<(), AppError> {
+        rule.validate().map_err(AppError::ValidationError)?;
+        self.modify_rules(|rules| {
+            if rules.iter().any(|r| r.id == rule.id) {
+                return Err(AppError::ValidationError(format!("Rule '{}' already exists", rule.id)));
+            }
+            rules.push(rule);
+            Ok(())
+        })
+    }
+
+    pub fn update_rule(&self, id: &str, rule: AlertRule) -> Result<(), AppError> {
+        if rule.id != id {
+            return Err(AppError::ValidationError(
+                "Rule id in the URL and body do not match".to_string(),
+            ));
+        }
+        rule.validate().map_err(AppError::ValidationError)?;
+        self.modify_rules(|rules| {
+            let existing = rules
+                .iter_mut()
+                .find(|r| r.id == id)
+                .ok_or_else(|| AppError::NotFound(format!("Rule '{}' not found", id)))?;
+            *existing = rule;
+            Ok(())
+        })
+    }
+
+    pub fn delete_rule(&self, id: &str) -> Result<(), AppError> {
+        self.modify_rules(|rules| {
+            let before = rules.len();
+            rules.retain(|r| r.id != id);
+            if rules.len() == before {
+                return Err(AppError::NotFound(format!("Rule '{}' not found", id)));
+            }
+            Ok(())
+        })
+    }
+
+    /// Apply a change to a copy of the rules, persist it, then swap it in
+    fn modify_rules(&self, change: impl FnOnce(&mut Vec<AlertRule>) -> Result<(), AppError>) -> Result<(), AppError> {
+        let mut rules = self.rules.write().unwrap();
+        let mut updated = rules.clone();
+        change(&mut updated)?;
+
+        if let Some(path) = &self.rules_path {
+            let content = serde_json::to_string_pretty(&updated)
+                .map_err(|e| AppError::SystemError(format!("Failed to serialize alert rules: {}", e)))?;
+            if let Some(parent) = path.parent() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    AppError::SystemError(format!("Failed to create {}: {}", parent.display(), e))
+                })?;
+            }
+            fs::write(path, content).map_err(|e| {
+                AppError::SystemError(format!("Failed to save alert rules to {}: {}", path.display(), e))
+            })?;
+        }
+
+        *rules = updated;
+        Ok(())
+    }
+
+    /// Pending and firing alerts
+    pub fn active_alerts(&self) -> Vec<Alert> {
+        let mut alerts: Vec<Alert> = self.active.lock().unwrap().values().cloned().collect();
+        alerts.sort_by(|a, b| a.since.cmp(&b.since).then_with(|| a.rule_id.cmp(&b.rule_id)));
+        alerts
+    }
+
+    /// Recently resolved alerts, newest first
+    pub fn resolved_alerts(&self) -> Vec<Alert> {
+        self.resolved.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// Evaluate every enabled rule and return the alerts that started firing or resolved
+    ///
+    /// When `docker_available` is false, alerts on `docker.*` metrics keep their
+    /// state instead of resolving because their series are missing.
+    pub fn evaluate(&self, now: i64, series: &[Series], docker_available: bool) -> Vec<Alert> {
+        let rules = self.rules();
+        let mut active = self.active.lock().unwrap();
+        let mut seen: HashSet<AlertKey> = HashSet::new();
+        let mut transitions = Vec::new();
+
+        for rule in rules.iter().filter(|r| r.enabled) {
+            if !docker_available && rule.metric.starts_with("docker.") {
+                seen.extend(active.keys().filter(|(id, _)| *id == rule.id).cloned());
+                continue;
+            }
+
+            let threshold = match &rule.threshold {
+                Threshold::Value(value) => *value,
+                Threshold::Metric(metric) => match series.iter().find(|s| &s.metric == metric) {
+                    Some(s) => s.value,
+                    None => continue,
+                },
+            };
+
+            let matching = series.iter().filter(|s| {
+                s.metric == rule.metric
+                    && rule.label.as_ref().is_none_or(|label| s.label.as_ref() == Some(label))
+            });
+
+            for observed in matching {
+                if !rule.operator.matches(observed.value, threshold) {
+                    continue;
+                }
+
+                let key = (rule.id.clone(), observed.label.clone());
+                seen.insert(key.clone());
+
+                let alert = active.entry(key).or_insert_with(|| Alert {
+                    rule_id: rule.id.clone(),
+                    rule_name: rule.name.clone(),
+                    severity: rule.severity,
+                    metric: rule.metric.clone(),
+                    label: observed.label.clone(),
+                    state: AlertState::Pending,
+                    value: observed.value,
+                    threshold,
+                    since: now,
+                    fired_at: None,
+                    resolved_at: None,
+                });
+                alert.value = observed.value;
+                alert.threshold = threshold;
+
+                if alert.state == AlertState::Pending && now - alert.since >= rule.for_secs() as i64 {
+                    alert.state = AlertState::Firing;
+                    alert.fired_at = Some(now);
+                    transitions.push(alert.clone());
+                }
+            }
+        }
+
+        let cleared: Vec<AlertKey> = active.keys().filter(|k| !seen.contains(*k)).cloned().collect();
+        let mut resolved = self.resolved.lock().unwrap();
+        for key in cleared {
+            let Some(mut alert) = active.remove(&key) else {
+                continue;
+            };
+            // Pending alerts never fired, so they disappear silently
+            if alert.state == AlertState::Firing {
+                alert.state = AlertState::Resolved;
+                alert.resolved_at = Some(now);
+                transitions.push(alert.clone());
+                if resolved.len() == RESOLVED_HISTORY {
+                    resolved.pop_front();
+                }
+                resolved.push_back(alert);
+            }
+        }
+
+        transitions
+    }
+}
+
+/// Rules used when no rules file exists yet
+pub fn default_rules() -> Vec<AlertRule> {
+    vec![
+        AlertRule {
+            id: "disk-usage-high".to_string(),
+            name: "Disk usage above 90%".to_string(),
+            metric: "disk.used_percent".to_string(),
+            label: None,
+            operator: Operator::Gt,
+            threshold: Threshold::Value(90.0),
+            for_duration: "5m".to_string(),
+            severity: Severity::Critical,
+            enabled: true,
+        },
+        AlertRule {
+            id: "memory-usage-high".to_string(),
+            name: "Memory usage above 90%".to_string(),
+            metric: "memory.used_percent".to_string(),
+            label: None,
+            operator: Operator::Gt,
+            threshold: Threshold::Value(90.0),
+            for_duration: "5m".to_string(),
+            severity: Severity::Warning,
+            enabled: true,
+        },
+        AlertRule {
+            id: "load-above-cores".to_string(),
+            name: "5-minute load average above core count".to_string(),
+            metric: "load.five".to_string(),
+            label: None,
+            operator: Operator::Gt,
+            threshold: Threshold::Metric("cpu.cores".to_string()),
+            for_duration: "10m".to_string(),
+            severity: Severity::Warning,
+            enabled: true,
+        },
+        // Disabled by default: it also matches containers that were stopped on purpose
+        AlertRule {
+            id: "container-not-running".to_string(),
+            name: "Container is not running".to_string(),
+            metric: "docker.container.running".to_string(),
+            label: None,
+            operator: Operator::Lt,
+            threshold: Threshold::Value(1.0),
+            for_duration: "1m".to_string(),
+            severity: Severity::Warning,
+            enabled: false,
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(id: &str, metric: &str, threshold: Threshold, for_duration: &str) -> AlertRule {
+        AlertRule {
+            id: id.to_string(),
+            name: id.to_string(),
+            metric: metric.to_string(),
+            label: None,
+            operator: Operator::Gt,
+            threshold,
+            for_duration: for_duration.to_string(),
+            severity: Severity::Warning,
+            enabled: true,
+        }
+    }
+
+    fn series(metric: &str, label: Option<&str>, value: f64) -> Series {
+        Series {
+            metric: metric.to_string(),
+            label: label.map(str::to_string),
+            value,
+        }
+    }
+
+    #[test]
+    fn test_alert_fires_after_for_duration_and_resolves() {
+        let engine = AlertEngine::new(vec![rule("mem", "memory.used_percent", Threshold::Value(80.0), "1m")]);
+        let high = [series("memory.used_percent", None, 95.0)];
+
+        assert!(engine.evaluate(0, &high, true).is_empty());
+        assert_eq!(engine.active_alerts()[0].state, AlertState::Pending);
+
+        assert!(engine.evaluate(30, &high, true).is_empty());
+
+        let fired = engine.evaluate(60, &high, true);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].state, AlertState::Firing);
+        assert_eq!(fired[0].fired_at, Some(60));
+
+        // Still firing: no new transition
+        assert!(engine.evaluate(90, &high, true).is_empty());
+
+        let resolved = engine.evaluate(120, &[series("memory.used_percent", None, 10.0)], true);
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].state, AlertState::Resolved);
+        assert!(engine.active_alerts().is_empty());
+        assert_eq!(engine.resolved_alerts().len(), 1);
+    }
+
+    #[test]
+    fn test_pending_alert_clears_silently() {
+        let engine = AlertEngine::new(vec![rule("mem", "memory.used_percent", Threshold::Value(80.0), "5m")]);
+
+        engine.evaluate(0, &[series("memory.used_percent", None, 95.0)], true);
+        let transitions = engine.evaluate(10, &[series("memory.used_percent", None, 50.0)], true);
+
+        assert!(transitions.is_empty());
+        assert!(engine.active_alerts().is_empty());
+        assert!(engine.resolved_alerts().is_empty());
+    }
+
+    #[test]
+    fn test_per_label_alerts_and_label_filter() {
+        let mut disk = rule("disk", "disk.used_percent", Threshold::Value(90.0), "0s");
+        let engine = AlertEngine::new(vec![disk.clone()]);
+        let observed = [
+            series("disk.used_percent", Some("/"), 95.0),
+            series("disk.used_percent", Some("/data"), 97.0),
+            series("disk.used_percent", Some("/boot"), 20.0),
+        ];
+
+        let fired = engine.evaluate(0, &observed, true);
+        assert_eq!(fired.len(), 2);
+
+        disk.label = Some("/data".to_string());
+        engine.update_rule("disk", disk).unwrap();
+        let transitions = engine.evaluate(1, &observed, true);
+        assert_eq!(transitions.len(), 1);
+        assert_eq!(transitions[0].label.as_deref(), Some("/"));
+        assert_eq!(transitions[0].state, AlertState::Resolved);
+    }
+
+    #[test]
+    fn test_threshold_from_other_metric() {
+        let engine = AlertEngine::new(vec![rule("load", "load.one", Threshold::Metric("cpu.cores".to_string()), "0s")]);
+
+        let below = [series("load.one", None, 3.0), series("cpu.cores", None, 4.0)];
+        assert!(engine.evaluate(0, &below, true).is_empty());
+
+        let above = [series("load.one", None, 5.0), series("cpu.cores", None, 4.0)];
+        let fired = engine.evaluate(1, &above, true);
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].threshold, 4.0);
+    }
+
+    #[test]
+    fn test_docker_alerts_kept_while_docker_unavailable() {
+        let mut container = rule("ctr", "docker.container.running", Threshold::Value(1.0), "0s");
+        container.operator = Operator::Lt;
+        let engine = AlertEngine::new(vec![container]);
+
+        engine.evaluate(0, &[series("docker.container.running", Some("web"), 0.0)], true);
+        assert!(engine.evaluate(1, &[], false).is_empty());
+        assert_eq!(engine.active_alerts().len(), 1);
+    }
+
+    #[test]
+    fn test_rule_management() {
+        let engine = AlertEngine::new(Vec::new());
+        let mem = rule("mem", "memory.used_percent", Threshold::Value(80.0), "1m");
+
+        engine.add_rule(mem.clone()).unwrap();
+        assert!(matches!(engine.add_rule(mem.clone()), Err(AppError::ValidationError(_))));
+        assert!(matches!(
+            engine.add_rule(rule("bad", "nope", Threshold::Value(1.0), "1m")),
+            Err(AppError::ValidationError(_))
+        ));
+        assert!(matches!(engine.update_rule("other", mem.clone()), Err(AppError::ValidationError(_))));
+        assert!(matches!(engine.delete_rule("other"), Err(AppError::NotFound(_))));
+
+        engine.delete_rule("mem").unwrap();
+        assert!(engine.rules().is_empty());
+    }
+
+    #[test]
+    fn test_rules_are_persisted() {
+        let path = std::env::temp_dir().join(format!("alert-rules-{}.json", std::process::id()));
+        let _ = fs::remove_file(&path);
+
+        let engine = AlertEngine::load(&path).unwrap();
+        assert_eq!(engine.rules(), default_rules());
+
+        engine.delete_rule("disk-usage-high").unwrap();
+        let reloaded = AlertEngine::load(&path).unwrap();
+        assert_eq!(reloaded.rules().len(), default_rules().len() - 1);
+
+        let _ = fs::remove_file(&path);
+    }
+}
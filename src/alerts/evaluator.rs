@@ -0,0 +1,118 @@
+// Periodic rule evaluation against the sampler snapshot and Docker state
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use log::{info, warn};
+
+use crate::alerts::engine::{AlertEngine, Series};
+use crate::alerts::models::AlertState;
+use crate::docker::client::get_docker_client;
+use crate::docker::collectors::collect_containers;
+use crate::docker::models::DockerContainer;
+use crate::metrics::models::MetricsSnapshot;
+use crate::metrics::store::MetricsStore;
+use crate::notifications::dispatcher::Notifier;
+use crate::notifications::models::Notification;
+
+/// Turn a sampler snapshot and container list into the series rules are evaluated against
+///
+/// Per-device values are labelled with the mount point or container name.
+pub fn collect_series(snapshot: &MetricsSnapshot, containers: Option<&[DockerContainer]>) -> Vec<Series> {
+    let mut series: Vec<Series> = snapshot
+        .history_values(None)
+        .into_iter()
+        .map(|(key, value)| {
+            let (metric, label) = match key.split_once(':') {
+                Some((metric, label)) => (metric.to_string(), Some(label.to_string())),
+                None => (key, None),
+            };
+            Series { metric, label, value }
+        })
+        .collect();
+
+    series.push(Series {
+        metric: "cpu.cores".to_string(),
+        label: None,
+        value: snapshot.cpu.per_core_usage.len() as f64,
+    });
+
+    for container in containers.unwrap_or_default() {
+        series.push(Series {
+            metric: "docker.container.running".to_string(),
+            label: Some(container.name.clone()),
+            value: if container.state == "running" { 1.0 } else { 0.0 },
+        });
+        if let Some(percent) = container.memory_percent {
+            series.push(Series {
+                metric: "docker.container.memory_percent".to_string(),
+                label: Some(container.name.clone()),
+                value: percent,
+            });
+        }
+    }
+
+    series
+}
+
+/// Evaluate the alert rules every `interval` and notify about firing and resolved alerts
+///
+/// Docker is only queried while an enabled rule references a `docker.*` metric.
+pub fn spawn_evaluator(
+    engine: Arc<AlertEngine>,
+    store: Arc<MetricsStore>,
+    notifier: Arc<Notifier>,
+    socket_path: String,
+    interval: Duration,
+) {
+    actix_web::rt::spawn(async move {
+        info!("Alert evaluator started (interval: {:?})", interval);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+            let Some(snapshot) = store.latest() else {
+                continue;
+            };
+
+            let needs_docker = engine
+                .rules()
+                .iter()
+                .any(|r| r.enabled && r.metric.starts_with("docker."));
+            let containers = if needs_docker {
+                let result = match get_docker_client(&socket_path).await {
+                    Ok(docker) => collect_containers(&docker).await.map_err(|e| e.to_string()),
+                    Err(e) => Err(e),
+                };
+                match result {
+                    Ok(containers) => Some(containers),
+                    Err(e) => {
+                        warn!("Alert evaluation could not query Docker: {}", e);
+                        None
+                    }
+                }
+            } else {
+                None
+            };
+
+            let series = collect_series(&snapshot, containers.as_deref());
+            let transitions = engine.evaluate(snapshot.timestamp, &series, containers.is_some());
+            for alert in transitions {
+                let label = alert.label.as_deref().unwrap_or("-");
+                match alert.state {
+                    AlertState::Firing => warn!(
+                        "Alert firing: {} [{}] value {:.2} threshold {:.2}",
+                        alert.rule_name, label, alert.value, alert.threshold
+                    ),
+                    _ => info!("Alert resolved: {} [{}]", alert.rule_name, label),
+                }
+
+                // Deliver in the background so retries do not delay the next evaluation
+                let notifier = notifier.clone();
+                actix_web::rt::spawn(async move {
+                    notifier.notify(&Notification::from_alert(&alert)).await;
+                });
+            }
+        }
+    });
+}
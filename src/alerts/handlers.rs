@@ -0,0 +1,94 @@
+// Alert and alert rule HTTP handlers
+
+use actix_web::{delete, get, post, put, web, HttpResponse};
+use log::info;
+use serde_json::json;
+
+use crate::alerts::models::{AlertQuery, AlertRule, AlertState};
+use crate::error::AppError;
+use crate::system::models::AppState;
+
+/// List alerts, by default only pending and firing ones
+#[get("/api/alerts")]
+pub async fn list_alerts(
+    data: web::Data<AppState>,
+    query: web::Query<AlertQuery>,
+) -> Result<HttpResponse, AppError> {
+    info!("GET /api/alerts");
+
+    let engine = &data.alerts;
+    let alerts = match query.state.as_deref() {
+        None => engine.active_alerts(),
+        Some("all") => {
+            let mut alerts = engine.active_alerts();
+            alerts.extend(engine.resolved_alerts());
+            alerts
+        }
+        Some("pending") | Some("firing") => {
+            let state = if query.state.as_deref() == Some("pending") {
+                AlertState::Pending
+            } else {
+                AlertState::Firing
+            };
+            engine.active_alerts().into_iter().filter(|a| a.state == state).collect()
+        }
+        Some("resolved") => engine.resolved_alerts(),
+        Some(other) => {
+            return Err(AppError::ValidationError(format!(
+                "Unknown state '{}'. Use pending, firing, resolved or all",
+                other
+            )))
+        }
+    };
+
+    Ok(HttpResponse::Ok().json(alerts))
+}
+
+/// List configured alert rules
+#[get("/api/alerts/rules")]
+pub async fn list_rules(data: web::Data<AppState>) -> HttpResponse {
+    info!("GET /api/alerts/rules");
+    HttpResponse::Ok().json(data.alerts.rules())
+}
+
+/// Add a new alert rule
+#[post("/api/alerts/rules")]
+pub async fn create_rule(
+    data: web::Data<AppState>,
+    rule: web::Json<AlertRule>,
+) -> Result<HttpResponse, AppError> {
+    info!("POST /api/alerts/rules - {}", rule.id);
+
+    let rule = rule.into_inner();
+    data.alerts.add_rule(rule.clone())?;
+    Ok(HttpResponse::Created().json(rule))
+}
+
+/// Replace an existing alert rule
+#[put("/api/alerts/rules/{id}")]
+pub async fn update_rule(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+    rule: web::Json<AlertRule>,
+) -> Result<HttpResponse, AppError> {
+    info!("PUT /api/alerts/rules/{}", id);
+
+    let rule = rule.into_inner();
+    data.alerts.update_rule(&id, rule.clone())?;
+    Ok(HttpResponse::Ok().json(rule))
+}
+
+/// Delete an alert rule; its active alerts resolve on the next evaluation
+#[delete("/api/alerts/rules/{id}")]
+pub async fn delete_rule(
+    data: web::Data<AppState>,
+    id: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    info!("DELETE /api/alerts/rules/{}", id);
+
+    data.alerts.delete_rule(&id)?;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": format!("Rule '{}' deleted", id)
+    })))
+}
@@ -0,0 +1,35 @@
+//! Alerting module
+//!
+//! This module evaluates declarative threshold rules against sampled metrics:
+//! - Rules compare a metric to a fixed value or to another metric (e.g. `cpu.cores`)
+//! - A rule must hold for its `for` duration before the alert fires
+//! - Per-device metrics raise one alert per mount point or container
+//! - Firing alerts resolve once the condition clears
+//! - Rules are managed over the API and saved to a JSON file
+//!
+//! # Submodules
+//!
+//! - [`models`]: Rule, alert and query data structures
+//! - [`engine`]: Rule storage and pending/firing/resolved state tracking
+//! - [`evaluator`]: Series collection and the periodic evaluation task
+//! - [`handlers`]: HTTP request handlers for alerts and rules
+//! - [`routes`]: Route registration for the alerts API
+//!
+//! # Example
+//!
+//! ```
+//! use ubuntu_resource_api::alerts::engine::{default_rules, AlertEngine, Series};
+//!
+//! let engine = AlertEngine::new(default_rules());
+//! let series = vec![Series { metric: "memory.used_percent".to_string(), label: None, value: 97.0 }];
+//!
+//! // The memory rule needs 5 minutes above its threshold before firing
+//! assert!(engine.evaluate(0, &series, false).is_empty());
+//! assert_eq!(engine.evaluate(300, &series, false).len(), 1);
+//! ```
+
+pub mod models;
+pub mod engine;
+pub mod evaluator;
+pub mod handlers;
+pub mod routes;
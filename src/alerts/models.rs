@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+
+use crate::utils::parse_duration_secs;
+
+/// Metrics that alert rules can reference
+///
+/// Per-device metrics are evaluated once per mount point or container name.
+pub const KNOWN_METRICS: &[&str] = &[
+    "cpu.usage_percent",
+    "cpu.cores",
+    "memory.used_percent",
+    "memory.used_bytes",
+    "memory.available_bytes",
+    "load.one",
+    "load.five",
+    "load.fifteen",
+    "disk.used_percent",
+    "disk.used_bytes",
+    "docker.container.running",
+    "docker.container.memory_percent",
+];
+
+/// Comparison between the observed value and the threshold
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Operator {
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Gte,
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Lte,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl Operator {
+    pub fn matches(&self, value: f64, threshold: f64) -> bool {
+        match self {
+            Operator::Gt => value > threshold,
+            Operator::Gte => value >= threshold,
+            Operator::Lt => value < threshold,
+            Operator::Lte => value <= threshold,
+            Operator::Eq => value == threshold,
+            Operator::Ne => value != threshold,
+        }
+    }
+}
+
+/// A fixed number or the current value of another scalar metric (e.g. `cpu.cores`)
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Threshold {
+    Value(f64),
+    Metric(String),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Warning => "warning",
+            Severity::Critical => "critical",
+        }
+    }
+}
+
+/// Declarative alert rule
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AlertRule {
+    pub id: String,
+    pub name: String,
+    pub metric: String,
+    /// Restrict a per-device metric to one mount point or container name
+    #[serde(default)]
+    pub label: Option<String>,
+    pub operator: Operator,
+    pub threshold: Threshold,
+    /// How long the condition must hold before the alert fires, e.g. `5m`
+    #[serde(rename = "for", default = "default_for")]
+    pub for_duration: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+fn default_for() -> String {
+    "0s".to_string()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl AlertRule {
+    /// Check the rule for unknown metrics, bad ids and unparsable durations
+    pub fn validate(&self) -> Result<(), String> {
+        if self.id.is_empty()
+            || !self.id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(format!(
+                "Invalid rule id '{}': use letters, digits, '-' and '_' only",
+                self.id
+            ));
+        }
+        if self.name.trim().is_empty() {
+            return Err("Rule name must not be empty".to_string());
+        }
+        if !KNOWN_METRICS.contains(&self.metric.as_str()) {
+            return Err(format!(
+                "Unknown metric '{}'. Available: {}",
+                self.metric,
+                KNOWN_METRICS.join(", ")
+            ));
+        }
+        if let Threshold::Metric(metric) = &self.threshold {
+            if !KNOWN_METRICS.contains(&metric.as_str()) {
+                return Err(format!("Unknown threshold metric '{}'", metric));
+            }
+        }
+        parse_duration_secs(&self.for_duration)?;
+        Ok(())
+    }
+
+    /// The `for` duration in seconds; rules are validated before they are stored
+    pub fn for_secs(&self) -> u64 {
+        parse_duration_secs(&self.for_duration).unwrap_or(0)
+    }
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertState {
+    /// Condition holds but has not lasted the `for` duration yet
+    Pending,
+    Firing,
+    Resolved,
+}
+
+impl AlertState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AlertState::Pending => "pending",
+            AlertState::Firing => "firing",
+            AlertState::Resolved => "resolved",
+        }
+    }
+}
+
+/// One alert instance of a rule, per device label
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Alert {
+    pub rule_id: String,
+    pub rule_name: String,
+    pub severity: Severity,
+    pub metric: String,
+    pub label: Option<String>,
+    pub state: AlertState,
+    pub value: f64,
+    pub threshold: f64,
+    /// When the condition started to hold (unix seconds)
+    pub since: i64,
+    pub fired_at: Option<i64>,
+    pub resolved_at: Option<i64>,
+}
+
+/// Query parameters for `/api/alerts`
+#[derive(Deserialize)]
+pub struct AlertQuery {
+    /// `pending`, `firing`, `resolved` or `all` (default: pending and firing)
+    pub state: Option<String>,
+}
@@ -0,0 +1,70 @@
+use actix_web::web;
+
+use crate::alerts::handlers;
+
+/// Configure all alert routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(handlers::list_alerts)
+        .service(handlers::list_rules)
+        .service(handlers::create_rule)
+        .service(handlers::update_rule)
+        .service(handlers::delete_rule);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::json;
+
+    use crate::system::models::AppState;
+
+    fn app_state() -> web::Data<AppState> {
+        web::Data::new(AppState::for_tests())
+    }
+
+    #[actix_web::test]
+    async fn test_rule_crud() {
+        let app = test::init_service(App::new().app_data(app_state()).configure(configure_routes)).await;
+        let rule = json!({
+            "id": "disk-root",
+            "name": "Root disk almost full",
+            "metric": "disk.used_percent",
+            "label": "/",
+            "operator": ">",
+            "threshold": 90,
+            "for": "5m",
+            "severity": "critical"
+        });
+
+        let req = test::TestRequest::post().uri("/api/alerts/rules").set_json(&rule).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::CREATED);
+
+        let req = test::TestRequest::post().uri("/api/alerts/rules").set_json(&rule).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get().uri("/api/alerts/rules").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body[0]["for"], "5m");
+        assert_eq!(body[0]["enabled"], true);
+
+        let req = test::TestRequest::delete().uri("/api/alerts/rules/disk-root").to_request();
+        assert!(test::call_service(&app, req).await.status().is_success());
+
+        let req = test::TestRequest::delete().uri("/api/alerts/rules/disk-root").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_list_alerts_rejects_unknown_state() {
+        let app = test::init_service(App::new().app_data(app_state()).configure(configure_routes)).await;
+
+        let req = test::TestRequest::get().uri("/api/alerts?state=bogus").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        let req = test::TestRequest::get().uri("/api/alerts").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body, json!([]));
+    }
+}
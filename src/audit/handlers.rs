@@ -0,0 +1,32 @@
+// Audit trail HTTP handlers
+
+use actix_web::{get, web, HttpResponse};
+use log::info;
+
+use crate::audit::models::AuditQuery;
+use crate::error::AppError;
+use crate::system::models::AppState;
+
+/// Query the audit trail, newest first
+#[get("/api/audit")]
+pub async fn get_audit_log(
+    data: web::Data<AppState>,
+    query: web::Query<AuditQuery>,
+) -> Result<HttpResponse, AppError> {
+    info!("GET /api/audit");
+
+    let query = query.into_inner();
+    if let (Some(from), Some(to)) = (query.from, query.to) {
+        if from > to {
+            return Err(AppError::ValidationError("'from' must not be later than 'to'".to_string()));
+        }
+    }
+
+    let audit = data.audit.clone();
+    let entries = web::block(move || audit.query(&query))
+        .await
+        .map_err(|e| AppError::SystemError(format!("Audit query failed: {}", e)))?
+        .map_err(|e| AppError::SystemError(format!("Failed to read audit log: {}", e)))?;
+
+    Ok(HttpResponse::Ok().json(entries))
+}
@@ -0,0 +1,241 @@
+// Append-only JSONL audit file with size-based rotation
+
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use actix_web::http::StatusCode;
+use log::warn;
+use serde_json::Value;
+
+use crate::audit::models::{AuditEntry, AuditOutcome, AuditQuery};
+use crate::auth::models::Identity;
+use crate::config::AuditConfig;
+
+/// Default and maximum number of entries returned by a query
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+
+/// Writes audit entries and answers queries over the current and rotated files
+pub struct AuditLog {
+    /// `None` disables auditing
+    path: Option<PathBuf>,
+    max_size_bytes: u64,
+    max_files: usize,
+    /// Serializes appends and rotation
+    lock: Mutex<()>,
+}
+
+impl AuditLog {
+    pub fn new(config: &AuditConfig) -> Self {
+        AuditLog {
+            path: Some(PathBuf::from(&config.log_path)),
+            max_size_bytes: config.max_size_bytes,
+            max_files: config.max_files,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// An audit log that records nothing
+    pub fn disabled() -> Self {
+        AuditLog {
+            path: None,
+            max_size_bytes: 0,
+            max_files: 0,
+            lock: Mutex::new(()),
+        }
+    }
+
+    /// Record the outcome of a mutating handler
+    ///
+    /// Failures to write are logged rather than returned, so an unwritable
+    /// audit file never blocks the action itself.
+    pub fn record(
+        &self,
+        identity: &Identity,
+        module: &str,
+        action: &str,
+        target: &str,
+        payload: Value,
+        status: StatusCode,
+    ) {
+        let entry = AuditEntry {
+            timestamp: chrono::Utc::now(),
+            actor: identity.name.clone(),
+            role: identity.role,
+            module: module.to_string(),
+            action: action.to_string(),
+            target: target.to_string(),
+            payload,
+            outcome: AuditOutcome {
+                success: status.is_success(),
+                status: status.as_u16(),
+            },
+        };
+
+        if let Err(e) = self.append(&entry) {
+            warn!("Failed to write audit entry for {} {}: {}", action, target, e);
+        }
+    }
+
+    /// Append one entry, rotating the file first if it would grow past the size limit
+    pub fn append(&self, entry: &AuditEntry) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().unwrap();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+        if size > 0 && size + line.len() as u64 > self.max_size_bytes {
+            self.rotate(path)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        file.write_all(line.as_bytes())
+    }
+
+    /// Shift `audit.jsonl` to `audit.jsonl.1`, `.1` to `.2`, ... dropping the oldest
+    fn rotate(&self, path: &Path) -> io::Result<()> {
+        if self.max_files == 0 {
+            return fs::remove_file(path);
+        }
+
+        let oldest = rotated_path(path, self.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (1..self.max_files).rev() {
+            let from = rotated_path(path, index);
+            if from.exists() {
+                fs::rename(&from, rotated_path(path, index + 1))?;
+            }
+        }
+        fs::rename(path, rotated_path(path, 1))
+    }
+
+    /// Matching entries across all files, newest first
+    pub fn query(&self, query: &AuditQuery) -> io::Result<Vec<AuditEntry>> {
+        let Some(path) = &self.path else {
+            return Ok(Vec::new());
+        };
+        let limit = query.limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT);
+
+        let _guard = self.lock.lock().unwrap();
+        let mut entries = Vec::new();
+        let files = std::iter::once(path.clone()).chain((1..=self.max_files).map(|i| rotated_path(path, i)));
+        for file in files {
+            let file = match File::open(&file) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            for line in BufReader::new(file).lines() {
+                // Skip lines that cannot be parsed, e.g. a partial write
+                let Ok(entry) = serde_json::from_str::<AuditEntry>(&line?) else {
+                    continue;
+                };
+                if query.matches(&entry) {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.timestamp));
+        entries.truncate(limit);
+        Ok(entries)
+    }
+}
+
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{}", index));
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::models::{AuthMethod, Role};
+    use serde_json::json;
+
+    fn temp_log(name: &str, max_size_bytes: u64, max_files: usize) -> (AuditLog, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("audit-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let path = dir.join("audit.jsonl");
+        let log = AuditLog::new(&AuditConfig {
+            log_path: path.to_string_lossy().to_string(),
+            max_size_bytes,
+            max_files,
+        });
+        (log, dir)
+    }
+
+    fn identity(name: &str) -> Identity {
+        Identity {
+            name: name.to_string(),
+            method: AuthMethod::Token,
+            role: Role::Operator,
+        }
+    }
+
+    #[test]
+    fn test_record_and_query_with_filters() {
+        let (log, dir) = temp_log("query", 1024 * 1024, 2);
+
+        log.record(&identity("alice"), "system", "process.kill", "42", json!({"pid": 42}), StatusCode::OK);
+        log.record(&identity("bob"), "docker", "container.remove", "abc", json!({"id": "abc"}), StatusCode::INTERNAL_SERVER_ERROR);
+        log.record(&identity("alice"), "nginx", "proxy.delete", "site", json!({"name": "site"}), StatusCode::OK);
+
+        let all = log.query(&AuditQuery::default()).unwrap();
+        assert_eq!(all.len(), 3);
+        assert_eq!(all[0].action, "proxy.delete");
+
+        let alice = log.query(&AuditQuery { actor: Some("alice".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(alice.len(), 2);
+
+        let docker = log.query(&AuditQuery { module: Some("docker".to_string()), ..Default::default() }).unwrap();
+        assert_eq!(docker.len(), 1);
+        assert!(!docker[0].outcome.success);
+        assert_eq!(docker[0].outcome.status, 500);
+
+        let future = log.query(&AuditQuery { from: Some(chrono::Utc::now().timestamp() + 60), ..Default::default() }).unwrap();
+        assert!(future.is_empty());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_rotation_keeps_max_files() {
+        let (log, dir) = temp_log("rotate", 200, 2);
+        let path = dir.join("audit.jsonl");
+
+        for pid in 0..10 {
+            log.record(&identity("alice"), "system", "process.kill", &pid.to_string(), json!({"pid": pid}), StatusCode::OK);
+        }
+
+        assert!(path.exists());
+        assert!(rotated_path(&path, 1).exists());
+        assert!(rotated_path(&path, 2).exists());
+        assert!(!rotated_path(&path, 3).exists());
+
+        // Entries in the kept files are still queryable, newest first
+        let entries = log.query(&AuditQuery::default()).unwrap();
+        assert!(entries.len() >= 3 && entries.len() < 10);
+        assert_eq!(entries[0].target, "9");
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_disabled_log_records_nothing() {
+        let log = AuditLog::disabled();
+        log.record(&identity("alice"), "system", "process.kill", "1", Value::Null, StatusCode::OK);
+        assert!(log.query(&AuditQuery::default()).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,19 @@
+//! Audit trail module
+//!
+//! This module records every mutating action taken through the API:
+//! - Who did it (token or user and role), when, the target and the request payload
+//! - The outcome as the HTTP status returned to the caller
+//! - Entries are appended to a JSONL file that rotates by size
+//! - The trail is queryable by actor, module and time range
+//!
+//! # Submodules
+//!
+//! - [`models`]: Audit entry and query structures
+//! - [`log`]: JSONL writer, rotation and queries
+//! - [`handlers`]: HTTP request handler for `/api/audit`
+//! - [`routes`]: Route registration for the audit API
+
+pub mod models;
+pub mod log;
+pub mod handlers;
+pub mod routes;
@@ -0,0 +1,53 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::auth::models::Role;
+
+/// One mutating action, stored as a line of the JSONL audit file
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// API token name or username
+    pub actor: String,
+    pub role: Role,
+    /// `system`, `docker` or `nginx`
+    pub module: String,
+    /// What was done, e.g. `container.remove`
+    pub action: String,
+    /// PID, container id, proxy name, ...
+    pub target: String,
+    pub payload: Value,
+    pub outcome: AuditOutcome,
+}
+
+/// Result of the audited action
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct AuditOutcome {
+    pub success: bool,
+    /// HTTP status returned to the caller
+    pub status: u16,
+}
+
+/// Query parameters for `/api/audit`
+#[derive(Deserialize, Default)]
+pub struct AuditQuery {
+    pub actor: Option<String>,
+    pub module: Option<String>,
+    /// Unix timestamp (seconds), inclusive
+    pub from: Option<i64>,
+    /// Unix timestamp (seconds), inclusive
+    pub to: Option<i64>,
+    /// Maximum entries to return, newest first (default: 100)
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, entry: &AuditEntry) -> bool {
+        let timestamp = entry.timestamp.timestamp();
+        self.actor.as_ref().is_none_or(|actor| &entry.actor == actor)
+            && self.module.as_ref().is_none_or(|module| &entry.module == module)
+            && self.from.is_none_or(|from| timestamp >= from)
+            && self.to.is_none_or(|to| timestamp <= to)
+    }
+}
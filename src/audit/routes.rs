@@ -0,0 +1,58 @@
+use actix_web::web;
+
+use crate::audit::handlers;
+
+/// Configure audit trail routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(handlers::get_audit_log);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use std::sync::Arc;
+
+    use crate::audit::log::AuditLog;
+    use crate::config::{AppConfig, AuditConfig};
+    use crate::system::models::AppState;
+
+    fn app_state(audit: AuditLog) -> web::Data<AppState> {
+        web::Data::new(AppState { audit: Arc::new(audit), ..AppState::for_tests() })
+    }
+
+    #[actix_web::test]
+    async fn test_mutating_handler_is_audited() {
+        let dir = std::env::temp_dir().join(format!("audit-routes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let audit = AuditLog::new(&AuditConfig {
+            log_path: dir.join("audit.jsonl").to_string_lossy().to_string(),
+            ..AppConfig::with_defaults().audit
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(audit))
+                .configure(configure_routes)
+                .configure(crate::system::routes::configure_routes)
+        ).await;
+
+        // No such process: the failed attempt is still recorded
+        let req = test::TestRequest::delete().uri("/api/processes/4294967290").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::get().uri("/api/audit?module=system").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["action"], "process.kill");
+        assert_eq!(body[0]["target"], "4294967290");
+        assert_eq!(body[0]["actor"], "anonymous");
+        assert_eq!(body[0]["outcome"]["success"], false);
+        assert_eq!(body[0]["outcome"]["status"], 404);
+
+        let req = test::TestRequest::get().uri("/api/audit?from=20&to=10").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
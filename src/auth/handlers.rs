@@ -0,0 +1,109 @@
+// Login, logout and identity handlers
+
+use actix_web::cookie::{time, Cookie, SameSite};
+use actix_web::http::header::LOCATION;
+use actix_web::{get, post, web, HttpRequest, HttpResponse};
+use log::{info, warn};
+
+use crate::auth::models::{Identity, LoginForm};
+use crate::auth::service::SESSION_COOKIE;
+use crate::error::AppError;
+use crate::system::models::AppState;
+
+// Embedded login page HTML
+const LOGIN_HTML: &str = include_str!("../templates/login.html");
+
+/// Where to go after logging in when no valid `next` is given
+const DEFAULT_PAGE: &str = "/dashboard";
+
+/// Serve the login page
+#[get("/login")]
+pub async fn login_page() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(LOGIN_HTML)
+}
+
+/// Check the submitted credentials and start a session
+#[post("/login")]
+pub async fn login(data: web::Data<AppState>, form: web::Form<LoginForm>) -> Result<HttpResponse, AppError> {
+    let form = form.into_inner();
+    let next = safe_next(form.next.as_deref()).to_string();
+    info!("POST /login - {}", form.username);
+
+    let auth = data.auth.clone();
+    let username = form.username.clone();
+    let session = web::block(move || auth.login(&username, &form.password))
+        .await
+        .map_err(|e| AppError::SystemError(format!("Login check failed: {}", e)))?;
+
+    let Some(session) = session else {
+        warn!("Failed login for '{}'", form.username);
+        return Ok(HttpResponse::SeeOther()
+            .insert_header((LOCATION, format!("/login?error=1&next={}", next)))
+            .finish());
+    };
+
+    let cookie = Cookie::build(SESSION_COOKIE, session)
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Strict)
+        .secure(data.auth.secure_cookie())
+        .max_age(time::Duration::seconds(data.auth.session_ttl().as_secs() as i64))
+        .finish();
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((LOCATION, next))
+        .cookie(cookie)
+        .finish())
+}
+
+/// End the current session
+#[post("/logout")]
+pub async fn logout(data: web::Data<AppState>, req: HttpRequest) -> HttpResponse {
+    if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+        data.auth.logout(cookie.value());
+    }
+
+    let mut removal = Cookie::build(SESSION_COOKIE, "").path("/").finish();
+    removal.make_removal();
+
+    HttpResponse::SeeOther()
+        .insert_header((LOCATION, "/login"))
+        .cookie(removal)
+        .finish()
+}
+
+/// Return the authenticated caller
+#[get("/api/auth/me")]
+pub async fn current_identity(identity: Identity) -> HttpResponse {
+    HttpResponse::Ok().json(identity)
+}
+
+/// Only allow redirects to local paths, so the login form cannot send users elsewhere
+fn safe_next(next: Option<&str>) -> &str {
+    match next {
+        Some(path)
+            if path.starts_with('/')
+                && !path.starts_with("//")
+                && path.chars().all(|c| c.is_ascii_alphanumeric() || "/-_.".contains(c)) =>
+        {
+            path
+        }
+        _ => DEFAULT_PAGE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_next() {
+        assert_eq!(safe_next(Some("/nginx")), "/nginx");
+        assert_eq!(safe_next(None), DEFAULT_PAGE);
+        assert_eq!(safe_next(Some("https://evil.example")), DEFAULT_PAGE);
+        assert_eq!(safe_next(Some("//evil.example")), DEFAULT_PAGE);
+        assert_eq!(safe_next(Some("/docker?x=<script>")), DEFAULT_PAGE);
+    }
+}
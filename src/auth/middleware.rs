@@ -0,0 +1,219 @@
+// Authentication middleware applied to every route
+
+use actix_web::body::{EitherBody, MessageBody};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::http::header::{AUTHORIZATION, LOCATION};
+use actix_web::middleware::Next;
+use actix_web::{web, Error, HttpMessage, HttpRequest, HttpResponse, ResponseError};
+
+use crate::auth::models::Identity;
+use crate::auth::permissions::required_role;
+use crate::auth::service::{AuthService, SESSION_COOKIE};
+use crate::error::AppError;
+use crate::system::models::AppState;
+
+/// Routes reachable without credentials
+const PUBLIC_PATHS: &[&str] = &["/health", "/login", "/logout"];
+
+/// HTML pages that redirect to the login page instead of answering 401
+const LOGIN_PAGES: &[&str] = &["/dashboard", "/nginx", "/docker"];
+
+/// Require a valid API token or session on every non-public route
+///
+/// Accepts `Authorization: Bearer <token>` for scripts and the session cookie
+/// set by `/login` for the dashboards, then checks the caller's role against
+/// [`required_role`]. The caller is stored in the request extensions and can
+/// be extracted as [`Identity`].
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, Error> {
+    let auth = req.app_data::<web::Data<AppState>>().map(|data| data.auth.clone());
+    let Some(auth) = auth.filter(|auth| auth.is_enabled()) else {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    };
+    if PUBLIC_PATHS.contains(&req.path()) {
+        return next.call(req).await.map(ServiceResponse::map_into_left_body);
+    }
+
+    match identify(&auth, req.request()) {
+        Ok(identity) => {
+            let required = required_role(req.method(), req.path());
+            if identity.role < required {
+                let error = AppError::Forbidden(format!(
+                    "'{}' has the {} role; {} {} requires {}",
+                    identity.name,
+                    identity.role.as_str(),
+                    req.method(),
+                    req.path(),
+                    required.as_str()
+                ));
+                return Ok(req.into_response(error.error_response()).map_into_right_body());
+            }
+
+            req.extensions_mut().insert(identity);
+            next.call(req).await.map(ServiceResponse::map_into_left_body)
+        }
+        Err(message) => {
+            let response = if LOGIN_PAGES.contains(&req.path()) {
+                HttpResponse::Found()
+                    .insert_header((LOCATION, format!("/login?next={}", req.path())))
+                    .finish()
+            } else {
+                AppError::Unauthorized(message.to_string()).error_response()
+            };
+            Ok(req.into_response(response).map_into_right_body())
+        }
+    }
+}
+
+/// Resolve the bearer token or session cookie to an identity
+fn identify(auth: &AuthService, req: &HttpRequest) -> Result<Identity, &'static str> {
+    if let Some(header) = req.headers().get(AUTHORIZATION) {
+        let token = header
+            .to_str()
+            .ok()
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .ok_or("Unsupported authorization scheme, use 'Bearer <token>'")?;
+        return auth.authenticate_token(token.trim()).ok_or("Invalid API token");
+    }
+
+    if let Some(cookie) = req.cookie(SESSION_COOKIE) {
+        return auth.session(cookie.value()).ok_or("Session expired, please log in again");
+    }
+
+    Err("Authentication required")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::http::StatusCode;
+    use actix_web::{middleware, test, App};
+    use std::sync::Arc;
+
+    use crate::auth::service::hash_token;
+    use crate::auth::models::Role;
+    use crate::config::{AppConfig, ApiTokenConfig, UserConfig};
+
+    fn app_state() -> web::Data<AppState> {
+        let mut config = AppConfig::with_defaults();
+        config.auth.api_tokens = vec![
+            ApiTokenConfig {
+                name: "ci".to_string(),
+                role: Role::Viewer,
+                sha256: hash_token("s3cret-token"),
+            },
+            ApiTokenConfig {
+                name: "deploy".to_string(),
+                role: Role::Operator,
+                sha256: hash_token("deploy-token"),
+            },
+        ];
+        config.auth.users = vec![UserConfig {
+            username: "ops".to_string(),
+            role: Role::Viewer,
+            password_hash: bcrypt::hash("hunter2", 4).unwrap(),
+        }];
+
+        web::Data::new(AppState {
+            auth: Arc::new(AuthService::from_config(&config.auth).unwrap()),
+            ..AppState::for_tests()
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_api_requires_token() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(authenticate))
+                .app_data(app_state())
+                .configure(crate::system::routes::configure_routes)
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/load").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get()
+            .uri("/api/load")
+            .insert_header((AUTHORIZATION, "Bearer wrong"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::UNAUTHORIZED);
+
+        let req = test::TestRequest::get()
+            .uri("/api/load")
+            .insert_header((AUTHORIZATION, "Bearer s3cret-token"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/health").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_dashboard_login_flow() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(authenticate))
+                .app_data(app_state())
+                .configure(crate::auth::routes::configure_routes)
+                .configure(crate::system::routes::configure_routes)
+        ).await;
+
+        let req = test::TestRequest::get().uri("/dashboard").to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FOUND);
+        assert_eq!(resp.headers().get(LOCATION).unwrap(), "/login?next=/dashboard");
+
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .set_form([("username", "ops"), ("password", "wrong"), ("next", "/dashboard")])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.headers().get(LOCATION).unwrap(), "/login?error=1&next=/dashboard");
+
+        let req = test::TestRequest::post()
+            .uri("/login")
+            .set_form([("username", "ops"), ("password", "hunter2"), ("next", "/dashboard")])
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::SEE_OTHER);
+        let cookie = resp.response().cookies().find(|c| c.name() == SESSION_COOKIE).unwrap().into_owned();
+        assert!(cookie.http_only().unwrap_or(false));
+
+        let req = test::TestRequest::get().uri("/dashboard").cookie(cookie.clone()).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let req = test::TestRequest::get().uri("/api/auth/me").cookie(cookie).to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["name"], "ops");
+        assert_eq!(body["method"], "session");
+        assert_eq!(body["role"], "viewer");
+    }
+
+    #[actix_web::test]
+    async fn test_viewer_cannot_kill_processes() {
+        let app = test::init_service(
+            App::new()
+                .wrap(middleware::from_fn(authenticate))
+                .app_data(app_state())
+                .configure(crate::system::routes::configure_routes)
+        ).await;
+
+        let req = test::TestRequest::delete()
+            .uri("/api/processes/4194304")
+            .insert_header((AUTHORIZATION, "Bearer s3cret-token"))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert_eq!(body["error"], "Forbidden");
+
+        // An operator gets through to the handler, which reports the unknown PID
+        let req = test::TestRequest::delete()
+            .uri("/api/processes/4194304")
+            .insert_header((AUTHORIZATION, "Bearer deploy-token"))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+    }
+}
@@ -0,0 +1,37 @@
+//! Authentication module
+//!
+//! This module protects the API and the dashboards:
+//! - Static API tokens for scripts, configured as SHA-256 digests
+//! - Username/password logins with bcrypt hashes for the HTML pages
+//! - In-memory sessions carried in an `HttpOnly`, `SameSite=Strict` cookie
+//! - Middleware that checks credentials before any system, nginx or docker route runs
+//! - Roles (`viewer`, `operator`, `admin`) checked per route; reads need a viewer,
+//!   killing processes, changing containers and writing nginx configs need an operator
+//!
+//! # Submodules
+//!
+//! - [`models`]: Identity, role and login form structures
+//! - [`service`]: Token verification and session store
+//! - [`permissions`]: Minimum role for each route
+//! - [`middleware`]: Request authentication and authorization middleware
+//! - [`handlers`]: Login page, login, logout and identity handlers
+//! - [`routes`]: Route registration for the auth endpoints
+//!
+//! # Example
+//!
+//! ```no_run
+//! use actix_web::{middleware, App};
+//! use ubuntu_resource_api::auth;
+//!
+//! let app = App::new()
+//!     .wrap(middleware::from_fn(auth::middleware::authenticate))
+//!     .configure(auth::routes::configure_routes)
+//!     .configure(ubuntu_resource_api::system::routes::configure_routes);
+//! ```
+
+pub mod models;
+pub mod service;
+pub mod permissions;
+pub mod middleware;
+pub mod handlers;
+pub mod routes;
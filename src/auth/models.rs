@@ -0,0 +1,84 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use serde::{Deserialize, Serialize};
+
+/// Access level, ordered from least to most privileged
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read metrics, logs and configuration
+    Viewer,
+    /// Also kill processes, manage containers and write nginx configs
+    Operator,
+    /// Everything, including routes not explicitly classified
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "viewer" => Some(Role::Viewer),
+            "operator" => Some(Role::Operator),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// How a request was authenticated
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthMethod {
+    Token,
+    Session,
+    /// Authentication is disabled
+    None,
+}
+
+/// The authenticated caller, attached to the request by the auth middleware
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Identity {
+    /// API token name or username
+    pub name: String,
+    pub method: AuthMethod,
+    pub role: Role,
+}
+
+impl Identity {
+    /// Caller when authentication is disabled; everything is allowed
+    pub fn anonymous() -> Self {
+        Identity {
+            name: "anonymous".to_string(),
+            method: AuthMethod::None,
+            role: Role::Admin,
+        }
+    }
+}
+
+impl FromRequest for Identity {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        let identity = req.extensions().get::<Identity>().cloned();
+        ready(Ok(identity.unwrap_or_else(Identity::anonymous)))
+    }
+}
+
+/// Form posted by the login page
+#[derive(Deserialize)]
+pub struct LoginForm {
+    pub username: String,
+    pub password: String,
+    /// Page to return to after logging in
+    pub next: Option<String>,
+}
@@ -0,0 +1,70 @@
+// Route permission policy
+
+use actix_web::http::Method;
+
+use crate::auth::models::Role;
+
+/// Minimum role needed to call `method` on `path`
+///
+/// Reads are open to viewers. Mutating calls in the system, docker, nginx,
+/// alerts and notifications groups need an operator; any other mutating
+/// route defaults to admin so new endpoints are locked down until classified.
+/// The audit trail is admin-only, including reads.
+pub fn required_role(method: &Method, path: &str) -> Role {
+    if path == "/api/audit" || path.starts_with("/api/audit/") {
+        return Role::Admin;
+    }
+
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Role::Viewer;
+    }
+
+    const OPERATOR_PREFIXES: &[&str] = &[
+        "/api/processes/",
+        "/api/docker/",
+        "/api/nginx/",
+        "/api/alerts/",
+        "/api/notifications/",
+    ];
+
+    match path {
+        // Formatting only echoes the submitted text back
+        "/api/nginx/format" => Role::Viewer,
+        _ if OPERATOR_PREFIXES.iter().any(|prefix| path.starts_with(prefix)) => Role::Operator,
+        _ => Role::Admin,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_need_viewer() {
+        assert_eq!(required_role(&Method::GET, "/api/processes"), Role::Viewer);
+        assert_eq!(required_role(&Method::GET, "/api/docker/containers/abc/logs"), Role::Viewer);
+        assert_eq!(required_role(&Method::GET, "/api/nginx/proxies"), Role::Viewer);
+        assert_eq!(required_role(&Method::POST, "/api/nginx/format"), Role::Viewer);
+    }
+
+    #[test]
+    fn test_mutations_need_operator() {
+        assert_eq!(required_role(&Method::DELETE, "/api/processes/42"), Role::Operator);
+        assert_eq!(required_role(&Method::DELETE, "/api/docker/containers/abc"), Role::Operator);
+        assert_eq!(required_role(&Method::POST, "/api/docker/containers/abc/restart"), Role::Operator);
+        assert_eq!(required_role(&Method::POST, "/api/nginx/proxies"), Role::Operator);
+        assert_eq!(required_role(&Method::PUT, "/api/nginx/proxies/site"), Role::Operator);
+        assert_eq!(required_role(&Method::POST, "/api/alerts/rules"), Role::Operator);
+    }
+
+    #[test]
+    fn test_unclassified_mutations_need_admin() {
+        assert_eq!(required_role(&Method::POST, "/api/something-new"), Role::Admin);
+    }
+
+    #[test]
+    fn test_audit_trail_needs_admin() {
+        assert_eq!(required_role(&Method::GET, "/api/audit"), Role::Admin);
+        assert_eq!(required_role(&Method::HEAD, "/api/audit"), Role::Admin);
+    }
+}
@@ -0,0 +1,12 @@
+use actix_web::web;
+
+use crate::auth::handlers;
+
+/// Configure login, logout and identity routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(handlers::login_page)
+        .service(handlers::login)
+        .service(handlers::logout)
+        .service(handlers::current_identity);
+}
@@ -0,0 +1,277 @@
+// Token verification and session management
+
+use std::collections::HashMap;
+use std::sync::{Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use rand::Rng;
+use sha2::{Digest, Sha256};
+
+use crate::auth::models::{AuthMethod, Identity, Role};
+use crate::config::AuthConfig;
+
+/// Name of the dashboard session cookie
+pub const SESSION_COOKIE: &str = "ura_session";
+
+/// Checked for unknown usernames so they take as long to reject as a wrong password
+const DUMMY_HASH: &str = "$2b$12$L8XqRI2zGO4vlNFSI96dEeYqWWnVimoyDH.rcbnNluJNNcS4UbYgW";
+
+struct Session {
+    username: String,
+    role: Role,
+    expires_at: Instant,
+}
+
+/// Configured tokens and logins; replaced as a whole when the configuration is reloaded
+struct Credentials {
+    enabled: bool,
+    /// Token name, role and SHA-256 digest
+    tokens: Vec<(String, Role, [u8; 32])>,
+    /// Username to bcrypt hash and role
+    users: HashMap<String, (String, Role)>,
+    session_ttl: Duration,
+    secure_cookie: bool,
+}
+
+impl Credentials {
+    fn from_config(config: &AuthConfig) -> Result<Self, String> {
+        let tokens = config
+            .api_tokens
+            .iter()
+            .map(|token| {
+                let mut digest = [0u8; 32];
+                hex::decode_to_slice(&token.sha256, &mut digest)
+                    .map_err(|e| format!("Invalid digest for API token '{}': {}", token.name, e))?;
+                Ok((token.name.clone(), token.role, digest))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Credentials {
+            enabled: config.enabled,
+            tokens,
+            users: config
+                .users
+                .iter()
+                .map(|u| (u.username.clone(), (u.password_hash.clone(), u.role)))
+                .collect(),
+            session_ttl: Duration::from_secs(config.session_ttl_secs),
+            secure_cookie: config.secure_cookie,
+        })
+    }
+}
+
+/// Verifies API tokens and passwords and keeps dashboard sessions in memory
+pub struct AuthService {
+    credentials: RwLock<Credentials>,
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+impl AuthService {
+    pub fn from_config(config: &AuthConfig) -> Result<Self, String> {
+        Ok(AuthService {
+            credentials: RwLock::new(Credentials::from_config(config)?),
+            sessions: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// A service that lets every request through
+    pub fn disabled() -> Self {
+        AuthService {
+            credentials: RwLock::new(Credentials {
+                enabled: false,
+                tokens: Vec::new(),
+                users: HashMap::new(),
+                session_ttl: Duration::ZERO,
+                secure_cookie: false,
+            }),
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Swap in reloaded tokens and logins
+    ///
+    /// Sessions of users that were removed are closed; remaining sessions
+    /// pick up the user's new role.
+    pub fn reload(&self, config: &AuthConfig) -> Result<(), String> {
+        let credentials = Credentials::from_config(config)?;
+        self.sessions.lock().unwrap().retain(|_, session| {
+            match credentials.users.get(&session.username) {
+                Some((_, role)) => {
+                    session.role = *role;
+                    true
+                }
+                None => false,
+            }
+        });
+        *self.credentials.write().unwrap() = credentials;
+        Ok(())
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.credentials.read().unwrap().enabled
+    }
+
+    /// Whether any token or login is configured
+    pub fn has_credentials(&self) -> bool {
+        let credentials = self.credentials.read().unwrap();
+        !credentials.tokens.is_empty() || !credentials.users.is_empty()
+    }
+
+    pub fn session_ttl(&self) -> Duration {
+        self.credentials.read().unwrap().session_ttl
+    }
+
+    pub fn secure_cookie(&self) -> bool {
+        self.credentials.read().unwrap().secure_cookie
+    }
+
+    /// Look up the API token matching a presented bearer token
+    pub fn authenticate_token(&self, token: &str) -> Option<Identity> {
+        let digest: [u8; 32] = Sha256::digest(token.as_bytes()).into();
+        self.credentials
+            .read()
+            .unwrap()
+            .tokens
+            .iter()
+            .find(|(_, _, expected)| *expected == digest)
+            .map(|(name, role, _)| Identity {
+                name: name.clone(),
+                method: AuthMethod::Token,
+                role: *role,
+            })
+    }
+
+    /// Check a username and password and open a session, returning its id
+    ///
+    /// bcrypt is deliberately slow, so call this from a blocking context.
+    pub fn login(&self, username: &str, password: &str) -> Option<String> {
+        // Verify without holding the lock: bcrypt takes a while
+        let user = self.credentials.read().unwrap().users.get(username).cloned();
+        let Some((hash, role)) = user else {
+            let _ = bcrypt::verify(password, DUMMY_HASH);
+            return None;
+        };
+        if !bcrypt::verify(password, &hash).unwrap_or(false) {
+            return None;
+        }
+
+        let session_id = hex::encode(rand::rng().random::<[u8; 32]>());
+        let now = Instant::now();
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|_, s| s.expires_at > now);
+        sessions.insert(
+            session_id.clone(),
+            Session {
+                username: username.to_string(),
+                role,
+                expires_at: now + self.session_ttl(),
+            },
+        );
+        Some(session_id)
+    }
+
+    /// Resolve a session cookie to its user
+    pub fn session(&self, session_id: &str) -> Option<Identity> {
+        let sessions = self.sessions.lock().unwrap();
+        sessions
+            .get(session_id)
+            .filter(|s| s.expires_at > Instant::now())
+            .map(|s| Identity {
+                name: s.username.clone(),
+                method: AuthMethod::Session,
+                role: s.role,
+            })
+    }
+
+    pub fn logout(&self, session_id: &str) {
+        self.sessions.lock().unwrap().remove(session_id);
+    }
+}
+
+/// Hex SHA-256 digest of an API token, as expected in `AUTH_API_TOKENS`
+pub fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ApiTokenConfig, AppConfig, UserConfig};
+
+    fn service() -> AuthService {
+        AuthService::from_config(&AuthConfig {
+            enabled: true,
+            api_tokens: vec![ApiTokenConfig {
+                name: "ci".to_string(),
+                role: Role::Viewer,
+                sha256: hash_token("s3cret-token"),
+            }],
+            users: vec![UserConfig {
+                username: "ops".to_string(),
+                role: Role::Operator,
+                password_hash: bcrypt::hash("hunter2", 4).unwrap(),
+            }],
+            session_ttl_secs: 3600,
+            secure_cookie: false,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn test_authenticate_token() {
+        let auth = service();
+        assert_eq!(auth.authenticate_token("s3cret-token").unwrap().name, "ci");
+        assert!(auth.authenticate_token("wrong").is_none());
+    }
+
+    #[test]
+    fn test_login_and_logout() {
+        let auth = service();
+        assert!(auth.login("ops", "wrong").is_none());
+        assert!(auth.login("nobody", "hunter2").is_none());
+        // Unknown users are checked against a real hash, so they cost as much as a wrong password
+        assert_eq!(bcrypt::verify("hunter2", DUMMY_HASH).ok(), Some(false));
+
+        let session = auth.login("ops", "hunter2").unwrap();
+        let identity = auth.session(&session).unwrap();
+        assert_eq!(identity.name, "ops");
+        assert_eq!(identity.method, AuthMethod::Session);
+        assert_eq!(identity.role, Role::Operator);
+
+        auth.logout(&session);
+        assert!(auth.session(&session).is_none());
+    }
+
+    #[test]
+    fn test_reload_replaces_credentials() {
+        let auth = service();
+        let ops = auth.login("ops", "hunter2").unwrap();
+
+        auth.reload(&AuthConfig {
+            enabled: true,
+            api_tokens: vec![ApiTokenConfig {
+                name: "deploy".to_string(),
+                role: Role::Operator,
+                sha256: hash_token("deploy-token"),
+            }],
+            users: vec![UserConfig {
+                username: "ops".to_string(),
+                role: Role::Admin,
+                password_hash: bcrypt::hash("hunter2", 4).unwrap(),
+            }],
+            session_ttl_secs: 60,
+            secure_cookie: true,
+        })
+        .unwrap();
+
+        assert!(auth.authenticate_token("s3cret-token").is_none());
+        assert_eq!(auth.authenticate_token("deploy-token").unwrap().role, Role::Operator);
+        assert_eq!(auth.session(&ops).unwrap().role, Role::Admin);
+        assert_eq!(auth.session_ttl(), Duration::from_secs(60));
+        assert!(auth.secure_cookie());
+
+        // Removing the user ends their sessions
+        auth.reload(&AuthConfig { users: Vec::new(), ..AppConfig::with_defaults().auth }).unwrap();
+        assert!(auth.session(&ops).is_none());
+    }
+}
@@ -1,82 +1,796 @@
 //! Configuration management module
 //!
 //! This module provides centralized configuration management for the application.
-//! Configuration can be loaded from environment variables or use sensible defaults.
+//! Configuration is read from a file, then environment variables, then sensible defaults.
+//!
+//! # Configuration File
+//!
+//! `/etc/ubuntu-resource-api/config.toml` is read if it exists; `CONFIG_FILE` points
+//! elsewhere (`.yaml`/`.yml` files are parsed as YAML). Its sections are `server`,
+//! `nginx`, `docker`, `retention`, `alerts`, `notifications`, `auth`, `audit` and `acme`, and
+//! each key stands in for one of the environment variables below (see `FILE_KEYS`).
+//! A value in the file wins over the environment variable; unknown keys are errors.
+//!
+//! ```toml
+//! [server]
+//! bind_address = "127.0.0.1:8080"
+//!
+//! [retention]
+//! sample_interval = "5s"
+//! history_resolutions = "5s:1h,5m:7d"
+//!
+//! [auth]
+//! api_tokens = ["deploy:operator:<sha256-hex>"]
+//! session_ttl = "8h"
+//! ```
 //!
 //! # Environment Variables
 //!
 //! - `SERVER_BIND_ADDRESS`: Server bind address and port (default: `0.0.0.0:8080`)
 //! - `NGINX_SITES_AVAILABLE`: Nginx sites-available directory (default: `/etc/nginx/sites-available`)
 //! - `NGINX_SITES_ENABLED`: Nginx sites-enabled directory (default: `/etc/nginx/sites-enabled`)
+//! - `NGINX_UPSTREAMS_DIR`: Directory for upstream pool files, included in `http` (default: `/etc/nginx/conf.d`)
+//! - `NGINX_REVISIONS_DIR`: Revision history of site configs (default: `/var/lib/ubuntu-resource-api/nginx-revisions`)
+//! - `NGINX_MAIN_CONFIG`: Main nginx config, copied for dry-run checks (default: `/etc/nginx/nginx.conf`)
+//! - `NGINX_CONTROLLER`: How nginx is reloaded: `systemd`, `signal` (`nginx -s reload`) or `fake` (default: `systemd`)
 //! - `DOCKER_SOCKET_PATH`: Docker socket path (default: `unix:///var/run/docker.sock`)
+//! - `METRICS_SAMPLE_INTERVAL`: Background sampler interval (default: `1s`)
+//! - `METRICS_HISTORY_RESOLUTIONS`: History ring buffers as `step:retention` pairs (default: `1s:10m,1m:24h`)
+//! - `ALERTS_EVALUATION_INTERVAL`: How often alert rules are evaluated (default: `10s`)
+//! - `ALERTS_RULES_PATH`: JSON file holding alert rules (default: `/etc/ubuntu-resource-api/alert_rules.json`)
+//! - `NOTIFY_WEBHOOK_URLS`: Comma-separated webhook URLs for alert notifications
+//! - `NOTIFY_SMTP_HOST`: SMTP server for alert emails; enables the email channel
+//! - `NOTIFY_SMTP_PORT`: SMTP port (default: `587`)
+//! - `NOTIFY_SMTP_TLS`: `starttls`, `tls` or `none` (default: `starttls`)
+//! - `NOTIFY_SMTP_USERNAME` / `NOTIFY_SMTP_PASSWORD`: SMTP credentials (optional)
+//! - `NOTIFY_SMTP_FROM`: Sender address (required with `NOTIFY_SMTP_HOST`)
+//! - `NOTIFY_SMTP_TO`: Comma-separated recipients (required with `NOTIFY_SMTP_HOST`)
+//! - `NOTIFY_MAX_RETRIES`: Delivery retries per channel (default: `3`)
+//! - `NOTIFY_RATE_LIMIT`: Maximum notifications per minute (default: `20`)
+//! - `NOTIFY_DEDUP_WINDOW`: Suppress repeats of the same alert state within this window (default: `15m`)
+//! - `AUTH_ENABLED`: Require authentication (default: `true`)
+//! - `AUTH_API_TOKENS`: Comma-separated `name[:role]:sha256-hex` API token digests
+//! - `AUTH_USERS`: Comma-separated `username[:role]:bcrypt-hash` dashboard logins.
+//!   Roles are `viewer`, `operator` and `admin`; entries without a role are viewers.
+//! - `AUTH_SESSION_TTL`: Dashboard session lifetime (default: `12h`)
+//! - `AUTH_SECURE_COOKIE`: Mark the session cookie `Secure` when served over HTTPS (default: `false`)
+//! - `AUDIT_LOG_PATH`: JSONL audit trail of mutating actions (default: `/var/log/ubuntu-resource-api/audit.jsonl`)
+//! - `AUDIT_MAX_SIZE_MB`: Rotate the audit log once it reaches this size (default: `10`)
+//! - `AUDIT_MAX_FILES`: Rotated audit files to keep (default: `5`)
+//! - `ACME_DIRECTORY_URL`: ACME directory certificates are issued from (default: Let's Encrypt)
+//! - `ACME_EMAIL`: Contact address registered with the ACME account (optional)
+//! - `ACME_CA_FILE`: Extra PEM root trusted when talking to the ACME server, e.g. Pebble's test CA (optional)
+//! - `ACME_RENEW_BEFORE_DAYS`: Renew certificates this many days before they expire (default: `30`)
+//! - `ACME_CHECK_INTERVAL`: How often certificates are checked for renewal (default: `12h`)
 //!
 //! # Example
 //!
 //! ```
 //! use ubuntu_resource_api::config::AppConfig;
 //!
-//! // Load from environment variables with fallback to defaults
-//! let config = AppConfig::from_env()
+//! // Load the configuration file and environment variables, falling back to defaults
+//! let config = AppConfig::load()
 //!     .unwrap_or_else(|_| AppConfig::with_defaults());
 //!
 //! println!("Server will bind to: {}", config.server.bind_address);
 //! ```
 
+use std::collections::{BTreeMap, HashMap};
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::auth::models::Role;
+use crate::error::AppError;
+use crate::utils::parse_duration_secs;
 
 /// Main application configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AppConfig {
     pub server: ServerConfig,
     pub nginx: NginxConfig,
     pub docker: DockerConfig,
+    pub retention: RetentionConfig,
+    pub alerts: AlertsConfig,
+    pub notifications: NotificationsConfig,
+    pub auth: AuthConfig,
+    pub audit: AuditConfig,
+    pub acme: AcmeConfig,
 }
 
 /// Server configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct ServerConfig {
     pub bind_address: String,
 }
 
 /// Nginx configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct NginxConfig {
     pub sites_available_path: String,
     pub sites_enabled_path: String,
+    /// Directory included in nginx's `http` context where upstream pools are written
+    pub upstreams_path: String,
+    /// Directory holding the numbered revisions of each site config
+    pub revisions_path: String,
+    /// `nginx.conf`; its directory is copied for dry-run `nginx -t` checks
+    pub main_config_path: String,
+    /// How nginx is tested and reloaded
+    pub controller: NginxControllerKind,
+}
+
+/// Selects the [`NginxController`](crate::nginx::controller::NginxController) implementation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NginxControllerKind {
+    /// `nginx -t` and `systemctl reload nginx`
+    Systemd,
+    /// `nginx -t` and `nginx -s reload`, for containers without systemd
+    Signal,
+    /// Runs nothing and accepts every config; for development without nginx
+    Fake,
+}
+
+impl NginxControllerKind {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "systemd" => Ok(NginxControllerKind::Systemd),
+            "signal" => Ok(NginxControllerKind::Signal),
+            "fake" => Ok(NginxControllerKind::Fake),
+            _ => Err(format!("Invalid nginx controller '{}': use systemd, signal or fake", value)),
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            NginxControllerKind::Systemd => "systemd",
+            NginxControllerKind::Signal => "signal",
+            NginxControllerKind::Fake => "fake",
+        }
+    }
 }
 
 /// Docker configuration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct DockerConfig {
     pub socket_path: String,
 }
 
+/// Alerting configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlertsConfig {
+    /// How often rules are evaluated, in seconds
+    pub evaluation_interval_secs: u64,
+    /// JSON file the rules are loaded from and saved to
+    pub rules_path: String,
+}
+
+/// Audit log configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditConfig {
+    pub log_path: String,
+    /// Size at which the current file is rotated
+    pub max_size_bytes: u64,
+    /// Number of rotated files kept next to the current one
+    pub max_files: usize,
+}
+
+/// ACME certificate issuance configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AcmeConfig {
+    pub directory_url: String,
+    pub email: Option<String>,
+    /// PEM root certificate trusted in addition to the system roots
+    pub ca_file: Option<String>,
+    pub renew_before_days: u32,
+    /// How often certificates are checked for renewal, in seconds
+    pub check_interval_secs: u64,
+}
+
+/// Authentication configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuthConfig {
+    pub enabled: bool,
+    pub api_tokens: Vec<ApiTokenConfig>,
+    pub users: Vec<UserConfig>,
+    pub session_ttl_secs: u64,
+    pub secure_cookie: bool,
+}
+
+/// A static API token, stored as the hex SHA-256 digest of the token
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiTokenConfig {
+    pub name: String,
+    pub role: Role,
+    pub sha256: String,
+}
+
+/// A dashboard login with a bcrypt password hash
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserConfig {
+    pub username: String,
+    pub role: Role,
+    pub password_hash: String,
+}
+
+impl AuthConfig {
+    /// Parse `name[:role]:sha256-hex` token entries
+    pub fn parse_api_tokens(value: &str) -> Result<Vec<ApiTokenConfig>, String> {
+        split_list(value)
+            .into_iter()
+            .map(|entry| {
+                let (name, role, digest) = split_credential(&entry)
+                    .ok_or_else(|| format!("Invalid API token entry '{}': expected name[:role]:sha256", entry))?;
+                if digest.len() != 64 || !digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                    return Err(format!("API token '{}' must be a hex SHA-256 digest", name));
+                }
+                Ok(ApiTokenConfig {
+                    name: name.to_string(),
+                    role: parse_role(name, role)?,
+                    sha256: digest.to_lowercase(),
+                })
+            })
+            .collect()
+    }
+
+    /// Parse `username[:role]:bcrypt-hash` entries
+    pub fn parse_users(value: &str) -> Result<Vec<UserConfig>, String> {
+        split_list(value)
+            .into_iter()
+            .map(|entry| {
+                let (username, role, hash) = split_credential(&entry)
+                    .ok_or_else(|| format!("Invalid user entry '{}': expected username[:role]:bcrypt-hash", entry))?;
+                if !hash.starts_with("$2") {
+                    return Err(format!("Password for user '{}' must be a bcrypt hash", username));
+                }
+                Ok(UserConfig {
+                    username: username.to_string(),
+                    role: parse_role(username, role)?,
+                    password_hash: hash.to_string(),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Split `name[:role]:secret`; the secret (a hex digest or bcrypt hash) never contains `:`
+fn split_credential(entry: &str) -> Option<(&str, Option<&str>, &str)> {
+    let (rest, secret) = entry.rsplit_once(':')?;
+    match rest.split_once(':') {
+        Some((name, role)) => Some((name, Some(role), secret)),
+        None => Some((rest, None, secret)),
+    }
+}
+
+fn parse_role(name: &str, role: Option<&str>) -> Result<Role, String> {
+    match role {
+        None => Ok(Role::Viewer),
+        Some(role) => Role::parse(role)
+            .ok_or_else(|| format!("Invalid role '{}' for '{}': use viewer, operator or admin", role, name)),
+    }
+}
+
+/// Parse a boolean environment value
+fn parse_bool(name: &str, value: &str) -> Result<bool, String> {
+    match value.to_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Ok(true),
+        "0" | "false" | "no" | "off" => Ok(false),
+        _ => Err(format!("Invalid {} '{}': expected true or false", name, value)),
+    }
+}
+
+/// Alert notification configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct NotificationsConfig {
+    pub webhook_urls: Vec<String>,
+    pub smtp: Option<SmtpConfig>,
+    /// Retries after the first failed attempt, per channel
+    pub max_retries: u32,
+    /// Notifications allowed per minute across all channels
+    pub rate_limit_per_minute: u32,
+    /// Identical alert notifications within this window are sent once
+    pub dedup_window_secs: u64,
+}
+
+/// SMTP email channel configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub tls: SmtpTls,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub from: String,
+    pub to: Vec<String>,
+}
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SmtpTls {
+    None,
+    StartTls,
+    Tls,
+}
+
+impl SmtpTls {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value.to_lowercase().as_str() {
+            "none" => Ok(SmtpTls::None),
+            "starttls" => Ok(SmtpTls::StartTls),
+            "tls" => Ok(SmtpTls::Tls),
+            _ => Err(format!("Invalid SMTP TLS mode '{}': use starttls, tls or none", value)),
+        }
+    }
+}
+
+/// Split a comma-separated list, dropping empty entries
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Metrics history retention configuration
+#[derive(Debug, Clone, PartialEq)]
+pub struct RetentionConfig {
+    /// How often the background sampler collects metrics, in seconds
+    pub sample_interval_secs: u64,
+    /// Ring buffer resolutions, finest first
+    pub resolutions: Vec<HistoryResolution>,
+}
+
+/// A single history ring buffer: one point every `step_secs`, kept for `retention_secs`
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryResolution {
+    pub step_secs: u64,
+    pub retention_secs: u64,
+}
+
+/// Most points one history resolution may keep in memory
+pub const MAX_HISTORY_POINTS: u64 = 100_000;
+
+impl RetentionConfig {
+    /// Parse a resolution list such as `1s:10m,1m:24h`
+    ///
+    /// Resolutions are sorted finest first. Every step must be a non-zero
+    /// multiple of the sample interval and shorter than its retention, and no
+    /// resolution may keep more than [`MAX_HISTORY_POINTS`] points.
+    pub fn parse_resolutions(value: &str, sample_interval_secs: u64) -> Result<Vec<HistoryResolution>, String> {
+        let mut resolutions = Vec::new();
+
+        for part in value.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+            let (step, retention) = part
+                .split_once(':')
+                .ok_or_else(|| format!("Invalid history resolution '{}': expected step:retention", part))?;
+            let step_secs = parse_duration_secs(step)?;
+            let retention_secs = parse_duration_secs(retention)?;
+
+            if step_secs == 0 {
+                return Err(format!("History step '{}' must be at least 1s", step));
+            }
+            if step_secs % sample_interval_secs != 0 {
+                return Err(format!(
+                    "History step '{}' must be a multiple of the sample interval ({}s)",
+                    step, sample_interval_secs
+                ));
+            }
+            if retention_secs < step_secs {
+                return Err(format!("History retention '{}' is shorter than its step '{}'", retention, step));
+            }
+            if retention_secs / step_secs > MAX_HISTORY_POINTS {
+                return Err(format!(
+                    "History resolution '{}' keeps more than {} points; use a longer step or a shorter retention",
+                    part, MAX_HISTORY_POINTS
+                ));
+            }
+
+            resolutions.push(HistoryResolution { step_secs, retention_secs });
+        }
+
+        if resolutions.is_empty() {
+            return Err("At least one history resolution is required".to_string());
+        }
+
+        resolutions.sort_by_key(|r| r.step_secs);
+        Ok(resolutions)
+    }
+}
+
+/// Default location of the configuration file
+pub const DEFAULT_CONFIG_PATH: &str = "/etc/ubuntu-resource-api/config.toml";
+
+/// Configuration file keys as `(section, key, environment variable)`
+///
+/// Every file setting stands in for the environment variable of the same
+/// meaning and goes through the same parsing and validation.
+const FILE_KEYS: &[(&str, &str, &str)] = &[
+    ("server", "bind_address", "SERVER_BIND_ADDRESS"),
+    ("nginx", "sites_available", "NGINX_SITES_AVAILABLE"),
+    ("nginx", "sites_enabled", "NGINX_SITES_ENABLED"),
+    ("nginx", "upstreams_dir", "NGINX_UPSTREAMS_DIR"),
+    ("nginx", "revisions_dir", "NGINX_REVISIONS_DIR"),
+    ("nginx", "main_config", "NGINX_MAIN_CONFIG"),
+    ("nginx", "controller", "NGINX_CONTROLLER"),
+    ("docker", "socket_path", "DOCKER_SOCKET_PATH"),
+    ("retention", "sample_interval", "METRICS_SAMPLE_INTERVAL"),
+    ("retention", "history_resolutions", "METRICS_HISTORY_RESOLUTIONS"),
+    ("alerts", "evaluation_interval", "ALERTS_EVALUATION_INTERVAL"),
+    ("alerts", "rules_path", "ALERTS_RULES_PATH"),
+    ("notifications", "webhook_urls", "NOTIFY_WEBHOOK_URLS"),
+    ("notifications", "smtp_host", "NOTIFY_SMTP_HOST"),
+    ("notifications", "smtp_port", "NOTIFY_SMTP_PORT"),
+    ("notifications", "smtp_tls", "NOTIFY_SMTP_TLS"),
+    ("notifications", "smtp_username", "NOTIFY_SMTP_USERNAME"),
+    ("notifications", "smtp_password", "NOTIFY_SMTP_PASSWORD"),
+    ("notifications", "smtp_from", "NOTIFY_SMTP_FROM"),
+    ("notifications", "smtp_to", "NOTIFY_SMTP_TO"),
+    ("notifications", "max_retries", "NOTIFY_MAX_RETRIES"),
+    ("notifications", "rate_limit", "NOTIFY_RATE_LIMIT"),
+    ("notifications", "dedup_window", "NOTIFY_DEDUP_WINDOW"),
+    ("auth", "enabled", "AUTH_ENABLED"),
+    ("auth", "api_tokens", "AUTH_API_TOKENS"),
+    ("auth", "users", "AUTH_USERS"),
+    ("auth", "session_ttl", "AUTH_SESSION_TTL"),
+    ("auth", "secure_cookie", "AUTH_SECURE_COOKIE"),
+    ("audit", "log_path", "AUDIT_LOG_PATH"),
+    ("audit", "max_size_mb", "AUDIT_MAX_SIZE_MB"),
+    ("audit", "max_files", "AUDIT_MAX_FILES"),
+    ("acme", "directory_url", "ACME_DIRECTORY_URL"),
+    ("acme", "email", "ACME_EMAIL"),
+    ("acme", "ca_file", "ACME_CA_FILE"),
+    ("acme", "renew_before_days", "ACME_RENEW_BEFORE_DAYS"),
+    ("acme", "check_interval", "ACME_CHECK_INTERVAL"),
+];
+
+/// A scalar or list value in the configuration file
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum FileValue {
+    Bool(bool),
+    Integer(i64),
+    Text(String),
+    List(Vec<String>),
+}
+
+impl FileValue {
+    /// The value in the form the matching environment variable would hold
+    fn into_setting(self) -> String {
+        match self {
+            FileValue::Bool(value) => value.to_string(),
+            FileValue::Integer(value) => value.to_string(),
+            FileValue::Text(value) => value,
+            FileValue::List(values) => values.join(","),
+        }
+    }
+}
+
+/// Settings read from a configuration file, keyed by environment variable name
+#[derive(Default)]
+struct FileSettings {
+    values: HashMap<&'static str, String>,
+}
+
+impl FileSettings {
+    fn parse(path: &Path, content: &str) -> Result<Self, String> {
+        let is_yaml = matches!(path.extension().and_then(|e| e.to_str()), Some("yaml" | "yml"));
+        let sections: BTreeMap<String, BTreeMap<String, FileValue>> = if is_yaml {
+            serde_yaml::from_str(content).map_err(|e| e.to_string())?
+        } else {
+            toml::from_str(content).map_err(|e| e.to_string())?
+        };
+
+        let mut values = HashMap::new();
+        for (section, entries) in sections {
+            for (key, value) in entries {
+                let (_, _, name) = FILE_KEYS
+                    .iter()
+                    .find(|(s, k, _)| *s == section && *k == key)
+                    .ok_or_else(|| format!("Unknown setting '{}.{}'", section, key))?;
+                values.insert(*name, value.into_setting());
+            }
+        }
+        Ok(FileSettings { values })
+    }
+
+    /// The file value if set, otherwise the environment variable
+    fn var(&self, name: &str) -> Result<String, env::VarError> {
+        match self.values.get(name) {
+            Some(value) => Ok(value.clone()),
+            None => env::var(name),
+        }
+    }
+}
+
 impl AppConfig {
     /// Load configuration from environment variables
     /// Returns an error if required variables are missing or invalid
     pub fn from_env() -> Result<Self, String> {
+        Self::from_settings(&FileSettings::default())
+    }
+
+    /// Load the configuration file at `CONFIG_FILE`, or the default path if it exists,
+    /// with environment variables and defaults filling in what it leaves out
+    pub fn load() -> Result<Self, AppError> {
+        match Self::config_path() {
+            Some(path) => Self::from_file(&path),
+            None => Self::from_env().map_err(AppError::ConfigError),
+        }
+    }
+
+    /// The configuration file to read, if any
+    ///
+    /// An explicit `CONFIG_FILE` is returned even if it does not exist so that
+    /// a typo is reported instead of silently ignored.
+    pub fn config_path() -> Option<PathBuf> {
+        match env::var("CONFIG_FILE") {
+            Ok(path) => Some(PathBuf::from(path)),
+            Err(_) => Some(PathBuf::from(DEFAULT_CONFIG_PATH)).filter(|path| path.exists()),
+        }
+    }
+
+    /// Load a TOML (or `.yaml`/`.yml`) configuration file
+    ///
+    /// Values in the file take precedence over environment variables, which
+    /// take precedence over the defaults.
+    pub fn from_file(path: &Path) -> Result<Self, AppError> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| AppError::ConfigError(format!("Failed to read {}: {}", path.display(), e)))?;
+        let settings = FileSettings::parse(path, &content)
+            .map_err(|e| AppError::ConfigError(format!("{}: {}", path.display(), e)))?;
+        Self::from_settings(&settings).map_err(AppError::ConfigError)
+    }
+
+    fn from_settings(settings: &FileSettings) -> Result<Self, String> {
         let server = ServerConfig {
-            bind_address: env::var("SERVER_BIND_ADDRESS")
+            bind_address: settings.var("SERVER_BIND_ADDRESS")
                 .unwrap_or_else(|_| Self::default_bind_address()),
         };
 
         let nginx = NginxConfig {
-            sites_available_path: env::var("NGINX_SITES_AVAILABLE")
+            sites_available_path: settings.var("NGINX_SITES_AVAILABLE")
                 .unwrap_or_else(|_| Self::default_nginx_sites_available()),
-            sites_enabled_path: env::var("NGINX_SITES_ENABLED")
+            sites_enabled_path: settings.var("NGINX_SITES_ENABLED")
                 .unwrap_or_else(|_| Self::default_nginx_sites_enabled()),
+            upstreams_path: settings.var("NGINX_UPSTREAMS_DIR")
+                .unwrap_or_else(|_| Self::default_nginx_upstreams()),
+            revisions_path: settings.var("NGINX_REVISIONS_DIR")
+                .unwrap_or_else(|_| Self::default_nginx_revisions()),
+            main_config_path: settings.var("NGINX_MAIN_CONFIG")
+                .unwrap_or_else(|_| Self::default_nginx_main_config()),
+            controller: match settings.var("NGINX_CONTROLLER") {
+                Ok(value) => NginxControllerKind::parse(&value)?,
+                Err(_) => NginxControllerKind::Systemd,
+            },
         };
 
         let docker = DockerConfig {
-            socket_path: env::var("DOCKER_SOCKET_PATH")
+            socket_path: settings.var("DOCKER_SOCKET_PATH")
                 .unwrap_or_else(|_| Self::default_docker_socket()),
         };
 
-        Ok(AppConfig {
+        let sample_interval_secs = match settings.var("METRICS_SAMPLE_INTERVAL") {
+            Ok(value) => parse_duration_secs(&value)?,
+            Err(_) => Self::default_sample_interval(),
+        };
+        if sample_interval_secs == 0 {
+            return Err("METRICS_SAMPLE_INTERVAL must be at least 1s".to_string());
+        }
+
+        let retention = RetentionConfig {
+            sample_interval_secs,
+            resolutions: RetentionConfig::parse_resolutions(
+                &settings.var("METRICS_HISTORY_RESOLUTIONS")
+                    .unwrap_or_else(|_| Self::default_history_resolutions()),
+                sample_interval_secs,
+            )?,
+        };
+
+        let evaluation_interval_secs = match settings.var("ALERTS_EVALUATION_INTERVAL") {
+            Ok(value) => parse_duration_secs(&value)?,
+            Err(_) => Self::default_alerts_evaluation_interval(),
+        };
+        if evaluation_interval_secs == 0 {
+            return Err("ALERTS_EVALUATION_INTERVAL must be at least 1s".to_string());
+        }
+
+        let alerts = AlertsConfig {
+            evaluation_interval_secs,
+            rules_path: settings.var("ALERTS_RULES_PATH")
+                .unwrap_or_else(|_| Self::default_alerts_rules_path()),
+        };
+
+        let smtp = match settings.var("NOTIFY_SMTP_HOST") {
+            Ok(host) => {
+                let port = match settings.var("NOTIFY_SMTP_PORT") {
+                    Ok(value) => value
+                        .parse()
+                        .map_err(|_| format!("Invalid NOTIFY_SMTP_PORT '{}'", value))?,
+                    Err(_) => Self::default_smtp_port(),
+                };
+                let tls = match settings.var("NOTIFY_SMTP_TLS") {
+                    Ok(value) => SmtpTls::parse(&value)?,
+                    Err(_) => SmtpTls::StartTls,
+                };
+                let from = settings.var("NOTIFY_SMTP_FROM")
+                    .map_err(|_| "NOTIFY_SMTP_FROM is required when NOTIFY_SMTP_HOST is set".to_string())?;
+                let to = split_list(&settings.var("NOTIFY_SMTP_TO").unwrap_or_default());
+                if to.is_empty() {
+                    return Err("NOTIFY_SMTP_TO is required when NOTIFY_SMTP_HOST is set".to_string());
+                }
+
+                Some(SmtpConfig {
+                    host,
+                    port,
+                    tls,
+                    username: settings.var("NOTIFY_SMTP_USERNAME").ok(),
+                    password: settings.var("NOTIFY_SMTP_PASSWORD").ok(),
+                    from,
+                    to,
+                })
+            }
+            Err(_) => None,
+        };
+
+        let notifications = NotificationsConfig {
+            webhook_urls: split_list(&settings.var("NOTIFY_WEBHOOK_URLS").unwrap_or_default()),
+            smtp,
+            max_retries: match settings.var("NOTIFY_MAX_RETRIES") {
+                Ok(value) => value
+                    .parse()
+                    .map_err(|_| format!("Invalid NOTIFY_MAX_RETRIES '{}'", value))?,
+                Err(_) => Self::default_notify_max_retries(),
+            },
+            rate_limit_per_minute: match settings.var("NOTIFY_RATE_LIMIT") {
+                Ok(value) => value
+                    .parse()
+                    .map_err(|_| format!("Invalid NOTIFY_RATE_LIMIT '{}'", value))?,
+                Err(_) => Self::default_notify_rate_limit(),
+            },
+            dedup_window_secs: match settings.var("NOTIFY_DEDUP_WINDOW") {
+                Ok(value) => parse_duration_secs(&value)?,
+                Err(_) => Self::default_notify_dedup_window(),
+            },
+        };
+
+        let auth = AuthConfig {
+            enabled: match settings.var("AUTH_ENABLED") {
+                Ok(value) => parse_bool("AUTH_ENABLED", &value)?,
+                Err(_) => true,
+            },
+            api_tokens: AuthConfig::parse_api_tokens(&settings.var("AUTH_API_TOKENS").unwrap_or_default())?,
+            users: AuthConfig::parse_users(&settings.var("AUTH_USERS").unwrap_or_default())?,
+            session_ttl_secs: match settings.var("AUTH_SESSION_TTL") {
+                Ok(value) => parse_duration_secs(&value)?,
+                Err(_) => Self::default_session_ttl(),
+            },
+            secure_cookie: match settings.var("AUTH_SECURE_COOKIE") {
+                Ok(value) => parse_bool("AUTH_SECURE_COOKIE", &value)?,
+                Err(_) => false,
+            },
+        };
+
+        let audit = AuditConfig {
+            log_path: settings.var("AUDIT_LOG_PATH").unwrap_or_else(|_| Self::default_audit_log_path()),
+            max_size_bytes: match settings.var("AUDIT_MAX_SIZE_MB") {
+                Ok(value) => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|mb| *mb > 0)
+                    .and_then(|mb| mb.checked_mul(1024 * 1024))
+                    .ok_or_else(|| format!("Invalid AUDIT_MAX_SIZE_MB '{}'", value))?,
+                Err(_) => Self::default_audit_max_size(),
+            },
+            max_files: match settings.var("AUDIT_MAX_FILES") {
+                Ok(value) => value
+                    .parse()
+                    .map_err(|_| format!("Invalid AUDIT_MAX_FILES '{}'", value))?,
+                Err(_) => Self::default_audit_max_files(),
+            },
+        };
+
+        let acme = AcmeConfig {
+            directory_url: settings.var("ACME_DIRECTORY_URL").unwrap_or_else(|_| Self::default_acme_directory_url()),
+            email: settings.var("ACME_EMAIL").ok().filter(|email| !email.is_empty()),
+            ca_file: settings.var("ACME_CA_FILE").ok().filter(|path| !path.is_empty()),
+            renew_before_days: match settings.var("ACME_RENEW_BEFORE_DAYS") {
+                Ok(value) => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|days| *days > 0)
+                    .ok_or_else(|| format!("Invalid ACME_RENEW_BEFORE_DAYS '{}'", value))?,
+                Err(_) => Self::default_acme_renew_before_days(),
+            },
+            check_interval_secs: match settings.var("ACME_CHECK_INTERVAL") {
+                Ok(value) => parse_duration_secs(&value)?,
+                Err(_) => Self::default_acme_check_interval(),
+            },
+        };
+        if acme.check_interval_secs == 0 {
+            return Err("ACME_CHECK_INTERVAL must be at least 1s".to_string());
+        }
+
+        let config = AppConfig {
             server,
             nginx,
             docker,
-        })
+            retention,
+            alerts,
+            notifications,
+            auth,
+            audit,
+            acme,
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Reject values that parse but cannot work
+    fn validate(&self) -> Result<(), String> {
+        let port = self.server.bind_address.rsplit_once(':').map(|(_, port)| port.parse::<u16>());
+        if !matches!(port, Some(Ok(_))) {
+            return Err(format!(
+                "Invalid server bind address '{}': expected host:port",
+                self.server.bind_address
+            ));
+        }
+
+        for path in [
+            &self.nginx.sites_available_path,
+            &self.nginx.sites_enabled_path,
+            &self.nginx.upstreams_path,
+            &self.nginx.revisions_path,
+            &self.nginx.main_config_path,
+        ] {
+            if !Path::new(path).is_absolute() {
+                return Err(format!("Nginx path '{}' must be an absolute path", path));
+            }
+        }
+
+        if self.alerts.rules_path.trim().is_empty() {
+            return Err("Alert rules path must not be empty".to_string());
+        }
+        if self.audit.log_path.trim().is_empty() {
+            return Err("Audit log path must not be empty".to_string());
+        }
+        if !self.acme.directory_url.starts_with("https://") && !self.acme.directory_url.starts_with("http://") {
+            return Err(format!("Invalid ACME directory URL '{}'", self.acme.directory_url));
+        }
+
+        Ok(())
+    }
+
+    /// Sections that differ from `other` and only take effect after a restart
+    ///
+    /// Nginx paths and authentication are applied on reload; everything else is
+    /// wired into listeners and background tasks at startup.
+    pub fn restart_required(&self, other: &AppConfig) -> Vec<&'static str> {
+        let mut sections = Vec::new();
+        if self.server != other.server {
+            sections.push("server");
+        }
+        if self.docker != other.docker {
+            sections.push("docker");
+        }
+        if self.retention != other.retention {
+            sections.push("retention");
+        }
+        if self.alerts != other.alerts {
+            sections.push("alerts");
+        }
+        if self.notifications != other.notifications {
+            sections.push("notifications");
+        }
+        if self.audit != other.audit {
+            sections.push("audit");
+        }
+        if self.acme != other.acme {
+            sections.push("acme");
+        }
+        sections
     }
 
     /// Create configuration with default values
@@ -88,10 +802,52 @@ impl AppConfig {
             nginx: NginxConfig {
                 sites_available_path: Self::default_nginx_sites_available(),
                 sites_enabled_path: Self::default_nginx_sites_enabled(),
+                upstreams_path: Self::default_nginx_upstreams(),
+                revisions_path: Self::default_nginx_revisions(),
+                main_config_path: Self::default_nginx_main_config(),
+                controller: NginxControllerKind::Systemd,
             },
             docker: DockerConfig {
                 socket_path: Self::default_docker_socket(),
             },
+            retention: RetentionConfig {
+                sample_interval_secs: Self::default_sample_interval(),
+                resolutions: RetentionConfig::parse_resolutions(
+                    &Self::default_history_resolutions(),
+                    Self::default_sample_interval(),
+                )
+                .expect("default history resolutions are valid"),
+            },
+            alerts: AlertsConfig {
+                evaluation_interval_secs: Self::default_alerts_evaluation_interval(),
+                rules_path: Self::default_alerts_rules_path(),
+            },
+            notifications: NotificationsConfig {
+                webhook_urls: Vec::new(),
+                smtp: None,
+                max_retries: Self::default_notify_max_retries(),
+                rate_limit_per_minute: Self::default_notify_rate_limit(),
+                dedup_window_secs: Self::default_notify_dedup_window(),
+            },
+            auth: AuthConfig {
+                enabled: true,
+                api_tokens: Vec::new(),
+                users: Vec::new(),
+                session_ttl_secs: Self::default_session_ttl(),
+                secure_cookie: false,
+            },
+            audit: AuditConfig {
+                log_path: Self::default_audit_log_path(),
+                max_size_bytes: Self::default_audit_max_size(),
+                max_files: Self::default_audit_max_files(),
+            },
+            acme: AcmeConfig {
+                directory_url: Self::default_acme_directory_url(),
+                email: None,
+                ca_file: None,
+                renew_before_days: Self::default_acme_renew_before_days(),
+                check_interval_secs: Self::default_acme_check_interval(),
+            },
         }
     }
 
@@ -108,9 +864,81 @@ impl AppConfig {
         "/etc/nginx/sites-enabled".to_string()
     }
 
+    fn default_nginx_upstreams() -> String {
+        "/etc/nginx/conf.d".to_string()
+    }
+
+    fn default_nginx_revisions() -> String {
+        "/var/lib/ubuntu-resource-api/nginx-revisions".to_string()
+    }
+
+    fn default_nginx_main_config() -> String {
+        "/etc/nginx/nginx.conf".to_string()
+    }
+
     fn default_docker_socket() -> String {
         "unix:///var/run/docker.sock".to_string()
     }
+
+    fn default_sample_interval() -> u64 {
+        1
+    }
+
+    fn default_history_resolutions() -> String {
+        "1s:10m,1m:24h".to_string()
+    }
+
+    fn default_alerts_evaluation_interval() -> u64 {
+        10
+    }
+
+    fn default_alerts_rules_path() -> String {
+        "/etc/ubuntu-resource-api/alert_rules.json".to_string()
+    }
+
+    fn default_smtp_port() -> u16 {
+        587
+    }
+
+    fn default_notify_max_retries() -> u32 {
+        3
+    }
+
+    fn default_notify_rate_limit() -> u32 {
+        20
+    }
+
+    fn default_notify_dedup_window() -> u64 {
+        900
+    }
+
+    fn default_session_ttl() -> u64 {
+        12 * 3600
+    }
+
+    fn default_audit_log_path() -> String {
+        "/var/log/ubuntu-resource-api/audit.jsonl".to_string()
+    }
+
+    fn default_audit_max_size() -> u64 {
+        10 * 1024 * 1024
+    }
+
+    fn default_audit_max_files() -> usize {
+        5
+    }
+
+    fn default_acme_directory_url() -> String {
+        "https://acme-v02.api.letsencrypt.org/directory".to_string()
+    }
+
+    fn default_acme_renew_before_days() -> u32 {
+        30
+    }
+
+    fn default_acme_check_interval() -> u64 {
+        12 * 3600
+    }
 }
 
 #[cfg(test)]
@@ -128,7 +956,218 @@ mod tests {
         assert_eq!(config.server.bind_address, "0.0.0.0:8080");
         assert_eq!(config.nginx.sites_available_path, "/etc/nginx/sites-available");
         assert_eq!(config.nginx.sites_enabled_path, "/etc/nginx/sites-enabled");
+        assert_eq!(config.nginx.upstreams_path, "/etc/nginx/conf.d");
+        assert_eq!(config.nginx.revisions_path, "/var/lib/ubuntu-resource-api/nginx-revisions");
+        assert_eq!(config.nginx.main_config_path, "/etc/nginx/nginx.conf");
+        assert_eq!(config.nginx.controller, NginxControllerKind::Systemd);
         assert_eq!(config.docker.socket_path, "unix:///var/run/docker.sock");
+        assert_eq!(config.retention.sample_interval_secs, 1);
+        assert_eq!(
+            config.retention.resolutions,
+            vec![
+                HistoryResolution { step_secs: 1, retention_secs: 600 },
+                HistoryResolution { step_secs: 60, retention_secs: 86_400 },
+            ]
+        );
+        assert_eq!(config.alerts.evaluation_interval_secs, 10);
+        assert_eq!(config.alerts.rules_path, "/etc/ubuntu-resource-api/alert_rules.json");
+        assert!(config.notifications.webhook_urls.is_empty());
+        assert!(config.notifications.smtp.is_none());
+        assert_eq!(config.notifications.dedup_window_secs, 900);
+        assert!(config.auth.enabled);
+        assert!(config.auth.api_tokens.is_empty());
+        assert_eq!(config.audit.max_size_bytes, 10 * 1024 * 1024);
+        assert_eq!(config.audit.max_files, 5);
+        assert_eq!(config.acme.renew_before_days, 30);
+        assert_eq!(config.acme.email, None);
+    }
+
+    #[test]
+    fn test_parse_auth_entries() {
+        let digest = "A".repeat(64);
+        let tokens = AuthConfig::parse_api_tokens(&format!("ci:{}", digest)).unwrap();
+        assert_eq!(
+            tokens,
+            vec![ApiTokenConfig { name: "ci".to_string(), role: Role::Viewer, sha256: "a".repeat(64) }]
+        );
+        let tokens = AuthConfig::parse_api_tokens(&format!("deploy:operator:{}", digest)).unwrap();
+        assert_eq!(tokens[0].role, Role::Operator);
+        assert!(AuthConfig::parse_api_tokens("ci:secret").is_err());
+        assert!(AuthConfig::parse_api_tokens("ci").is_err());
+        assert!(AuthConfig::parse_api_tokens(&format!("ci:root:{}", digest)).is_err());
+
+        let users = AuthConfig::parse_users("ops:$2y$05$abcdefghijklmnopqrstuu").unwrap();
+        assert_eq!(users[0].username, "ops");
+        assert_eq!(users[0].role, Role::Viewer);
+        let users = AuthConfig::parse_users("root:admin:$2y$05$abcdefghijklmnopqrstuu").unwrap();
+        assert_eq!(users[0].role, Role::Admin);
+        assert!(AuthConfig::parse_users("ops:plaintext").is_err());
+    }
+
+    #[test]
+    fn test_smtp_from_env() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+
+        env::set_var("NOTIFY_SMTP_HOST", "mail.example.com");
+        env::set_var("NOTIFY_SMTP_TLS", "tls");
+        env::set_var("NOTIFY_SMTP_FROM", "monitor@example.com");
+        env::remove_var("NOTIFY_SMTP_TO");
+        assert!(AppConfig::from_env().is_err());
+
+        env::set_var("NOTIFY_SMTP_TO", "ops@example.com, oncall@example.com");
+        let smtp = AppConfig::from_env().unwrap().notifications.smtp.unwrap();
+        assert_eq!(smtp.port, 587);
+        assert_eq!(smtp.tls, SmtpTls::Tls);
+        assert_eq!(smtp.to, vec!["ops@example.com", "oncall@example.com"]);
+
+        env::remove_var("NOTIFY_SMTP_HOST");
+        env::remove_var("NOTIFY_SMTP_TLS");
+        env::remove_var("NOTIFY_SMTP_FROM");
+        env::remove_var("NOTIFY_SMTP_TO");
+    }
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("ura-config-{}-{}", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_from_file_takes_precedence_over_env() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        env::set_var("SERVER_BIND_ADDRESS", "127.0.0.1:9000");
+        env::set_var("NGINX_SITES_ENABLED", "/custom/nginx/enabled");
+
+        let digest = "a".repeat(64);
+        let path = write_config(
+            "precedence.toml",
+            &format!(
+                r#"
+[server]
+bind_address = "127.0.0.1:7000"
+
+[retention]
+sample_interval = 5
+history_resolutions = "5s:1h"
+
+[auth]
+api_tokens = ["deploy:operator:{}"]
+secure_cookie = true
+"#,
+                digest
+            ),
+        );
+        let config = AppConfig::from_file(&path).unwrap();
+
+        assert_eq!(config.server.bind_address, "127.0.0.1:7000");
+        assert_eq!(config.nginx.sites_enabled_path, "/custom/nginx/enabled");
+        assert_eq!(config.nginx.sites_available_path, "/etc/nginx/sites-available");
+        assert_eq!(config.retention.sample_interval_secs, 5);
+        assert_eq!(config.auth.api_tokens[0].role, Role::Operator);
+        assert!(config.auth.secure_cookie);
+
+        env::remove_var("SERVER_BIND_ADDRESS");
+        env::remove_var("NGINX_SITES_ENABLED");
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_from_yaml_file() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        let path = write_config(
+            "config.yaml",
+            "docker:\n  socket_path: tcp://localhost:2375\nalerts:\n  evaluation_interval: 30s\nnginx:\n  controller: signal\n",
+        );
+        let config = AppConfig::from_file(&path).unwrap();
+
+        assert_eq!(config.docker.socket_path, "tcp://localhost:2375");
+        assert_eq!(config.alerts.evaluation_interval_secs, 30);
+        assert_eq!(config.nginx.controller, NginxControllerKind::Signal);
+        let _ = fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_from_file_rejects_invalid_values() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        let cases = [
+            ("unknown.toml", "[server]\nport = 8080\n"),
+            ("section.toml", "[metrics]\nsample_interval = \"1s\"\n"),
+            ("count.toml", "[audit]\nmax_files = \"many\"\n"),
+            ("bind.toml", "[server]\nbind_address = \"localhost\"\n"),
+            ("relative.toml", "[nginx]\nsites_available = \"nginx/sites\"\n"),
+            ("controller.toml", "[nginx]\ncontroller = \"openrc\"\n"),
+            ("syntax.toml", "[server\n"),
+        ];
+
+        for (name, content) in cases {
+            let path = write_config(name, content);
+            let result = AppConfig::from_file(&path);
+            let _ = fs::remove_file(&path);
+            assert!(matches!(result, Err(AppError::ConfigError(_))), "{} should be rejected", name);
+        }
+
+        let missing = env::temp_dir().join("ura-config-missing.toml");
+        assert!(matches!(AppConfig::from_file(&missing), Err(AppError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_audit_max_size_must_fit_in_bytes() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        let path = write_config("audit-size.toml", "[audit]\nmax_size_mb = 99999999999999\n");
+        let result = AppConfig::from_file(&path);
+        let _ = fs::remove_file(&path);
+        let Err(AppError::ConfigError(message)) = result else {
+            panic!("an overflowing AUDIT_MAX_SIZE_MB should be rejected");
+        };
+        assert!(message.contains("Invalid AUDIT_MAX_SIZE_MB"), "{}", message);
+    }
+
+    #[test]
+    fn test_acme_check_interval_must_be_positive() {
+        let _lock = TEST_MUTEX.lock().unwrap();
+        for value in ["0", "0s"] {
+            let path = write_config("acme-interval.toml", &format!("[acme]\ncheck_interval = \"{}\"\n", value));
+            let result = AppConfig::from_file(&path);
+            let _ = fs::remove_file(&path);
+            let Err(AppError::ConfigError(message)) = result else {
+                panic!("ACME_CHECK_INTERVAL = {} should be rejected", value);
+            };
+            assert!(message.contains("ACME_CHECK_INTERVAL must be at least 1s"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn test_restart_required() {
+        let config = AppConfig::with_defaults();
+        let mut reloaded = config.clone();
+        reloaded.nginx.sites_enabled_path = "/srv/nginx/enabled".to_string();
+        reloaded.nginx.controller = NginxControllerKind::Signal;
+        reloaded.auth.session_ttl_secs = 60;
+        assert!(config.restart_required(&reloaded).is_empty());
+
+        reloaded.server.bind_address = "127.0.0.1:9000".to_string();
+        reloaded.retention.sample_interval_secs = 5;
+        assert_eq!(config.restart_required(&reloaded), vec!["server", "retention"]);
+    }
+
+    #[test]
+    fn test_parse_resolutions() {
+        let resolutions = RetentionConfig::parse_resolutions("5m:7d, 10s:1h", 5).unwrap();
+        assert_eq!(
+            resolutions,
+            vec![
+                HistoryResolution { step_secs: 10, retention_secs: 3_600 },
+                HistoryResolution { step_secs: 300, retention_secs: 604_800 },
+            ]
+        );
+
+        assert!(RetentionConfig::parse_resolutions("", 1).is_err());
+        assert!(RetentionConfig::parse_resolutions("1m", 1).is_err());
+        assert!(RetentionConfig::parse_resolutions("7s:1h", 5).is_err());
+        assert!(RetentionConfig::parse_resolutions("1h:1m", 1).is_err());
+        assert!(RetentionConfig::parse_resolutions("0s:10m", 1).is_err());
+        assert!(RetentionConfig::parse_resolutions("1s:36500d", 1).is_err());
+        assert!(RetentionConfig::parse_resolutions("1s:24h", 1).is_ok());
     }
 
     #[test]
@@ -0,0 +1,92 @@
+// Conversions from Docker API responses into API models
+//
+// Shared by the HTTP handlers and the Prometheus exporter.
+
+use bollard::container::{ListContainersOptions, StatsOptions};
+use bollard::Docker;
+use futures_util::stream::TryStreamExt;
+
+use crate::docker::models::DockerContainer;
+
+/// List all containers, including memory statistics for running ones
+pub async fn collect_containers(docker: &Docker) -> Result<Vec<DockerContainer>, bollard::errors::Error> {
+    let options = Some(ListContainersOptions::<String> {
+        all: true,
+        ..Default::default()
+    });
+
+    let containers = docker.list_containers(options).await?;
+    let mut result: Vec<DockerContainer> = Vec::new();
+
+    for c in containers.iter() {
+        let name = c
+            .names
+            .as_ref()
+            .and_then(|n| n.first())
+            .map(|s| s.trim_start_matches('/').to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+
+        let ports = c
+            .ports
+            .as_ref()
+            .map(|p| {
+                p.iter()
+                    .filter_map(|port| match (port.public_port, port.private_port) {
+                        (Some(pub_port), priv_port) => {
+                            Some(format!("{}:{}", pub_port, priv_port))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })
+            .unwrap_or_default();
+
+        let container_id = c.id.as_ref().unwrap_or(&String::new()).clone();
+        let state = c.state.as_ref().unwrap_or(&String::new()).clone();
+
+        // Fetch memory stats for running containers
+        let (memory_usage, memory_limit, memory_percent) = if state == "running" {
+            match docker
+                .stats(
+                    &container_id,
+                    Some(StatsOptions {
+                        stream: false,
+                        one_shot: true,
+                    }),
+                )
+                .try_next()
+                .await
+            {
+                Ok(Some(stats)) => {
+                    let mem_usage = stats.memory_stats.usage.unwrap_or(0);
+                    let mem_limit = stats.memory_stats.limit.unwrap_or(0);
+                    let mem_percent = if mem_limit > 0 {
+                        (mem_usage as f64 / mem_limit as f64) * 100.0
+                    } else {
+                        0.0
+                    };
+                    (Some(mem_usage), Some(mem_limit), Some(mem_percent))
+                }
+                _ => (None, None, None),
+            }
+        } else {
+            (None, None, None)
+        };
+
+        result.push(DockerContainer {
+            id: container_id,
+            name,
+            image: c.image.as_ref().unwrap_or(&String::new()).clone(),
+            state,
+            status: c.status.as_ref().unwrap_or(&String::new()).clone(),
+            ports,
+            created: c.created.unwrap_or(0),
+            memory_usage,
+            memory_limit,
+            memory_percent,
+        });
+    }
+
+    Ok(result)
+}
@@ -1,16 +1,17 @@
 // Docker management HTTP handlers
 
 use actix_web::{delete, get, post, web, HttpResponse, Responder};
-use bollard::container::{
-    ListContainersOptions, LogsOptions, RemoveContainerOptions, StatsOptions, StopContainerOptions,
-};
+use bollard::container::{LogsOptions, RemoveContainerOptions, StopContainerOptions};
 use bollard::image::ListImagesOptions;
 use bollard::network::ListNetworksOptions;
 use bollard::volume::ListVolumesOptions;
-use futures_util::stream::{StreamExt, TryStreamExt};
+use futures_util::stream::StreamExt;
 use log::{error, info};
+use serde_json::json;
 
+use crate::auth::models::Identity;
 use crate::docker::client::get_docker_client;
+use crate::docker::collectors::collect_containers;
 use crate::docker::models::{
     DockerContainer, DockerImage, DockerLogsResponse, DockerNetwork, DockerResponse, DockerVolume,
 };
@@ -39,85 +40,8 @@ pub async fn list_containers(data: web::Data<crate::system::models::AppState>) -
         }
     };
 
-    let options = Some(ListContainersOptions::<String> {
-        all: true,
-        ..Default::default()
-    });
-
-    match docker.list_containers(options).await {
-        Ok(containers) => {
-            let mut result: Vec<DockerContainer> = Vec::new();
-
-            for c in containers.iter() {
-                let name = c
-                    .names
-                    .as_ref()
-                    .and_then(|n| n.first())
-                    .map(|s| s.trim_start_matches('/').to_string())
-                    .unwrap_or_else(|| "unknown".to_string());
-
-                let ports = c
-                    .ports
-                    .as_ref()
-                    .map(|p| {
-                        p.iter()
-                            .filter_map(|port| match (port.public_port, port.private_port) {
-                                (Some(pub_port), priv_port) => {
-                                    Some(format!("{}:{}", pub_port, priv_port))
-                                }
-                                _ => None,
-                            })
-                            .collect::<Vec<_>>()
-                            .join(", ")
-                    })
-                    .unwrap_or_default();
-
-                let container_id = c.id.as_ref().unwrap_or(&String::new()).clone();
-                let state = c.state.as_ref().unwrap_or(&String::new()).clone();
-
-                // Fetch memory stats for running containers
-                let (memory_usage, memory_limit, memory_percent) = if state == "running" {
-                    match docker
-                        .stats(
-                            &container_id,
-                            Some(StatsOptions {
-                                stream: false,
-                                one_shot: true,
-                            }),
-                        )
-                        .try_next()
-                        .await
-                    {
-                        Ok(Some(stats)) => {
-                            let mem_usage = stats.memory_stats.usage.unwrap_or(0);
-                            let mem_limit = stats.memory_stats.limit.unwrap_or(0);
-                            let mem_percent = if mem_limit > 0 {
-                                (mem_usage as f64 / mem_limit as f64) * 100.0
-                            } else {
-                                0.0
-                            };
-                            (Some(mem_usage), Some(mem_limit), Some(mem_percent))
-                        }
-                        _ => (None, None, None),
-                    }
-                } else {
-                    (None, None, None)
-                };
-
-                result.push(DockerContainer {
-                    id: container_id,
-                    name,
-                    image: c.image.as_ref().unwrap_or(&String::new()).clone(),
-                    state,
-                    status: c.status.as_ref().unwrap_or(&String::new()).clone(),
-                    ports,
-                    created: c.created.unwrap_or(0),
-                    memory_usage,
-                    memory_limit,
-                    memory_percent,
-                });
-            }
-
+    match collect_containers(&docker).await {
+        Ok(result) => {
             info!("Found {} containers", result.len());
             HttpResponse::Ok().json(result)
         }
@@ -130,7 +54,18 @@ pub async fn list_containers(data: web::Data<crate::system::models::AppState>) -
 
 /// Start a Docker container by ID
 #[post("/api/docker/containers/{id}/start")]
-pub async fn start_container(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn start_container(
+    id: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = id.to_string();
+    let response = start_container_by_id(id, data.clone()).await;
+    data.audit.record(&identity, "docker", "container.start", &target, json!({"id": target}), response.status());
+    response
+}
+
+async fn start_container_by_id(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("POST /api/docker/containers/{}/start", id);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -148,7 +83,7 @@ pub async fn start_container(id: web::Path<String>, data: web::Data<crate::syste
             info!("Container {} started", id);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Container started successfully"),
+                message: "✅ Container started successfully".to_string(),
             })
         }
         Err(e) => {
@@ -163,7 +98,18 @@ pub async fn start_container(id: web::Path<String>, data: web::Data<crate::syste
 
 /// Stop a Docker container by ID
 #[post("/api/docker/containers/{id}/stop")]
-pub async fn stop_container(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn stop_container(
+    id: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = id.to_string();
+    let response = stop_container_by_id(id, data.clone()).await;
+    data.audit.record(&identity, "docker", "container.stop", &target, json!({"id": target}), response.status());
+    response
+}
+
+async fn stop_container_by_id(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("POST /api/docker/containers/{}/stop", id);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -183,7 +129,7 @@ pub async fn stop_container(id: web::Path<String>, data: web::Data<crate::system
             info!("Container {} stopped", id);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Container stopped successfully"),
+                message: "✅ Container stopped successfully".to_string(),
             })
         }
         Err(e) => {
@@ -198,7 +144,18 @@ pub async fn stop_container(id: web::Path<String>, data: web::Data<crate::system
 
 /// Restart a Docker container by ID
 #[post("/api/docker/containers/{id}/restart")]
-pub async fn restart_container(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn restart_container(
+    id: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = id.to_string();
+    let response = restart_container_by_id(id, data.clone()).await;
+    data.audit.record(&identity, "docker", "container.restart", &target, json!({"id": target}), response.status());
+    response
+}
+
+async fn restart_container_by_id(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("POST /api/docker/containers/{}/restart", id);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -216,7 +173,7 @@ pub async fn restart_container(id: web::Path<String>, data: web::Data<crate::sys
             info!("Container {} restarted", id);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Container restarted successfully"),
+                message: "✅ Container restarted successfully".to_string(),
             })
         }
         Err(e) => {
@@ -231,7 +188,18 @@ pub async fn restart_container(id: web::Path<String>, data: web::Data<crate::sys
 
 /// Remove a Docker container by ID
 #[delete("/api/docker/containers/{id}")]
-pub async fn remove_container(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn remove_container(
+    id: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = id.to_string();
+    let response = remove_container_by_id(id, data.clone()).await;
+    data.audit.record(&identity, "docker", "container.remove", &target, json!({"id": target, "force": true}), response.status());
+    response
+}
+
+async fn remove_container_by_id(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("DELETE /api/docker/containers/{}", id);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -254,7 +222,7 @@ pub async fn remove_container(id: web::Path<String>, data: web::Data<crate::syst
             info!("Container {} removed", id);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Container removed successfully"),
+                message: "✅ Container removed successfully".to_string(),
             })
         }
         Err(e) => {
@@ -339,7 +307,7 @@ pub async fn list_images(data: web::Data<crate::system::models::AppState>) -> im
                     };
 
                     let parts: Vec<&str> = repo_tag_str.split(':').collect();
-                    let repository = parts.get(0).unwrap_or(&"<none>").to_string();
+                    let repository = parts.first().unwrap_or(&"<none>").to_string();
                     let tag = parts.get(1).unwrap_or(&"<none>").to_string();
 
                     DockerImage {
@@ -364,7 +332,18 @@ pub async fn list_images(data: web::Data<crate::system::models::AppState>) -> im
 
 /// Remove a Docker image by ID
 #[delete("/api/docker/images/{id}")]
-pub async fn remove_image(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn remove_image(
+    id: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = id.to_string();
+    let response = remove_image_by_id(id, data.clone()).await;
+    data.audit.record(&identity, "docker", "image.remove", &target, json!({"id": target}), response.status());
+    response
+}
+
+async fn remove_image_by_id(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("DELETE /api/docker/images/{}", id);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -382,7 +361,7 @@ pub async fn remove_image(id: web::Path<String>, data: web::Data<crate::system::
             info!("Image {} removed", id);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Image removed successfully"),
+                message: "✅ Image removed successfully".to_string(),
             })
         }
         Err(e) => {
@@ -438,7 +417,18 @@ pub async fn list_volumes(data: web::Data<crate::system::models::AppState>) -> i
 
 /// Remove a Docker volume by name
 #[delete("/api/docker/volumes/{name}")]
-pub async fn remove_volume(name: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn remove_volume(
+    name: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = name.to_string();
+    let response = remove_volume_by_name(name, data.clone()).await;
+    data.audit.record(&identity, "docker", "volume.remove", &target, json!({"name": target}), response.status());
+    response
+}
+
+async fn remove_volume_by_name(name: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("DELETE /api/docker/volumes/{}", name);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -456,7 +446,7 @@ pub async fn remove_volume(name: web::Path<String>, data: web::Data<crate::syste
             info!("Volume {} removed", name);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Volume removed successfully"),
+                message: "✅ Volume removed successfully".to_string(),
             })
         }
         Err(e) => {
@@ -520,7 +510,18 @@ pub async fn list_networks(data: web::Data<crate::system::models::AppState>) ->
 
 /// Remove a Docker network by ID
 #[delete("/api/docker/networks/{id}")]
-pub async fn remove_network(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> impl Responder {
+pub async fn remove_network(
+    id: web::Path<String>,
+    data: web::Data<crate::system::models::AppState>,
+    identity: Identity,
+) -> impl Responder {
+    let target = id.to_string();
+    let response = remove_network_by_id(id, data.clone()).await;
+    data.audit.record(&identity, "docker", "network.remove", &target, json!({"id": target}), response.status());
+    response
+}
+
+async fn remove_network_by_id(id: web::Path<String>, data: web::Data<crate::system::models::AppState>) -> HttpResponse {
     info!("DELETE /api/docker/networks/{}", id);
 
     let docker = match get_docker_client(&data.docker_config.socket_path).await {
@@ -538,7 +539,7 @@ pub async fn remove_network(id: web::Path<String>, data: web::Data<crate::system
             info!("Network {} removed", id);
             HttpResponse::Ok().json(DockerResponse {
                 success: true,
-                message: format!("✅ Network removed successfully"),
+                message: "✅ Network removed successfully".to_string(),
             })
         }
         Err(e) => {
@@ -11,6 +11,7 @@
 //!
 //! - [`models`]: Data structures for Docker entities (containers, images, volumes, networks)
 //! - [`client`]: Docker client initialization and utilities
+//! - [`collectors`]: Conversions from Docker API responses into the models
 //! - [`handlers`]: HTTP request handlers for Docker management endpoints
 //! - [`routes`]: Route registration for Docker management API
 //!
@@ -34,5 +35,6 @@
 
 pub mod models;
 pub mod client;
+pub mod collectors;
 pub mod handlers;
 pub mod routes;
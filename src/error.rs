@@ -11,6 +11,9 @@
 //! - [`AppError::ConfigError`]: Configuration loading errors
 //! - [`AppError::NotFound`]: Resource not found errors (404)
 //! - [`AppError::ValidationError`]: Input validation errors (400)
+//! - [`AppError::Unauthorized`]: Missing or invalid credentials (401)
+//! - [`AppError::Forbidden`]: Authenticated caller lacks the required role (403)
+//! - [`AppError::Conflict`]: Request clashes with the current state of a resource (409)
 //!
 //! # Error Response Format
 //!
@@ -54,6 +57,12 @@ pub enum AppError {
     NotFound(String),
     /// Input validation errors
     ValidationError(String),
+    /// Missing or invalid credentials
+    Unauthorized(String),
+    /// Authenticated caller lacks the required role
+    Forbidden(String),
+    /// Request clashes with the current state of a resource
+    Conflict(String),
 }
 
 impl fmt::Display for AppError {
@@ -65,6 +74,9 @@ impl fmt::Display for AppError {
             AppError::ConfigError(msg) => write!(f, "Configuration Error: {}", msg),
             AppError::NotFound(msg) => write!(f, "Not Found: {}", msg),
             AppError::ValidationError(msg) => write!(f, "Validation Error: {}", msg),
+            AppError::Unauthorized(msg) => write!(f, "Unauthorized: {}", msg),
+            AppError::Forbidden(msg) => write!(f, "Forbidden: {}", msg),
+            AppError::Conflict(msg) => write!(f, "Conflict: {}", msg),
         }
     }
 }
@@ -96,6 +108,20 @@ impl ResponseError for AppError {
                 "error": "Configuration Error",
                 "message": msg
             })),
+            AppError::Unauthorized(msg) => HttpResponse::Unauthorized()
+                .insert_header(("WWW-Authenticate", "Bearer"))
+                .json(json!({
+                    "error": "Unauthorized",
+                    "message": msg
+                })),
+            AppError::Forbidden(msg) => HttpResponse::Forbidden().json(json!({
+                "error": "Forbidden",
+                "message": msg
+            })),
+            AppError::Conflict(msg) => HttpResponse::Conflict().json(json!({
+                "error": "Conflict",
+                "message": msg
+            })),
         }
     }
 
@@ -107,6 +133,9 @@ impl ResponseError for AppError {
             AppError::NginxError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::DockerError(_) => StatusCode::INTERNAL_SERVER_ERROR,
             AppError::ConfigError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            AppError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            AppError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AppError::Conflict(_) => StatusCode::CONFLICT,
         }
     }
 }
@@ -140,6 +169,18 @@ mod tests {
         // Test ConfigError returns 500
         let err = AppError::ConfigError("Config failure".to_string());
         assert_eq!(err.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        // Test Unauthorized returns 401
+        let err = AppError::Unauthorized("No credentials".to_string());
+        assert_eq!(err.status_code(), StatusCode::UNAUTHORIZED);
+
+        // Test Forbidden returns 403
+        let err = AppError::Forbidden("Operator role required".to_string());
+        assert_eq!(err.status_code(), StatusCode::FORBIDDEN);
+
+        // Test Conflict returns 409
+        let err = AppError::Conflict("Site is managed by hand".to_string());
+        assert_eq!(err.status_code(), StatusCode::CONFLICT);
     }
 
     #[test]
@@ -161,6 +202,15 @@ mod tests {
 
         let err = AppError::ConfigError("test config".to_string());
         assert_eq!(format!("{}", err), "Configuration Error: test config");
+
+        let err = AppError::Unauthorized("test auth".to_string());
+        assert_eq!(format!("{}", err), "Unauthorized: test auth");
+
+        let err = AppError::Forbidden("test role".to_string());
+        assert_eq!(format!("{}", err), "Forbidden: test role");
+
+        let err = AppError::Conflict("test conflict".to_string());
+        assert_eq!(format!("{}", err), "Conflict: test conflict");
     }
 
     #[test]
@@ -10,6 +10,11 @@
 //! - [`system`]: System monitoring (CPU, memory, disk, network, processes)
 //! - [`nginx`]: Nginx proxy management (CRUD operations, config generation)
 //! - [`docker`]: Docker management (containers, images, volumes, networks)
+//! - [`metrics`]: Background metrics sampling and time-series history
+//! - [`alerts`]: Threshold alert rules evaluated against sampled metrics
+//! - [`notifications`]: Webhook and email delivery of alert notifications
+//! - [`auth`]: API token and session authentication for all routes
+//! - [`audit`]: Persistent audit trail of mutating actions
 //! - [`config`]: Configuration management with environment variable support
 //! - [`error`]: Common error types and consistent error handling
 //! - [`utils`]: Shared utility functions
@@ -40,3 +45,8 @@ pub mod utils;
 pub mod system;
 pub mod nginx;
 pub mod docker;
+pub mod metrics;
+pub mod alerts;
+pub mod notifications;
+pub mod auth;
+pub mod audit;
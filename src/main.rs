@@ -1,9 +1,24 @@
 use actix_web::{web, App, HttpServer, middleware};
-use log::info;
+use tokio::signal::unix::{signal, SignalKind};
+use log::{info, warn};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use sysinfo::System;
 
 // Import modules from lib.rs
+use ubuntu_resource_api::alerts::engine::AlertEngine;
+use ubuntu_resource_api::alerts::evaluator::spawn_evaluator;
+use ubuntu_resource_api::audit::log::AuditLog;
+use ubuntu_resource_api::auth::service::AuthService;
 use ubuntu_resource_api::config::AppConfig;
+use ubuntu_resource_api::metrics::store::MetricsStore;
+use ubuntu_resource_api::nginx::acme::renewal::spawn_renewal;
+use ubuntu_resource_api::nginx::acme::AcmeClient;
+use ubuntu_resource_api::nginx::controller;
+use ubuntu_resource_api::metrics::sampler::spawn_sampler;
+use ubuntu_resource_api::metrics::stream::spawn_docker_poller;
+use ubuntu_resource_api::notifications::dispatcher::Notifier;
 use ubuntu_resource_api::system::models::AppState;
 
 #[actix_web::main]
@@ -11,17 +26,82 @@ async fn main() -> std::io::Result<()> {
     // Initialize logging
     env_logger::init_from_env(env_logger::Env::new().default_filter_or("info"));
     
-    // Load configuration
-    let config = AppConfig::from_env().unwrap_or_else(|_| AppConfig::with_defaults());
+    // Load configuration: file, then environment, then defaults
+    let config = AppConfig::load()
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?;
+    match AppConfig::config_path() {
+        Some(path) => info!("📄 Configuration file: {}", path.display()),
+        None => info!("📄 No configuration file; using environment variables and defaults"),
+    }
     let bind_addr = config.server.bind_address.clone();
 
+    // Start background metrics sampler
+    let metrics = Arc::new(MetricsStore::new(&config.retention));
+    spawn_sampler(metrics.clone(), Duration::from_secs(config.retention.sample_interval_secs))?;
+    spawn_docker_poller(metrics.clone(), config.docker.socket_path.clone(), Duration::from_secs(5));
+
+    // Set up authentication
+    let auth = Arc::new(
+        AuthService::from_config(&config.auth)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+    );
+    if !auth.is_enabled() {
+        warn!("⚠️  Authentication is disabled (AUTH_ENABLED=false); anyone who can reach the server has full access");
+    } else if !auth.has_credentials() {
+        warn!("⚠️  No AUTH_API_TOKENS or AUTH_USERS configured; every protected route will be rejected");
+    }
+
+    // Open the audit trail for mutating actions
+    let audit = Arc::new(AuditLog::new(&config.audit));
+
+    // Set up the ACME client for managed nginx certificates
+    let acme = Arc::new(
+        AcmeClient::new(&config.acme)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e.to_string()))?,
+    );
+
+    // Set up alert notification channels
+    let notifier = Arc::new(
+        Notifier::from_config(&config.notifications)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?,
+    );
+
+    // Load alert rules and start evaluating them
+    let alerts = Arc::new(
+        AlertEngine::load(Path::new(&config.alerts.rules_path))
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?,
+    );
+    spawn_evaluator(
+        alerts.clone(),
+        metrics.clone(),
+        notifier.clone(),
+        config.docker.socket_path.clone(),
+        Duration::from_secs(config.alerts.evaluation_interval_secs),
+    );
+
     // Create shared application state
     let app_state = web::Data::new(AppState {
         system: std::sync::Mutex::new(System::new_all()),
-        nginx_config: config.nginx.clone(),
+        nginx_config: RwLock::new(config.nginx.clone()),
+        nginx_controller: RwLock::new(controller::from_kind(config.nginx.controller)),
         docker_config: config.docker.clone(),
+        metrics,
+        nginx_lock: Default::default(),
+        htpasswd: Default::default(),
+        nginx_stats: Default::default(),
+        alerts,
+        notifier,
+        auth,
+        audit,
+        acme,
     });
 
+    // Issue missing certificates and renew expiring ones
+    spawn_renewal(app_state.clone(), Duration::from_secs(config.acme.check_interval_secs));
+
+    // Reload the configuration file on SIGHUP
+    spawn_config_reloader(app_state.clone(), config)?;
+
     info!("🚀 Ubuntu Resource API starting on http://{}", bind_addr);
     info!("📊 Dashboard: http://{}/dashboard", bind_addr);
     info!("🔄 Nginx Manager: http://{}/nginx", bind_addr);
@@ -30,13 +110,51 @@ async fn main() -> std::io::Result<()> {
     // Start HTTP server
     HttpServer::new(move || {
         App::new()
+            .wrap(middleware::from_fn(ubuntu_resource_api::auth::middleware::authenticate))
             .wrap(middleware::Logger::default())
             .app_data(app_state.clone())
+            .configure(ubuntu_resource_api::auth::routes::configure_routes)
             .configure(ubuntu_resource_api::system::routes::configure_routes)
             .configure(ubuntu_resource_api::nginx::routes::configure_routes)
             .configure(ubuntu_resource_api::docker::routes::configure_routes)
+            .configure(ubuntu_resource_api::metrics::routes::configure_routes)
+            .configure(ubuntu_resource_api::alerts::routes::configure_routes)
+            .configure(ubuntu_resource_api::notifications::routes::configure_routes)
+            .configure(ubuntu_resource_api::audit::routes::configure_routes)
     })
     .bind(bind_addr)?
     .run()
     .await
 }
+
+/// Re-read the configuration on every SIGHUP and apply what can change at runtime
+///
+/// An invalid file is logged and the running configuration is kept.
+fn spawn_config_reloader(state: web::Data<AppState>, startup: AppConfig) -> std::io::Result<()> {
+    let mut hangups = signal(SignalKind::hangup())?;
+
+    actix_web::rt::spawn(async move {
+        while hangups.recv().await.is_some() {
+            info!("🔄 SIGHUP received, reloading configuration");
+            let config = match AppConfig::load() {
+                Ok(config) => config,
+                Err(e) => {
+                    warn!("⚠️  Configuration reload failed, keeping the current settings: {}", e);
+                    continue;
+                }
+            };
+            if let Err(e) = state.reload(&config) {
+                warn!("⚠️  Configuration reload failed, keeping the current settings: {}", e);
+                continue;
+            }
+
+            // Compared with startup: those sections still run with their original values
+            let pending = startup.restart_required(&config);
+            if !pending.is_empty() {
+                warn!("⚠️  Changes to [{}] take effect after a restart", pending.join(", "));
+            }
+            info!("✅ Configuration reloaded (nginx paths and authentication applied)");
+        }
+    });
+    Ok(())
+}
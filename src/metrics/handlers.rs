@@ -0,0 +1,172 @@
+// Metrics history and exporter HTTP handlers
+
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{get, web, HttpResponse};
+use futures_util::stream;
+use log::{info, warn};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::docker::client::get_docker_client;
+use crate::docker::collectors::collect_containers;
+use crate::error::AppError;
+use crate::metrics::models::{HistoryQuery, HistoryResponse, MetricGroup, StreamQuery};
+use crate::metrics::prometheus::{self, PrometheusWriter};
+use crate::metrics::store::MetricsStore;
+use crate::metrics::stream::{StreamEvent, Topic};
+use crate::system::models::AppState;
+use crate::utils::parse_duration_secs;
+
+/// Default range when `from` is omitted
+const DEFAULT_RANGE_SECS: i64 = 600;
+
+/// Idle time after which a comment is sent to keep proxies from closing the stream
+const STREAM_KEEP_ALIVE: Duration = Duration::from_secs(15);
+
+/// Return sampled history for one metric group
+#[get("/api/history/{metric}")]
+pub async fn get_history(
+    data: web::Data<AppState>,
+    metric: web::Path<String>,
+    query: web::Query<HistoryQuery>,
+) -> Result<HttpResponse, AppError> {
+    info!("GET /api/history/{}", metric);
+
+    let group = MetricGroup::parse(&metric).ok_or_else(|| {
+        let known: Vec<&str> = MetricGroup::ALL.iter().map(|g| g.as_str()).collect();
+        AppError::NotFound(format!("Unknown metric '{}'. Available: {}", metric, known.join(", ")))
+    })?;
+
+    let to = query.to.unwrap_or_else(|| chrono::Utc::now().timestamp());
+    let from = query.from.unwrap_or(to - DEFAULT_RANGE_SECS);
+    if from > to {
+        return Err(AppError::ValidationError("'from' must not be later than 'to'".to_string()));
+    }
+
+    let step = match &query.step {
+        Some(step) => {
+            let secs = parse_duration_secs(step).map_err(AppError::ValidationError)?;
+            if secs == 0 {
+                return Err(AppError::ValidationError("'step' must be at least 1s".to_string()));
+            }
+            Some(secs)
+        }
+        None => None,
+    };
+
+    let (step_secs, points) = data.metrics.history.query(group, from, to, step);
+
+    Ok(HttpResponse::Ok().json(HistoryResponse {
+        metric: group.as_str().to_string(),
+        from,
+        to,
+        step_secs,
+        points,
+    }))
+}
+
+/// Expose host, Docker and nginx metrics in the Prometheus text format
+///
+/// Host metrics come from the sampler's latest snapshot and are omitted until
+/// the first sample has been taken.
+#[get("/metrics")]
+pub async fn prometheus_metrics(data: web::Data<AppState>) -> HttpResponse {
+    let mut writer = PrometheusWriter::new();
+
+    if let Some(snapshot) = data.metrics.latest() {
+        prometheus::render_host(&mut writer, &snapshot);
+    }
+
+    let containers = match get_docker_client(&data.docker_config.socket_path).await {
+        Ok(docker) => collect_containers(&docker)
+            .await
+            .map_err(|e| warn!("Failed to list containers for metrics: {}", e))
+            .ok(),
+        Err(e) => {
+            warn!("{}", e);
+            None
+        }
+    };
+    prometheus::render_docker(&mut writer, containers.as_deref());
+
+    prometheus::render_nginx(&mut writer, &data.nginx_stats);
+
+    HttpResponse::Ok()
+        .content_type(prometheus::CONTENT_TYPE)
+        .body(writer.finish())
+}
+
+/// Per-connection state of an event stream
+struct StreamState {
+    pending: VecDeque<Arc<StreamEvent>>,
+    receiver: broadcast::Receiver<Arc<StreamEvent>>,
+    topics: Vec<Topic>,
+    metrics: Arc<MetricsStore>,
+}
+
+/// Push live metric updates as Server-Sent Events
+///
+/// Each subscribed topic first receives a full snapshot, followed by changes only.
+#[get("/api/stream")]
+pub async fn stream_metrics(
+    data: web::Data<AppState>,
+    query: web::Query<StreamQuery>,
+) -> Result<HttpResponse, AppError> {
+    let topics = match &query.topics {
+        Some(names) => names
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| {
+                Topic::parse(name).ok_or_else(|| {
+                    let known: Vec<&str> = Topic::ALL.iter().map(|t| t.as_str()).collect();
+                    AppError::ValidationError(format!(
+                        "Unknown topic '{}'. Available: {}",
+                        name,
+                        known.join(", ")
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        None => Topic::ALL.to_vec(),
+    };
+    info!("GET /api/stream - Subscribing to {} topics", topics.len());
+
+    let (initial, receiver) = data.metrics.stream.subscribe(&topics);
+    let state = StreamState {
+        pending: initial.into_iter().map(Arc::new).collect(),
+        receiver,
+        topics,
+        metrics: data.metrics.clone(),
+    };
+
+    let events = stream::unfold(state, |mut state| async move {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Some((Ok::<_, actix_web::Error>(web::Bytes::from(event.to_sse())), state));
+            }
+
+            match tokio::time::timeout(STREAM_KEEP_ALIVE, state.receiver.recv()).await {
+                Err(_) => return Some((Ok(web::Bytes::from_static(b": keep-alive\n\n")), state)),
+                Ok(Ok(event)) => {
+                    if state.topics.iter().any(|t| t.as_str() == event.topic()) {
+                        state.pending.push_back(event);
+                    }
+                }
+                // Missed some diffs: start this client over from full snapshots
+                Ok(Err(RecvError::Lagged(_))) => {
+                    let snapshots = state.metrics.stream.snapshot(&state.topics);
+                    state.pending.extend(snapshots.into_iter().map(Arc::new));
+                }
+                Ok(Err(RecvError::Closed)) => return None,
+            }
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(events))
+}
@@ -0,0 +1,285 @@
+// In-memory time-series history backed by fixed-size ring buffers
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::RwLock;
+
+use crate::config::{HistoryResolution, RetentionConfig};
+use crate::metrics::models::{HistoryPoint, MetricGroup};
+
+/// Running sums for the bucket currently being filled
+struct Bucket {
+    start: i64,
+    sums: BTreeMap<String, (f64, u32)>,
+}
+
+impl Bucket {
+    fn to_point(&self) -> HistoryPoint {
+        HistoryPoint {
+            timestamp: self.start,
+            values: self
+                .sums
+                .iter()
+                .map(|(key, (sum, count))| (key.clone(), sum / *count as f64))
+                .collect(),
+        }
+    }
+}
+
+/// A ring buffer holding one averaged point per `step_secs`
+pub struct RingBuffer {
+    step_secs: u64,
+    retention_secs: u64,
+    capacity: usize,
+    points: VecDeque<HistoryPoint>,
+    pending: Option<Bucket>,
+}
+
+impl RingBuffer {
+    pub fn new(resolution: &HistoryResolution) -> Self {
+        let capacity = (resolution.retention_secs / resolution.step_secs).max(1) as usize;
+        RingBuffer {
+            step_secs: resolution.step_secs,
+            retention_secs: resolution.retention_secs,
+            capacity,
+            points: VecDeque::with_capacity(capacity),
+            pending: None,
+        }
+    }
+
+    pub fn step_secs(&self) -> u64 {
+        self.step_secs
+    }
+
+    /// Add a raw sample, closing the current bucket when the sample falls into a new one
+    pub fn push(&mut self, timestamp: i64, values: &BTreeMap<String, f64>) {
+        let start = timestamp - timestamp.rem_euclid(self.step_secs as i64);
+
+        if self.pending.as_ref().is_some_and(|b| b.start != start) {
+            if let Some(bucket) = self.pending.take() {
+                if self.points.len() == self.capacity {
+                    self.points.pop_front();
+                }
+                self.points.push_back(bucket.to_point());
+            }
+        }
+
+        let bucket = self.pending.get_or_insert_with(|| Bucket {
+            start,
+            sums: BTreeMap::new(),
+        });
+        for (key, value) in values {
+            let entry = bucket.sums.entry(key.clone()).or_insert((0.0, 0));
+            entry.0 += value;
+            entry.1 += 1;
+        }
+    }
+
+    /// Whether this buffer still holds data as old as `from`, given the newest sample time
+    fn covers(&self, from: i64, newest: i64) -> bool {
+        newest - self.retention_secs as i64 <= from
+    }
+
+    /// Closed points plus the partially filled bucket within `[from, to]`
+    fn points_between(&self, from: i64, to: i64) -> Vec<HistoryPoint> {
+        self.points
+            .iter()
+            .cloned()
+            .chain(self.pending.as_ref().map(Bucket::to_point))
+            .filter(|p| p.timestamp >= from && p.timestamp <= to)
+            .collect()
+    }
+}
+
+/// Thread-safe set of ring buffers at increasing resolutions
+pub struct MetricsHistory {
+    rings: RwLock<Vec<RingBuffer>>,
+    newest: RwLock<Option<i64>>,
+}
+
+impl MetricsHistory {
+    pub fn new(config: &RetentionConfig) -> Self {
+        MetricsHistory {
+            rings: RwLock::new(config.resolutions.iter().map(RingBuffer::new).collect()),
+            newest: RwLock::new(None),
+        }
+    }
+
+    /// Record one raw sample into every ring buffer
+    pub fn record(&self, timestamp: i64, values: &BTreeMap<String, f64>) {
+        let mut rings = self.rings.write().unwrap();
+        for ring in rings.iter_mut() {
+            ring.push(timestamp, values);
+        }
+        *self.newest.write().unwrap() = Some(timestamp);
+    }
+
+    /// Timestamp of the most recent sample, if any
+    pub fn newest(&self) -> Option<i64> {
+        *self.newest.read().unwrap()
+    }
+
+    /// Return points for one metric group in `[from, to]`
+    ///
+    /// Picks the finest ring buffer that still covers `from` and whose step does not
+    /// exceed the requested one, then averages points into `step` sized buckets.
+    /// Returns the effective step together with the points.
+    pub fn query(&self, group: MetricGroup, from: i64, to: i64, step: Option<u64>) -> (u64, Vec<HistoryPoint>) {
+        let rings = self.rings.read().unwrap();
+        let newest = self.newest().unwrap_or(to);
+
+        let fits_step = |ring: &&RingBuffer| step.is_none_or(|s| ring.step_secs <= s);
+        let ring = rings
+            .iter()
+            .filter(fits_step)
+            .find(|r| r.covers(from, newest))
+            .or_else(|| rings.iter().find(|r| r.covers(from, newest)))
+            .or_else(|| rings.last());
+
+        let Some(ring) = ring else {
+            return (step.unwrap_or(0), Vec::new());
+        };
+
+        let effective_step = step.unwrap_or(ring.step_secs).max(ring.step_secs);
+        let prefix = format!("{}.", group.as_str());
+
+        let points = ring
+            .points_between(from, to)
+            .into_iter()
+            .map(|p| HistoryPoint {
+                timestamp: p.timestamp,
+                values: p
+                    .values
+                    .into_iter()
+                    .filter_map(|(k, v)| k.strip_prefix(&prefix).map(|k| (k.to_string(), v)))
+                    .collect(),
+            })
+            .filter(|p| !p.values.is_empty())
+            .collect::<Vec<_>>();
+
+        let points = if effective_step > ring.step_secs {
+            downsample(points, effective_step)
+        } else {
+            points
+        };
+
+        (effective_step, points)
+    }
+}
+
+/// Average consecutive points into buckets of `step_secs`
+fn downsample(points: Vec<HistoryPoint>, step_secs: u64) -> Vec<HistoryPoint> {
+    let mut buckets: Vec<Bucket> = Vec::new();
+
+    for point in points {
+        let start = point.timestamp - point.timestamp.rem_euclid(step_secs as i64);
+        if buckets.last().is_none_or(|b| b.start != start) {
+            buckets.push(Bucket {
+                start,
+                sums: BTreeMap::new(),
+            });
+        }
+        if let Some(bucket) = buckets.last_mut() {
+            for (key, value) in point.values {
+                let entry = bucket.sums.entry(key).or_insert((0.0, 0));
+                entry.0 += value;
+                entry.1 += 1;
+            }
+        }
+    }
+
+    buckets.iter().map(Bucket::to_point).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn retention(resolutions: &[(u64, u64)]) -> RetentionConfig {
+        RetentionConfig {
+            sample_interval_secs: 1,
+            resolutions: resolutions
+                .iter()
+                .map(|&(step_secs, retention_secs)| HistoryResolution { step_secs, retention_secs })
+                .collect(),
+        }
+    }
+
+    fn sample(cpu: f64) -> BTreeMap<String, f64> {
+        BTreeMap::from([
+            ("cpu.usage_percent".to_string(), cpu),
+            ("memory.used_percent".to_string(), 50.0),
+        ])
+    }
+
+    #[test]
+    fn test_ring_buffer_drops_oldest_points() {
+        let mut ring = RingBuffer::new(&HistoryResolution { step_secs: 1, retention_secs: 3 });
+        for t in 0..10 {
+            ring.push(t, &sample(t as f64));
+        }
+
+        let points = ring.points_between(i64::MIN, i64::MAX);
+        let timestamps: Vec<i64> = points.iter().map(|p| p.timestamp).collect();
+        // Three closed points plus the bucket still being filled
+        assert_eq!(timestamps, vec![6, 7, 8, 9]);
+    }
+
+    #[test]
+    fn test_ring_buffer_averages_into_buckets() {
+        let mut ring = RingBuffer::new(&HistoryResolution { step_secs: 60, retention_secs: 3_600 });
+        ring.push(60, &sample(10.0));
+        ring.push(90, &sample(30.0));
+        ring.push(120, &sample(50.0));
+
+        let points = ring.points_between(i64::MIN, i64::MAX);
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].timestamp, 60);
+        assert_eq!(points[0].values["cpu.usage_percent"], 20.0);
+        assert_eq!(points[1].values["cpu.usage_percent"], 50.0);
+    }
+
+    #[test]
+    fn test_query_filters_by_group_and_range() {
+        let history = MetricsHistory::new(&retention(&[(1, 600)]));
+        for t in 1_000..1_010 {
+            history.record(t, &sample(1.0));
+        }
+
+        let (step, points) = history.query(MetricGroup::Cpu, 1_005, 1_007, None);
+        assert_eq!(step, 1);
+        assert_eq!(points.len(), 3);
+        assert_eq!(points[0].values.keys().collect::<Vec<_>>(), vec!["usage_percent"]);
+
+        let (_, points) = history.query(MetricGroup::Disk, 1_000, 1_010, None);
+        assert!(points.is_empty());
+    }
+
+    #[test]
+    fn test_query_picks_coarser_ring_for_long_ranges() {
+        let history = MetricsHistory::new(&retention(&[(1, 60), (10, 3_600)]));
+        for t in 0..600 {
+            history.record(t, &sample(t as f64));
+        }
+
+        let (step, points) = history.query(MetricGroup::Cpu, 0, 600, None);
+        assert_eq!(step, 10);
+        assert_eq!(points.len(), 60);
+
+        let (step, _) = history.query(MetricGroup::Cpu, 590, 600, None);
+        assert_eq!(step, 1);
+    }
+
+    #[test]
+    fn test_query_downsamples_to_requested_step() {
+        let history = MetricsHistory::new(&retention(&[(1, 600)]));
+        for t in 0..60 {
+            history.record(t, &sample(t as f64));
+        }
+
+        let (step, points) = history.query(MetricGroup::Cpu, 0, 59, Some(30));
+        assert_eq!(step, 30);
+        assert_eq!(points.len(), 2);
+        assert_eq!(points[0].values["usage_percent"], 14.5);
+        assert_eq!(points[1].values["usage_percent"], 44.5);
+    }
+}
@@ -0,0 +1,44 @@
+//! Metrics history module
+//!
+//! This module keeps a rolling in-memory history of host metrics:
+//! - A background sampler collects CPU, memory, load, disk and network data
+//! - The latest snapshot is shared so handlers can answer without refreshing sysinfo
+//! - Samples are averaged into ring buffers at configurable resolutions
+//! - History is served per metric group for charting
+//! - Host, Docker and nginx metrics are exported in the Prometheus text format
+//! - Changes are pushed to dashboards over Server-Sent Events
+//!
+//! # Submodules
+//!
+//! - [`models`]: Data structures for history points, queries and snapshots
+//! - [`history`]: Ring buffers and range queries
+//! - [`store`]: Latest snapshot and history shared between the sampler and handlers
+//! - [`sampler`]: Background sampler thread
+//! - [`prometheus`]: Prometheus text exposition format rendering
+//! - [`stream`]: Topic hub and diff computation for live updates
+//! - [`handlers`]: HTTP request handlers for history endpoints
+//! - [`routes`]: Route registration for the history API
+//!
+//! # Example
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//! use std::time::Duration;
+//! use ubuntu_resource_api::config::AppConfig;
+//! use ubuntu_resource_api::metrics::store::MetricsStore;
+//! use ubuntu_resource_api::metrics::sampler::spawn_sampler;
+//!
+//! let config = AppConfig::with_defaults();
+//! let store = Arc::new(MetricsStore::new(&config.retention));
+//! spawn_sampler(store.clone(), Duration::from_secs(config.retention.sample_interval_secs))
+//!     .expect("failed to start sampler");
+//! ```
+
+pub mod models;
+pub mod history;
+pub mod store;
+pub mod sampler;
+pub mod prometheus;
+pub mod stream;
+pub mod handlers;
+pub mod routes;
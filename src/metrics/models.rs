@@ -0,0 +1,138 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::system::models::{CpuUsage, DiskInfo, LoadAverage, MemoryInfo, NetworkInfo, ProcessInfo};
+
+/// Metric groups recorded by the background sampler
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricGroup {
+    Cpu,
+    Memory,
+    Load,
+    Disk,
+    Network,
+}
+
+impl MetricGroup {
+    pub const ALL: [MetricGroup; 5] = [
+        MetricGroup::Cpu,
+        MetricGroup::Memory,
+        MetricGroup::Load,
+        MetricGroup::Disk,
+        MetricGroup::Network,
+    ];
+
+    /// Name used in URLs and as the series key prefix
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MetricGroup::Cpu => "cpu",
+            MetricGroup::Memory => "memory",
+            MetricGroup::Load => "load",
+            MetricGroup::Disk => "disk",
+            MetricGroup::Network => "network",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|g| g.as_str() == name)
+    }
+}
+
+/// A single history point
+///
+/// Series keys are `field` for scalar metrics (e.g. `usage_percent`) and
+/// `field:label` for per-device metrics (e.g. `used_percent:/` or `rx_bytes_per_sec:eth0`).
+/// Internally keys are prefixed with the metric group (`cpu.usage_percent`).
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistoryPoint {
+    pub timestamp: i64,
+    pub values: BTreeMap<String, f64>,
+}
+
+/// Query parameters for `/api/history/{metric}`
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// Start of the range as a unix timestamp in seconds (default: 10 minutes before `to`)
+    pub from: Option<i64>,
+    /// End of the range as a unix timestamp in seconds (default: now)
+    pub to: Option<i64>,
+    /// Desired spacing between points, e.g. `30s` or `5m` (default: finest available)
+    pub step: Option<String>,
+}
+
+/// History response for one metric group
+#[derive(Serialize)]
+pub struct HistoryResponse {
+    pub metric: String,
+    pub from: i64,
+    pub to: i64,
+    pub step_secs: u64,
+    pub points: Vec<HistoryPoint>,
+}
+
+/// Everything the sampler collects in one tick
+#[derive(Serialize, Clone)]
+pub struct MetricsSnapshot {
+    pub timestamp: i64,
+    pub cpu: CpuUsage,
+    pub memory: MemoryInfo,
+    pub load: LoadAverage,
+    pub disks: Vec<DiskInfo>,
+    pub networks: Vec<NetworkInfo>,
+    /// Top processes by CPU usage
+    pub processes: Vec<ProcessInfo>,
+}
+
+impl MetricsSnapshot {
+    /// Flatten into history series keys
+    ///
+    /// Network throughput is derived from the byte counters of `previous`, so it is
+    /// only present from the second sample onwards.
+    pub fn history_values(&self, previous: Option<&MetricsSnapshot>) -> BTreeMap<String, f64> {
+        let mut values = BTreeMap::new();
+
+        values.insert("cpu.usage_percent".to_string(), self.cpu.overall_usage_percent as f64);
+
+        values.insert("memory.used_percent".to_string(), self.memory.used_percent);
+        values.insert("memory.used_bytes".to_string(), self.memory.used_bytes as f64);
+        values.insert("memory.available_bytes".to_string(), self.memory.available_bytes as f64);
+
+        values.insert("load.one".to_string(), self.load.one_min);
+        values.insert("load.five".to_string(), self.load.five_min);
+        values.insert("load.fifteen".to_string(), self.load.fifteen_min);
+
+        for disk in &self.disks {
+            values.insert(format!("disk.used_percent:{}", disk.mount_point), disk.used_percent);
+            values.insert(format!("disk.used_bytes:{}", disk.mount_point), disk.used_bytes as f64);
+        }
+
+        if let Some(previous) = previous {
+            let elapsed = (self.timestamp - previous.timestamp) as f64;
+            if elapsed > 0.0 {
+                for network in &self.networks {
+                    let Some(before) = previous
+                        .networks
+                        .iter()
+                        .find(|n| n.interface_name == network.interface_name)
+                    else {
+                        continue;
+                    };
+
+                    let rx = network.received_bytes.saturating_sub(before.received_bytes) as f64 / elapsed;
+                    let tx = network.transmitted_bytes.saturating_sub(before.transmitted_bytes) as f64 / elapsed;
+                    values.insert(format!("network.rx_bytes_per_sec:{}", network.interface_name), rx);
+                    values.insert(format!("network.tx_bytes_per_sec:{}", network.interface_name), tx);
+                }
+            }
+        }
+
+        values
+    }
+}
+
+/// Query parameters for `/api/stream`
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    /// Comma-separated topics, e.g. `cpu,memory,processes` (default: all)
+    pub topics: Option<String>,
+}
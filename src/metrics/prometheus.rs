@@ -0,0 +1,244 @@
+// Prometheus text exposition format rendering
+
+use std::fmt::Write;
+
+use crate::docker::models::DockerContainer;
+use crate::metrics::models::MetricsSnapshot;
+use crate::nginx::stats::{ProxyOperation, ProxyOperationStats};
+use crate::system::models::{DiskInfo, NetworkInfo};
+
+/// Content type for the text exposition format
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+/// Metric family name, help text and value accessor
+type Family<T> = (&'static str, &'static str, fn(&T) -> u64);
+
+/// Minimal writer for the Prometheus text format
+#[derive(Default)]
+pub struct PrometheusWriter {
+    output: String,
+}
+
+impl PrometheusWriter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a metric family with its `HELP` and `TYPE` lines
+    pub fn family(&mut self, name: &str, kind: &str, help: &str) -> &mut Self {
+        let _ = writeln!(self.output, "# HELP {} {}", name, help);
+        let _ = writeln!(self.output, "# TYPE {} {}", name, kind);
+        self
+    }
+
+    /// Write a single sample
+    pub fn sample(&mut self, name: &str, labels: &[(&str, &str)], value: f64) -> &mut Self {
+        self.output.push_str(name);
+        if !labels.is_empty() {
+            let rendered: Vec<String> = labels
+                .iter()
+                .map(|(key, value)| format!("{}=\"{}\"", key, escape_label_value(value)))
+                .collect();
+            let _ = write!(self.output, "{{{}}}", rendered.join(","));
+        }
+        let _ = writeln!(self.output, " {}", value);
+        self
+    }
+
+    pub fn finish(self) -> String {
+        self.output
+    }
+}
+
+/// Escape backslashes, quotes and newlines in label values
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Render host metrics from a sampler snapshot
+pub fn render_host(writer: &mut PrometheusWriter, snapshot: &MetricsSnapshot) {
+    writer
+        .family("host_cpu_usage_percent", "gauge", "Overall CPU usage in percent")
+        .sample("host_cpu_usage_percent", &[], snapshot.cpu.overall_usage_percent as f64);
+
+    writer.family("host_cpu_core_usage_percent", "gauge", "Per-core CPU usage in percent");
+    for (core, usage) in snapshot.cpu.per_core_usage.iter().enumerate() {
+        let core = core.to_string();
+        writer.sample("host_cpu_core_usage_percent", &[("core", &core)], *usage as f64);
+    }
+
+    let memory = &snapshot.memory;
+    for (name, help, value) in [
+        ("host_memory_total_bytes", "Total memory in bytes", memory.total_bytes),
+        ("host_memory_used_bytes", "Used memory in bytes", memory.used_bytes),
+        ("host_memory_free_bytes", "Free memory in bytes", memory.free_bytes),
+        ("host_memory_available_bytes", "Available memory in bytes", memory.available_bytes),
+    ] {
+        writer.family(name, "gauge", help).sample(name, &[], value as f64);
+    }
+
+    for (name, help, value) in [
+        ("host_load1", "1-minute load average", snapshot.load.one_min),
+        ("host_load5", "5-minute load average", snapshot.load.five_min),
+        ("host_load15", "15-minute load average", snapshot.load.fifteen_min),
+    ] {
+        writer.family(name, "gauge", help).sample(name, &[], value);
+    }
+
+    let disk_families: [Family<DiskInfo>; 3] = [
+        ("host_disk_total_bytes", "Filesystem size in bytes", |d| d.total_bytes),
+        ("host_disk_used_bytes", "Filesystem used space in bytes", |d| d.used_bytes),
+        ("host_disk_available_bytes", "Filesystem available space in bytes", |d| d.available_bytes),
+    ];
+    for (name, help, value) in disk_families {
+        writer.family(name, "gauge", help);
+        for disk in &snapshot.disks {
+            let labels = [
+                ("mount_point", disk.mount_point.as_str()),
+                ("device", disk.name.as_str()),
+                ("filesystem", disk.filesystem.as_str()),
+            ];
+            writer.sample(name, &labels, value(disk) as f64);
+        }
+    }
+
+    let network_families: [Family<NetworkInfo>; 6] = [
+        ("host_network_received_bytes_total", "Bytes received", |n| n.received_bytes),
+        ("host_network_transmitted_bytes_total", "Bytes transmitted", |n| n.transmitted_bytes),
+        ("host_network_received_packets_total", "Packets received", |n| n.received_packets),
+        ("host_network_transmitted_packets_total", "Packets transmitted", |n| n.transmitted_packets),
+        ("host_network_receive_errors_total", "Receive errors", |n| n.errors_in),
+        ("host_network_transmit_errors_total", "Transmit errors", |n| n.errors_out),
+    ];
+    for (name, help, value) in network_families {
+        writer.family(name, "counter", help);
+        for network in &snapshot.networks {
+            writer.sample(name, &[("interface", network.interface_name.as_str())], value(network) as f64);
+        }
+    }
+}
+
+/// Render Docker container metrics; `None` means the daemon could not be reached
+pub fn render_docker(writer: &mut PrometheusWriter, containers: Option<&[DockerContainer]>) {
+    writer
+        .family("docker_up", "gauge", "Whether the Docker daemon could be queried")
+        .sample("docker_up", &[], if containers.is_some() { 1.0 } else { 0.0 });
+
+    let Some(containers) = containers else {
+        return;
+    };
+
+    writer.family("docker_container_running", "gauge", "Whether the container is in the running state");
+    for container in containers {
+        let labels = [("name", container.name.as_str()), ("image", container.image.as_str())];
+        let running = if container.state == "running" { 1.0 } else { 0.0 };
+        writer.sample("docker_container_running", &labels, running);
+    }
+
+    writer.family("docker_container_memory_usage_bytes", "gauge", "Container memory usage in bytes");
+    for container in containers {
+        if let Some(usage) = container.memory_usage {
+            let labels = [("name", container.name.as_str()), ("image", container.image.as_str())];
+            writer.sample("docker_container_memory_usage_bytes", &labels, usage as f64);
+        }
+    }
+
+    writer.family("docker_container_memory_limit_bytes", "gauge", "Container memory limit in bytes");
+    for container in containers {
+        if let Some(limit) = container.memory_limit {
+            let labels = [("name", container.name.as_str()), ("image", container.image.as_str())];
+            writer.sample("docker_container_memory_limit_bytes", &labels, limit as f64);
+        }
+    }
+}
+
+/// Render nginx proxy operation counters
+pub fn render_nginx(writer: &mut PrometheusWriter, stats: &ProxyOperationStats) {
+    writer.family(
+        "nginx_proxy_operations_total",
+        "counter",
+        "Nginx proxy operations handled by this service",
+    );
+    for operation in ProxyOperation::ALL {
+        writer.sample(
+            "nginx_proxy_operations_total",
+            &[("operation", operation.as_str()), ("result", "success")],
+            stats.succeeded(operation) as f64,
+        );
+        writer.sample(
+            "nginx_proxy_operations_total",
+            &[("operation", operation.as_str()), ("result", "failure")],
+            stats.failed(operation) as f64,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn container(name: &str, state: &str) -> DockerContainer {
+        DockerContainer {
+            id: "abc".to_string(),
+            name: name.to_string(),
+            image: "nginx:latest".to_string(),
+            state: state.to_string(),
+            status: String::new(),
+            ports: String::new(),
+            created: 0,
+            memory_usage: Some(1024),
+            memory_limit: None,
+            memory_percent: None,
+        }
+    }
+
+    #[test]
+    fn test_sample_escapes_label_values() {
+        let mut writer = PrometheusWriter::new();
+        writer.sample("m", &[("path", "C:\\dir \"x\"\nnext")], 1.5);
+        assert_eq!(writer.finish(), "m{path=\"C:\\\\dir \\\"x\\\"\\nnext\"} 1.5\n");
+    }
+
+    #[test]
+    fn test_render_docker() {
+        let mut writer = PrometheusWriter::new();
+        render_docker(&mut writer, Some(&[container("web", "running"), container("db", "exited")]));
+        let output = writer.finish();
+
+        assert!(output.contains("docker_up 1\n"));
+        assert!(output.contains("docker_container_running{name=\"web\",image=\"nginx:latest\"} 1\n"));
+        assert!(output.contains("docker_container_running{name=\"db\",image=\"nginx:latest\"} 0\n"));
+        assert!(output.contains("docker_container_memory_usage_bytes{name=\"web\",image=\"nginx:latest\"} 1024\n"));
+        assert!(output.contains("# TYPE docker_container_memory_limit_bytes gauge\n"));
+    }
+
+    #[test]
+    fn test_render_docker_unreachable() {
+        let mut writer = PrometheusWriter::new();
+        render_docker(&mut writer, None);
+        let output = writer.finish();
+
+        assert!(output.contains("docker_up 0\n"));
+        assert!(!output.contains("docker_container_running"));
+    }
+
+    #[test]
+    fn test_render_nginx_counters() {
+        let stats = ProxyOperationStats::default();
+        stats.record(ProxyOperation::Create, true);
+        stats.record(ProxyOperation::Create, true);
+        stats.record(ProxyOperation::Delete, false);
+
+        let mut writer = PrometheusWriter::new();
+        render_nginx(&mut writer, &stats);
+        let output = writer.finish();
+
+        assert!(output.contains("nginx_proxy_operations_total{operation=\"create\",result=\"success\"} 2\n"));
+        assert!(output.contains("nginx_proxy_operations_total{operation=\"delete\",result=\"failure\"} 1\n"));
+        assert!(output.contains("nginx_proxy_operations_total{operation=\"update\",result=\"success\"} 0\n"));
+        assert!(output.contains("nginx_proxy_operations_total{operation=\"disable\",result=\"failure\"} 0\n"));
+    }
+}
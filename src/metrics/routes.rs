@@ -0,0 +1,11 @@
+use actix_web::web;
+
+use crate::metrics::handlers;
+
+/// Configure all metrics history and exporter routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg
+        .service(handlers::get_history)
+        .service(handlers::prometheus_metrics)
+        .service(handlers::stream_metrics);
+}
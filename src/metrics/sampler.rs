@@ -0,0 +1,98 @@
+// Background sampler feeding the metrics store
+
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::info;
+use sysinfo::{Disks, Networks, System};
+
+use crate::metrics::store::MetricsStore;
+use crate::metrics::models::MetricsSnapshot;
+use crate::system::collectors::{
+    collect_cpu_usage, collect_disks, collect_load_average, collect_memory, collect_networks,
+    collect_processes,
+};
+
+/// Number of processes kept in each snapshot
+const SNAPSHOT_PROCESS_LIMIT: usize = 50;
+
+/// Collects host metrics using its own sysinfo handles
+///
+/// The sampler never touches `AppState.system`, so request handlers do not
+/// contend with it for the lock.
+pub struct MetricsSampler {
+    system: System,
+    disks: Disks,
+    networks: Networks,
+    previous: Option<MetricsSnapshot>,
+}
+
+impl MetricsSampler {
+    pub fn new() -> Self {
+        let mut system = System::new();
+        system.refresh_cpu_usage();
+
+        MetricsSampler {
+            system,
+            disks: Disks::new_with_refreshed_list(),
+            networks: Networks::new_with_refreshed_list(),
+            previous: None,
+        }
+    }
+
+    /// Refresh all sources and take a snapshot
+    pub fn sample(&mut self) -> MetricsSnapshot {
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        self.system.refresh_processes();
+        self.disks.refresh_list();
+        self.networks.refresh_list();
+
+        MetricsSnapshot {
+            timestamp: chrono::Utc::now().timestamp(),
+            cpu: collect_cpu_usage(&self.system),
+            memory: collect_memory(&self.system),
+            load: collect_load_average(),
+            disks: collect_disks(&self.disks),
+            networks: collect_networks(&self.networks),
+            processes: collect_processes(&self.system, SNAPSHOT_PROCESS_LIMIT),
+        }
+    }
+
+    /// Take a snapshot, record it into the history and publish it as the latest
+    pub fn record(&mut self, store: &MetricsStore) -> MetricsSnapshot {
+        let snapshot = self.sample();
+        store
+            .history
+            .record(snapshot.timestamp, &snapshot.history_values(self.previous.as_ref()));
+        store.publish(snapshot.clone());
+        self.previous = Some(snapshot.clone());
+        snapshot
+    }
+}
+
+impl Default for MetricsSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Start the sampler on a dedicated thread
+///
+/// sysinfo refreshes are blocking, so they run outside the actix runtime.
+pub fn spawn_sampler(store: Arc<MetricsStore>, interval: Duration) -> std::io::Result<thread::JoinHandle<()>> {
+    thread::Builder::new()
+        .name("metrics-sampler".to_string())
+        .spawn(move || {
+            info!("Metrics sampler started (interval: {:?})", interval);
+            let mut sampler = MetricsSampler::new();
+            let mut next_tick = Instant::now() + interval;
+
+            loop {
+                thread::sleep(next_tick.saturating_duration_since(Instant::now()));
+                next_tick += interval;
+                sampler.record(&store);
+            }
+        })
+}
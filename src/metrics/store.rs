@@ -0,0 +1,45 @@
+// Shared metrics store written by the sampler and read by handlers
+
+use std::sync::RwLock;
+
+use crate::config::RetentionConfig;
+use crate::metrics::history::MetricsHistory;
+use crate::metrics::models::MetricsSnapshot;
+use crate::metrics::stream::{StreamHub, Topic};
+
+/// Latest snapshot, the rolling history and the live update hub
+///
+/// Readers only take short read locks, so any number of clients can poll
+/// without waiting on a sysinfo refresh.
+pub struct MetricsStore {
+    latest: RwLock<Option<MetricsSnapshot>>,
+    pub history: MetricsHistory,
+    pub stream: StreamHub,
+}
+
+impl MetricsStore {
+    pub fn new(config: &RetentionConfig) -> Self {
+        MetricsStore {
+            latest: RwLock::new(None),
+            history: MetricsHistory::new(config),
+            stream: StreamHub::new(),
+        }
+    }
+
+    /// Most recent snapshot, or `None` until the sampler has completed its first tick
+    pub fn latest(&self) -> Option<MetricsSnapshot> {
+        self.latest.read().unwrap().clone()
+    }
+
+    /// Publish a new snapshot and push the changes to stream subscribers
+    pub fn publish(&self, snapshot: MetricsSnapshot) {
+        self.stream.update(Topic::Cpu, &snapshot.cpu);
+        self.stream.update(Topic::Memory, &snapshot.memory);
+        self.stream.update(Topic::Load, &snapshot.load);
+        self.stream.update(Topic::Disks, &snapshot.disks);
+        self.stream.update(Topic::Network, &snapshot.networks);
+        self.stream.update(Topic::Processes, &snapshot.processes);
+
+        *self.latest.write().unwrap() = Some(snapshot);
+    }
+}
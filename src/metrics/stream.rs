@@ -0,0 +1,299 @@
+// Live metric updates fanned out to Server-Sent Events subscribers
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use log::{info, warn};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::docker::client::get_docker_client;
+use crate::docker::collectors::collect_containers;
+use crate::metrics::store::MetricsStore;
+
+/// How many undelivered events a slow subscriber may fall behind before it is resynced
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Topics a client can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Topic {
+    Cpu,
+    Memory,
+    Load,
+    Disks,
+    Network,
+    Processes,
+    DockerContainers,
+}
+
+impl Topic {
+    pub const ALL: [Topic; 7] = [
+        Topic::Cpu,
+        Topic::Memory,
+        Topic::Load,
+        Topic::Disks,
+        Topic::Network,
+        Topic::Processes,
+        Topic::DockerContainers,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Topic::Cpu => "cpu",
+            Topic::Memory => "memory",
+            Topic::Load => "load",
+            Topic::Disks => "disks",
+            Topic::Network => "network",
+            Topic::Processes => "processes",
+            Topic::DockerContainers => "docker.containers",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|t| t.as_str() == name)
+    }
+
+    /// Identity field for list topics, which are sent as keyed diffs
+    pub fn key(&self) -> Option<&'static str> {
+        match self {
+            Topic::Disks => Some("mount_point"),
+            Topic::Network => Some("interface_name"),
+            Topic::Processes => Some("pid"),
+            Topic::DockerContainers => Some("id"),
+            Topic::Cpu | Topic::Memory | Topic::Load => None,
+        }
+    }
+}
+
+/// A message pushed to subscribers
+///
+/// `snapshot` carries the full topic value. `diff` is only sent for list topics
+/// and lists the items that were added or changed, plus the keys that disappeared.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StreamEvent {
+    Snapshot {
+        topic: &'static str,
+        data: Value,
+    },
+    Diff {
+        topic: &'static str,
+        upsert: Vec<Value>,
+        remove: Vec<Value>,
+    },
+}
+
+impl StreamEvent {
+    pub fn topic(&self) -> &'static str {
+        match self {
+            StreamEvent::Snapshot { topic, .. } | StreamEvent::Diff { topic, .. } => topic,
+        }
+    }
+
+    /// Encode as a single Server-Sent Events message
+    pub fn to_sse(&self) -> String {
+        format!("data: {}\n\n", serde_json::to_string(self).unwrap_or_default())
+    }
+}
+
+/// Keeps the current value of every topic and broadcasts changes
+pub struct StreamHub {
+    sender: broadcast::Sender<Arc<StreamEvent>>,
+    current: Mutex<HashMap<Topic, Value>>,
+}
+
+impl StreamHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        StreamHub {
+            sender,
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Store a new topic value and broadcast what changed, if anything
+    pub fn update<T: Serialize>(&self, topic: Topic, value: &T) {
+        let Ok(next) = serde_json::to_value(value) else {
+            return;
+        };
+
+        let mut current = self.current.lock().unwrap();
+        let event = diff_event(topic, current.get(&topic), &next);
+        current.insert(topic, next);
+        drop(current);
+
+        if let Some(event) = event {
+            // Sending only fails when nobody is subscribed
+            let _ = self.sender.send(Arc::new(event));
+        }
+    }
+
+    /// Full snapshots for the requested topics that already have a value
+    pub fn snapshot(&self, topics: &[Topic]) -> Vec<StreamEvent> {
+        let current = self.current.lock().unwrap();
+        topics
+            .iter()
+            .filter_map(|topic| {
+                current.get(topic).map(|data| StreamEvent::Snapshot {
+                    topic: topic.as_str(),
+                    data: data.clone(),
+                })
+            })
+            .collect()
+    }
+
+    /// Subscribe to future events and return the current snapshots to start from
+    ///
+    /// The receiver is created first, so an update racing with the snapshot is
+    /// delivered again rather than lost; applying it twice is harmless.
+    pub fn subscribe(&self, topics: &[Topic]) -> (Vec<StreamEvent>, broadcast::Receiver<Arc<StreamEvent>>) {
+        let receiver = self.sender.subscribe();
+        (self.snapshot(topics), receiver)
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+impl Default for StreamHub {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Work out which event, if any, turns `previous` into `next`
+fn diff_event(topic: Topic, previous: Option<&Value>, next: &Value) -> Option<StreamEvent> {
+    if previous == Some(next) {
+        return None;
+    }
+
+    let snapshot = || StreamEvent::Snapshot {
+        topic: topic.as_str(),
+        data: next.clone(),
+    };
+
+    let (Some(key), Some(Value::Array(before)), Value::Array(after)) = (topic.key(), previous, next) else {
+        return Some(snapshot());
+    };
+
+    let before: HashMap<&Value, &Value> = before
+        .iter()
+        .filter_map(|item| item.get(key).map(|k| (k, item)))
+        .collect();
+    let after_keys: Vec<&Value> = after.iter().filter_map(|item| item.get(key)).collect();
+
+    let upsert: Vec<Value> = after
+        .iter()
+        .filter(|item| match item.get(key) {
+            Some(k) => before.get(k) != Some(item),
+            None => false,
+        })
+        .cloned()
+        .collect();
+    let remove: Vec<Value> = before
+        .keys()
+        .filter(|k| !after_keys.contains(k))
+        .map(|k| (*k).clone())
+        .collect();
+
+    Some(StreamEvent::Diff {
+        topic: topic.as_str(),
+        upsert,
+        remove,
+    })
+}
+
+/// Periodically refresh the `docker.containers` topic while anyone is subscribed
+pub fn spawn_docker_poller(store: Arc<MetricsStore>, socket_path: String, interval: Duration) {
+    actix_web::rt::spawn(async move {
+        info!("Docker stream poller started (interval: {:?})", interval);
+        let mut ticker = tokio::time::interval(interval);
+
+        loop {
+            ticker.tick().await;
+            if store.stream.subscriber_count() == 0 {
+                continue;
+            }
+
+            let result = match get_docker_client(&socket_path).await {
+                Ok(docker) => collect_containers(&docker).await.map_err(|e| e.to_string()),
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(containers) => store.stream.update(Topic::DockerContainers, &containers),
+                Err(e) => warn!("Docker stream poll failed: {}", e),
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_unchanged_value_sends_nothing() {
+        let value = json!({"overall_usage_percent": 5.0});
+        assert_eq!(diff_event(Topic::Cpu, Some(&value), &value), None);
+    }
+
+    #[test]
+    fn test_scalar_topic_sends_snapshot() {
+        let before = json!({"used_percent": 10.0});
+        let after = json!({"used_percent": 11.0});
+        assert_eq!(
+            diff_event(Topic::Memory, Some(&before), &after),
+            Some(StreamEvent::Snapshot { topic: "memory", data: after.clone() })
+        );
+    }
+
+    #[test]
+    fn test_first_list_value_sends_snapshot() {
+        let after = json!([{"pid": 1, "cpu_usage": 0.0}]);
+        assert!(matches!(
+            diff_event(Topic::Processes, None, &after),
+            Some(StreamEvent::Snapshot { topic: "processes", .. })
+        ));
+    }
+
+    #[test]
+    fn test_list_topic_sends_keyed_diff() {
+        let before = json!([
+            {"pid": 1, "cpu_usage": 1.0},
+            {"pid": 2, "cpu_usage": 2.0},
+            {"pid": 3, "cpu_usage": 3.0},
+        ]);
+        let after = json!([
+            {"pid": 1, "cpu_usage": 1.0},
+            {"pid": 3, "cpu_usage": 9.0},
+            {"pid": 4, "cpu_usage": 4.0},
+        ]);
+
+        assert_eq!(
+            diff_event(Topic::Processes, Some(&before), &after),
+            Some(StreamEvent::Diff {
+                topic: "processes",
+                upsert: vec![json!({"pid": 3, "cpu_usage": 9.0}), json!({"pid": 4, "cpu_usage": 4.0})],
+                remove: vec![json!(2)],
+            })
+        );
+    }
+
+    #[test]
+    fn test_subscribers_receive_updates() {
+        let hub = StreamHub::new();
+        hub.update(Topic::Load, &json!({"one_min": 1.0}));
+
+        let (initial, mut receiver) = hub.subscribe(&[Topic::Load, Topic::Cpu]);
+        assert_eq!(initial.len(), 1);
+        assert_eq!(initial[0].topic(), "load");
+
+        hub.update(Topic::Load, &json!({"one_min": 2.0}));
+        let event = receiver.try_recv().unwrap();
+        assert_eq!(event.to_sse(), "data: {\"type\":\"snapshot\",\"topic\":\"load\",\"data\":{\"one_min\":2.0}}\n\n");
+    }
+}
@@ -0,0 +1,721 @@
+// ACME (RFC 8555) client issuing certificates through HTTP-01 challenges
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use log::info;
+use rcgen::{CertificateParams, KeyPair as CertificateKey};
+use ring::rand::SystemRandom;
+use ring::signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::config::AcmeConfig;
+use crate::error::AppError;
+use crate::nginx::acme::renewal::certificate_name;
+use crate::nginx::acme::store::CertificateStore;
+use crate::nginx::acme::{CERTIFICATES_DIR, CHALLENGE_DIR};
+
+/// Attempts while waiting for an authorization or order to leave `pending`/`processing`
+const POLL_ATTEMPTS: u32 = 30;
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct Order {
+    status: String,
+    #[serde(default)]
+    authorizations: Vec<String>,
+    finalize: String,
+    certificate: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Authorization {
+    status: String,
+    #[serde(default)]
+    challenges: Vec<Challenge>,
+}
+
+#[derive(Deserialize)]
+struct Challenge {
+    #[serde(rename = "type")]
+    kind: String,
+    url: String,
+    #[serde(default)]
+    token: String,
+    error: Option<Problem>,
+}
+
+/// RFC 7807 problem document returned on ACME errors
+#[derive(Deserialize, Default)]
+struct Problem {
+    #[serde(rename = "type", default)]
+    kind: String,
+    #[serde(default)]
+    detail: String,
+}
+
+/// Issues certificates from an ACME directory into a [`CertificateStore`]
+///
+/// Challenge responses are written to a directory that nginx serves under
+/// `/.well-known/acme-challenge/`. The account key is kept in the store
+/// directory and reused, so the account is registered once.
+pub struct AcmeClient {
+    config: AcmeConfig,
+    http: reqwest::Client,
+    store: CertificateStore,
+    challenge_dir: PathBuf,
+    /// One issuance at a time; orders for the same names would otherwise race
+    issuing: tokio::sync::Mutex<()>,
+}
+
+impl AcmeClient {
+    /// A client storing certificates under [`CERTIFICATES_DIR`]
+    pub fn new(config: &AcmeConfig) -> Result<Self, AppError> {
+        Self::with_dirs(config, CERTIFICATES_DIR, CHALLENGE_DIR)
+    }
+
+    pub fn with_dirs(
+        config: &AcmeConfig,
+        certs_dir: impl Into<PathBuf>,
+        challenge_dir: impl Into<PathBuf>,
+    ) -> Result<Self, AppError> {
+        let mut http = reqwest::Client::builder().timeout(Duration::from_secs(30));
+        if let Some(ca_file) = &config.ca_file {
+            let pem = fs::read(ca_file)
+                .map_err(|e| AppError::ConfigError(format!("Cannot read ACME_CA_FILE '{}': {}", ca_file, e)))?;
+            let root = reqwest::Certificate::from_pem(&pem)
+                .map_err(|e| AppError::ConfigError(format!("Invalid ACME_CA_FILE '{}': {}", ca_file, e)))?;
+            http = http.add_root_certificate(root);
+        }
+
+        Ok(AcmeClient {
+            config: config.clone(),
+            http: http
+                .build()
+                .map_err(|e| AppError::ConfigError(format!("Cannot create ACME HTTP client: {}", e)))?,
+            store: CertificateStore::new(certs_dir),
+            challenge_dir: challenge_dir.into(),
+            issuing: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    pub fn store(&self) -> &CertificateStore {
+        &self.store
+    }
+
+    pub fn renew_before_days(&self) -> u32 {
+        self.config.renew_before_days
+    }
+
+    /// Order a certificate for `domains`, as `(chain_pem, key_pem)`
+    ///
+    /// Nothing is stored; the caller writes the pair under the nginx lock.
+    pub async fn order(&self, domains: &[String]) -> Result<(String, String), AppError> {
+        certificate_name(domains)?;
+        let _issuing = self.issuing.lock().await;
+        info!("Requesting ACME certificate for {}", domains.join(", "));
+
+        let mut session = self.open_session().await?;
+
+        let identifiers: Vec<Value> = domains.iter().map(|d| json!({"type": "dns", "value": d})).collect();
+        let new_order = session.directory.new_order.clone();
+        let response = session.post(&new_order, Some(json!({ "identifiers": identifiers }))).await?;
+        let order_url = location(&response)?;
+        let order: Order = read_json(response).await?;
+
+        for authorization in &order.authorizations {
+            self.authorize(&mut session, authorization).await?;
+        }
+
+        let key = CertificateKey::generate().map_err(csr_error)?;
+        let csr = CertificateParams::new(domains.to_vec())
+            .and_then(|params| params.serialize_request(&key))
+            .map_err(csr_error)?;
+        session
+            .post(&order.finalize, Some(json!({ "csr": URL_SAFE_NO_PAD.encode(csr.der()) })))
+            .await?;
+
+        let order = session
+            .poll(&order_url, |order: &Order| order.status != "pending" && order.status != "processing")
+            .await?;
+        let certificate_url = match (order.status.as_str(), order.certificate) {
+            ("valid", Some(url)) => url,
+            (status, _) => return Err(acme_error(format!("order finished as '{}'", status))),
+        };
+
+        let chain = session
+            .post(&certificate_url, None)
+            .await?
+            .text()
+            .await
+            .map_err(|e| acme_error(e.to_string()))?;
+        info!("✅ ACME certificate issued for {}", domains.join(", "));
+        Ok((chain, key.serialize_pem()))
+    }
+
+    /// Fetch the directory and register (or look up) the account
+    async fn open_session(&self) -> Result<Session<'_>, AppError> {
+        let directory: Directory = read_json(
+            self.http
+                .get(&self.config.directory_url)
+                .send()
+                .await
+                .map_err(|e| acme_error(format!("directory {}: {}", self.config.directory_url, e)))?,
+        )
+        .await?;
+
+        let mut session = Session {
+            http: &self.http,
+            directory,
+            key: load_account_key(&self.store.dir().join("account.pk8"))?,
+            rng: SystemRandom::new(),
+            kid: None,
+            nonce: None,
+        };
+
+        let mut account = json!({ "termsOfServiceAgreed": true });
+        if let Some(email) = &self.config.email {
+            account["contact"] = json!([format!("mailto:{}", email)]);
+        }
+        let new_account = session.directory.new_account.clone();
+        let response = session.post(&new_account, Some(account)).await?;
+        session.kid = Some(location(&response)?);
+        Ok(session)
+    }
+
+    /// Complete the HTTP-01 challenge of one authorization
+    async fn authorize(&self, session: &mut Session<'_>, url: &str) -> Result<(), AppError> {
+        let authorization: Authorization = read_json(session.post(url, None).await?).await?;
+        if authorization.status == "valid" {
+            return Ok(());
+        }
+        let challenge = authorization
+            .challenges
+            .into_iter()
+            .find(|c| c.kind == "http-01")
+            .ok_or_else(|| acme_error("the server offered no http-01 challenge".to_string()))?;
+        if challenge.token.is_empty()
+            || !challenge.token.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(acme_error(format!("invalid challenge token '{}'", challenge.token)));
+        }
+
+        let token_path = self.challenge_dir.join(&challenge.token);
+        fs::create_dir_all(&self.challenge_dir)
+            .and_then(|_| fs::write(&token_path, session.key_authorization(&challenge.token)))
+            .map_err(|e| AppError::NginxError(format!("Cannot write {}: {}", token_path.display(), e)))?;
+
+        let result = async {
+            session.post(&challenge.url, Some(json!({}))).await?;
+            let authorization = session
+                .poll(url, |a: &Authorization| a.status != "pending" && a.status != "processing")
+                .await?;
+            if authorization.status == "valid" {
+                return Ok(());
+            }
+            let detail = authorization
+                .challenges
+                .into_iter()
+                .find_map(|c| c.error)
+                .map(|p| format!(": {}", p.detail))
+                .unwrap_or_default();
+            Err(acme_error(format!("authorization {}{}", authorization.status, detail)))
+        }
+        .await;
+
+        let _ = fs::remove_file(&token_path);
+        result
+    }
+}
+
+/// Signed requests against one directory with one account key
+struct Session<'a> {
+    http: &'a reqwest::Client,
+    directory: Directory,
+    key: EcdsaKeyPair,
+    rng: SystemRandom,
+    /// Account URL, sent instead of the key once the account exists
+    kid: Option<String>,
+    nonce: Option<String>,
+}
+
+impl Session<'_> {
+    /// Send a JWS-signed POST; `None` is a POST-as-GET
+    ///
+    /// A `badNonce` rejection is retried once with the fresh nonce it carries.
+    async fn post(&mut self, url: &str, payload: Option<Value>) -> Result<reqwest::Response, AppError> {
+        for attempt in 0..2 {
+            let nonce = match self.nonce.take() {
+                Some(nonce) => nonce,
+                None => self.fetch_nonce().await?,
+            };
+            let body = self.sign(url, &nonce, payload.as_ref())?;
+            let response = self
+                .http
+                .post(url)
+                .header(reqwest::header::CONTENT_TYPE, "application/jose+json")
+                .body(body)
+                .send()
+                .await
+                .map_err(|e| acme_error(format!("{}: {}", url, e)))?;
+            self.nonce = replay_nonce(&response);
+
+            if response.status().is_success() {
+                return Ok(response);
+            }
+            let status = response.status();
+            let problem: Problem = response.json().await.unwrap_or_default();
+            if problem.kind == "urn:ietf:params:acme:error:badNonce" && attempt == 0 {
+                continue;
+            }
+            return Err(acme_error(format!("{} ({}): {}", problem.kind, status, problem.detail)));
+        }
+        unreachable!("the second attempt always returns")
+    }
+
+    /// POST-as-GET `url` until `done` holds for the response
+    async fn poll<T, F>(&mut self, url: &str, done: F) -> Result<T, AppError>
+    where
+        T: serde::de::DeserializeOwned,
+        F: Fn(&T) -> bool,
+    {
+        for _ in 0..POLL_ATTEMPTS {
+            let value: T = read_json(self.post(url, None).await?).await?;
+            if done(&value) {
+                return Ok(value);
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        Err(acme_error(format!("timed out waiting for {}", url)))
+    }
+
+    async fn fetch_nonce(&self) -> Result<String, AppError> {
+        let response = self
+            .http
+            .head(&self.directory.new_nonce)
+            .send()
+            .await
+            .map_err(|e| acme_error(format!("newNonce: {}", e)))?;
+        replay_nonce(&response).ok_or_else(|| acme_error("newNonce returned no Replay-Nonce".to_string()))
+    }
+
+    fn sign(&self, url: &str, nonce: &str, payload: Option<&Value>) -> Result<String, AppError> {
+        let mut protected = json!({ "alg": "ES256", "nonce": nonce, "url": url });
+        match &self.kid {
+            Some(kid) => protected["kid"] = json!(kid),
+            None => protected["jwk"] = jwk(&self.key),
+        }
+        let protected = URL_SAFE_NO_PAD.encode(protected.to_string());
+        let payload = payload
+            .map(|payload| URL_SAFE_NO_PAD.encode(payload.to_string()))
+            .unwrap_or_default();
+        let signature = self
+            .key
+            .sign(&self.rng, format!("{}.{}", protected, payload).as_bytes())
+            .map_err(|_| acme_error("cannot sign request".to_string()))?;
+
+        Ok(json!({
+            "protected": protected,
+            "payload": payload,
+            "signature": URL_SAFE_NO_PAD.encode(signature.as_ref()),
+        })
+        .to_string())
+    }
+
+    /// `token.thumbprint`, served at `/.well-known/acme-challenge/<token>`
+    fn key_authorization(&self, token: &str) -> String {
+        format!("{}.{}", token, thumbprint(&self.key))
+    }
+}
+
+/// Public account key as a JWK
+fn jwk(key: &EcdsaKeyPair) -> Value {
+    // Uncompressed point: 0x04 || x || y
+    let point = key.public_key().as_ref();
+    json!({
+        "crv": "P-256",
+        "kty": "EC",
+        "x": URL_SAFE_NO_PAD.encode(&point[1..33]),
+        "y": URL_SAFE_NO_PAD.encode(&point[33..65]),
+    })
+}
+
+/// RFC 7638 thumbprint of the account key
+fn thumbprint(key: &EcdsaKeyPair) -> String {
+    let jwk = jwk(key);
+    // Required members in lexicographic order, no whitespace
+    let canonical = format!(
+        r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+        jwk["x"].as_str().unwrap_or_default(),
+        jwk["y"].as_str().unwrap_or_default()
+    );
+    URL_SAFE_NO_PAD.encode(Sha256::digest(canonical.as_bytes()))
+}
+
+/// Load the PKCS#8 account key, generating it on first use
+fn load_account_key(path: &Path) -> Result<EcdsaKeyPair, AppError> {
+    let rng = SystemRandom::new();
+    let pkcs8 = match fs::read(path) {
+        Ok(pkcs8) => pkcs8,
+        Err(_) => {
+            let document = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng)
+                .map_err(|_| acme_error("cannot generate account key".to_string()))?;
+            if let Some(dir) = path.parent() {
+                let _ = fs::create_dir_all(dir);
+            }
+            write_account_key(path, document.as_ref())?;
+            document.as_ref().to_vec()
+        }
+    };
+    EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, &rng)
+        .map_err(|_| acme_error(format!("invalid account key {}", path.display())))
+}
+
+fn write_account_key(path: &Path, der: &[u8]) -> Result<(), AppError> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)
+        .and_then(|mut file| file.write_all(der))
+        .map_err(|e| AppError::NginxError(format!("Cannot write {}: {}", path.display(), e)))
+}
+
+fn replay_nonce(response: &reqwest::Response) -> Option<String> {
+    response
+        .headers()
+        .get("Replay-Nonce")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+}
+
+fn location(response: &reqwest::Response) -> Result<String, AppError> {
+    response
+        .headers()
+        .get(reqwest::header::LOCATION)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .ok_or_else(|| acme_error(format!("{} returned no Location", response.url())))
+}
+
+async fn read_json<T: serde::de::DeserializeOwned>(response: reqwest::Response) -> Result<T, AppError> {
+    let url = response.url().to_string();
+    response
+        .json()
+        .await
+        .map_err(|e| acme_error(format!("unexpected response from {}: {}", url, e)))
+}
+
+fn acme_error(message: String) -> AppError {
+    AppError::NginxError(format!("ACME: {}", message))
+}
+
+fn csr_error(e: rcgen::Error) -> AppError {
+    acme_error(format!("cannot create the certificate request: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{web, App, HttpRequest, HttpResponse, HttpServer};
+    use base64::engine::general_purpose::STANDARD;
+    use rcgen::{BasicConstraints, CertificateSigningRequestParams, DnType, IsCa};
+    use ring::signature::{UnparsedPublicKey, ECDSA_P256_SHA256_FIXED};
+    use std::collections::{HashMap, HashSet};
+    use std::sync::Mutex;
+    use crate::nginx::acme::store::{certificate_info, CertificateSource};
+
+    /// Minimal ACME server: verifies every JWS and nonce, checks HTTP-01 key
+    /// authorizations in the challenge directory and signs CSRs with its own CA
+    struct MockAcme {
+        base: String,
+        challenge_dir: PathBuf,
+        ca: (rcgen::Certificate, rcgen::KeyPair),
+        nonces: HashSet<String>,
+        issued_nonces: usize,
+        bad_nonce_sent: bool,
+        accounts: Vec<Value>,
+        /// id -> (status, authorization ids, certificate)
+        orders: HashMap<usize, (String, Vec<usize>, Option<String>)>,
+        /// id -> (status, domain, token)
+        authorizations: HashMap<usize, (String, String, String)>,
+    }
+
+    impl MockAcme {
+        fn nonce(&mut self) -> String {
+            self.issued_nonces += 1;
+            let nonce = format!("nonce{}", self.issued_nonces);
+            self.nonces.insert(nonce.clone());
+            nonce
+        }
+
+        /// Check the JWS for `url`; returns the payload and the account index
+        fn verify(&mut self, url: &str, body: &[u8]) -> Result<(Value, Option<usize>), String> {
+            let jws: Value = serde_json::from_slice(body).map_err(|e| e.to_string())?;
+            let field = |name: &str| jws[name].as_str().unwrap_or_default().to_string();
+            let protected: Value = serde_json::from_slice(&URL_SAFE_NO_PAD.decode(field("protected")).unwrap()).unwrap();
+
+            if protected["url"] != url || protected["alg"] != "ES256" {
+                return Err(format!("bad protected header {}", protected));
+            }
+            let nonce = protected["nonce"].as_str().unwrap_or_default();
+            if !self.nonces.remove(nonce) || !self.bad_nonce_sent {
+                self.bad_nonce_sent = true;
+                return Err("badNonce".to_string());
+            }
+
+            let (jwk, account) = match protected["kid"].as_str() {
+                Some(kid) => {
+                    let index: usize = kid.rsplit('/').next().unwrap().parse().unwrap();
+                    (self.accounts[index].clone(), Some(index))
+                }
+                None => (protected["jwk"].clone(), None),
+            };
+            let mut point = vec![4u8];
+            point.extend(URL_SAFE_NO_PAD.decode(jwk["x"].as_str().unwrap()).unwrap());
+            point.extend(URL_SAFE_NO_PAD.decode(jwk["y"].as_str().unwrap()).unwrap());
+            UnparsedPublicKey::new(&ECDSA_P256_SHA256_FIXED, point)
+                .verify(
+                    format!("{}.{}", field("protected"), field("payload")).as_bytes(),
+                    &URL_SAFE_NO_PAD.decode(field("signature")).unwrap(),
+                )
+                .map_err(|_| "bad signature".to_string())?;
+
+            let payload = match field("payload").as_str() {
+                "" => Value::Null,
+                payload => serde_json::from_slice(&URL_SAFE_NO_PAD.decode(payload).unwrap()).unwrap(),
+            };
+            let account = account.or_else(|| self.accounts.iter().position(|a| *a == jwk));
+            if account.is_none() {
+                self.accounts.push(jwk);
+            }
+            Ok((payload, account.or(Some(self.accounts.len() - 1))))
+        }
+
+        fn order_json(&self, id: usize) -> Value {
+            let (status, authorizations, certificate) = &self.orders[&id];
+            json!({
+                "status": status,
+                "authorizations": authorizations.iter().map(|a| format!("{}/authz/{}", self.base, a)).collect::<Vec<_>>(),
+                "finalize": format!("{}/finalize/{}", self.base, id),
+                "certificate": certificate.as_ref().map(|_| format!("{}/cert/{}", self.base, id)),
+            })
+        }
+
+        fn handle(&mut self, path: &str, body: &[u8]) -> HttpResponse {
+            let url = format!("{}{}", self.base, path);
+            let (payload, account) = match self.verify(&url, body) {
+                Ok(verified) => verified,
+                Err(e) => {
+                    let kind = if e == "badNonce" { "badNonce" } else { "malformed" };
+                    return HttpResponse::BadRequest()
+                        .insert_header(("Replay-Nonce", self.nonce()))
+                        .json(json!({"type": format!("urn:ietf:params:acme:error:{}", kind), "detail": e}));
+                }
+            };
+            let account = account.unwrap();
+            let nonce = self.nonce();
+            let segments: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+
+            match segments.as_slice() {
+                ["acct"] => HttpResponse::Created()
+                    .insert_header(("Replay-Nonce", nonce))
+                    .insert_header(("Location", format!("{}/acct/{}", self.base, account)))
+                    .json(json!({"status": "valid"})),
+                ["order"] => {
+                    let id = self.orders.len();
+                    let mut authorizations = Vec::new();
+                    for identifier in payload["identifiers"].as_array().unwrap() {
+                        let authz = self.authorizations.len();
+                        let domain = identifier["value"].as_str().unwrap().to_string();
+                        self.authorizations
+                            .insert(authz, ("pending".to_string(), domain, format!("token-{}-{}", id, authz)));
+                        authorizations.push(authz);
+                    }
+                    self.orders.insert(id, ("pending".to_string(), authorizations, None));
+                    HttpResponse::Created()
+                        .insert_header(("Replay-Nonce", nonce))
+                        .insert_header(("Location", format!("{}/order/{}", self.base, id)))
+                        .json(self.order_json(id))
+                }
+                ["order", id] => HttpResponse::Ok()
+                    .insert_header(("Replay-Nonce", nonce))
+                    .json(self.order_json(id.parse().unwrap())),
+                ["authz", id] | ["chall", id] => {
+                    let id: usize = id.parse().unwrap();
+                    if segments[0] == "chall" {
+                        // HTTP-01 validation: the file nginx would serve
+                        let (_, _, token) = &self.authorizations[&id];
+                        let jwk = &self.accounts[account];
+                        let expected = format!(
+                            "{}.{}",
+                            token,
+                            URL_SAFE_NO_PAD.encode(Sha256::digest(
+                                format!(r#"{{"crv":"P-256","kty":"EC","x":{},"y":{}}}"#, jwk["x"], jwk["y"]).as_bytes()
+                            ))
+                        );
+                        let served = fs::read_to_string(self.challenge_dir.join(token)).unwrap_or_default();
+                        let status = if served == expected { "valid" } else { "invalid" };
+                        self.authorizations.get_mut(&id).unwrap().0 = status.to_string();
+                    }
+                    let (status, domain, token) = &self.authorizations[&id];
+                    HttpResponse::Ok().insert_header(("Replay-Nonce", nonce)).json(json!({
+                        "status": status,
+                        "identifier": {"type": "dns", "value": domain},
+                        "challenges": [{
+                            "type": "http-01",
+                            "url": format!("{}/chall/{}", self.base, id),
+                            "token": token,
+                        }],
+                    }))
+                }
+                ["finalize", id] => {
+                    let id: usize = id.parse().unwrap();
+                    let all_valid = self.orders[&id].1.iter().all(|a| self.authorizations[a].0 == "valid");
+                    let der = URL_SAFE_NO_PAD.decode(payload["csr"].as_str().unwrap()).unwrap();
+                    let pem = format!(
+                        "-----BEGIN CERTIFICATE REQUEST-----\n{}\n-----END CERTIFICATE REQUEST-----\n",
+                        STANDARD.encode(der)
+                    );
+                    let leaf = CertificateSigningRequestParams::from_pem(&pem)
+                        .unwrap()
+                        .signed_by(&self.ca.0, &self.ca.1)
+                        .unwrap();
+                    let order = self.orders.get_mut(&id).unwrap();
+                    if all_valid {
+                        order.0 = "valid".to_string();
+                        order.2 = Some(format!("{}{}", leaf.pem(), self.ca.0.pem()));
+                    } else {
+                        order.0 = "invalid".to_string();
+                    }
+                    HttpResponse::Ok().insert_header(("Replay-Nonce", nonce)).json(self.order_json(id))
+                }
+                ["cert", id] => HttpResponse::Ok()
+                    .insert_header(("Replay-Nonce", nonce))
+                    .content_type("application/pem-certificate-chain")
+                    .body(self.orders[&id.parse().unwrap()].2.clone().unwrap()),
+                _ => HttpResponse::NotFound().finish(),
+            }
+        }
+    }
+
+    async fn mock_endpoint(req: HttpRequest, body: web::Bytes, state: web::Data<Mutex<MockAcme>>) -> HttpResponse {
+        let mut mock = state.lock().unwrap();
+        match (req.method().as_str(), req.path()) {
+            ("GET", "/dir") => HttpResponse::Ok().json(json!({
+                "newNonce": format!("{}/nonce", mock.base),
+                "newAccount": format!("{}/acct", mock.base),
+                "newOrder": format!("{}/order", mock.base),
+            })),
+            ("HEAD", "/nonce") => HttpResponse::Ok().insert_header(("Replay-Nonce", mock.nonce())).finish(),
+            ("POST", path) => mock.handle(path, &body),
+            _ => HttpResponse::NotFound().finish(),
+        }
+    }
+
+    /// Start the mock server; returns its directory URL
+    fn start_mock(challenge_dir: PathBuf) -> String {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+
+        let ca_key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.distinguished_name.push(DnType::CommonName, "Mock ACME CA");
+        let ca_cert = params.self_signed(&ca_key).unwrap();
+
+        let state = web::Data::new(Mutex::new(MockAcme {
+            base: base.clone(),
+            challenge_dir,
+            ca: (ca_cert, ca_key),
+            nonces: HashSet::new(),
+            issued_nonces: 0,
+            bad_nonce_sent: false,
+            accounts: Vec::new(),
+            orders: HashMap::new(),
+            authorizations: HashMap::new(),
+        }));
+        let server = HttpServer::new(move || App::new().app_data(state.clone()).default_service(web::to(mock_endpoint)))
+            .workers(1)
+            .listen(listener)
+            .unwrap()
+            .run();
+        actix_web::rt::spawn(server);
+        format!("{}/dir", base)
+    }
+
+    fn acme_config(directory_url: String) -> AcmeConfig {
+        AcmeConfig {
+            directory_url,
+            email: Some("ops@example.com".to_string()),
+            ca_file: std::env::var("ACME_CA_FILE").ok(),
+            renew_before_days: 30,
+            check_interval_secs: 3600,
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_issue_against_local_acme_server() {
+        let dir = std::env::temp_dir().join(format!("ura-acme-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let challenge_dir = dir.join("challenges");
+        let client = AcmeClient::with_dirs(&acme_config(start_mock(challenge_dir.clone())), dir.join("certs"), &challenge_dir)
+            .unwrap();
+
+        let domains = vec!["app.example.com".to_string(), "www.app.example.com".to_string()];
+        let mut placeholder = crate::nginx::staging::Changeset::new();
+        client.store().stage_placeholder(&mut placeholder, "app.example.com", &domains).unwrap();
+        placeholder.apply().unwrap();
+
+        let (chain, key) = client.order(&domains).await.unwrap();
+        client.store().save("app.example.com", CertificateSource::Acme, &chain, &key).unwrap();
+        let info = client.store().info("app.example.com").unwrap();
+        assert!(!info.self_signed);
+        assert_eq!(info.issuer, "CN=Mock ACME CA");
+        assert!(info.covers(&domains));
+        assert_eq!(fs::read_dir(&challenge_dir).unwrap().count(), 0);
+
+        // A renewal reuses the stored account key
+        let account_key = fs::read(dir.join("certs/account.pk8")).unwrap();
+        client.order(&domains[..1]).await.unwrap();
+        assert_eq!(fs::read(dir.join("certs/account.pk8")).unwrap(), account_key);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    /// Against a running Pebble started with `PEBBLE_VA_ALWAYS_VALID=1`:
+    ///
+    /// `PEBBLE_DIRECTORY_URL=https://localhost:14000/dir ACME_CA_FILE=pebble.minica.pem cargo test -- --ignored pebble`
+    #[actix_web::test]
+    #[ignore]
+    async fn test_issue_against_pebble() {
+        let directory_url = std::env::var("PEBBLE_DIRECTORY_URL").expect("PEBBLE_DIRECTORY_URL is set");
+        let dir = std::env::temp_dir().join(format!("ura-pebble-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let client = AcmeClient::with_dirs(&acme_config(directory_url), dir.join("certs"), dir.join("challenges"))
+            .unwrap();
+
+        let (chain, _) = client.order(&["pebble.example.com".to_string()]).await.unwrap();
+        let info = certificate_info("pebble.example.com", CertificateSource::Acme, chain.as_bytes()).unwrap();
+        assert!(!info.self_signed);
+        assert_eq!(info.sans, vec!["pebble.example.com"]);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,31 @@
+//! Managed TLS certificates for nginx proxies
+//!
+//! Proxies created with `acme: true` get a certificate from an ACME directory
+//! (Let's Encrypt by default) using the HTTP-01 challenge, which nginx serves from
+//! [`CHALLENGE_DIR`]. Certificates live in [`CERTIFICATES_DIR`] under the proxy's
+//! first server name and are renewed in the background before they expire.
+//!
+//! - [`client`]: ACME protocol client (account, orders, challenges, finalization)
+//! - [`store`]: Certificate files, self-signed placeholders and certificate details
+//! - [`renewal`]: Issuance for new sites and periodic renewal
+
+pub mod client;
+pub mod renewal;
+pub mod store;
+
+pub use client::AcmeClient;
+pub use store::{CertificateInfo, CertificateSource, CertificateStore};
+
+/// Where issued certificates are stored, one directory per primary domain
+pub const CERTIFICATES_DIR: &str = "/etc/ubuntu-resource-api/certs";
+
+/// Where HTTP-01 challenge responses are written for nginx to serve
+pub const CHALLENGE_DIR: &str = "/var/lib/ubuntu-resource-api/acme-challenge";
+
+/// Certificate and key paths nginx is configured with for `domain`
+pub fn certificate_paths(domain: &str) -> (String, String) {
+    (
+        format!("{}/{}/fullchain.pem", CERTIFICATES_DIR, domain),
+        format!("{}/{}/privkey.pem", CERTIFICATES_DIR, domain),
+    )
+}
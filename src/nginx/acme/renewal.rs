@@ -0,0 +1,172 @@
+// Issue certificates for new ACME sites and renew them before they expire
+
+use std::fs;
+use std::time::Duration;
+
+use actix_web::web;
+use log::{info, warn};
+
+use crate::error::AppError;
+use crate::nginx::acme::{CertificateInfo, CertificateSource};
+use crate::nginx::config::import::import_site;
+use crate::nginx::service::commit;
+use crate::nginx::staging::Changeset;
+use crate::system::models::AppState;
+
+/// Whether `domains` need a (new) certificate
+///
+/// True when there is none, it is a self-signed placeholder, it does not cover
+/// every name, or it expires within `renew_before_days` of `now`. An uploaded
+/// or self-signed certificate of the same name is never replaced.
+pub fn needs_issuance(info: Option<&CertificateInfo>, domains: &[String], renew_before_days: u32, now: i64) -> bool {
+    match info {
+        None => true,
+        Some(info) if info.source != CertificateSource::Acme => false,
+        Some(info) => {
+            info.self_signed
+                || !info.covers(domains)
+                || info.not_after - now < i64::from(renew_before_days) * 86400
+        }
+    }
+}
+
+/// Fail unless ACME may write certificate `name`: it does not exist or came from ACME
+///
+/// Uploaded and self-signed certificates share the store with ACME ones, and
+/// a site must not silently swap out a certificate the user provided.
+pub fn check_acme_owned(info: Option<&CertificateInfo>) -> Result<(), AppError> {
+    match info {
+        Some(info) if info.source != CertificateSource::Acme => Err(AppError::Conflict(format!(
+            "Certificate '{}' is {} and is not replaced through ACME",
+            info.name,
+            info.source.as_str()
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// The name a certificate for `domains` is stored under, its first domain
+pub fn certificate_name(domains: &[String]) -> Result<&str, AppError> {
+    domains
+        .first()
+        .map(String::as_str)
+        .ok_or_else(|| AppError::ValidationError("No domain to issue a certificate for".to_string()))
+}
+
+/// Server names of every site that uses ACME certificates
+pub fn acme_sites(sites_available: &str) -> Vec<Vec<String>> {
+    let Ok(entries) = fs::read_dir(sites_available) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .filter_map(|entry| {
+            let content = fs::read_to_string(entry.path()).ok()?;
+            let name = entry.file_name().to_str()?.to_string();
+            let proxy = import_site(&name, &content);
+            proxy.acme.then(|| proxy.domains())
+        })
+        .collect()
+}
+
+/// Issue a certificate for `domains`, then store it and reload nginx so it is served
+///
+/// The nginx lock is only taken once the ACME server is done. The key and
+/// chain are then staged, tested on a copy of the tree and moved into place
+/// under it like any other nginx change, so no test or reload ever sees a
+/// half-replaced pair.
+pub async fn issue_and_reload(state: &web::Data<AppState>, domains: &[String]) -> Result<CertificateInfo, AppError> {
+    let name = certificate_name(domains)?.to_string();
+    check_acme_owned(state.acme.store().info(&name).as_ref())?;
+    let (chain, key) = state.acme.order(domains).await?;
+    let _lock = state.nginx_lock.lock().await;
+    let state = state.clone();
+    web::block(move || {
+        let store = state.acme.store();
+        // The certificate may have been uploaded while the order ran
+        check_acme_owned(store.info(&name).as_ref())?;
+        let mut changes = Changeset::new();
+        store.stage(&mut changes, &name, CertificateSource::Acme, &chain, &key);
+        commit(&state.nginx_config(), &*state.nginx_controller(), changes)?;
+        store
+            .info(&name)
+            .ok_or_else(|| AppError::NginxError(format!("The issued certificate of {} cannot be parsed", name)))
+    })
+    .await
+    .map_err(|e| AppError::NginxError(e.to_string()))?
+}
+
+/// Check every ACME site every `interval`, issuing what is missing or due
+///
+/// The first check runs immediately, which replaces placeholders left by
+/// sites created while the ACME server was unreachable.
+pub fn spawn_renewal(state: web::Data<AppState>, interval: Duration) {
+    actix_web::rt::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let sites_available = state.nginx_config().sites_available_path;
+            for domains in acme_sites(&sites_available) {
+                let name = match certificate_name(&domains) {
+                    Ok(name) => name,
+                    Err(e) => {
+                        warn!("⚠️  Skipping certificate renewal: {}", e);
+                        continue;
+                    }
+                };
+                let info = state.acme.store().info(name);
+                let now = chrono::Utc::now().timestamp();
+                if !needs_issuance(info.as_ref(), &domains, state.acme.renew_before_days(), now) {
+                    continue;
+                }
+                match issue_and_reload(&state, &domains).await {
+                    Ok(info) => info!("🔐 Certificate for {} renewed until {}", name, info.not_after),
+                    Err(e) => warn!("⚠️  Certificate renewal for {} failed: {}", name, e),
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(not_after: i64, self_signed: bool) -> CertificateInfo {
+        CertificateInfo {
+            name: "app.example.com".to_string(),
+            source: crate::nginx::acme::CertificateSource::Acme,
+            subject: "CN=app.example.com".to_string(),
+            issuer: "CN=Test CA".to_string(),
+            sans: vec!["app.example.com".to_string(), "www.app.example.com".to_string()],
+            not_before: 0,
+            not_after,
+            self_signed,
+        }
+    }
+
+    #[test]
+    fn test_needs_issuance() {
+        let now = 1_700_000_000;
+        let domains = vec!["app.example.com".to_string()];
+
+        assert!(needs_issuance(None, &domains, 30, now));
+        assert!(!needs_issuance(Some(&info(now + 60 * 86400, false)), &domains, 30, now));
+        assert!(needs_issuance(Some(&info(now + 10 * 86400, false)), &domains, 30, now));
+        assert!(needs_issuance(Some(&info(now + 60 * 86400, true)), &domains, 30, now));
+        assert_eq!(certificate_name(&domains).unwrap(), "app.example.com");
+        assert!(matches!(certificate_name(&[]), Err(AppError::ValidationError(_))));
+
+        let more = vec!["app.example.com".to_string(), "api.example.com".to_string()];
+        assert!(needs_issuance(Some(&info(now + 60 * 86400, false)), &more, 30, now));
+
+        // A certificate the user provided is theirs, however close to expiry
+        for source in [CertificateSource::Uploaded, CertificateSource::SelfSigned] {
+            let users = CertificateInfo { source, ..info(now + 10 * 86400, true) };
+            assert!(!needs_issuance(Some(&users), &domains, 30, now));
+            assert!(matches!(check_acme_owned(Some(&users)), Err(AppError::Conflict(_))));
+        }
+        assert!(check_acme_owned(Some(&info(now, true))).is_ok());
+        assert!(check_acme_owned(None).is_ok());
+    }
+}
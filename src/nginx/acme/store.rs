@@ -0,0 +1,344 @@
+// Certificate files on disk, one directory per certificate
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use rcgen::{CertificateParams, DistinguishedName, DnType, KeyPair};
+use serde::{Deserialize, Serialize};
+use x509_parser::extensions::GeneralName;
+use x509_parser::pem::parse_x509_pem;
+
+use crate::error::AppError;
+use crate::nginx::staging::Changeset;
+
+/// Where a stored certificate came from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum CertificateSource {
+    /// Issued and renewed through ACME, named after the proxy's first domain
+    Acme,
+    /// PEM certificate chain and key provided by the user
+    Uploaded,
+    /// Generated by the service for internal proxies
+    SelfSigned,
+}
+
+impl CertificateSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CertificateSource::Acme => "acme",
+            CertificateSource::Uploaded => "uploaded",
+            CertificateSource::SelfSigned => "self_signed",
+        }
+    }
+
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim() {
+            "acme" => Some(CertificateSource::Acme),
+            "uploaded" => Some(CertificateSource::Uploaded),
+            "self_signed" => Some(CertificateSource::SelfSigned),
+            _ => None,
+        }
+    }
+}
+
+/// Summary of a stored certificate
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CertificateInfo {
+    pub name: String,
+    pub source: CertificateSource,
+    pub subject: String,
+    pub issuer: String,
+    /// DNS subject alternative names
+    pub sans: Vec<String>,
+    /// Unix timestamp
+    pub not_before: i64,
+    /// Unix timestamp
+    pub not_after: i64,
+    pub self_signed: bool,
+}
+
+impl CertificateInfo {
+    /// Whether every name in `domains` is covered by the certificate
+    pub fn covers(&self, domains: &[String]) -> bool {
+        domains.iter().all(|domain| self.sans.contains(domain))
+    }
+}
+
+/// `<dir>/<name>/fullchain.pem` and `<dir>/<name>/privkey.pem` for each certificate,
+/// with the [`CertificateSource`] in `<dir>/<name>/source`
+pub struct CertificateStore {
+    dir: PathBuf,
+}
+
+impl CertificateStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        CertificateStore { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn chain_path(&self, name: &str) -> PathBuf {
+        self.dir.join(name).join("fullchain.pem")
+    }
+
+    pub fn key_path(&self, name: &str) -> PathBuf {
+        self.dir.join(name).join("privkey.pem")
+    }
+
+    /// Write a certificate chain and its private key, replacing any previous pair
+    ///
+    /// The files go through [`CertificateStore::stage`]; if one cannot be
+    /// written the earlier ones are put back.
+    pub fn save(&self, name: &str, source: CertificateSource, chain_pem: &str, key_pem: &str) -> Result<(), AppError> {
+        let mut changes = Changeset::new();
+        self.stage(&mut changes, name, source, chain_pem, key_pem);
+        changes.apply_or_undo()
+    }
+
+    /// Stage writing a certificate chain and its private key into `changes`
+    ///
+    /// Each file is replaced through a temp file and a rename, so a reader sees
+    /// either the old or the new file; callers that let nginx read the pair
+    /// hold the nginx lock until every file is in place.
+    pub fn stage(&self, changes: &mut Changeset, name: &str, source: CertificateSource, chain_pem: &str, key_pem: &str) {
+        changes.create_dir(&self.dir.join(name));
+        changes.write_private(&self.key_path(name), key_pem);
+        changes.write(&self.chain_path(name), chain_pem);
+        changes.write(&self.dir.join(name).join("source"), source.as_str());
+    }
+
+    /// The stored certificate `name`, or `None` if it does not exist or cannot be parsed
+    pub fn info(&self, name: &str) -> Option<CertificateInfo> {
+        let pem = fs::read(self.chain_path(name)).ok()?;
+        let source = fs::read_to_string(self.dir.join(name).join("source"))
+            .ok()
+            .and_then(|source| CertificateSource::parse(&source))
+            .unwrap_or(CertificateSource::Acme);
+        certificate_info(name, source, &pem).ok()
+    }
+
+    /// Every stored certificate, sorted by name
+    pub fn list(&self) -> Vec<CertificateInfo> {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+        let mut certificates: Vec<CertificateInfo> = entries
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| self.info(entry.file_name().to_str()?))
+            .collect();
+        certificates.sort_by(|a, b| a.name.cmp(&b.name));
+        certificates
+    }
+
+    /// Delete certificate `name` and its key
+    pub fn remove(&self, name: &str) -> Result<(), AppError> {
+        let dir = self.dir.join(name);
+        if !dir.is_dir() {
+            return Err(AppError::NotFound(format!("Certificate '{}' not found", name)));
+        }
+        fs::remove_dir_all(&dir)
+            .map_err(|e| AppError::NginxError(format!("Cannot remove {}: {}", dir.display(), e)))
+    }
+
+    /// Stage a short-lived self-signed certificate for `domains` unless `name` already exists
+    ///
+    /// Lets nginx load a TLS site before its first ACME certificate is issued.
+    pub fn stage_placeholder(&self, changes: &mut Changeset, name: &str, domains: &[String]) -> Result<(), AppError> {
+        if self.chain_path(name).exists() && self.key_path(name).exists() {
+            return Ok(());
+        }
+        let (chain, key) = self_signed(domains, 7)?;
+        self.stage(changes, name, CertificateSource::Acme, &chain, &key);
+        Ok(())
+    }
+}
+
+/// A self-signed certificate for `domains` valid for `days`, as `(cert_pem, key_pem)`
+pub fn self_signed(domains: &[String], days: i64) -> Result<(String, String), AppError> {
+    let error = |e: rcgen::Error| AppError::NginxError(format!("Cannot generate certificate: {}", e));
+
+    let key = KeyPair::generate().map_err(error)?;
+    let mut params = CertificateParams::new(domains.to_vec()).map_err(error)?;
+    let mut name = DistinguishedName::new();
+    name.push(DnType::CommonName, domains.first().cloned().unwrap_or_default());
+    params.distinguished_name = name;
+    let now = time::OffsetDateTime::now_utc();
+    params.not_before = now - time::Duration::minutes(5);
+    params.not_after = now + time::Duration::days(days);
+
+    let cert = params.self_signed(&key).map_err(error)?;
+    Ok((cert.pem(), key.serialize_pem()))
+}
+
+/// Check an uploaded chain and key before they are stored
+///
+/// Every PEM block of the chain must be a certificate, each one must be issued
+/// by the next, and the key (PKCS#8, or PKCS#1 for RSA) must belong to the first.
+pub fn validate_pem_pair(chain_pem: &str, key_pem: &str) -> Result<(), AppError> {
+    let invalid = |message: String| AppError::ValidationError(message);
+
+    let blocks: Vec<_> = x509_parser::pem::Pem::iter_from_buffer(chain_pem.as_bytes())
+        .collect::<Result<_, _>>()
+        .map_err(|e| invalid(format!("Certificate chain is not valid PEM: {}", e)))?;
+    if blocks.is_empty() {
+        return Err(invalid("Certificate chain contains no certificate".to_string()));
+    }
+    let certificates = blocks
+        .iter()
+        .enumerate()
+        .map(|(i, block)| {
+            if block.label != "CERTIFICATE" {
+                return Err(invalid(format!("Chain entry {} is a {}, not a CERTIFICATE", i + 1, block.label)));
+            }
+            block
+                .parse_x509()
+                .map_err(|e| invalid(format!("Chain entry {} cannot be parsed: {}", i + 1, e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, pair) in certificates.windows(2).enumerate() {
+        if pair[0].issuer() != pair[1].subject() {
+            return Err(invalid(format!(
+                "Chain entry {} is issued by '{}', but entry {} is '{}'",
+                i + 1,
+                pair[0].issuer(),
+                i + 2,
+                pair[1].subject()
+            )));
+        }
+    }
+
+    let key = x509_parser::pem::Pem::iter_from_buffer(key_pem.as_bytes())
+        .next()
+        .ok_or_else(|| invalid("Private key contains no PEM block".to_string()))?
+        .map_err(|e| invalid(format!("Private key is not valid PEM: {}", e)))?;
+    let public_key = match key.label.as_str() {
+        "PRIVATE KEY" => KeyPair::try_from(key.contents.as_slice())
+            .map(|key| key.public_key_raw().to_vec())
+            .map_err(|e| invalid(format!("Private key cannot be parsed: {}", e)))?,
+        "RSA PRIVATE KEY" => ring::signature::RsaKeyPair::from_der(&key.contents)
+            .map(|key| ring::signature::KeyPair::public_key(&key).as_ref().to_vec())
+            .map_err(|e| invalid(format!("Private key cannot be parsed: {}", e)))?,
+        tag => {
+            return Err(invalid(format!(
+                "Unsupported private key type '{}'; convert it with `openssl pkcs8 -topk8 -nocrypt`",
+                tag
+            )))
+        }
+    };
+    if public_key != certificates[0].public_key().subject_public_key.data.as_ref() {
+        return Err(invalid("Private key does not match the certificate".to_string()));
+    }
+    Ok(())
+}
+
+/// Parse the first certificate of a PEM chain
+pub fn certificate_info(name: &str, source: CertificateSource, pem: &[u8]) -> Result<CertificateInfo, AppError> {
+    let invalid = |e: String| AppError::ValidationError(format!("Invalid certificate: {}", e));
+
+    let (_, pem) = parse_x509_pem(pem).map_err(|e| invalid(e.to_string()))?;
+    let cert = pem.parse_x509().map_err(|e| invalid(e.to_string()))?;
+
+    let sans = match cert.subject_alternative_name() {
+        Ok(Some(extension)) => extension
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(dns) => Some(dns.to_string()),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Ok(CertificateInfo {
+        name: name.to_string(),
+        source,
+        subject: cert.subject().to_string(),
+        issuer: cert.issuer().to_string(),
+        sans,
+        not_before: cert.validity().not_before.timestamp(),
+        not_after: cert.validity().not_after.timestamp(),
+        self_signed: cert.subject() == cert.issuer(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_placeholder_and_info() {
+        let dir = std::env::temp_dir().join(format!("ura-certs-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = CertificateStore::new(&dir);
+        let domains = vec!["app.example.com".to_string(), "www.app.example.com".to_string()];
+
+        assert!(store.info("app.example.com").is_none());
+        let mut changes = Changeset::new();
+        store.stage_placeholder(&mut changes, "app.example.com", &domains).unwrap();
+        assert!(!dir.exists());
+        changes.apply().unwrap();
+
+        let info = store.info("app.example.com").unwrap();
+        assert!(info.self_signed);
+        assert!(info.covers(&domains));
+        assert_eq!(info.subject, "CN=app.example.com");
+        let days = (info.not_after - chrono::Utc::now().timestamp()) / 86400;
+        assert!((6..=7).contains(&days));
+
+        // An existing certificate is left alone
+        let mut changes = Changeset::new();
+        store.stage_placeholder(&mut changes, "app.example.com", &domains).unwrap();
+        assert!(changes.staged().is_empty());
+
+        let mode = || {
+            let permissions = fs::metadata(store.key_path("app.example.com")).unwrap().permissions();
+            std::os::unix::fs::PermissionsExt::mode(&permissions) & 0o777
+        };
+        assert_eq!(mode(), 0o600);
+
+        // A replaced pair keeps its key private
+        let (chain, key) = self_signed(&domains, 30).unwrap();
+        store.save("app.example.com", CertificateSource::Uploaded, &chain, &key).unwrap();
+        assert_eq!(fs::read_to_string(store.chain_path("app.example.com")).unwrap(), chain);
+        assert_eq!(store.info("app.example.com").unwrap().source, CertificateSource::Uploaded);
+        assert_eq!(mode(), 0o600);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_validate_pem_pair() {
+        let domains = vec!["internal.example".to_string()];
+        let (cert, key) = self_signed(&domains, 30).unwrap();
+        let (other_cert, other_key) = self_signed(&domains, 30).unwrap();
+
+        assert!(validate_pem_pair(&cert, &key).is_ok());
+        let err = validate_pem_pair(&cert, &other_key).unwrap_err();
+        assert!(err.to_string().contains("does not match"));
+        assert!(validate_pem_pair("not a certificate", &key).is_err());
+        assert!(validate_pem_pair(&cert, &cert).is_err());
+
+        // Leaf followed by its issuer, then by an unrelated certificate
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = rcgen::IsCa::Ca(rcgen::BasicConstraints::Unconstrained);
+        ca_params.distinguished_name.push(DnType::CommonName, "Internal CA");
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let leaf_key = KeyPair::generate().unwrap();
+        let leaf = CertificateParams::new(domains)
+            .unwrap()
+            .signed_by(&leaf_key, &ca, &ca_key)
+            .unwrap();
+
+        let chain = format!("{}{}", leaf.pem(), ca.pem());
+        assert!(validate_pem_pair(&chain, &leaf_key.serialize_pem()).is_ok());
+        let chain = format!("{}{}", leaf.pem(), other_cert);
+        assert!(validate_pem_pair(&chain, &leaf_key.serialize_pem()).is_err());
+    }
+}
@@ -0,0 +1,233 @@
+// Typed syntax tree for nginx configuration files
+
+use std::fmt;
+
+/// A parsed configuration file or block body
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Config {
+    pub nodes: Vec<Node>,
+}
+
+/// One entry of a file or block body
+#[derive(Debug, Clone, PartialEq)]
+pub enum Node {
+    Directive(Directive),
+    Comment(Comment),
+    /// One or more empty lines between entries, kept so formatting survives a round trip
+    Blank,
+}
+
+/// A simple (`name args;`) or block (`name args { ... }`) directive
+#[derive(Debug, Clone, PartialEq)]
+pub struct Directive {
+    pub name: String,
+    pub args: Vec<Arg>,
+    /// Body of a block directive, `None` for a simple directive
+    pub block: Option<Vec<Node>>,
+    /// 1-based line of the directive name
+    pub line: usize,
+}
+
+/// A directive argument as written, including how it was quoted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Arg {
+    /// Text between the quotes, with escapes left as written
+    pub value: String,
+    pub quote: Quote,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quote {
+    None,
+    Single,
+    Double,
+}
+
+/// A `#` comment
+#[derive(Debug, Clone, PartialEq)]
+pub struct Comment {
+    /// Text after the `#`
+    pub text: String,
+    pub line: usize,
+    /// Written after a directive on the same line
+    pub inline: bool,
+}
+
+impl Arg {
+    pub fn plain(value: impl Into<String>) -> Self {
+        Arg {
+            value: value.into(),
+            quote: Quote::None,
+        }
+    }
+}
+
+impl Directive {
+    /// A simple directive with unquoted arguments
+    pub fn new(name: impl Into<String>, args: &[&str]) -> Self {
+        Directive {
+            name: name.into(),
+            args: args.iter().map(|a| Arg::plain(*a)).collect(),
+            block: None,
+            line: 0,
+        }
+    }
+
+    /// A block directive with unquoted arguments
+    pub fn block(name: impl Into<String>, args: &[&str], body: Vec<Node>) -> Self {
+        Directive {
+            block: Some(body),
+            ..Directive::new(name, args)
+        }
+    }
+
+    /// Argument values without quotes
+    pub fn arg_values(&self) -> Vec<&str> {
+        self.args.iter().map(|a| a.value.as_str()).collect()
+    }
+
+    pub fn first_arg(&self) -> Option<&str> {
+        self.args.first().map(|a| a.value.as_str())
+    }
+
+    pub fn is_block(&self) -> bool {
+        self.block.is_some()
+    }
+
+    pub fn is_include(&self) -> bool {
+        self.name == "include" && self.block.is_none()
+    }
+
+    /// Directives directly inside this block
+    pub fn children(&self) -> impl Iterator<Item = &Directive> {
+        directives(self.block.as_deref().unwrap_or_default())
+    }
+
+    /// First child directive called `name`
+    pub fn child(&self, name: &str) -> Option<&Directive> {
+        self.children().find(|d| d.name == name)
+    }
+}
+
+impl Config {
+    /// Top-level directives
+    pub fn directives(&self) -> impl Iterator<Item = &Directive> {
+        directives(&self.nodes)
+    }
+
+    /// Every directive in the tree, depth first
+    pub fn walk(&self) -> Vec<&Directive> {
+        fn collect<'a>(nodes: &'a [Node], out: &mut Vec<&'a Directive>) {
+            for directive in directives(nodes) {
+                out.push(directive);
+                if let Some(block) = &directive.block {
+                    collect(block, out);
+                }
+            }
+        }
+
+        let mut out = Vec::new();
+        collect(&self.nodes, &mut out);
+        out
+    }
+
+    /// `server` blocks at the top level or inside an `http` block
+    pub fn servers(&self) -> Vec<&Directive> {
+        self.directives()
+            .flat_map(|d| match d.name.as_str() {
+                "server" if d.is_block() => vec![d],
+                "http" => d.children().filter(|c| c.name == "server" && c.is_block()).collect(),
+                _ => Vec::new(),
+            })
+            .collect()
+    }
+
+    /// Paths referenced by `include` directives anywhere in the tree
+    pub fn includes(&self) -> Vec<&str> {
+        self.walk()
+            .into_iter()
+            .filter(|d| d.is_include())
+            .filter_map(|d| d.first_arg())
+            .collect()
+    }
+}
+
+/// The directives among `nodes`, skipping comments and blank lines
+pub fn directives(nodes: &[Node]) -> impl Iterator<Item = &Directive> {
+    nodes.iter().filter_map(|node| match node {
+        Node::Directive(directive) => Some(directive),
+        _ => None,
+    })
+}
+
+const INDENT: &str = "    ";
+
+/// Write `nodes` at `depth`, one entry per line
+fn write_nodes(f: &mut fmt::Formatter<'_>, nodes: &[Node], depth: usize) -> fmt::Result {
+    let indent = INDENT.repeat(depth);
+    let last = nodes.len().saturating_sub(1);
+
+    for (i, node) in nodes.iter().enumerate() {
+        match node {
+            Node::Directive(directive) => {
+                write!(f, "{}{}", indent, directive.name)?;
+                for arg in &directive.args {
+                    write!(f, " {}", arg)?;
+                }
+                match &directive.block {
+                    Some(body) => {
+                        // An inline comment after `{` stays on that line
+                        let (inline, body) = match body.first() {
+                            Some(Node::Comment(c)) if c.inline => (Some(c), &body[1..]),
+                            _ => (None, &body[..]),
+                        };
+                        write!(f, " {{")?;
+                        if let Some(comment) = inline {
+                            write!(f, " #{}", comment.text)?;
+                        }
+                        writeln!(f)?;
+                        write_nodes(f, body, depth + 1)?;
+                        write!(f, "{}}}", indent)?;
+                    }
+                    None => write!(f, ";")?,
+                }
+                // A following inline comment belongs on this line
+                if let Some(Node::Comment(c)) = nodes.get(i + 1) {
+                    if c.inline {
+                        write!(f, " #{}", c.text)?;
+                    }
+                }
+                writeln!(f)?;
+            }
+            Node::Comment(comment) if comment.inline && i > 0 => {}
+            Node::Comment(comment) => writeln!(f, "{}#{}", indent, comment.text)?,
+            // Blank lines only separate entries
+            Node::Blank if i == 0 || i == last => {}
+            Node::Blank => writeln!(f)?,
+        }
+    }
+    Ok(())
+}
+
+impl fmt::Display for Arg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.quote {
+            Quote::None => write!(f, "{}", self.value),
+            Quote::Single => write!(f, "'{}'", self.value),
+            Quote::Double => write!(f, "\"{}\"", self.value),
+        }
+    }
+}
+
+impl fmt::Display for Directive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_nodes(f, std::slice::from_ref(&Node::Directive(self.clone())), 0)
+    }
+}
+
+/// Serializes with four-space indentation, one directive per line
+impl fmt::Display for Config {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write_nodes(f, &self.nodes, 0)
+    }
+}
@@ -0,0 +1,635 @@
+// Reconstruct NginxProxy definitions from site files on disk
+
+use std::path::Path;
+
+use crate::nginx::acme::CERTIFICATES_DIR;
+use crate::nginx::config::ast::{Arg, Config, Directive, Node, Quote};
+use crate::nginx::config::{generate_nginx_config, parse, EXTRA_CONFIG_COMMENT};
+use crate::nginx::models::{BasicAuth, LocationRoute, NginxProxy, RateLimit, SiteKind};
+
+/// Rebuild the site a file was generated from
+///
+/// The site kind is guessed from the first server block and every plausible
+/// kind is tried in turn. Directives that the generator does not emit become
+/// `extra_config`; a second server block is read as the HTTP to HTTPS
+/// redirect. The result is only marked managed if generating it again yields
+/// the same directives as the file; anything else (other server blocks,
+/// upstreams, custom directives in places the generator cannot put them,
+/// edited template values) is returned with `unmanaged: true` and no extra config.
+pub fn import_site(name: &str, content: &str) -> NginxProxy {
+    let Ok(config) = parse(content) else {
+        return unmanaged(name, "unknown".to_string(), "unknown".to_string(), false);
+    };
+    let (domain, backend, ssl) = summarize_site(&config);
+
+    let servers = config.servers();
+    let zones = config
+        .directives()
+        .filter(|d| matches!(d.name.as_str(), "limit_req_zone" | "limit_conn_zone"))
+        .count();
+    if !(1..=2).contains(&servers.len()) || config.directives().count() != servers.len() + zones {
+        return unmanaged(name, domain, backend, ssl);
+    }
+    let server = servers[0];
+
+    let mut proxy = NginxProxy {
+        name: name.to_string(),
+        domain,
+        backend,
+        kind: SiteKind::Proxy,
+        ssl,
+        extra_config: None,
+        acme: false,
+        certificate: None,
+        redirect_http: servers.len() == 2,
+        hsts: server
+            .children()
+            .any(|d| d.name == "add_header" && d.first_arg() == Some("Strict-Transport-Security")),
+        ocsp_stapling: server.child("ssl_stapling").and_then(|d| d.first_arg()) == Some("on"),
+        // Usernames are not in the config; the proxy listing reads them from the htpasswd file
+        basic_auth: server
+            .child("auth_basic")
+            .and_then(|d| d.first_arg())
+            .filter(|realm| *realm != "off")
+            .map(|realm| BasicAuth {
+                realm: realm.to_string(),
+                users: Vec::new(),
+            }),
+        allow: addresses(server, "allow"),
+        deny: addresses(server, "deny"),
+        rate_limit: rate_limit(&config, server),
+        connection_limit: server
+            .child("limit_conn")
+            .and_then(|d| d.arg_values().get(1)?.parse().ok()),
+        unmanaged: false,
+    };
+    // A certificate from the store is ACME-managed if the site serves challenges for it
+    let stored = server
+        .child("ssl_certificate")
+        .and_then(|d| d.first_arg())
+        .and_then(stored_certificate_name);
+    if let (true, Some(name)) = (proxy.ssl, stored) {
+        let serves_challenges = servers.iter().any(|server| {
+            server
+                .children()
+                .any(|d| d.name == "location" && d.arg_values().contains(&"/.well-known/acme-challenge/"))
+        });
+        if serves_challenges && name == proxy.primary_domain() {
+            proxy.acme = true;
+        } else {
+            proxy.certificate = Some(name.to_string());
+        }
+    }
+
+    candidate_kinds(server)
+        .into_iter()
+        .find_map(|kind| import_as(&config, NginxProxy { kind, ..proxy.clone() }))
+        .unwrap_or_else(|| unmanaged(name, proxy.domain, proxy.backend, proxy.ssl))
+}
+
+/// [`import_site`] for a file on disk, following `include` directives for the summary
+///
+/// A site that keeps its server names or backend in included snippets is
+/// unmanaged, but is still listed with what the snippets say. Relative
+/// includes resolve against `include_dir`, the directory of the main nginx
+/// config; if one cannot be read the file alone is summarized.
+pub fn import_site_file(name: &str, content: &str, include_dir: &Path) -> NginxProxy {
+    let proxy = import_site(name, content);
+    if !proxy.unmanaged {
+        return proxy;
+    }
+    match parse(content).and_then(|config| config.expand_includes(include_dir)) {
+        Ok(expanded) => {
+            let (domain, backend, ssl) = summarize_site(&expanded);
+            NginxProxy { domain, backend, ssl, ..proxy }
+        }
+        Err(_) => proxy,
+    }
+}
+
+/// Site kinds `server` may have been generated as, most likely first
+///
+/// A lone `location /` proxy is read as [`SiteKind::Proxy`], which generates
+/// the same directives as a one-route [`SiteKind::Locations`].
+fn candidate_kinds(server: &Directive) -> Vec<SiteKind> {
+    let locations: Vec<&Directive> = server.children().filter(|d| d.name == "location" && d.is_block()).collect();
+    let routes: Vec<LocationRoute> = locations
+        .iter()
+        .filter(|d| d.args.len() == 1 && d.first_arg().is_some_and(|path| path.starts_with('/')))
+        .filter_map(|d| {
+            Some(LocationRoute {
+                path: d.first_arg()?.to_string(),
+                backend: d.child("proxy_pass")?.first_arg()?.to_string(),
+            })
+        })
+        .collect();
+    let root = root_location(server);
+
+    let mut kinds = Vec::new();
+    if routes.iter().any(|route| route.path != "/") {
+        kinds.push(SiteKind::Locations { locations: routes });
+    }
+    kinds.push(SiteKind::Proxy);
+    if let Some(directory) = server.child("root").and_then(|d| d.first_arg()) {
+        kinds.push(SiteKind::Static {
+            root: directory.to_string(),
+            index: server.child("index").map(|d| d.arg_values().join(" ")).unwrap_or_default(),
+            try_files: root
+                .and_then(|location| location.child("try_files"))
+                .map(|d| d.arg_values().join(" "))
+                .unwrap_or_default(),
+            expires: locations
+                .iter()
+                .find_map(|location| location.child("expires"))
+                .and_then(|d| d.first_arg())
+                .map(str::to_string),
+        });
+    }
+    if let Some(redirect) = root.and_then(|location| location.child("return")) {
+        if let [code, url] = redirect.arg_values()[..] {
+            if let Ok(code) = code.parse() {
+                let (target, preserve_path) = match url.strip_suffix("$request_uri") {
+                    Some(target) => (target, true),
+                    None => (url, false),
+                };
+                kinds.push(SiteKind::Redirect {
+                    target: target.to_string(),
+                    code,
+                    preserve_path,
+                });
+            }
+        }
+    }
+    kinds
+}
+
+/// `proxy` with the extra config of `config`, if generating it gives back the same directives
+fn import_as(config: &Config, mut proxy: NginxProxy) -> Option<NginxProxy> {
+    if proxy.kind != SiteKind::Proxy {
+        proxy.backend = String::new();
+    }
+    let server = config.servers()[0];
+    let template = parse(&generate_nginx_config(&proxy)).ok()?;
+    let template_server = template.servers()[0];
+
+    let server_extra = leftover(body(server), body(template_server));
+    let has_directive = |nodes: &[Node]| nodes.iter().any(|n| matches!(n, Node::Directive(_)));
+    let extra = if proxy.kind == SiteKind::Proxy {
+        let location_extra = match (root_location(server), root_location(template_server)) {
+            (Some(location), Some(template_location)) => leftover(body(location), body(template_location)),
+            _ => Vec::new(),
+        };
+        // The generator puts extra config inside `location /` unless it holds a location block
+        let server_has_location = server_extra
+            .iter()
+            .any(|n| matches!(n, Node::Directive(d) if d.name == "location"));
+        match (has_directive(&server_extra), has_directive(&location_extra)) {
+            (false, false) => None,
+            (false, true) => Some(location_extra),
+            (true, false) if server_has_location => Some(server_extra),
+            _ => return None,
+        }
+    } else {
+        has_directive(&server_extra).then_some(server_extra)
+    };
+    proxy.extra_config = extra.map(|nodes| Config { nodes }.to_string().trim().to_string());
+
+    let regenerated = parse(&generate_nginx_config(&proxy)).ok()?;
+    (normalize(&regenerated.nodes) == normalize(&config.nodes)).then_some(proxy)
+}
+
+/// All server names, backend and whether TLS is enabled, from the first `server` block
+pub fn summarize_site(config: &Config) -> (String, String, bool) {
+    let server = config.servers().into_iter().next();
+
+    let domain = server
+        .and_then(|server| server.child("server_name"))
+        .map(|d| d.arg_values().join(" "))
+        .filter(|names| !names.is_empty());
+
+    let backend = server.and_then(|server| {
+        std::iter::once(server)
+            .chain(server.children().filter(|d| d.name == "location"))
+            .find_map(|block| block.child("proxy_pass"))
+            .and_then(|d| d.first_arg())
+            .map(str::to_string)
+    });
+
+    let ssl = server.is_some_and(|server| {
+        server
+            .children()
+            .any(|d| d.name == "listen" && d.arg_values().contains(&"ssl"))
+    });
+
+    (
+        domain.unwrap_or_else(|| "unknown".to_string()),
+        backend.unwrap_or_else(|| "unknown".to_string()),
+        ssl,
+    )
+}
+
+fn unmanaged(name: &str, domain: String, backend: String, ssl: bool) -> NginxProxy {
+    NginxProxy {
+        name: name.to_string(),
+        domain,
+        backend,
+        kind: SiteKind::Proxy,
+        ssl,
+        extra_config: None,
+        acme: false,
+        certificate: None,
+        redirect_http: false,
+        hsts: false,
+        ocsp_stapling: false,
+        basic_auth: None,
+        allow: Vec::new(),
+        deny: Vec::new(),
+        rate_limit: None,
+        connection_limit: None,
+        unmanaged: true,
+    }
+}
+
+/// Arguments of the `allow` or `deny` directives of `server`, without `all`
+fn addresses(server: &Directive, name: &str) -> Vec<String> {
+    server
+        .children()
+        .filter(|d| d.name == name)
+        .filter_map(|d| d.first_arg())
+        .filter(|address| *address != "all")
+        .map(str::to_string)
+        .collect()
+}
+
+/// `limit_req` of `server` with the rate of the site's `limit_req_zone`
+fn rate_limit(config: &Config, server: &Directive) -> Option<RateLimit> {
+    let args = server.child("limit_req")?.arg_values();
+    let rate = config
+        .directives()
+        .find(|d| d.name == "limit_req_zone")?
+        .arg_values()
+        .into_iter()
+        .find_map(|arg| arg.strip_prefix("rate="))?
+        .to_string();
+    Some(RateLimit {
+        rate,
+        burst: args
+            .iter()
+            .find_map(|arg| arg.strip_prefix("burst="))
+            .and_then(|burst| burst.parse().ok())
+            .unwrap_or(0),
+        nodelay: args.contains(&"nodelay"),
+    })
+}
+
+/// Certificate name from a `ssl_certificate` path inside the certificate store
+fn stored_certificate_name(path: &str) -> Option<&str> {
+    path.strip_prefix(CERTIFICATES_DIR)?
+        .strip_prefix('/')?
+        .strip_suffix("/fullchain.pem")
+        .filter(|name| !name.contains('/'))
+}
+
+fn body(directive: &Directive) -> &[Node] {
+    directive.block.as_deref().unwrap_or_default()
+}
+
+fn is_root_location(directive: &Directive) -> bool {
+    directive.name == "location" && directive.arg_values() == ["/"] && directive.is_block()
+}
+
+fn root_location(server: &Directive) -> Option<&Directive> {
+    server.children().find(|d| is_root_location(d))
+}
+
+/// Entries of `nodes` that the generator did not produce, in their original order
+///
+/// Each template directive is matched at most once. `location /` is matched
+/// by name only since its body is compared separately.
+fn leftover(nodes: &[Node], template: &[Node]) -> Vec<Node> {
+    let mut unmatched: Vec<&Directive> = crate::nginx::config::ast::directives(template).collect();
+    let template_comments: Vec<&str> = template
+        .iter()
+        .filter_map(|n| match n {
+            Node::Comment(c) => Some(c.text.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    let mut extra = Vec::new();
+    for node in nodes {
+        match node {
+            Node::Directive(directive) => {
+                let matched = unmatched.iter().position(|t| {
+                    if is_root_location(directive) {
+                        is_root_location(t)
+                    } else {
+                        normalize_directive(directive) == normalize_directive(t)
+                    }
+                });
+                match matched {
+                    Some(index) => {
+                        unmatched.remove(index);
+                    }
+                    None => extra.push(node.clone()),
+                }
+            }
+            Node::Comment(comment)
+                if comment.text == EXTRA_CONFIG_COMMENT || template_comments.contains(&comment.text.as_str()) => {}
+            Node::Comment(comment) => {
+                let mut comment = comment.clone();
+                comment.inline = false;
+                extra.push(Node::Comment(comment));
+            }
+            Node::Blank => extra.push(Node::Blank),
+        }
+    }
+
+    while matches!(extra.first(), Some(Node::Blank)) {
+        extra.remove(0);
+    }
+    while matches!(extra.last(), Some(Node::Blank)) {
+        extra.pop();
+    }
+    extra
+}
+
+/// Directives only: comments, blank lines, line numbers and quoting are ignored
+fn normalize(nodes: &[Node]) -> Vec<Directive> {
+    crate::nginx::config::ast::directives(nodes).map(normalize_directive).collect()
+}
+
+fn normalize_directive(directive: &Directive) -> Directive {
+    Directive {
+        name: directive.name.clone(),
+        args: directive
+            .args
+            .iter()
+            .map(|a| Arg { value: a.value.clone(), quote: Quote::None })
+            .collect(),
+        block: directive
+            .block
+            .as_ref()
+            .map(|block| normalize(block).into_iter().map(Node::Directive).collect()),
+        line: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nginx::models::BasicAuthUser;
+
+    fn proxy(domain: &str, ssl: bool, extra_config: Option<&str>) -> NginxProxy {
+        NginxProxy {
+            name: "app".to_string(),
+            domain: domain.to_string(),
+            backend: "http://127.0.0.1:3000".to_string(),
+            kind: SiteKind::Proxy,
+            ssl,
+            extra_config: extra_config.map(str::to_string),
+            acme: false,
+            certificate: None,
+            redirect_http: false,
+            hsts: false,
+            ocsp_stapling: false,
+            basic_auth: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            rate_limit: None,
+            connection_limit: None,
+            unmanaged: false,
+        }
+    }
+
+    fn assert_round_trip(original: NginxProxy) {
+        let imported = import_site("app", &generate_nginx_config(&original));
+        assert!(!imported.unmanaged);
+        assert_eq!(imported.domain, original.domain);
+        assert_eq!(imported.backend, original.backend);
+        assert_eq!(imported.kind, original.kind);
+        assert_eq!(imported.ssl, original.ssl);
+        assert_eq!(imported.extra_config, original.extra_config);
+        assert_eq!(imported.acme, original.acme);
+        assert_eq!(imported.certificate, original.certificate);
+        assert_eq!(imported.redirect_http, original.redirect_http);
+        assert_eq!(imported.hsts, original.hsts);
+        assert_eq!(imported.ocsp_stapling, original.ocsp_stapling);
+        // Users come from the htpasswd file, not the config
+        assert_eq!(imported.basic_auth.map(|auth| auth.realm), original.basic_auth.map(|auth| auth.realm));
+        assert_eq!(imported.allow, original.allow);
+        assert_eq!(imported.deny, original.deny);
+        assert_eq!(imported.rate_limit, original.rate_limit);
+        assert_eq!(imported.connection_limit, original.connection_limit);
+    }
+
+    #[test]
+    fn test_round_trip_generated_sites() {
+        assert_round_trip(proxy("app.example.com", false, None));
+        assert_round_trip(proxy("app.example.com www.app.example.com", true, None));
+        assert_round_trip(proxy(
+            "app.example.com",
+            false,
+            Some("# Uploads\nclient_max_body_size 100M;\nproxy_read_timeout 300s;"),
+        ));
+        assert_round_trip(proxy(
+            "app.example.com",
+            true,
+            Some("client_max_body_size 100M;\n\nlocation ~ ^/api/docs {\n    return 404;\n}"),
+        ));
+        assert_round_trip(NginxProxy {
+            acme: true,
+            ..proxy("app.example.com www.app.example.com", true, Some("gzip on;"))
+        });
+        assert_round_trip(NginxProxy {
+            certificate: Some("internal-wildcard".to_string()),
+            ..proxy("app.internal", true, None)
+        });
+        // An uploaded certificate named after the domain is not mistaken for ACME
+        assert_round_trip(NginxProxy {
+            certificate: Some("app.internal".to_string()),
+            ..proxy("app.internal", true, None)
+        });
+        assert_round_trip(NginxProxy {
+            acme: true,
+            redirect_http: true,
+            hsts: true,
+            ocsp_stapling: true,
+            ..proxy("app.example.com www.app.example.com", true, Some("location /ws {\n    proxy_pass http://ws;\n}"))
+        });
+        assert_round_trip(NginxProxy {
+            redirect_http: true,
+            hsts: true,
+            ..proxy("app.example.com", true, Some("gzip on;"))
+        });
+    }
+
+    #[test]
+    fn test_round_trip_access_control() {
+        let guarded = NginxProxy {
+            basic_auth: Some(BasicAuth {
+                realm: "Admin area".to_string(),
+                users: vec![BasicAuthUser { username: "alice".to_string(), password: None }],
+            }),
+            allow: vec!["10.0.0.0/8".to_string(), "192.168.1.10".to_string()],
+            deny: vec!["10.0.0.13".to_string()],
+            rate_limit: Some(RateLimit { rate: "120r/m".to_string(), burst: 10, nodelay: false }),
+            connection_limit: Some(20),
+            ..proxy("app.example.com", true, Some("client_max_body_size 10m;"))
+        };
+        assert_round_trip(guarded.clone());
+        assert_round_trip(NginxProxy { acme: true, redirect_http: true, ..guarded.clone() });
+        assert_round_trip(NginxProxy {
+            allow: Vec::new(),
+            deny: vec!["203.0.113.0/24".to_string()],
+            rate_limit: Some(RateLimit { rate: "5r/s".to_string(), burst: 0, nodelay: true }),
+            connection_limit: None,
+            basic_auth: None,
+            ..guarded
+        });
+    }
+
+    #[test]
+    fn test_round_trip_site_kinds() {
+        let site = |kind: SiteKind, extra_config: Option<&str>| NginxProxy {
+            backend: String::new(),
+            kind,
+            ..proxy("app.example.com", true, extra_config)
+        };
+        let route = |path: &str, backend: &str| LocationRoute { path: path.to_string(), backend: backend.to_string() };
+
+        assert_round_trip(site(
+            SiteKind::Static {
+                root: "/var/www/app".to_string(),
+                index: "index.html index.htm".to_string(),
+                try_files: "$uri $uri/ =404".to_string(),
+                expires: None,
+            },
+            None,
+        ));
+        assert_round_trip(NginxProxy {
+            acme: true,
+            redirect_http: true,
+            hsts: true,
+            ..site(
+                SiteKind::Static {
+                    root: "/srv/spa".to_string(),
+                    index: "index.html".to_string(),
+                    try_files: "$uri /index.html".to_string(),
+                    expires: Some("7d".to_string()),
+                },
+                Some("gzip on;"),
+            )
+        });
+        assert_round_trip(site(
+            SiteKind::Redirect {
+                target: "https://new.example.com".to_string(),
+                code: 301,
+                preserve_path: true,
+            },
+            None,
+        ));
+        assert_round_trip(NginxProxy {
+            acme: true,
+            ..site(
+                SiteKind::Redirect {
+                    target: "https://new.example.com/landing".to_string(),
+                    code: 302,
+                    preserve_path: false,
+                },
+                None,
+            )
+        });
+        assert_round_trip(site(
+            SiteKind::Locations {
+                locations: vec![route("/", "http://127.0.0.1:3000"), route("/api/", "http://api_pool")],
+            },
+            Some("client_max_body_size 10m;"),
+        ));
+        assert_round_trip(site(SiteKind::Locations { locations: vec![route("/ws", "http://127.0.0.1:4000")] }, None));
+
+        // A single `location /` route generates the same directives as a plain proxy
+        let routed = NginxProxy {
+            backend: "http://127.0.0.1:3000".to_string(),
+            ..site(SiteKind::Locations { locations: vec![route("/", "http://127.0.0.1:3000")] }, None)
+        };
+        let imported = import_site("app", &generate_nginx_config(&routed));
+        assert!(!imported.unmanaged);
+        assert_eq!(imported.kind, SiteKind::Proxy);
+        assert_eq!(imported.backend, "http://127.0.0.1:3000");
+    }
+
+    #[test]
+    fn test_unrepresentable_sites_are_unmanaged() {
+        // A hand-written second server block (HTTP to HTTPS redirect)
+        let content = format!(
+            "{}\nserver {{\n    listen 80;\n    return 301 https://$host$request_uri;\n}}\n",
+            generate_nginx_config(&proxy("app.example.com", true, None))
+        );
+        let imported = import_site("app", &content);
+        assert!(imported.unmanaged);
+        assert_eq!(imported.domain, "app.example.com");
+
+        // An edited template value
+        let content = generate_nginx_config(&proxy("app.example.com", false, None))
+            .replace("proxy_read_timeout 60s;", "proxy_read_timeout 300s;");
+        assert!(import_site("app", &content).unmanaged);
+
+        // Custom directives both in `location /` and at server level
+        let content = generate_nginx_config(&proxy("app.example.com", false, Some("gzip on;")))
+            .replace("server_name app.example.com;", "server_name app.example.com;\n    root /srv;");
+        assert!(import_site("app", &content).unmanaged);
+
+        // A static site with a hand-edited `location /`
+        let content = generate_nginx_config(&NginxProxy {
+            backend: String::new(),
+            kind: SiteKind::Static {
+                root: "/var/www".to_string(),
+                index: "index.html".to_string(),
+                try_files: "$uri =404".to_string(),
+                expires: None,
+            },
+            ..proxy("app.example.com", false, None)
+        })
+        .replace("try_files $uri =404;", "try_files $uri =404;\n        autoindex on;");
+        assert!(import_site("app", &content).unmanaged);
+
+        // Not nginx syntax
+        let imported = import_site("app", "server {");
+        assert!(imported.unmanaged);
+        assert_eq!(imported.domain, "unknown");
+    }
+
+    #[test]
+    fn test_site_file_follows_includes() {
+        let dir = std::env::temp_dir().join(format!("nginx-import-include-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("snippets")).unwrap();
+        std::fs::write(dir.join("snippets/app.conf"), "server_name app.example.com;\nlocation / {\n    proxy_pass http://127.0.0.1:3000;\n}\n").unwrap();
+
+        let content = "server {\n    listen 80;\n    include snippets/app.conf;\n}\n";
+        assert_eq!(import_site("app", content).domain, "unknown");
+        let proxy = import_site_file("app", content, &dir);
+        assert!(proxy.unmanaged);
+        assert_eq!(proxy.domain, "app.example.com");
+        assert_eq!(proxy.backend, "http://127.0.0.1:3000");
+
+        // An include that cannot be read leaves the file's own summary
+        let proxy = import_site_file("app", "server {\n    include missing.conf;\n}\n", &dir);
+        assert_eq!(proxy.domain, "unknown");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_hand_written_site() {
+        let imported = import_site(
+            "legacy",
+            "server {\n    listen 443 ssl;\n    server_name a.example.com b.example.com;\n    location / {\n        proxy_pass http://10.0.0.5:8080;\n    }\n}\n",
+        );
+        assert!(imported.unmanaged);
+        assert_eq!(imported.domain, "a.example.com b.example.com");
+        assert_eq!(imported.backend, "http://10.0.0.5:8080");
+        assert!(imported.ssl);
+    }
+}
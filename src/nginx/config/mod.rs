@@ -0,0 +1,615 @@
+//! Nginx configuration parsing, validation and generation
+//!
+//! - [`parser`]: Tokenizer and parser producing a [`ast::Config`] with line numbers
+//! - [`ast`]: Typed syntax tree that serializes back to formatted text
+//! - [`import`]: Rebuilds proxy definitions from existing site files
+//! - [`upstream`]: Generates and imports load-balanced upstream pools
+
+pub mod ast;
+pub mod import;
+pub mod parser;
+pub mod upstream;
+
+pub use ast::{Arg, Comment, Config, Directive, Node, Quote};
+pub use parser::{parse, ParseError};
+pub use upstream::generate_upstream_config;
+
+use crate::nginx::acme::{certificate_paths, CHALLENGE_DIR};
+use crate::nginx::htpasswd::htpasswd_path;
+use crate::nginx::models::{NginxProxy, SiteKind};
+
+/// Formats nginx configuration with proper indentation
+///
+/// Text that parses is re-serialized from its syntax tree; anything else is
+/// re-indented line by line so the user can still see where a brace is missing.
+pub fn format_nginx_config(config: &str) -> String {
+    match parse(config) {
+        Ok(parsed) => parsed.to_string().trim_end().to_string(),
+        Err(_) => reindent_lines(config),
+    }
+}
+
+fn reindent_lines(config: &str) -> String {
+    let mut formatted = String::new();
+    let mut indent_level: i32 = 0;
+    let indent = "    "; // 4 spaces
+    
+    for line in config.lines() {
+        let trimmed = line.trim();
+        
+        // Skip empty lines
+        if trimmed.is_empty() {
+            formatted.push('\n');
+            continue;
+        }
+        
+        // Decrease indent for closing braces
+        if trimmed.starts_with('}') {
+            indent_level = indent_level.saturating_sub(1);
+        }
+        
+        // Add indentation
+        for _ in 0..indent_level {
+            formatted.push_str(indent);
+        }
+        
+        // Add the line
+        formatted.push_str(trimmed);
+        formatted.push('\n');
+        
+        // Increase indent for opening braces
+        if trimmed.ends_with('{') {
+            indent_level += 1;
+        }
+    }
+    
+    formatted.trim_end().to_string()
+}
+
+/// Validates and formats nginx extra configuration
+pub fn validate_nginx_extra_config(config: &str) -> Result<String, String> {
+    let parsed = parse(config).map_err(|e| format!("Sintaksis xətası: {}", e))?;
+    Ok(parsed.to_string().trim_end().to_string())
+}
+
+/// Comment the generator writes above extra configuration
+pub(crate) const EXTRA_CONFIG_COMMENT: &str = " Əlavə konfiqurasiya";
+
+/// Generates complete nginx configuration for a proxy
+///
+/// With `redirect_http` a second server on port 80 follows the main one; it
+/// answers ACME challenges and sends everything else to HTTPS.
+pub fn generate_nginx_config(proxy: &NginxProxy) -> String {
+    let ssl_config = if proxy.ssl && proxy.acme {
+        let (certificate, key) = certificate_paths(proxy.primary_domain());
+        let (listen_http, challenges) = if proxy.redirect_http {
+            (String::new(), String::new())
+        } else {
+            (
+                "\n    # HTTP-01 challenge-ləri üçün 80 portu da dinlənilir\n    listen 80;\n    listen [::]:80;".to_string(),
+                format!("\n\n{}", challenge_location(Some(proxy))),
+            )
+        };
+        format!(r#"{}
+    listen 443 ssl http2;
+    listen [::]:443 ssl http2;
+    
+    # SSL sertifikatı (ACME ilə avtomatik yenilənir)
+    ssl_certificate {};
+    ssl_certificate_key {};
+    
+    ssl_protocols TLSv1.2 TLSv1.3;
+    ssl_ciphers HIGH:!aNULL:!MD5;
+    ssl_prefer_server_ciphers on;{}{}"#, listen_http, certificate, key, tls_options(proxy, Some(&certificate)), challenges)
+    } else if let (true, Some(name)) = (proxy.ssl, &proxy.certificate) {
+        let (certificate, key) = certificate_paths(name);
+        format!(r#"
+    listen 443 ssl http2;
+    listen [::]:443 ssl http2;
+    
+    # SSL sertifikatı ({})
+    ssl_certificate {};
+    ssl_certificate_key {};
+    
+    ssl_protocols TLSv1.2 TLSv1.3;
+    ssl_ciphers HIGH:!aNULL:!MD5;
+    ssl_prefer_server_ciphers on;{}"#, name, certificate, key, tls_options(proxy, Some(&certificate)))
+    } else if proxy.ssl {
+        format!(r#"
+    listen 443 ssl http2;
+    listen [::]:443 ssl http2;
+    
+    # SSL sertifikatları (Let's Encrypt və ya özəl)
+    # ssl_certificate /etc/letsencrypt/live/{}/fullchain.pem;
+    # ssl_certificate_key /etc/letsencrypt/live/{}/privkey.pem;
+    
+    ssl_protocols TLSv1.2 TLSv1.3;
+    ssl_ciphers HIGH:!aNULL:!MD5;
+    ssl_prefer_server_ciphers on;{}"#, proxy.primary_domain(), proxy.primary_domain(), tls_options(proxy, None))
+    } else {
+        "    listen 80;\n    listen [::]:80;".to_string()
+    };
+
+    let redirect_server = if proxy.ssl && proxy.redirect_http {
+        let challenges = if proxy.acme {
+            format!("{}\n\n", challenge_location(None))
+        } else {
+            String::new()
+        };
+        format!(r#"
+
+# HTTP sorğuları HTTPS-ə yönləndirilir
+server {{
+    listen 80;
+    listen [::]:80;
+    server_name {};
+
+{}    location / {{
+        return 301 https://$host$request_uri;
+    }}
+}}"#, proxy.domain, challenges)
+    } else {
+        String::new()
+    };
+
+    let created = chrono::Local::now().format("%Y-%m-%d %H:%M:%S");
+    let (header, body) = match &proxy.kind {
+        SiteKind::Proxy => {
+            let (extra_in_location, extra_in_server) = proxy_extra_config(proxy);
+            (
+                format!("# Nginx Reverse Proxy - {}\n# Yaradılma: {}\n# Backend: {}", proxy.name, created, proxy.backend),
+                format!("{}{}", proxy_location("/", &proxy.backend, &extra_in_location), extra_in_server),
+            )
+        }
+        SiteKind::Static { root, index, try_files, expires } => {
+            let cache = match expires {
+                Some(expires) => format!(
+                    "\n\n    # Statik fayllar brauzerdə keşlənir\n    location ~* \\.(css|js|mjs|png|jpe?g|gif|svg|ico|webp|woff2?|ttf)$ {{\n        expires {};\n        add_header Cache-Control \"public\";{}\n        try_files $uri =404;\n    }}",
+                    expires,
+                    // `add_header` in a location hides the server's, so HSTS is repeated
+                    if proxy.ssl && proxy.hsts {
+                        "\n        add_header Strict-Transport-Security \"max-age=31536000\" always;"
+                    } else {
+                        ""
+                    }
+                ),
+                None => String::new(),
+            };
+            (
+                format!("# Nginx Statik Sayt - {}\n# Yaradılma: {}\n# Root: {}", proxy.name, created, root),
+                format!(
+                    "    root {};\n    index {};\n\n    location / {{\n        try_files {};\n    }}{}{}",
+                    root,
+                    index,
+                    try_files,
+                    cache,
+                    server_extra_config(proxy)
+                ),
+            )
+        }
+        SiteKind::Redirect { target, code, preserve_path } => {
+            let uri = if *preserve_path { "$request_uri" } else { "" };
+            (
+                format!("# Nginx Yönləndirmə - {}\n# Yaradılma: {}\n# Hədəf: {}", proxy.name, created, target),
+                format!(
+                    "    location / {{\n        return {} {}{};\n    }}{}",
+                    code,
+                    target,
+                    uri,
+                    server_extra_config(proxy)
+                ),
+            )
+        }
+        SiteKind::Locations { locations } => (
+            format!(
+                "# Nginx Reverse Proxy - {}\n# Yaradılma: {}\n# Backend-lər: {}",
+                proxy.name,
+                created,
+                locations
+                    .iter()
+                    .map(|route| format!("{} -> {}", route.path, route.backend))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            format!(
+                "{}{}",
+                locations
+                    .iter()
+                    .map(|route| proxy_location(&route.path, &route.backend, ""))
+                    .collect::<Vec<_>>()
+                    .join("\n\n"),
+                server_extra_config(proxy)
+            ),
+        ),
+    };
+
+    format!(r#"{}{}
+
+server {{
+{}
+    server_name {};{}
+
+{}
+}}{}
+"#, header, limit_zones(proxy), ssl_config, proxy.domain, access_control(proxy), body, redirect_server)
+}
+
+/// Whether the main server restricts who may send requests
+fn restricts_access(proxy: &NginxProxy) -> bool {
+    proxy.basic_auth.is_some() || !proxy.allow.is_empty() || !proxy.deny.is_empty()
+}
+
+/// `limit_req_zone` and `limit_conn_zone`, which nginx only accepts at `http` level
+///
+/// Sites are included there, so the zones sit above the server block and are
+/// named after the site to stay unique.
+fn limit_zones(proxy: &NginxProxy) -> String {
+    let mut zones = String::new();
+    if let Some(limit) = &proxy.rate_limit {
+        zones.push_str(&format!(
+            "\n\nlimit_req_zone $binary_remote_addr zone=req_{}:10m rate={};",
+            proxy.name, limit.rate
+        ));
+    }
+    if proxy.connection_limit.is_some() {
+        let separator = if zones.is_empty() { "\n\n" } else { "\n" };
+        zones.push_str(&format!("{}limit_conn_zone $binary_remote_addr zone=conn_{}:10m;", separator, proxy.name));
+    }
+    zones
+}
+
+/// Server-level `deny`/`allow`, basic auth and request limits
+fn access_control(proxy: &NginxProxy) -> String {
+    let mut config = String::new();
+    if !proxy.allow.is_empty() || !proxy.deny.is_empty() {
+        config.push_str("\n\n    # IP ünvanlarına görə giriş");
+        for address in &proxy.deny {
+            config.push_str(&format!("\n    deny {};", address));
+        }
+        for address in &proxy.allow {
+            config.push_str(&format!("\n    allow {};", address));
+        }
+        if !proxy.allow.is_empty() {
+            config.push_str("\n    deny all;");
+        }
+    }
+    if let Some(auth) = &proxy.basic_auth {
+        config.push_str(&format!(
+            "\n\n    # Basic auth (istifadəçilər API ilə idarə olunur)\n    auth_basic \"{}\";\n    auth_basic_user_file {};",
+            auth.realm,
+            htpasswd_path(&proxy.name)
+        ));
+    }
+    if proxy.rate_limit.is_some() || proxy.connection_limit.is_some() {
+        config.push_str("\n\n    # Sorğu limitləri");
+        if let Some(limit) = &proxy.rate_limit {
+            let burst = if limit.burst > 0 { format!(" burst={}", limit.burst) } else { String::new() };
+            let nodelay = if limit.nodelay { " nodelay" } else { "" };
+            config.push_str(&format!("\n    limit_req zone=req_{}{}{};\n    limit_req_status 429;", proxy.name, burst, nodelay));
+        }
+        if let Some(connections) = proxy.connection_limit {
+            config.push_str(&format!("\n    limit_conn conn_{} {};\n    limit_conn_status 429;", proxy.name, connections));
+        }
+    }
+    config
+}
+
+/// A `location` proxying to `backend` with the usual forwarding headers and WebSocket upgrade
+fn proxy_location(path: &str, backend: &str, extra: &str) -> String {
+    format!(r#"    location {} {{
+        proxy_pass {};
+        proxy_http_version 1.1;
+        proxy_set_header Upgrade $http_upgrade;
+        proxy_set_header Connection 'upgrade';
+        proxy_set_header Host $host;
+        proxy_set_header X-Real-IP $remote_addr;
+        proxy_set_header X-Forwarded-For $proxy_add_x_forwarded_for;
+        proxy_set_header X-Forwarded-Proto $scheme;
+        proxy_cache_bypass $http_upgrade;
+        proxy_connect_timeout 60s;
+        proxy_send_timeout 60s;
+        proxy_read_timeout 60s;{}
+    }}"#, path, backend, extra)
+}
+
+/// Extra config of a proxy site, as `(inside location /, at server level)`
+///
+/// Location blocks have to go at server level; anything else goes inside `location /`.
+fn proxy_extra_config(proxy: &NginxProxy) -> (String, String) {
+    let Some(extra) = &proxy.extra_config else {
+        return (String::new(), String::new());
+    };
+    let trimmed = extra.trim();
+    let has_location = parse(trimmed)
+        .map(|parsed| parsed.directives().any(|d| d.name == "location"))
+        .unwrap_or_else(|_| trimmed.contains("location"));
+    if has_location {
+        (String::new(), server_extra_config(proxy))
+    } else {
+        (
+            format!("\n        #{}\n{}", EXTRA_CONFIG_COMMENT, indent_lines(trimmed, "        ")),
+            String::new(),
+        )
+    }
+}
+
+/// Extra config placed at server level, as all site kinds but proxies do
+fn server_extra_config(proxy: &NginxProxy) -> String {
+    match &proxy.extra_config {
+        Some(extra) if !extra.trim().is_empty() => format!("\n    #{}\n{}", EXTRA_CONFIG_COMMENT, indent_lines(extra.trim(), "    ")),
+        _ => String::new(),
+    }
+}
+
+fn indent_lines(text: &str, indent: &str) -> String {
+    text.lines()
+        .map(|line| format!("{}{}", indent, line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The location serving HTTP-01 challenge files
+///
+/// The CA has to reach it even when `proxy` restricts access to the rest of the server.
+fn challenge_location(proxy: Option<&NginxProxy>) -> String {
+    let open = if proxy.is_some_and(restricts_access) {
+        "\n        allow all;\n        auth_basic off;"
+    } else {
+        ""
+    };
+    format!("    location ^~ /.well-known/acme-challenge/ {{\n        alias {}/;\n        default_type text/plain;{}\n    }}", CHALLENGE_DIR, open)
+}
+
+/// HSTS and OCSP stapling directives; stapling needs the chain of a stored certificate
+fn tls_options(proxy: &NginxProxy, chain: Option<&str>) -> String {
+    let mut options = String::new();
+    if proxy.hsts {
+        options.push_str("\n\n    add_header Strict-Transport-Security \"max-age=31536000\" always;");
+    }
+    if let (true, Some(chain)) = (proxy.ocsp_stapling, chain) {
+        options.push_str(&format!(
+            "\n\n    # OCSP stapling (resolver: systemd-resolved)\n    ssl_stapling on;\n    ssl_stapling_verify on;\n    ssl_trusted_certificate {};\n    resolver 127.0.0.53 valid=300s;",
+            chain
+        ));
+    }
+    options
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nginx::models::{BasicAuth, LocationRoute, RateLimit};
+
+    fn proxy(extra_config: Option<&str>) -> NginxProxy {
+        NginxProxy {
+            name: "app".to_string(),
+            domain: "app.example.com".to_string(),
+            backend: "http://127.0.0.1:3000".to_string(),
+            kind: SiteKind::Proxy,
+            ssl: false,
+            extra_config: extra_config.map(str::to_string),
+            acme: false,
+            certificate: None,
+            redirect_http: false,
+            hsts: false,
+            ocsp_stapling: false,
+            basic_auth: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            rate_limit: None,
+            connection_limit: None,
+            unmanaged: false,
+        }
+    }
+
+    #[test]
+    fn test_validate_extra_config() {
+        let formatted = validate_nginx_extra_config("client_max_body_size 10m;\nlocation /api {\nproxy_pass http://api;\n}").unwrap();
+        assert_eq!(formatted, "client_max_body_size 10m;\nlocation /api {\n    proxy_pass http://api;\n}");
+
+        let err = validate_nginx_extra_config("location /api {\n    proxy_pass http://api;\n").unwrap_err();
+        assert!(err.contains("Sətir 1"), "{}", err);
+        let err = validate_nginx_extra_config("gzip on;\nclient_max_body_size 10m\n").unwrap_err();
+        assert!(err.contains("Sətir 2"), "{}", err);
+    }
+
+    #[test]
+    fn test_format_falls_back_for_invalid_config() {
+        assert_eq!(format_nginx_config("location / {\nroot /srv;"), "location / {\n    root /srv;");
+    }
+
+    #[test]
+    fn test_generated_config_parses() {
+        let config = parse(&generate_nginx_config(&proxy(None))).unwrap();
+        let server = config.servers()[0];
+        assert_eq!(server.child("server_name").unwrap().first_arg(), Some("app.example.com"));
+        let location = server.child("location").unwrap();
+        assert_eq!(location.child("proxy_pass").unwrap().first_arg(), Some("http://127.0.0.1:3000"));
+    }
+
+    #[test]
+    fn test_acme_config_serves_challenges() {
+        let proxy = NginxProxy {
+            ssl: true,
+            acme: true,
+            ..proxy(None)
+        };
+        let config = parse(&generate_nginx_config(&proxy)).unwrap();
+        let server = config.servers()[0];
+        assert_eq!(
+            server.child("ssl_certificate").unwrap().first_arg(),
+            Some("/etc/ubuntu-resource-api/certs/app.example.com/fullchain.pem")
+        );
+        assert!(server.children().any(|d| d.name == "listen" && d.arg_values() == ["80"]));
+        let challenge = server
+            .children()
+            .find(|d| d.name == "location" && d.arg_values() == ["^~", "/.well-known/acme-challenge/"])
+            .unwrap();
+        assert_eq!(challenge.child("alias").unwrap().first_arg(), Some("/var/lib/ubuntu-resource-api/acme-challenge/"));
+    }
+
+    #[test]
+    fn test_redirect_server_and_tls_options() {
+        let proxy = NginxProxy {
+            ssl: true,
+            acme: true,
+            redirect_http: true,
+            hsts: true,
+            ocsp_stapling: true,
+            ..proxy(None)
+        };
+        let config = parse(&generate_nginx_config(&proxy)).unwrap();
+        let servers = config.servers();
+        assert_eq!(servers.len(), 2);
+
+        // The HTTPS server no longer listens on 80 or serves challenges itself
+        let main = servers[0];
+        assert!(!main.children().any(|d| d.name == "listen" && d.arg_values() == ["80"]));
+        assert_eq!(main.children().filter(|d| d.name == "location").count(), 1);
+        assert_eq!(
+            main.child("add_header").unwrap().arg_values(),
+            ["Strict-Transport-Security", "max-age=31536000", "always"]
+        );
+        assert_eq!(main.child("ssl_stapling").unwrap().first_arg(), Some("on"));
+        assert_eq!(
+            main.child("ssl_trusted_certificate").unwrap().first_arg(),
+            Some("/etc/ubuntu-resource-api/certs/app.example.com/fullchain.pem")
+        );
+
+        let redirect = servers[1];
+        assert_eq!(redirect.child("server_name").unwrap().first_arg(), Some("app.example.com"));
+        let locations: Vec<_> = redirect
+            .children()
+            .filter(|d| d.name == "location")
+            .map(|d| d.arg_values())
+            .collect();
+        assert_eq!(locations, vec![vec!["^~", "/.well-known/acme-challenge/"], vec!["/"]]);
+        let root = redirect.children().find(|d| d.name == "location" && d.arg_values() == ["/"]).unwrap();
+        assert_eq!(root.child("return").unwrap().arg_values(), ["301", "https://$host$request_uri"]);
+
+        // Without ACME there is nothing to answer on port 80 but the redirect
+        let proxy = NginxProxy { acme: false, ocsp_stapling: false, ..proxy };
+        let config = parse(&generate_nginx_config(&proxy)).unwrap();
+        assert_eq!(config.servers()[1].children().filter(|d| d.name == "location").count(), 1);
+    }
+
+    #[test]
+    fn test_site_kinds_generate() {
+        let site = |kind: SiteKind| NginxProxy { backend: String::new(), kind, ..proxy(Some("gzip on;")) };
+
+        let config = parse(&generate_nginx_config(&NginxProxy {
+            ssl: true,
+            hsts: true,
+            ..site(SiteKind::Static {
+                root: "/var/www/app".to_string(),
+                index: "index.html".to_string(),
+                try_files: "$uri /index.html".to_string(),
+                expires: Some("30d".to_string()),
+            })
+        }))
+        .unwrap();
+        let server = config.servers()[0];
+        assert_eq!(server.child("root").unwrap().first_arg(), Some("/var/www/app"));
+        assert_eq!(server.child("gzip").unwrap().first_arg(), Some("on"));
+        assert!(server.child("proxy_pass").is_none());
+        let locations: Vec<_> = server.children().filter(|d| d.name == "location").collect();
+        assert_eq!(locations[0].child("try_files").unwrap().arg_values(), ["$uri", "/index.html"]);
+        assert_eq!(locations[1].child("expires").unwrap().first_arg(), Some("30d"));
+        let headers: Vec<_> = locations[1].children().filter(|d| d.name == "add_header").filter_map(|d| d.first_arg()).collect();
+        assert_eq!(headers, ["Cache-Control", "Strict-Transport-Security"]);
+
+        let config = parse(&generate_nginx_config(&site(SiteKind::Redirect {
+            target: "https://new.example.com".to_string(),
+            code: 308,
+            preserve_path: true,
+        })))
+        .unwrap();
+        let location = config.servers()[0].child("location").unwrap();
+        assert_eq!(location.child("return").unwrap().arg_values(), ["308", "https://new.example.com$request_uri"]);
+
+        let config = parse(&generate_nginx_config(&site(SiteKind::Locations {
+            locations: vec![
+                LocationRoute { path: "/".to_string(), backend: "http://web:3000".to_string() },
+                LocationRoute { path: "/api/".to_string(), backend: "http://api_pool".to_string() },
+            ],
+        })))
+        .unwrap();
+        let routes: Vec<_> = config.servers()[0]
+            .children()
+            .filter(|d| d.name == "location")
+            .map(|d| (d.first_arg().unwrap(), d.child("proxy_pass").unwrap().first_arg().unwrap()))
+            .collect();
+        assert_eq!(routes, [("/", "http://web:3000"), ("/api/", "http://api_pool")]);
+        // Extra config stays at server level for every kind but a plain proxy
+        assert!(config.servers()[0].child("gzip").is_some());
+    }
+
+    #[test]
+    fn test_access_control_and_limits() {
+        let guarded = NginxProxy {
+            ssl: true,
+            acme: true,
+            basic_auth: Some(BasicAuth { realm: "Admin area".to_string(), users: Vec::new() }),
+            allow: vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string()],
+            deny: vec!["10.0.0.13".to_string()],
+            rate_limit: Some(RateLimit { rate: "10r/s".to_string(), burst: 20, nodelay: true }),
+            connection_limit: Some(5),
+            ..proxy(None)
+        };
+        let config = parse(&generate_nginx_config(&guarded)).unwrap();
+
+        // Zones live outside the server block, at http level
+        let zones: Vec<_> = config.directives().filter(|d| d.name != "server").map(|d| d.arg_values()).collect();
+        assert_eq!(
+            zones,
+            vec![
+                vec!["$binary_remote_addr", "zone=req_app:10m", "rate=10r/s"],
+                vec!["$binary_remote_addr", "zone=conn_app:10m"],
+            ]
+        );
+
+        let server = config.servers()[0];
+        let rules: Vec<_> = server
+            .children()
+            .filter(|d| d.name == "allow" || d.name == "deny")
+            .map(|d| format!("{} {}", d.name, d.first_arg().unwrap()))
+            .collect();
+        assert_eq!(rules, ["deny 10.0.0.13", "allow 10.0.0.0/8", "allow 2001:db8::/32", "deny all"]);
+        assert_eq!(server.child("auth_basic").unwrap().first_arg(), Some("Admin area"));
+        assert_eq!(
+            server.child("auth_basic_user_file").unwrap().first_arg(),
+            Some("/etc/ubuntu-resource-api/htpasswd/app")
+        );
+        assert_eq!(server.child("limit_req").unwrap().arg_values(), ["zone=req_app", "burst=20", "nodelay"]);
+        assert_eq!(server.child("limit_conn").unwrap().arg_values(), ["conn_app", "5"]);
+
+        // Let's Encrypt must still reach the challenges
+        let challenge = server
+            .children()
+            .find(|d| d.name == "location" && d.arg_values() == ["^~", "/.well-known/acme-challenge/"])
+            .unwrap();
+        assert_eq!(challenge.child("allow").unwrap().first_arg(), Some("all"));
+        assert_eq!(challenge.child("auth_basic").unwrap().first_arg(), Some("off"));
+
+        // Without any of it the site is unchanged
+        let plain = generate_nginx_config(&NginxProxy { ssl: true, acme: true, ..proxy(None) });
+        assert!(!plain.contains("limit_") && !plain.contains("auth_basic") && !plain.contains("allow"));
+    }
+
+    #[test]
+    fn test_extra_config_placement() {
+        // Plain directives go inside `location /`
+        let config = parse(&generate_nginx_config(&proxy(Some("# location-free\nclient_max_body_size 10m;")))).unwrap();
+        let location = config.servers()[0].child("location").unwrap();
+        assert!(location.child("client_max_body_size").is_some());
+
+        // Location blocks go at server level
+        let config = parse(&generate_nginx_config(&proxy(Some("location /ws {\n    proxy_pass http://ws;\n}")))).unwrap();
+        let locations: Vec<_> = config.servers()[0]
+            .children()
+            .filter(|d| d.name == "location")
+            .filter_map(|d| d.first_arg())
+            .collect();
+        assert_eq!(locations, vec!["/", "/ws"]);
+    }
+}
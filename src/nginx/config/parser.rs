@@ -0,0 +1,440 @@
+// Tokenizer and parser for nginx configuration syntax
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use crate::nginx::config::ast::{Arg, Comment, Config, Directive, Node, Quote};
+
+/// A syntax error with the 1-based line it was found on
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Sətir {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TokenKind {
+    Word(Arg),
+    Semicolon,
+    OpenBrace,
+    CloseBrace,
+    Comment(String),
+    /// An empty line between entries
+    Blank,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Token {
+    kind: TokenKind,
+    line: usize,
+    /// Whether another token came earlier on the same line
+    follows_on_line: bool,
+}
+
+fn error(line: usize, message: impl Into<String>) -> ParseError {
+    ParseError {
+        line,
+        message: message.into(),
+    }
+}
+
+/// Split `input` into words, quoted strings, punctuation and comments
+fn tokenize(input: &str) -> Result<Vec<Token>, ParseError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut line = 1;
+    let mut line_has_token = false;
+    let mut newlines_in_gap = 0;
+    let mut i = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        if c == '\n' {
+            line += 1;
+            line_has_token = false;
+            newlines_in_gap += 1;
+            i += 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if newlines_in_gap >= 2 && !tokens.is_empty() {
+            tokens.push(Token { kind: TokenKind::Blank, line, follows_on_line: false });
+        }
+        newlines_in_gap = 0;
+        let follows_on_line = line_has_token;
+        line_has_token = true;
+        let start_line = line;
+
+        let kind = match c {
+            ';' => {
+                i += 1;
+                TokenKind::Semicolon
+            }
+            '{' => {
+                i += 1;
+                TokenKind::OpenBrace
+            }
+            '}' => {
+                i += 1;
+                TokenKind::CloseBrace
+            }
+            '#' => {
+                let start = i + 1;
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+                TokenKind::Comment(chars[start..i].iter().collect::<String>().trim_end().to_string())
+            }
+            '"' | '\'' => {
+                let quote = c;
+                let mut value = String::new();
+                i += 1;
+                loop {
+                    match chars.get(i) {
+                        None => return Err(error(start_line, "Dırnaq bağlanmayıb")),
+                        Some(&ch) if ch == quote => break,
+                        Some('\\') if i + 1 < chars.len() => {
+                            value.push('\\');
+                            value.push(chars[i + 1]);
+                            if chars[i + 1] == '\n' {
+                                line += 1;
+                            }
+                            i += 2;
+                            continue;
+                        }
+                        Some(&ch) => {
+                            if ch == '\n' {
+                                line += 1;
+                            }
+                            value.push(ch);
+                        }
+                    }
+                    i += 1;
+                }
+                i += 1;
+                TokenKind::Word(Arg {
+                    value,
+                    quote: if quote == '"' { Quote::Double } else { Quote::Single },
+                })
+            }
+            _ => {
+                let mut value = String::new();
+                while let Some(&ch) = chars.get(i) {
+                    if ch.is_whitespace() || ch == ';' || ch == '}' || ch == '"' || ch == '\'' {
+                        break;
+                    }
+                    // `${name}` is a variable, not a block
+                    if ch == '{' {
+                        if !value.ends_with('$') {
+                            break;
+                        }
+                        while let Some(&ch) = chars.get(i) {
+                            value.push(ch);
+                            i += 1;
+                            if ch == '}' {
+                                break;
+                            }
+                        }
+                        continue;
+                    }
+                    if ch == '\\' && i + 1 < chars.len() {
+                        value.push(ch);
+                        i += 1;
+                    }
+                    value.push(chars[i]);
+                    i += 1;
+                }
+                TokenKind::Word(Arg::plain(value))
+            }
+        };
+
+        tokens.push(Token { kind, line: start_line, follows_on_line });
+    }
+
+    Ok(tokens)
+}
+
+/// Parse nginx configuration text into a syntax tree
+pub fn parse(input: &str) -> Result<Config, ParseError> {
+    let tokens = tokenize(input)?;
+    let mut pos = 0;
+    let nodes = parse_block(&tokens, &mut pos, None)?;
+    Ok(Config { nodes })
+}
+
+/// Parse entries until the closing brace of the block opened on `open_line`, or the end of input
+fn parse_block(tokens: &[Token], pos: &mut usize, open_line: Option<usize>) -> Result<Vec<Node>, ParseError> {
+    let mut nodes = Vec::new();
+
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        match &token.kind {
+            TokenKind::Comment(text) => nodes.push(Node::Comment(Comment {
+                text: text.clone(),
+                line: token.line,
+                inline: token.follows_on_line,
+            })),
+            TokenKind::Blank => {
+                if !matches!(nodes.last(), None | Some(Node::Blank)) {
+                    nodes.push(Node::Blank);
+                }
+            }
+            TokenKind::CloseBrace => {
+                if open_line.is_none() {
+                    return Err(error(token.line, "Gözlənilməz '}': açılan mötərizə yoxdur"));
+                }
+                if matches!(nodes.last(), Some(Node::Blank)) {
+                    nodes.pop();
+                }
+                return Ok(nodes);
+            }
+            TokenKind::Semicolon => return Err(error(token.line, "Gözlənilməz ';': direktiv adı yoxdur")),
+            TokenKind::OpenBrace => return Err(error(token.line, "Gözlənilməz '{': blok adı yoxdur")),
+            TokenKind::Word(name) => {
+                let (directive, comments) = parse_directive(tokens, pos, name.value.clone(), token.line)?;
+                nodes.extend(comments.into_iter().map(Node::Comment));
+                nodes.push(Node::Directive(directive));
+            }
+        }
+    }
+
+    match open_line {
+        Some(line) => Err(error(line, "Blok bağlanmayıb: '}' çatışmır")),
+        None => Ok(nodes),
+    }
+}
+
+/// Parse the arguments and terminator of the directive whose name was just read
+///
+/// Comments between the arguments are returned separately, to be kept on
+/// their own lines before the directive; the arguments are joined up.
+fn parse_directive(
+    tokens: &[Token],
+    pos: &mut usize,
+    name: String,
+    line: usize,
+) -> Result<(Directive, Vec<Comment>), ParseError> {
+    let mut args = Vec::new();
+    let mut comments = Vec::new();
+
+    while let Some(token) = tokens.get(*pos) {
+        *pos += 1;
+        match &token.kind {
+            TokenKind::Word(arg) => args.push(arg.clone()),
+            TokenKind::Semicolon => return Ok((Directive { name, args, block: None, line }, comments)),
+            TokenKind::OpenBrace => {
+                let block = parse_block(tokens, pos, Some(line))?;
+                return Ok((Directive { name, args, block: Some(block), line }, comments));
+            }
+            TokenKind::Comment(text) => comments.push(Comment {
+                text: text.clone(),
+                line: token.line,
+                inline: false,
+            }),
+            TokenKind::Blank => {}
+            TokenKind::CloseBrace => {
+                return Err(error(line, format!("Direktiv nöqtəli vergüllə (;) bitməlidir: {}", name)));
+            }
+        }
+    }
+
+    Err(error(line, format!("Direktiv nöqtəli vergüllə (;) bitməlidir: {}", name)))
+}
+
+/// How deep `include` directives are followed
+const MAX_INCLUDE_DEPTH: usize = 8;
+
+impl Config {
+    /// A copy of the tree with every `include` replaced by the files it names
+    ///
+    /// Relative paths are resolved against `base_dir`, and a `*` wildcard in
+    /// the file name matches every file in that directory, in name order.
+    /// Includes that match no file are dropped, like nginx does for wildcards.
+    pub fn expand_includes(&self, base_dir: &Path) -> Result<Config, ParseError> {
+        Ok(Config {
+            nodes: expand_nodes(&self.nodes, base_dir, 0)?,
+        })
+    }
+}
+
+fn expand_nodes(nodes: &[Node], base_dir: &Path, depth: usize) -> Result<Vec<Node>, ParseError> {
+    let mut expanded = Vec::new();
+
+    for node in nodes {
+        match node {
+            Node::Directive(directive) if directive.is_include() => {
+                if depth >= MAX_INCLUDE_DEPTH {
+                    return Err(error(directive.line, "Include dərinliyi həddi aşılıb"));
+                }
+                let pattern = directive
+                    .first_arg()
+                    .ok_or_else(|| error(directive.line, "include direktivi fayl yolu tələb edir"))?;
+                for path in resolve_include(base_dir, pattern) {
+                    let content = fs::read_to_string(&path).map_err(|e| {
+                        error(directive.line, format!("{} oxuna bilmədi: {}", path.display(), e))
+                    })?;
+                    let included = parse(&content).map_err(|e| {
+                        error(directive.line, format!("{}: {}", path.display(), e))
+                    })?;
+                    expanded.extend(expand_nodes(&included.nodes, base_dir, depth + 1)?);
+                }
+            }
+            Node::Directive(directive) => {
+                let mut directive = directive.clone();
+                if let Some(block) = &directive.block {
+                    directive.block = Some(expand_nodes(block, base_dir, depth)?);
+                }
+                expanded.push(Node::Directive(directive));
+            }
+            other => expanded.push(other.clone()),
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Files matched by an include pattern
+fn resolve_include(base_dir: &Path, pattern: &str) -> Vec<std::path::PathBuf> {
+    let path = base_dir.join(pattern);
+    let file_pattern = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    if !file_pattern.contains('*') {
+        return vec![path];
+    }
+
+    let (prefix, suffix) = file_pattern.split_once('*').unwrap_or_default();
+    let Some(dir) = path.parent() else {
+        return Vec::new();
+    };
+    let mut matches: Vec<_> = fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|entry| entry.path())
+                .filter(|p| p.is_file())
+                .filter(|p| {
+                    p.file_name()
+                        .and_then(|n| n.to_str())
+                        .is_some_and(|n| n.len() >= prefix.len() + suffix.len() && n.starts_with(prefix) && n.ends_with(suffix))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    matches.sort();
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SITE: &str = r#"# Managed site
+upstream app {
+    server 127.0.0.1:3000 weight=2;
+}
+
+server {
+    listen 80; # plain HTTP
+    server_name example.com www.example.com;
+
+    location / {
+        proxy_pass http://app;
+        proxy_set_header Connection 'upgrade';
+        add_header X-Note "a ; b { c }";
+        set $target ${scheme}://$host;
+    }
+    include snippets/*.conf;
+}
+"#;
+
+    #[test]
+    fn test_parse_blocks_and_arguments() {
+        let config = parse(SITE).unwrap();
+        let servers = config.servers();
+        assert_eq!(servers.len(), 1);
+
+        let server = servers[0];
+        assert_eq!(server.line, 6);
+        assert_eq!(server.child("server_name").unwrap().arg_values(), vec!["example.com", "www.example.com"]);
+
+        let location = server.child("location").unwrap();
+        assert_eq!(location.first_arg(), Some("/"));
+        assert_eq!(location.line, 10);
+        let header = location.child("add_header").unwrap();
+        assert_eq!(header.args[1], Arg { value: "a ; b { c }".to_string(), quote: Quote::Double });
+        assert_eq!(location.child("set").unwrap().arg_values(), vec!["$target", "${scheme}://$host"]);
+
+        assert_eq!(config.includes(), vec!["snippets/*.conf"]);
+    }
+
+    #[test]
+    fn test_serialize_round_trip() {
+        let config = parse(SITE).unwrap();
+        let text = config.to_string();
+        assert_eq!(text, SITE);
+        assert_eq!(parse(&text).unwrap(), config);
+    }
+
+    #[test]
+    fn test_serialize_normalizes_indentation() {
+        let config = parse("server {\nlisten 80;\n  location / {\nroot /srv;}}").unwrap();
+        assert_eq!(config.to_string(), "server {\n    listen 80;\n    location / {\n        root /srv;\n    }\n}\n");
+    }
+
+    #[test]
+    fn test_parse_errors_report_lines() {
+        let err = parse("server {\n    listen 80;\n").unwrap_err();
+        assert_eq!(err.line, 1);
+
+        let err = parse("server {\n    listen 80\n}\n").unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let err = parse("listen 80;\n}\n").unwrap_err();
+        assert_eq!(err.line, 2);
+
+        let err = parse("add_header X \"open;\n").unwrap_err();
+        assert_eq!(err.line, 1);
+        assert!(err.to_string().starts_with("Sətir 1:"));
+    }
+
+    #[test]
+    fn test_comments_between_arguments_move_before_the_directive() {
+        let config = parse("server {\n    listen 443 # TLS\n        ssl;\n}\n").unwrap();
+        let server = config.servers()[0];
+        assert_eq!(server.child("listen").unwrap().arg_values(), vec!["443", "ssl"]);
+        assert_eq!(config.to_string(), "server {\n    # TLS\n    listen 443 ssl;\n}\n");
+        assert_eq!(parse(&config.to_string()).unwrap().to_string(), config.to_string());
+    }
+
+    #[test]
+    fn test_expand_includes() {
+        let dir = std::env::temp_dir().join(format!("nginx-include-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("snippets")).unwrap();
+        fs::write(dir.join("snippets/b.conf"), "gzip on;\n").unwrap();
+        fs::write(dir.join("snippets/a.conf"), "client_max_body_size 10m;\n").unwrap();
+
+        let config = parse("server {\n    include snippets/*.conf;\n}\n").unwrap();
+        let expanded = config.expand_includes(&dir).unwrap();
+        let names: Vec<_> = expanded.servers()[0].children().map(|d| d.name.as_str()).collect();
+        assert_eq!(names, vec!["client_max_body_size", "gzip"]);
+
+        let missing = parse("include missing.conf;").unwrap();
+        assert!(missing.expand_includes(&dir).is_err());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,201 @@
+// Generation and import of upstream pool files
+
+use crate::nginx::config::ast::{Comment, Config, Directive, Node};
+use crate::nginx::config::parse;
+use crate::nginx::models::{BalanceMethod, NginxUpstream, UpstreamServer};
+
+/// File name an upstream pool is stored under
+pub fn upstream_file_name(name: &str) -> String {
+    format!("upstream-{}.conf", name)
+}
+
+/// Pool name from a file written by [`upstream_file_name`]
+pub fn upstream_name_from_file(file_name: &str) -> Option<&str> {
+    file_name.strip_prefix("upstream-")?.strip_suffix(".conf")
+}
+
+/// Generates the `upstream` block for a pool
+pub fn generate_upstream_config(upstream: &NginxUpstream) -> String {
+    let mut body = Vec::new();
+    if let Some(method) = upstream.method.directive() {
+        body.push(Node::Directive(Directive::new(method, &[])));
+    }
+    for server in &upstream.servers {
+        let mut args = vec![server.address.clone()];
+        if let Some(weight) = server.weight {
+            args.push(format!("weight={}", weight));
+        }
+        if let Some(max_fails) = server.max_fails {
+            args.push(format!("max_fails={}", max_fails));
+        }
+        if let Some(fail_timeout) = &server.fail_timeout {
+            args.push(format!("fail_timeout={}", fail_timeout));
+        }
+        if server.backup {
+            args.push("backup".to_string());
+        }
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        body.push(Node::Directive(Directive::new("server", &args)));
+    }
+
+    let comment = |text: String| {
+        Node::Comment(Comment {
+            text,
+            line: 0,
+            inline: false,
+        })
+    };
+    Config {
+        nodes: vec![
+            comment(format!(" Nginx Upstream - {}", upstream.name)),
+            comment(format!(" Yaradılma: {}", chrono::Local::now().format("%Y-%m-%d %H:%M:%S"))),
+            Node::Blank,
+            Node::Directive(Directive::block("upstream", &[&upstream.name], body)),
+        ],
+    }
+    .to_string()
+}
+
+/// Rebuild a pool from the first `upstream` block of a file
+///
+/// Server parameters this model does not cover (`max_conns`, `resolve`, ...)
+/// are dropped, as are balancing methods other than round-robin,
+/// `least_conn` and `ip_hash`.
+pub fn import_upstream(content: &str) -> Option<NginxUpstream> {
+    let config = parse(content).ok()?;
+    let block = config
+        .directives()
+        .find(|d| d.name == "upstream" && d.is_block())?;
+
+    let method = if block.child("least_conn").is_some() {
+        BalanceMethod::LeastConn
+    } else if block.child("ip_hash").is_some() {
+        BalanceMethod::IpHash
+    } else {
+        BalanceMethod::RoundRobin
+    };
+
+    let servers = block
+        .children()
+        .filter(|d| d.name == "server")
+        .filter_map(|d| {
+            let mut args = d.arg_values().into_iter();
+            let mut server = UpstreamServer {
+                address: args.next()?.to_string(),
+                weight: None,
+                max_fails: None,
+                fail_timeout: None,
+                backup: false,
+            };
+            for arg in args {
+                match arg.split_once('=') {
+                    Some(("weight", value)) => server.weight = value.parse().ok(),
+                    Some(("max_fails", value)) => server.max_fails = value.parse().ok(),
+                    Some(("fail_timeout", value)) => server.fail_timeout = Some(value.to_string()),
+                    None if arg == "backup" => server.backup = true,
+                    _ => {}
+                }
+            }
+            Some(server)
+        })
+        .collect();
+
+    Some(NginxUpstream {
+        name: block.first_arg()?.to_string(),
+        method,
+        servers,
+    })
+}
+
+/// Upstream name a proxy backend such as `http://pool/api` points at
+pub fn backend_host(backend: &str) -> &str {
+    let rest = backend
+        .strip_prefix("http://")
+        .or_else(|| backend.strip_prefix("https://"))
+        .unwrap_or(backend);
+    rest.split('/').next().unwrap_or(rest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pool() -> NginxUpstream {
+        NginxUpstream {
+            name: "app_pool".to_string(),
+            method: BalanceMethod::LeastConn,
+            servers: vec![
+                UpstreamServer {
+                    address: "10.0.0.1:3000".to_string(),
+                    weight: Some(3),
+                    max_fails: Some(2),
+                    fail_timeout: Some("30s".to_string()),
+                    backup: false,
+                },
+                UpstreamServer {
+                    address: "10.0.0.2:3000".to_string(),
+                    weight: None,
+                    max_fails: None,
+                    fail_timeout: None,
+                    backup: true,
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn test_generate_upstream() {
+        let config = generate_upstream_config(&pool());
+        assert!(config.contains("upstream app_pool {\n    least_conn;\n"));
+        assert!(config.contains("    server 10.0.0.1:3000 weight=3 max_fails=2 fail_timeout=30s;\n"));
+        assert!(config.contains("    server 10.0.0.2:3000 backup;\n"));
+        assert!(parse(&config).is_ok());
+    }
+
+    #[test]
+    fn test_upstream_round_trip() {
+        let original = pool();
+        assert_eq!(import_upstream(&generate_upstream_config(&original)), Some(original));
+
+        let round_robin = NginxUpstream {
+            method: BalanceMethod::RoundRobin,
+            ..pool()
+        };
+        assert!(!generate_upstream_config(&round_robin).contains("least_conn"));
+        assert_eq!(import_upstream(&generate_upstream_config(&round_robin)), Some(round_robin));
+
+        assert_eq!(import_upstream("server { listen 80; }"), None);
+    }
+
+    #[test]
+    fn test_validate_upstream() {
+        assert!(pool().validate().is_ok());
+
+        let mut invalid = pool();
+        invalid.name = "../pool".to_string();
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = pool();
+        invalid.method = BalanceMethod::IpHash;
+        assert!(invalid.validate().unwrap_err().contains("ip_hash"));
+
+        let mut invalid = pool();
+        invalid.servers[0].fail_timeout = Some("30 s".to_string());
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = pool();
+        invalid.servers[0].backup = true;
+        assert!(invalid.validate().is_err());
+
+        let mut invalid = pool();
+        invalid.servers.clear();
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn test_backend_host() {
+        assert_eq!(backend_host("http://app_pool"), "app_pool");
+        assert_eq!(backend_host("https://app_pool/api/"), "app_pool");
+        assert_eq!(backend_host("http://127.0.0.1:3000"), "127.0.0.1:3000");
+    }
+}
@@ -0,0 +1,212 @@
+// Running nginx: config tests, reloads and the version
+
+use std::path::Path;
+use std::process::{Command, Output};
+use std::sync::{Arc, Mutex};
+
+use crate::config::NginxControllerKind;
+use crate::error::AppError;
+use crate::nginx::models::NginxTestResult;
+
+/// Everything the handlers need from the nginx binary and its service manager
+pub trait NginxController: Send + Sync {
+    /// `nginx -t`, checking `config` instead of the default main config when given
+    ///
+    /// A config that fails the test is an `Ok` with `success: false`; `Err`
+    /// means nginx could not be run at all.
+    fn test(&self, config: Option<&Path>) -> Result<NginxTestResult, AppError>;
+
+    /// Make the running nginx pick up the config on disk
+    fn reload(&self) -> Result<(), AppError>;
+
+    /// The version nginx reports, e.g. `nginx/1.24.0`
+    fn version(&self) -> Result<String, AppError>;
+}
+
+/// The controller selected by `NGINX_CONTROLLER`
+pub fn from_kind(kind: NginxControllerKind) -> Arc<dyn NginxController> {
+    match kind {
+        NginxControllerKind::Systemd => Arc::new(SystemdController),
+        NginxControllerKind::Signal => Arc::new(SignalController),
+        NginxControllerKind::Fake => Arc::new(FakeController::new()),
+    }
+}
+
+/// Reloads through `systemctl reload nginx`
+pub struct SystemdController;
+
+impl NginxController for SystemdController {
+    fn test(&self, config: Option<&Path>) -> Result<NginxTestResult, AppError> {
+        nginx_test(config)
+    }
+
+    fn reload(&self) -> Result<(), AppError> {
+        let output = Command::new("systemctl")
+            .args(["reload", "nginx"])
+            .output()
+            .map_err(|e| AppError::NginxError(format!("Systemctl çalışdırıla bilmədi: {}. Root icazəsi lazımdır.", e)))?;
+        check_reload(output)
+    }
+
+    fn version(&self) -> Result<String, AppError> {
+        nginx_version()
+    }
+}
+
+/// Reloads with `nginx -s reload`, for containers without systemd
+pub struct SignalController;
+
+impl NginxController for SignalController {
+    fn test(&self, config: Option<&Path>) -> Result<NginxTestResult, AppError> {
+        nginx_test(config)
+    }
+
+    fn reload(&self) -> Result<(), AppError> {
+        let output = Command::new("nginx")
+            .args(["-s", "reload"])
+            .output()
+            .map_err(|e| AppError::NginxError(format!("Nginx çalışdırıla bilmədi: {}. Nginx quraşdırılıb?", e)))?;
+        check_reload(output)
+    }
+
+    fn version(&self) -> Result<String, AppError> {
+        nginx_version()
+    }
+}
+
+/// Runs nothing; records its calls and passes every test unless told otherwise
+#[derive(Default)]
+pub struct FakeController {
+    calls: Mutex<Vec<String>>,
+    test_failure: Mutex<Option<String>>,
+    test_error: Mutex<Option<String>>,
+    reload_error: Mutex<Option<String>>,
+}
+
+impl FakeController {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fail every following test with `output`; `None` lets them pass again
+    pub fn fail_tests(&self, output: Option<&str>) {
+        *self.test_failure.lock().unwrap() = output.map(str::to_string);
+    }
+
+    /// Make every following test fail to run, as if nginx were missing
+    pub fn fail_test_runs(&self, error: Option<&str>) {
+        *self.test_error.lock().unwrap() = error.map(str::to_string);
+    }
+
+    /// Fail every following reload with `error`
+    pub fn fail_reloads(&self, error: Option<&str>) {
+        *self.reload_error.lock().unwrap() = error.map(str::to_string);
+    }
+
+    /// Calls so far, oldest first: `test`, `test <config>` or `reload`
+    pub fn calls(&self) -> Vec<String> {
+        self.calls.lock().unwrap().clone()
+    }
+}
+
+impl NginxController for FakeController {
+    fn test(&self, config: Option<&Path>) -> Result<NginxTestResult, AppError> {
+        self.calls.lock().unwrap().push(match config {
+            Some(config) => format!("test {}", config.display()),
+            None => "test".to_string(),
+        });
+        if let Some(error) = self.test_error.lock().unwrap().clone() {
+            return Err(AppError::NginxError(error));
+        }
+        Ok(match self.test_failure.lock().unwrap().clone() {
+            Some(output) => NginxTestResult { success: false, output },
+            None => NginxTestResult {
+                success: true,
+                output: "nginx: configuration file test is successful".to_string(),
+            },
+        })
+    }
+
+    fn reload(&self) -> Result<(), AppError> {
+        self.calls.lock().unwrap().push("reload".to_string());
+        match self.reload_error.lock().unwrap().clone() {
+            Some(error) => Err(AppError::NginxError(error)),
+            None => Ok(()),
+        }
+    }
+
+    fn version(&self) -> Result<String, AppError> {
+        Ok("nginx/fake".to_string())
+    }
+}
+
+fn nginx_test(config: Option<&Path>) -> Result<NginxTestResult, AppError> {
+    let mut command = Command::new("nginx");
+    command.arg("-t");
+    if let Some(config) = config {
+        command.arg("-c").arg(config);
+    }
+    let output = command
+        .output()
+        .map_err(|e| AppError::NginxError(format!("Nginx test edilə bilmədi: {}. Nginx quraşdırılıb?", e)))?;
+    Ok(NginxTestResult {
+        success: output.status.success(),
+        output: combined_output(&output),
+    })
+}
+
+fn nginx_version() -> Result<String, AppError> {
+    let output = Command::new("nginx")
+        .arg("-v")
+        .output()
+        .map_err(|e| AppError::NginxError(format!("Nginx çalışdırıla bilmədi: {}. Nginx quraşdırılıb?", e)))?;
+    // `nginx version: nginx/1.24.0 (Ubuntu)`, printed on stderr
+    let text = combined_output(&output);
+    Ok(text.strip_prefix("nginx version: ").unwrap_or(&text).to_string())
+}
+
+fn check_reload(output: Output) -> Result<(), AppError> {
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(AppError::NginxError(format!("Nginx reload edilə bilmədi: {}", combined_output(&output))))
+    }
+}
+
+/// Nginx reports on stderr; stdout is kept in case a wrapper prints there
+fn combined_output(output: &Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+    .trim()
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_records_calls_and_fails_on_request() {
+        let fake = FakeController::new();
+        assert!(fake.test(None).unwrap().success);
+        fake.reload().unwrap();
+
+        fake.fail_tests(Some("unknown directive \"bogus\""));
+        let result = fake.test(Some(Path::new("/tmp/nginx.conf"))).unwrap();
+        assert!(!result.success);
+        assert_eq!(result.output, "unknown directive \"bogus\"");
+
+        fake.fail_tests(None);
+        assert!(fake.test(None).unwrap().success);
+        assert_eq!(fake.calls(), vec!["test", "reload", "test /tmp/nginx.conf", "test"]);
+        assert_eq!(fake.version().unwrap(), "nginx/fake");
+
+        fake.fail_test_runs(Some("nginx: not found"));
+        fake.fail_reloads(Some("nginx is not running"));
+        assert!(matches!(fake.test(None), Err(AppError::NginxError(_))));
+        assert!(matches!(fake.reload(), Err(AppError::NginxError(_))));
+    }
+}
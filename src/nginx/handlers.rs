@@ -3,8 +3,22 @@ use log::{info, warn, error};
 use std::fs;
 use std::path::Path;
 
-use crate::nginx::models::{NginxProxy, NginxResponse, FormatRequest, FormatResponse};
-use crate::nginx::config::{validate_nginx_extra_config, generate_nginx_config};
+use crate::auth::models::Identity;
+use crate::config::NginxConfig;
+use crate::nginx::models::{BasicAuthUser, CertificateRequest, NginxProxy, NginxUpstream, NginxResponse, FormatRequest, FormatResponse, NginxTestResult, NginxVersion, ProxyPreview, ProxyWriteQuery, RevisionDiffQuery};
+use crate::error::AppError;
+use crate::nginx::acme::renewal::{certificate_name, check_acme_owned, issue_and_reload, needs_issuance};
+use crate::nginx::acme::store::{self_signed, validate_pem_pair};
+use crate::nginx::acme::{certificate_paths, CertificateInfo, CertificateSource, CERTIFICATES_DIR};
+use crate::nginx::config::import::{import_site, import_site_file};
+use crate::nginx::config::upstream::{backend_host, import_upstream, upstream_file_name, upstream_name_from_file};
+use crate::nginx::config::{generate_nginx_config, generate_upstream_config, parse, validate_nginx_extra_config};
+use crate::nginx::preview::{write_placeholder, PreviewTree};
+use crate::nginx::revisions::{unified_diff, RevisionStore};
+use crate::nginx::service::{commit, normalize, ProxyService, ProxyWrite};
+use crate::nginx::staging::Changeset;
+use crate::nginx::validation::validate_site_name;
+use crate::nginx::stats::ProxyOperation;
 use crate::system::models::AppState;
 
 const NGINX_ADMIN_HTML: &str = include_str!("../templates/nginx_admin.html");
@@ -40,12 +54,45 @@ pub async fn format_nginx_extra_config(req: web::Json<FormatRequest>) -> impl Re
     }
 }
 
+/// A proxy as listed, with whether its site is linked into `sites_enabled_path`
+#[derive(serde::Serialize)]
+struct ProxyEntry {
+    #[serde(flatten)]
+    proxy: NginxProxy,
+    enabled: bool,
+}
+
+/// Whether `sites_enabled/<name>` exists (a dangling symlink counts as enabled)
+fn site_enabled(sites_enabled: &str, name: &str) -> bool {
+    fs::symlink_metadata(format!("{}/{}", sites_enabled, name)).is_ok()
+}
+
+/// Report the nginx version and how it is controlled
+#[actix_web::get("/api/nginx/version")]
+pub async fn get_nginx_version(data: web::Data<AppState>) -> impl Responder {
+    let controller = data.nginx_controller();
+    match web::block(move || controller.version()).await {
+        Ok(Ok(version)) => HttpResponse::Ok().json(NginxVersion {
+            controller: data.nginx_config().controller.as_str(),
+            version,
+        }),
+        Ok(Err(e)) => error_response(e),
+        Err(e) => error_response(AppError::NginxError(e.to_string())),
+    }
+}
+
 /// List all nginx proxy configurations
 #[actix_web::get("/api/nginx/proxies")]
 pub async fn get_nginx_proxies(data: web::Data<AppState>) -> impl Responder {
     info!("GET /api/nginx/proxies - Listing nginx configurations");
     
-    let nginx_sites_available = &data.nginx_config.sites_available_path;
+    let nginx_config = data.nginx_config();
+    let nginx_sites_available = &nginx_config.sites_available_path;
+    // nginx resolves relative includes against the directory of its main config
+    let include_dir = Path::new(&nginx_config.main_config_path)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
     let mut proxies = Vec::new();
     
     // Check if directory exists
@@ -69,31 +116,22 @@ pub async fn get_nginx_proxies(data: web::Data<AppState>) -> impl Responder {
                                 continue;
                             }
                             
-                            let domain = content.lines()
-                                .find(|l| l.trim().starts_with("server_name"))
-                                .and_then(|l| l.split_whitespace().nth(1))
-                                .unwrap_or("unknown")
-                                .trim_end_matches(';')
-                                .to_string();
-                            
-                            let backend = content.lines()
-                                .find(|l| l.trim().starts_with("proxy_pass"))
-                                .and_then(|l| l.split_whitespace().nth(1))
-                                .unwrap_or("unknown")
-                                .trim_end_matches(';')
-                                .to_string();
-                            
-                            let ssl = content.contains("listen 443 ssl");
-                            
-                            info!("Found proxy config: {} -> {} ({})", name, domain, backend);
-                            
-                            proxies.push(NginxProxy {
-                                name: name.to_string(),
-                                domain,
-                                backend,
-                                ssl,
-                                extra_config: None,
-                            });
+                            let mut proxy = import_site_file(name, &content, &include_dir);
+                            if let Some(auth) = &mut proxy.basic_auth {
+                                auth.users = data
+                                    .htpasswd
+                                    .users(name)
+                                    .into_iter()
+                                    .map(|username| BasicAuthUser { username, password: None })
+                                    .collect();
+                            }
+                            if proxy.unmanaged {
+                                info!("Found unmanaged config: {} ({})", name, proxy.domain);
+                            } else {
+                                info!("Found proxy config: {} -> {} ({})", name, proxy.domain, proxy.backend);
+                            }
+                            let enabled = site_enabled(&nginx_config.sites_enabled_path, name);
+                            proxies.push(ProxyEntry { proxy, enabled });
                         }
                     } else {
                         warn!("Could not read file: {:?}", path);
@@ -114,464 +152,886 @@ pub async fn get_nginx_proxies(data: web::Data<AppState>) -> impl Responder {
     HttpResponse::Ok().json(proxies)
 }
 
-/// Create a new nginx proxy configuration
-#[actix_web::post("/api/nginx/proxies")]
-pub async fn create_nginx_proxy(data: web::Data<AppState>, proxy: web::Json<NginxProxy>) -> impl Responder {
-    let proxy_domain = proxy.domain.clone();
-    info!("POST /api/nginx/proxies - Creating proxy: {} -> {}", proxy_domain, proxy.backend);
-    
-    let nginx_sites_available = &data.nginx_config.sites_available_path;
-    let nginx_sites_enabled = &data.nginx_config.sites_enabled_path;
-    
-    // Validate and format extra_config if provided
-    let validated_proxy = if let Some(extra) = &proxy.extra_config {
-        if !extra.trim().is_empty() {
-            match validate_nginx_extra_config(extra) {
-                Ok(formatted) => {
-                    info!("Extra config validated and formatted");
-                    NginxProxy {
-                        name: proxy.name.clone(),
-                        domain: proxy.domain.clone(),
-                        backend: proxy.backend.clone(),
-                        ssl: proxy.ssl,
-                        extra_config: Some(formatted),
-                    }
-                },
-                Err(e) => {
-                    error!("Extra config validation failed: {}", e);
-                    return HttpResponse::BadRequest().json(NginxResponse {
-                        success: false,
-                        message: format!("❌ Əlavə konfiqurasiya xətası: {}", e),
-                    });
-                }
+/// Issue the certificate of a saved ACME proxy in the background
+///
+/// Nothing happens if a valid certificate for the same names already exists;
+/// failures are retried by the renewal task.
+fn spawn_issuance(data: &web::Data<AppState>, domains: Vec<String>) {
+    let data = data.clone();
+    actix_web::rt::spawn(async move {
+        let name = match certificate_name(&domains) {
+            Ok(name) => name,
+            Err(e) => {
+                warn!("⚠️  Skipping certificate issuance: {}", e);
+                return;
             }
-        } else {
-            proxy.into_inner()
+        };
+        let info = data.acme.store().info(name);
+        let now = chrono::Utc::now().timestamp();
+        if !needs_issuance(info.as_ref(), &domains, data.acme.renew_before_days(), now) {
+            return;
         }
-    } else {
-        proxy.into_inner()
+        match issue_and_reload(&data, &domains).await {
+            Ok(_) => info!("🔐 Certificate issued for {}", domains.join(", ")),
+            Err(e) => warn!("⚠️  Certificate issuance for {} failed: {}", name, e),
+        }
+    });
+}
+
+/// Issue (or renew) the ACME certificate of a proxy now
+#[actix_web::post("/api/nginx/proxies/{name}/certificate")]
+pub async fn issue_nginx_certificate(data: web::Data<AppState>, name: web::Path<String>, identity: Identity) -> impl Responder {
+    info!("POST /api/nginx/proxies/{}/certificate - Issuing certificate", name);
+    let response = issue_certificate(&data, &name).await;
+    data.audit.record(&identity, "nginx", "certificate.issue", &name, serde_json::json!({"name": name.as_str()}), response.status());
+    response
+}
+
+async fn issue_certificate(data: &web::Data<AppState>, name: &str) -> HttpResponse {
+    if let Err(e) = validate_site_name(name) {
+        return error_response(AppError::ValidationError(format!("name: {}", e)));
+    }
+    let config_path = format!("{}/{}", data.nginx_config().sites_available_path, name);
+    let Ok(content) = fs::read_to_string(&config_path) else {
+        return HttpResponse::NotFound().json(NginxResponse {
+            success: false,
+            message: format!("'{}' proxy tapılmadı", name),
+        });
     };
-    
-    // Check if nginx directories exist
-    if !Path::new(nginx_sites_available).exists() {
-        error!("Nginx sites-available directory not found");
-        return HttpResponse::InternalServerError().json(NginxResponse {
+    let proxy = import_site(name, &content);
+    if !proxy.acme {
+        return HttpResponse::BadRequest().json(NginxResponse {
             success: false,
-            message: format!("Nginx qovluğu tapılmadı: {}. Nginx quraşdırılıb?", nginx_sites_available),
+            message: format!("'{}' ACME sertifikatından istifadə etmir", name),
         });
     }
-    
-    let config_path = format!("{}/{}", nginx_sites_available, validated_proxy.name);
-    let enabled_path = format!("{}/{}", nginx_sites_enabled, validated_proxy.name);
-    let backup_path = format!("{}/{}.backup", nginx_sites_available, validated_proxy.name);
-    
-    info!("Config path: {}", config_path);
-    info!("Enabled path: {}", enabled_path);
-    
-    // Backup existing config if it exists
-    let had_backup = if Path::new(&config_path).exists() {
-        info!("Backing up existing config");
-        match fs::copy(&config_path, &backup_path) {
-            Ok(_) => {
-                info!("Backup created successfully");
-                true
-            },
-            Err(e) => {
-                error!("Failed to create backup: {}", e);
-                return HttpResponse::InternalServerError().json(NginxResponse {
-                    success: false,
-                    message: format!("Mövcud konfiqurasiya yedəklənə bilmədi: {}. Dəyişiklik təhlükəlidir.", e),
-                });
-            }
-        }
-    } else {
-        false
-    };
-    
-    // Generate nginx config
-    let config_content = generate_nginx_config(&validated_proxy);
-    
-    // Write config file
-    match fs::write(&config_path, &config_content) {
-        Ok(_) => {
-            info!("Config file written successfully");
-            
-            // Create symlink to enable
-            if Path::new(&enabled_path).exists() {
-                info!("Removing existing symlink");
-                let _ = fs::remove_file(&enabled_path);
-            }
-            
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::symlink;
-                if let Err(e) = symlink(&config_path, &enabled_path) {
-                    error!("Failed to create symlink: {}", e);
-                    // Restore backup if we had one
-                    if had_backup {
-                        let _ = fs::copy(&backup_path, &config_path);
-                        let _ = fs::remove_file(&backup_path);
-                    }
-                    return HttpResponse::InternalServerError().json(NginxResponse {
-                        success: false,
-                        message: format!("Symlink yaradıla bilmədi: {}. Root icazəsi lazımdır.", e),
-                    });
-                }
-                info!("Symlink created successfully");
-            }
-            
-            // Test nginx config
-            info!("Testing nginx configuration...");
-            let output = std::process::Command::new("nginx")
-                .args(&["-t"])
-                .output();
-            
-            match output {
-                Ok(result) => {
-                    let stderr = String::from_utf8_lossy(&result.stderr);
-                    info!("Nginx test output: {}", stderr);
-                    
-                    if result.status.success() {
-                        // Test passed - remove backup and reload nginx
-                        if had_backup {
-                            let _ = fs::remove_file(&backup_path);
-                            info!("Backup removed after successful test");
-                        }
-                        
-                        info!("Reloading nginx...");
-                        let reload = std::process::Command::new("systemctl")
-                            .args(&["reload", "nginx"])
-                            .output();
-                        
-                        match reload {
-                            Ok(reload_result) => {
-                                if reload_result.status.success() {
-                                    info!("Nginx reloaded successfully");
-                                    HttpResponse::Ok().json(NginxResponse {
-                                        success: true,
-                                        message: format!("✅ {} proxy konfiqurasiyası yaradıldı və aktiv edildi", validated_proxy.domain),
-                                    })
-                                } else {
-                                    let reload_err = String::from_utf8_lossy(&reload_result.stderr);
-                                    error!("Nginx reload failed: {}", reload_err);
-                                    HttpResponse::InternalServerError().json(NginxResponse {
-                                        success: false,
-                                        message: format!("Nginx reload edilə bilmədi: {}. Systemctl icazəsi lazımdır.", reload_err),
-                                    })
-                                }
-                            },
-                            Err(e) => {
-                                error!("Failed to execute systemctl: {}", e);
-                                HttpResponse::InternalServerError().json(NginxResponse {
-                                    success: false,
-                                    message: format!("Systemctl çalışdırıla bilmədi: {}. Root icazəsi lazımdır.", e),
-                                })
-                            }
-                        }
-                    } else {
-                        // Test failed - restore backup if we had one
-                        error!("Nginx config test failed, rolling back");
-                        
-                        if had_backup {
-                            info!("Restoring backup config");
-                            match fs::copy(&backup_path, &config_path) {
-                                Ok(_) => {
-                                    let _ = fs::remove_file(&backup_path);
-                                    info!("Backup restored successfully");
-                                    HttpResponse::BadRequest().json(NginxResponse {
-                                        success: false,
-                                        message: format!("❌ Nginx konfiqurasiya xətası: {}\n\n✅ Əvvəlki konfiqurasiya bərpa edildi.", stderr),
-                                    })
-                                },
-                                Err(e) => {
-                                    error!("Failed to restore backup: {}", e);
-                                    HttpResponse::InternalServerError().json(NginxResponse {
-                                        success: false,
-                                        message: format!("❌ Nginx xətası: {}\n❌ Yedək bərpa edilə bilmədi: {}", stderr, e),
-                                    })
-                                }
-                            }
-                        } else {
-                            // No backup - just remove the bad config
-                            let _ = fs::remove_file(&config_path);
-                            let _ = fs::remove_file(&enabled_path);
-                            HttpResponse::BadRequest().json(NginxResponse {
-                                success: false,
-                                message: format!("❌ Nginx konfiqurasiya xətası: {}\n\nYanlış konfiqurasiya silindi.", stderr),
-                            })
-                        }
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to test nginx config: {}", e);
-                    // Restore backup if we had one
-                    if had_backup {
-                        let _ = fs::copy(&backup_path, &config_path);
-                        let _ = fs::remove_file(&backup_path);
-                    }
-                    HttpResponse::InternalServerError().json(NginxResponse {
-                        success: false,
-                        message: format!("Nginx test edilə bilmədi: {}. Nginx quraşdırılıb?", e),
-                    })
-                }
-            }
-        },
+    let domains = proxy.domains();
+    let checked = certificate_name(&domains).and_then(|name| check_acme_owned(data.acme.store().info(name).as_ref()));
+    if let Err(e) = checked {
+        return error_response(e);
+    }
+    match issue_and_reload(data, &domains).await {
+        Ok(info) => HttpResponse::Ok().json(info),
+        Err(e @ AppError::Conflict(_)) => error_response(e),
         Err(e) => {
-            error!("Failed to write config file: {}", e);
-            // Restore backup if we had one
-            if had_backup {
-                let _ = fs::copy(&backup_path, &config_path);
-                let _ = fs::remove_file(&backup_path);
-            }
-            HttpResponse::InternalServerError().json(NginxResponse {
+            error!("Certificate issuance for {} failed: {}", proxy.domain, e);
+            HttpResponse::BadGateway().json(NginxResponse {
                 success: false,
-                message: format!("Fayl yazıla bilmədi: {}. Root icazəsi lazımdır. Container root olaraq çalışmalıdır.", e),
+                message: format!("Sertifikat alına bilmədi: {}", e),
             })
-        },
+        }
+    }
+}
+
+/// Create a new nginx proxy configuration
+///
+/// With `?preview=true` nothing is written; see [`preview_proxy`].
+#[actix_web::post("/api/nginx/proxies")]
+pub async fn create_nginx_proxy(
+    data: web::Data<AppState>,
+    proxy: web::Json<NginxProxy>,
+    query: web::Query<ProxyWriteQuery>,
+    identity: Identity,
+) -> impl Responder {
+    if query.preview {
+        info!("POST /api/nginx/proxies?preview=true - Previewing proxy: {}", proxy.name);
+        return preview_proxy(&data, proxy.into_inner(), false).await;
+    }
+    let _lock = data.nginx_lock.lock().await;
+    let target = proxy.name.clone();
+    let payload = serde_json::to_value(&*proxy).unwrap_or_default();
+    let domains = (proxy.ssl && proxy.acme).then(|| proxy.domains());
+    let response = create_proxy(&data, proxy).await;
+    if response.status().is_success() {
+        record_revision(&data, &identity, &target, "create", None);
+    }
+    if let (Some(domains), true) = (domains, response.status().is_success()) {
+        spawn_issuance(&data, domains);
+    }
+    data.nginx_stats.record(ProxyOperation::Create, response.status().is_success());
+    data.audit.record(&identity, "nginx", "proxy.create", &target, payload, response.status());
+    response
+}
+
+async fn create_proxy(data: &web::Data<AppState>, proxy: web::Json<NginxProxy>) -> HttpResponse {
+    info!("POST /api/nginx/proxies - Creating proxy: {} -> {}", proxy.domain, proxy.backend);
+    let proxy = proxy.into_inner();
+    match with_proxy_service(data, move |service| service.apply(proxy, ProxyWrite::Create)).await {
+        Ok(proxy) => HttpResponse::Ok().json(NginxResponse {
+            success: true,
+            message: format!("✅ {} proxy konfiqurasiyası yaradıldı və aktiv edildi", proxy.domain),
+        }),
+        Err(e) => error_response(e),
     }
 }
 
 /// Delete an nginx proxy configuration
 #[actix_web::delete("/api/nginx/proxies/{name}")]
-pub async fn delete_nginx_proxy(data: web::Data<AppState>, name: web::Path<String>) -> impl Responder {
+pub async fn delete_nginx_proxy(data: web::Data<AppState>, name: web::Path<String>, identity: Identity) -> impl Responder {
+    let _lock = data.nginx_lock.lock().await;
+    let target = name.to_string();
+    let response = delete_proxy(&data, name).await;
+    if response.status().is_success() {
+        record_revision(&data, &identity, &target, "delete", None);
+    }
+    data.nginx_stats.record(ProxyOperation::Delete, response.status().is_success());
+    data.audit.record(&identity, "nginx", "proxy.delete", &target, serde_json::json!({"name": target}), response.status());
+    response
+}
+
+async fn delete_proxy(data: &web::Data<AppState>, name: web::Path<String>) -> HttpResponse {
     info!("DELETE /api/nginx/proxies/{} - Deleting proxy", name);
-    
-    let nginx_sites_available = &data.nginx_config.sites_available_path;
-    let nginx_sites_enabled = &data.nginx_config.sites_enabled_path;
-    
-    let config_path = format!("{}/{}", nginx_sites_available, name.as_str());
-    let enabled_path = format!("{}/{}", nginx_sites_enabled, name.as_str());
-    
-    // Remove symlink
-    if Path::new(&enabled_path).exists() {
-        if let Err(e) = fs::remove_file(&enabled_path) {
-            error!("Failed to remove enabled link: {}", e);
-            return HttpResponse::InternalServerError().json(NginxResponse {
+    let target = name.to_string();
+    match with_proxy_service(data, move |service| service.delete(&target)).await {
+        Ok(()) => HttpResponse::Ok().json(NginxResponse {
+            success: true,
+            message: format!("✅ {} proxy konfiqurasiyası silindi", name.as_str()),
+        }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Update an existing nginx proxy configuration
+///
+/// With `?preview=true` nothing is written; see [`preview_proxy`].
+#[actix_web::put("/api/nginx/proxies/{name}")]
+pub async fn update_nginx_proxy(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    proxy: web::Json<NginxProxy>,
+    query: web::Query<ProxyWriteQuery>,
+    identity: Identity,
+) -> impl Responder {
+    if query.preview {
+        info!("PUT /api/nginx/proxies/{}?preview=true - Previewing update", name);
+        if proxy.name != *name {
+            return HttpResponse::BadRequest().json(NginxResponse {
                 success: false,
-                message: format!("Enabled link silinə bilmədi: {}. Root icazəsi lazımdır.", e),
+                message: "URL-dəki ad və body-dəki ad uyğun gəlmir".to_string(),
             });
         }
-        info!("Symlink removed");
+        return preview_proxy(&data, proxy.into_inner(), true).await;
     }
-    
-    // Remove config file
-    match fs::remove_file(&config_path) {
-        Ok(_) => {
-            info!("Config file removed");
-            // Reload nginx
-            let reload = std::process::Command::new("systemctl")
-                .args(&["reload", "nginx"])
-                .output();
-            
-            match reload {
-                Ok(_) => {
-                    info!("Nginx reloaded");
-                    HttpResponse::Ok().json(NginxResponse {
-                        success: true,
-                        message: format!("✅ {} proxy konfiqurasiyası silindi", name.as_str()),
-                    })
-                },
-                Err(e) => {
-                    warn!("Failed to reload nginx: {}", e);
-                    HttpResponse::Ok().json(NginxResponse {
-                        success: true,
-                        message: format!("✅ {} silindi (nginx reload edilmədi: {})", name.as_str(), e),
-                    })
-                }
-            }
-        },
-        Err(e) => {
-            error!("Failed to remove config file: {}", e);
-            HttpResponse::InternalServerError().json(NginxResponse {
-                success: false,
-                message: format!("Silinə bilmədi: {}. Root icazəsi lazımdır.", e),
-            })
-        },
+    let _lock = data.nginx_lock.lock().await;
+    let target = name.to_string();
+    let payload = serde_json::to_value(&*proxy).unwrap_or_default();
+    let domains = (proxy.ssl && proxy.acme).then(|| proxy.domains());
+    let response = update_proxy(&data, name, proxy).await;
+    if response.status().is_success() {
+        record_revision(&data, &identity, &target, "update", None);
     }
+    if let (Some(domains), true) = (domains, response.status().is_success()) {
+        spawn_issuance(&data, domains);
+    }
+    data.nginx_stats.record(ProxyOperation::Update, response.status().is_success());
+    data.audit.record(&identity, "nginx", "proxy.update", &target, payload, response.status());
+    response
 }
 
-/// Update an existing nginx proxy configuration
-#[actix_web::put("/api/nginx/proxies/{name}")]
-pub async fn update_nginx_proxy(data: web::Data<AppState>, name: web::Path<String>, proxy: web::Json<NginxProxy>) -> impl Responder {
-    let proxy_name = name.into_inner();
-    info!("PUT /api/nginx/proxies/{} - Updating proxy", proxy_name);
-
-    let nginx_sites_available = &data.nginx_config.sites_available_path;
-    let nginx_sites_enabled = &data.nginx_config.sites_enabled_path;
-
-    // Validate that the name matches
-    if proxy.name != proxy_name {
+async fn update_proxy(data: &web::Data<AppState>, name: web::Path<String>, proxy: web::Json<NginxProxy>) -> HttpResponse {
+    info!("PUT /api/nginx/proxies/{} - Updating proxy", name);
+    if proxy.name != *name {
         return HttpResponse::BadRequest().json(NginxResponse {
             success: false,
             message: "URL-dəki ad və body-dəki ad uyğun gəlmir".to_string(),
         });
     }
 
-    // Validate and format extra_config if provided
-    let validated_proxy = if let Some(extra) = &proxy.extra_config {
-        if !extra.trim().is_empty() {
-            match validate_nginx_extra_config(extra) {
-                Ok(formatted) => {
-                    info!("Extra config validated and formatted");
-                    NginxProxy {
-                        name: proxy.name.clone(),
-                        domain: proxy.domain.clone(),
-                        backend: proxy.backend.clone(),
-                        ssl: proxy.ssl,
-                        extra_config: Some(formatted),
-                    }
-                },
-                Err(e) => {
-                    error!("Extra config validation failed: {}", e);
-                    return HttpResponse::BadRequest().json(NginxResponse {
-                        success: false,
-                        message: format!("❌ Əlavə konfiqurasiya xətası: {}", e),
-                    });
-                }
-            }
-        } else {
-            proxy.into_inner()
-        }
-    } else {
-        proxy.into_inner()
-    };
+    let proxy = proxy.into_inner();
+    match with_proxy_service(data, move |service| service.apply(proxy, ProxyWrite::Update)).await {
+        Ok(proxy) => HttpResponse::Ok().json(NginxResponse {
+            success: true,
+            message: format!("✅ {} proxy konfiqurasiyası yeniləndi", proxy.domain),
+        }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Run `f` with a [`ProxyService`] on the blocking thread pool, since
+/// `nginx -t`, the reload and password hashing would stall the async workers
+async fn with_proxy_service<T: Send + 'static>(
+    data: &web::Data<AppState>,
+    f: impl FnOnce(&ProxyService) -> Result<T, AppError> + Send + 'static,
+) -> Result<T, AppError> {
+    let data = data.clone();
+    web::block(move || {
+        let nginx_config = data.nginx_config();
+        let controller = data.nginx_controller();
+        f(&ProxyService::new(&nginx_config, &*controller, data.acme.store(), &data.htpasswd))
+    })
+    .await
+    .map_err(|e| AppError::NginxError(e.to_string()))?
+}
+
+/// Link a site into `sites_enabled_path`, then test and reload nginx
+#[actix_web::post("/api/nginx/proxies/{name}/enable")]
+pub async fn enable_nginx_proxy(data: web::Data<AppState>, name: web::Path<String>, identity: Identity) -> impl Responder {
+    info!("POST /api/nginx/proxies/{}/enable - Enabling site", name);
+    let _lock = data.nginx_lock.lock().await;
+    let response = set_site_enabled(&data, &name, true).await;
+    data.nginx_stats.record(ProxyOperation::Enable, response.status().is_success());
+    data.audit.record(&identity, "nginx", "proxy.enable", &name, serde_json::json!({"name": name.as_str()}), response.status());
+    response
+}
+
+/// Remove a site's `sites_enabled_path` link, keeping its config, then test and reload nginx
+#[actix_web::post("/api/nginx/proxies/{name}/disable")]
+pub async fn disable_nginx_proxy(data: web::Data<AppState>, name: web::Path<String>, identity: Identity) -> impl Responder {
+    info!("POST /api/nginx/proxies/{}/disable - Disabling site", name);
+    let _lock = data.nginx_lock.lock().await;
+    let response = set_site_enabled(&data, &name, false).await;
+    data.nginx_stats.record(ProxyOperation::Disable, response.status().is_success());
+    data.audit.record(&identity, "nginx", "proxy.disable", &name, serde_json::json!({"name": name.as_str()}), response.status());
+    response
+}
 
-    let config_path = format!("{}/{}", nginx_sites_available, validated_proxy.name);
-    let enabled_path = format!("{}/{}", nginx_sites_enabled, validated_proxy.name);
-    let backup_path = format!("{}/{}.backup", nginx_sites_available, validated_proxy.name);
+/// Create or remove the `sites_enabled_path` symlink of a site
+///
+/// Only symlinks are touched: a regular file in `sites_enabled_path` is
+/// reported as a conflict instead of being deleted.
+async fn set_site_enabled(data: &web::Data<AppState>, name: &str, enable: bool) -> HttpResponse {
+    let nginx_config = data.nginx_config();
+    let config_path = format!("{}/{}", nginx_config.sites_available_path, name);
+    let enabled_path = format!("{}/{}", nginx_config.sites_enabled_path, name);
 
-    // Check if config exists
-    if !Path::new(&config_path).exists() {
-        error!("Config file not found: {}", config_path);
+    if validate_site_name(name).is_err() || !Path::new(&config_path).is_file() {
         return HttpResponse::NotFound().json(NginxResponse {
             success: false,
-            message: format!("Konfiqurasiya tapılmadı: {}", validated_proxy.name),
+            message: format!("'{}' proxy tapılmadı", name),
         });
     }
-
-    // Backup existing config
-    info!("Backing up existing config");
-    if let Err(e) = fs::copy(&config_path, &backup_path) {
-        error!("Failed to create backup: {}", e);
-        return HttpResponse::InternalServerError().json(NginxResponse {
+    let link = fs::symlink_metadata(&enabled_path).ok();
+    if link.as_ref().is_some_and(|metadata| !metadata.file_type().is_symlink()) {
+        return HttpResponse::Conflict().json(NginxResponse {
             success: false,
-            message: format!("Yedək yaradıla bilmədi: {}", e),
+            message: format!("{} symlink deyil; onu əl ilə idarə edin", enabled_path),
+        });
+    }
+    if link.is_some() == enable {
+        return HttpResponse::Ok().json(NginxResponse {
+            success: true,
+            message: if enable {
+                format!("'{}' artıq aktivdir", name)
+            } else {
+                format!("'{}' artıq deaktivdir", name)
+            },
         });
     }
 
-    // Generate new config
-    let config_content = generate_nginx_config(&validated_proxy);
-
-    // Write new config
-    match fs::write(&config_path, &config_content) {
-        Ok(_) => {
-            info!("Config file updated successfully");
-
-            // Ensure symlink exists
-            if !Path::new(&enabled_path).exists() {
-                #[cfg(unix)]
-                {
-                    use std::os::unix::fs::symlink;
-                    if let Err(e) = symlink(&config_path, &enabled_path) {
-                        error!("Failed to create symlink: {}", e);
-                        let _ = fs::copy(&backup_path, &config_path);
-                        let _ = fs::remove_file(&backup_path);
-                        return HttpResponse::InternalServerError().json(NginxResponse {
-                            success: false,
-                            message: format!("Symlink yaradıla bilmədi: {}", e),
-                        });
-                    }
-                }
-            }
+    let mut changes = Changeset::new();
+    if enable {
+        changes.link(Path::new(&config_path), Path::new(&enabled_path));
+    } else {
+        changes.remove(Path::new(&enabled_path));
+    }
 
-            // Test nginx config
-            info!("Testing nginx configuration...");
-            let output = std::process::Command::new("nginx")
-                .args(&["-t"])
-                .output();
-
-            match output {
-                Ok(result) => {
-                    let stderr = String::from_utf8_lossy(&result.stderr);
-                    info!("Nginx test output: {}", stderr);
-
-                    if result.status.success() {
-                        // Test passed - remove backup and reload nginx
-                        let _ = fs::remove_file(&backup_path);
-                        info!("Backup removed after successful test");
-
-                        info!("Reloading nginx...");
-                        let reload = std::process::Command::new("systemctl")
-                            .args(&["reload", "nginx"])
-                            .output();
-
-                        match reload {
-                            Ok(reload_result) => {
-                                if reload_result.status.success() {
-                                    info!("Nginx reloaded successfully");
-                                    HttpResponse::Ok().json(NginxResponse {
-                                        success: true,
-                                        message: format!("✅ {} proxy konfiqurasiyası yeniləndi", validated_proxy.domain),
-                                    })
-                                } else {
-                                    let reload_err = String::from_utf8_lossy(&reload_result.stderr);
-                                    error!("Nginx reload failed: {}", reload_err);
-                                    HttpResponse::InternalServerError().json(NginxResponse {
-                                        success: false,
-                                        message: format!("Nginx reload edilə bilmədi: {}", reload_err),
-                                    })
-                                }
-                            },
-                            Err(e) => {
-                                error!("Failed to execute systemctl: {}", e);
-                                HttpResponse::InternalServerError().json(NginxResponse {
-                                    success: false,
-                                    message: format!("Systemctl çalışdırıla bilmədi: {}", e),
-                                })
-                            }
-                        }
-                    } else {
-                        // Test failed - restore backup
-                        error!("Nginx config test failed, rolling back");
-                        info!("Restoring backup config");
-                        match fs::copy(&backup_path, &config_path) {
-                            Ok(_) => {
-                                let _ = fs::remove_file(&backup_path);
-                                info!("Backup restored successfully");
-                                HttpResponse::BadRequest().json(NginxResponse {
-                                    success: false,
-                                    message: format!("❌ Nginx konfiqurasiya xətası: {}\n\n✅ Əvvəlki konfiqurasiya bərpa edildi.", stderr),
-                                })
-                            },
-                            Err(e) => {
-                                error!("Failed to restore backup: {}", e);
-                                HttpResponse::InternalServerError().json(NginxResponse {
-                                    success: false,
-                                    message: format!("❌ Nginx xətası: {}\n❌ Yedək bərpa edilə bilmədi: {}", stderr, e),
-                                })
-                            }
-                        }
-                    }
-                },
-                Err(e) => {
-                    error!("Failed to test nginx config: {}", e);
-                    let _ = fs::copy(&backup_path, &config_path);
-                    let _ = fs::remove_file(&backup_path);
-                    HttpResponse::InternalServerError().json(NginxResponse {
-                        success: false,
-                        message: format!("Nginx test edilə bilmədi: {}", e),
-                    })
-                }
+    let message = if enable {
+        format!("✅ '{}' aktiv edildi", name)
+    } else {
+        format!("✅ '{}' deaktiv edildi; konfiqurasiya saxlanıldı", name)
+    };
+    match commit_blocking(data, changes).await {
+        Ok(()) => HttpResponse::Ok().json(NginxResponse { success: true, message }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// Render, diff and test a proxy change without touching sites-available or sites-enabled
+///
+/// Runs the same checks as a real create or update, then `nginx -t` on a
+/// temporary copy of the config tree that holds the rendered site, on the
+/// blocking thread pool.
+async fn preview_proxy(data: &web::Data<AppState>, proxy: NginxProxy, update: bool) -> HttpResponse {
+    let data = data.clone();
+    match web::block(move || render_preview(&data, proxy, update)).await {
+        Ok(Ok(preview)) => HttpResponse::Ok().json(preview),
+        Ok(Err(e)) => error_response(e),
+        Err(e) => error_response(AppError::NginxError(e.to_string())),
+    }
+}
+
+fn render_preview(data: &AppState, proxy: NginxProxy, update: bool) -> Result<ProxyPreview, AppError> {
+    let nginx_config = data.nginx_config();
+    let controller = data.nginx_controller();
+    let service = ProxyService::new(&nginx_config, &*controller, data.acme.store(), &data.htpasswd);
+    let write = if update { ProxyWrite::Update } else { ProxyWrite::Create };
+    let proxy = normalize(proxy)?;
+    let current = service.check(&proxy, write)?;
+
+    let config = generate_nginx_config(&proxy);
+    let diff = match &current {
+        Some(current) => unified_diff(current, &config, &format!("{} (current)", proxy.name), &format!("{} (preview)", proxy.name)),
+        None => unified_diff("", &config, "/dev/null", &format!("{} (preview)", proxy.name)),
+    };
+    let enabled = !update || site_enabled(&nginx_config.sites_enabled_path, &proxy.name);
+    let test = test_in_copy(data, &nginx_config, &proxy, &config, enabled);
+    Ok(ProxyPreview { config, diff, enabled, test })
+}
+
+/// `nginx -t` on a copy of the config tree with `config` as the site of `proxy`
+///
+/// An ACME proxy without a certificate yet gets a throwaway placeholder in the copy.
+fn test_in_copy(data: &AppState, nginx_config: &NginxConfig, proxy: &NginxProxy, config: &str, enabled: bool) -> NginxTestResult {
+    let failed = |output: String| NginxTestResult { success: false, output };
+    let main_config = Path::new(&nginx_config.main_config_path);
+    if !main_config.is_file() {
+        return failed(format!("Nginx əsas konfiqurasiyası tapılmadı: {}", main_config.display()));
+    }
+
+    let placeholder = (proxy.ssl && proxy.acme && data.acme.store().info(proxy.primary_domain()).is_none())
+        .then(|| Path::new(CERTIFICATES_DIR).join(proxy.primary_domain()));
+    let tree = match PreviewTree::new(nginx_config, &std::env::temp_dir(), placeholder.as_slice()) {
+        Ok(tree) => tree,
+        Err(e) => return failed(e.to_string()),
+    };
+    if let Some(dir) = &placeholder {
+        if let Err(e) = write_placeholder(&tree.path(dir), &proxy.domains()) {
+            return failed(e.to_string());
+        }
+    }
+
+    let available = Path::new(&nginx_config.sites_available_path).join(&proxy.name);
+    let enabled_path = Path::new(&nginx_config.sites_enabled_path).join(&proxy.name);
+    let written = tree.write(&available, config).and_then(|_| {
+        if enabled {
+            tree.write(&enabled_path, config)
+        } else {
+            tree.remove(&enabled_path);
+            Ok(())
+        }
+    });
+    if let Err(e) = written {
+        return failed(e.to_string());
+    }
+    match data.nginx_controller().test(Some(&tree.path(main_config))) {
+        Ok(result) => result,
+        Err(e) => failed(e.to_string()),
+    }
+}
+
+/// Store the site file as it is now as a new revision
+///
+/// A missing file is recorded as a deletion. Failures are logged rather than
+/// returned since the change itself has already been applied.
+fn record_revision(data: &AppState, identity: &Identity, name: &str, action: &str, rollback_of: Option<u32>) {
+    let nginx_config = data.nginx_config();
+    let content = fs::read_to_string(format!("{}/{}", nginx_config.sites_available_path, name)).unwrap_or_default();
+    let store = RevisionStore::new(&nginx_config.revisions_path);
+    match store.record(name, &content, &identity.name, action, rollback_of) {
+        Ok(revision) => info!("Recorded revision {} of {}", revision.number, name),
+        Err(e) => warn!("⚠️  Could not record revision of {}: {}", name, e),
+    }
+}
+
+fn revision_store(data: &AppState) -> RevisionStore {
+    RevisionStore::new(data.nginx_config().revisions_path)
+}
+
+/// List the revisions of a site, newest first
+#[actix_web::get("/api/nginx/proxies/{name}/revisions")]
+pub async fn get_nginx_revisions(data: web::Data<AppState>, name: web::Path<String>) -> impl Responder {
+    info!("GET /api/nginx/proxies/{}/revisions - Listing revisions", name);
+    HttpResponse::Ok().json(revision_store(&data).list(&name))
+}
+
+/// Unified diff between two revisions of a site
+#[actix_web::get("/api/nginx/proxies/{name}/revisions/diff")]
+pub async fn diff_nginx_revisions(
+    data: web::Data<AppState>,
+    name: web::Path<String>,
+    query: web::Query<RevisionDiffQuery>,
+) -> Result<HttpResponse, AppError> {
+    info!("GET /api/nginx/proxies/{}/revisions/diff - Comparing {} and {}", name, query.from, query.to);
+    let diff = revision_store(&data).diff(&name, query.from, query.to)?;
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "from": query.from,
+        "to": query.to,
+        "diff": diff,
+    })))
+}
+
+/// One revision of a site with its full config text
+#[actix_web::get("/api/nginx/proxies/{name}/revisions/{number}")]
+pub async fn get_nginx_revision(data: web::Data<AppState>, path: web::Path<(String, u32)>) -> Result<HttpResponse, AppError> {
+    let (name, number) = path.into_inner();
+    info!("GET /api/nginx/proxies/{}/revisions/{} - Showing revision", name, number);
+    let store = revision_store(&data);
+    let revision = store.get(&name, number)?;
+    let content = store.content(&name, number)?;
+    let mut body = serde_json::to_value(revision).unwrap_or_default();
+    body["content"] = serde_json::Value::String(content);
+    Ok(HttpResponse::Ok().json(body))
+}
+
+/// Put the config of an earlier revision back, then test and reload nginx
+///
+/// The revision is restored through [`ProxyService::apply`] like any other
+/// write: a deleted site is linked again, the htpasswd file follows the
+/// restored basic auth settings and nothing changes if `nginx -t` fails. A
+/// successful rollback is itself recorded as a new revision.
+#[actix_web::post("/api/nginx/proxies/{name}/revisions/{number}/rollback")]
+pub async fn rollback_nginx_proxy(data: web::Data<AppState>, path: web::Path<(String, u32)>, identity: Identity) -> impl Responder {
+    let (name, number) = path.into_inner();
+    info!("POST /api/nginx/proxies/{}/revisions/{}/rollback - Rolling back", name, number);
+    let _lock = data.nginx_lock.lock().await;
+
+    let response = match restore_revision(&data, &name, number).await {
+        Ok(domains) => {
+            record_revision(&data, &identity, &name, "rollback", Some(number));
+            if let Some(domains) = domains {
+                spawn_issuance(&data, domains);
             }
-        },
+            HttpResponse::Ok().json(NginxResponse {
+                success: true,
+                message: format!("✅ '{}' {} nömrəli revizyaya qaytarıldı", name, number),
+            })
+        }
+        Err(response) => response,
+    };
+
+    data.audit.record(&identity, "nginx", "proxy.rollback", &name, serde_json::json!({"name": name, "revision": number}), response.status());
+    response
+}
+
+/// Write revision `number` of site `name` back, returning the domains to issue an ACME certificate for
+async fn restore_revision(data: &web::Data<AppState>, name: &str, number: u32) -> Result<Option<Vec<String>>, HttpResponse> {
+    validate_site_name(name).map_err(|e| error_response(AppError::ValidationError(format!("name: {}", e))))?;
+    let content = revision_store(data).content(name, number).map_err(error_response)?;
+    if content.is_empty() {
+        return Err(HttpResponse::BadRequest().json(NginxResponse {
+            success: false,
+            message: format!("{} nömrəli revizya saytın silinməsidir; əvvəlki revizyanı seçin", number),
+        }));
+    }
+
+    let mut proxy = import_site(name, &content);
+    if proxy.unmanaged {
+        return Err(HttpResponse::Conflict().json(NginxResponse {
+            success: false,
+            message: format!("{} nömrəli revizya proxy modeli ilə ifadə oluna bilmir; faylı əl ilə bərpa edin", number),
+        }));
+    }
+    // Revisions hold no passwords, so the users keep the hashes they have now
+    if let Some(auth) = &mut proxy.basic_auth {
+        auth.users = data
+            .htpasswd
+            .users(name)
+            .into_iter()
+            .map(|username| BasicAuthUser { username, password: None })
+            .collect();
+        if auth.users.is_empty() {
+            return Err(HttpResponse::Conflict().json(NginxResponse {
+                success: false,
+                message: format!("'{}' üçün basic auth istifadəçisi qalmayıb; onları yeniləmə ilə təyin edin", name),
+            }));
+        }
+    }
+
+    let write = if Path::new(&data.nginx_config().sites_available_path).join(name).is_file() {
+        ProxyWrite::Update
+    } else {
+        ProxyWrite::Create
+    };
+    let domains = (proxy.ssl && proxy.acme).then(|| proxy.domains());
+    with_proxy_service(data, move |service| service.apply(proxy, write))
+        .await
+        .map_err(error_response)?;
+    Ok(domains)
+}
+
+/// List all upstream pools
+#[actix_web::get("/api/nginx/upstreams")]
+pub async fn get_nginx_upstreams(data: web::Data<AppState>) -> impl Responder {
+    info!("GET /api/nginx/upstreams - Listing upstream pools");
+
+    let upstreams_dir = data.nginx_config().upstreams_path;
+    let entries = match fs::read_dir(&upstreams_dir) {
+        Ok(entries) => entries,
         Err(e) => {
-            error!("Failed to write config file: {}", e);
-            let _ = fs::copy(&backup_path, &config_path);
-            let _ = fs::remove_file(&backup_path);
-            HttpResponse::InternalServerError().json(NginxResponse {
+            warn!("Could not read upstreams directory {}: {}", upstreams_dir, e);
+            return HttpResponse::Ok().json(serde_json::json!({
+                "upstreams": [],
+                "warning": format!("Upstream qovluğu oxuna bilmədi: {}: {}", upstreams_dir, e)
+            }));
+        }
+    };
+
+    let mut upstreams: Vec<NginxUpstream> = entries
+        .flatten()
+        .filter(|entry| {
+            entry
+                .file_name()
+                .to_str()
+                .and_then(upstream_name_from_file)
+                .is_some()
+        })
+        .filter_map(|entry| fs::read_to_string(entry.path()).ok())
+        .filter_map(|content| import_upstream(&content))
+        .collect();
+    upstreams.sort_by(|a, b| a.name.cmp(&b.name));
+
+    HttpResponse::Ok().json(upstreams)
+}
+
+/// Show one upstream pool
+#[actix_web::get("/api/nginx/upstreams/{name}")]
+pub async fn get_nginx_upstream(data: web::Data<AppState>, name: web::Path<String>) -> impl Responder {
+    let path = upstream_path(&data, &name);
+    match fs::read_to_string(&path).ok().and_then(|content| import_upstream(&content)) {
+        Some(upstream) => HttpResponse::Ok().json(upstream),
+        None => upstream_not_found(&name),
+    }
+}
+
+/// Create a new upstream pool
+#[actix_web::post("/api/nginx/upstreams")]
+pub async fn create_nginx_upstream(data: web::Data<AppState>, upstream: web::Json<NginxUpstream>, identity: Identity) -> impl Responder {
+    let upstream = upstream.into_inner();
+    info!("POST /api/nginx/upstreams - Creating upstream: {}", upstream.name);
+    let _lock = data.nginx_lock.lock().await;
+
+    let response = match upstream.validate() {
+        Err(message) => HttpResponse::BadRequest().json(NginxResponse { success: false, message }),
+        Ok(()) if Path::new(&upstream_path(&data, &upstream.name)).exists() => {
+            HttpResponse::Conflict().json(NginxResponse {
                 success: false,
-                message: format!("Fayl yazıla bilmədi: {}", e),
+                message: format!("'{}' upstream artıq mövcuddur", upstream.name),
             })
-        },
+        }
+        Ok(()) => {
+            apply_config_change(
+                &data,
+                &upstream_path(&data, &upstream.name),
+                Some(&generate_upstream_config(&upstream)),
+                format!("✅ {} upstream yaradıldı", upstream.name),
+            )
+            .await
+        }
+    };
+
+    let payload = serde_json::to_value(&upstream).unwrap_or_default();
+    data.audit.record(&identity, "nginx", "upstream.create", &upstream.name, payload, response.status());
+    response
+}
+
+/// Replace the servers and balancing method of an upstream pool
+#[actix_web::put("/api/nginx/upstreams/{name}")]
+pub async fn update_nginx_upstream(data: web::Data<AppState>, name: web::Path<String>, upstream: web::Json<NginxUpstream>, identity: Identity) -> impl Responder {
+    let upstream = upstream.into_inner();
+    info!("PUT /api/nginx/upstreams/{} - Updating upstream", name);
+    let _lock = data.nginx_lock.lock().await;
+
+    let path = upstream_path(&data, &name);
+    let response = if upstream.name != name.as_str() {
+        HttpResponse::BadRequest().json(NginxResponse {
+            success: false,
+            message: "URL-dəki ad və body-dəki ad uyğun gəlmir".to_string(),
+        })
+    } else if let Err(message) = upstream.validate() {
+        HttpResponse::BadRequest().json(NginxResponse { success: false, message })
+    } else if !Path::new(&path).exists() {
+        upstream_not_found(&name)
+    } else {
+        apply_config_change(
+            &data,
+            &path,
+            Some(&generate_upstream_config(&upstream)),
+            format!("✅ {} upstream yeniləndi", upstream.name),
+        )
+        .await
+    };
+
+    let payload = serde_json::to_value(&upstream).unwrap_or_default();
+    data.audit.record(&identity, "nginx", "upstream.update", &name, payload, response.status());
+    response
+}
+
+/// Delete an upstream pool that no proxy uses
+#[actix_web::delete("/api/nginx/upstreams/{name}")]
+pub async fn delete_nginx_upstream(data: web::Data<AppState>, name: web::Path<String>, identity: Identity) -> impl Responder {
+    info!("DELETE /api/nginx/upstreams/{} - Deleting upstream", name);
+    let _lock = data.nginx_lock.lock().await;
+
+    let path = upstream_path(&data, &name);
+    let users = upstream_users(&data.nginx_config().sites_available_path, &name);
+    let response = if !Path::new(&path).exists() {
+        upstream_not_found(&name)
+    } else if !users.is_empty() {
+        HttpResponse::Conflict().json(NginxResponse {
+            success: false,
+            message: format!("'{}' upstream istifadə olunur: {}", name, users.join(", ")),
+        })
+    } else {
+        apply_config_change(&data, &path, None, format!("✅ {} upstream silindi", name)).await
+    };
+
+    data.audit.record(&identity, "nginx", "upstream.delete", &name, serde_json::json!({"name": name.as_str()}), response.status());
+    response
+}
+
+/// File an upstream is stored in; the name is validated before anything is written to it
+fn upstream_path(data: &AppState, name: &str) -> String {
+    format!("{}/{}", data.nginx_config().upstreams_path, upstream_file_name(name))
+}
+
+fn upstream_not_found(name: &str) -> HttpResponse {
+    HttpResponse::NotFound().json(NginxResponse {
+        success: false,
+        message: format!("'{}' upstream tapılmadı", name),
+    })
+}
+
+/// Sites whose `proxy_pass` points at upstream `name`
+fn upstream_users(sites_available: &str, name: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(sites_available) else {
+        return Vec::new();
+    };
+
+    let mut users: Vec<String> = entries
+        .flatten()
+        .filter(|entry| {
+            fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|content| parse(&content).ok())
+                .is_some_and(|config| {
+                    config
+                        .walk()
+                        .into_iter()
+                        .filter(|d| d.name == "proxy_pass")
+                        .filter_map(|d| d.first_arg())
+                        .any(|backend| backend_host(backend) == name)
+                })
+        })
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    users.sort();
+    users
+}
+
+/// Write (or with `None` remove) a config file, then test and reload nginx
+///
+/// The file is only written once `nginx -t` accepts a copy of the tree with
+/// the change made; the previous file is put back if the reload fails.
+async fn apply_config_change(data: &web::Data<AppState>, path: &str, content: Option<&str>, success_message: String) -> HttpResponse {
+    let mut changes = Changeset::new();
+    match content {
+        Some(content) => changes.write(Path::new(path), content),
+        None => changes.remove(Path::new(path)),
+    }
+
+    match commit_blocking(data, changes).await {
+        Ok(()) => HttpResponse::Ok().json(NginxResponse {
+            success: true,
+            message: success_message,
+        }),
+        Err(e) => error_response(e),
+    }
+}
+
+/// [`commit`] `changes` on the blocking thread pool
+async fn commit_blocking(data: &web::Data<AppState>, changes: Changeset) -> Result<(), AppError> {
+    let nginx_config = data.nginx_config();
+    let controller = data.nginx_controller();
+    web::block(move || commit(&nginx_config, &*controller, changes))
+        .await
+        .map_err(|e| AppError::NginxError(e.to_string()))?
+}
+
+/// A stored certificate and the sites whose `ssl_certificate` points at it
+#[derive(serde::Serialize)]
+struct CertificateEntry {
+    #[serde(flatten)]
+    info: CertificateInfo,
+    used_by: Vec<String>,
+}
+
+/// List stored certificates with subject, SANs and expiry
+#[actix_web::get("/api/nginx/certificates")]
+pub async fn get_nginx_certificates(data: web::Data<AppState>) -> impl Responder {
+    info!("GET /api/nginx/certificates - Listing certificates");
+
+    let sites_available = data.nginx_config().sites_available_path;
+    let certificates: Vec<CertificateEntry> = data
+        .acme
+        .store()
+        .list()
+        .into_iter()
+        .map(|info| CertificateEntry {
+            used_by: certificate_users(&sites_available, &info.name),
+            info,
+        })
+        .collect();
+    HttpResponse::Ok().json(certificates)
+}
+
+/// Upload a PEM certificate or generate a self-signed one
+#[actix_web::post("/api/nginx/certificates")]
+pub async fn create_nginx_certificate(data: web::Data<AppState>, request: web::Json<CertificateRequest>, identity: Identity) -> impl Responder {
+    let request = request.into_inner();
+    let name = request.name().to_string();
+    let payload = certificate_payload(&request);
+    info!("POST /api/nginx/certificates - Adding certificate: {}", name);
+    let _lock = data.nginx_lock.lock().await;
+
+    let response = if data.acme.store().info(&name).is_some() {
+        HttpResponse::Conflict().json(NginxResponse {
+            success: false,
+            message: format!("'{}' sertifikatı artıq mövcuddur", name),
+        })
+    } else {
+        match store_certificate(&data, request, false).await {
+            Ok(info) => HttpResponse::Created().json(info),
+            Err(e) => error_response(e),
+        }
+    };
+
+    data.audit.record(&identity, "nginx", "certificate.create", &name, payload, response.status());
+    response
+}
+
+/// Replace a stored certificate, testing and reloading nginx if a site uses it
+#[actix_web::put("/api/nginx/certificates/{name}")]
+pub async fn update_nginx_certificate(data: web::Data<AppState>, name: web::Path<String>, request: web::Json<CertificateRequest>, identity: Identity) -> impl Responder {
+    let request = request.into_inner();
+    let payload = certificate_payload(&request);
+    info!("PUT /api/nginx/certificates/{} - Replacing certificate", name);
+    let _lock = data.nginx_lock.lock().await;
+
+    let response = if request.name() != name.as_str() {
+        HttpResponse::BadRequest().json(NginxResponse {
+            success: false,
+            message: "URL-dəki ad və body-dəki ad uyğun gəlmir".to_string(),
+        })
+    } else if data.acme.store().info(&name).is_none() {
+        HttpResponse::NotFound().json(NginxResponse {
+            success: false,
+            message: format!("'{}' sertifikatı tapılmadı", name.as_str()),
+        })
+    } else {
+        let in_use = !certificate_users(&data.nginx_config().sites_available_path, &name).is_empty();
+        match store_certificate(&data, request, in_use).await {
+            Ok(info) => HttpResponse::Ok().json(info),
+            Err(e) => error_response(e),
+        }
+    };
+
+    data.audit.record(&identity, "nginx", "certificate.update", &name, payload, response.status());
+    response
+}
+
+/// Delete a stored certificate no site uses
+#[actix_web::delete("/api/nginx/certificates/{name}")]
+pub async fn delete_nginx_certificate(data: web::Data<AppState>, name: web::Path<String>, identity: Identity) -> impl Responder {
+    info!("DELETE /api/nginx/certificates/{} - Deleting certificate", name);
+    let _lock = data.nginx_lock.lock().await;
+
+    let users = certificate_users(&data.nginx_config().sites_available_path, &name);
+    let response = if name.is_empty() || name.starts_with('.') || name.contains('/') {
+        HttpResponse::BadRequest().json(NginxResponse {
+            success: false,
+            message: format!("Yanlış sertifikat adı: '{}'", name.as_str()),
+        })
+    } else if !users.is_empty() {
+        HttpResponse::Conflict().json(NginxResponse {
+            success: false,
+            message: format!("'{}' sertifikatı istifadə olunur: {}", name.as_str(), users.join(", ")),
+        })
+    } else {
+        match data.acme.store().remove(&name) {
+            Ok(()) => HttpResponse::Ok().json(NginxResponse {
+                success: true,
+                message: format!("✅ {} sertifikatı silindi", name.as_str()),
+            }),
+            Err(e) => error_response(e),
+        }
+    };
+
+    data.audit.record(&identity, "nginx", "certificate.delete", &name, serde_json::json!({"name": name.as_str()}), response.status());
+    response
+}
+
+/// Validate or generate the PEM pair of `request` and store it, on the blocking thread pool
+///
+/// A certificate sites already use is committed like any other nginx change:
+/// tested on a copy of the tree, moved into place and reloaded, and put back
+/// if the reload fails.
+async fn store_certificate(data: &web::Data<AppState>, request: CertificateRequest, in_use: bool) -> Result<CertificateInfo, AppError> {
+    let data = data.clone();
+    web::block(move || {
+        request.validate().map_err(AppError::ValidationError)?;
+        let (source, certificate, private_key) = match &request {
+            CertificateRequest::Uploaded { certificate, private_key, .. } => {
+                validate_pem_pair(certificate, private_key)?;
+                (CertificateSource::Uploaded, certificate.clone(), private_key.clone())
+            }
+            CertificateRequest::SelfSigned { domains, days, .. } => {
+                let (certificate, private_key) = self_signed(domains, i64::from(*days))?;
+                (CertificateSource::SelfSigned, certificate, private_key)
+            }
+        };
+
+        let store = data.acme.store();
+        let mut changes = Changeset::new();
+        store.stage(&mut changes, request.name(), source, &certificate, &private_key);
+        if in_use {
+            commit(&data.nginx_config(), &*data.nginx_controller(), changes)?;
+        } else {
+            changes.apply_or_undo()?;
+        }
+        store
+            .info(request.name())
+            .ok_or_else(|| AppError::NginxError(format!("Stored certificate '{}' cannot be read", request.name())))
+    })
+    .await
+    .map_err(|e| AppError::NginxError(e.to_string()))?
+}
+
+/// Audit payload without the private key
+fn certificate_payload(request: &CertificateRequest) -> serde_json::Value {
+    match request {
+        CertificateRequest::Uploaded { name, .. } => serde_json::json!({"name": name, "source": "uploaded"}),
+        CertificateRequest::SelfSigned { name, domains, days } => {
+            serde_json::json!({"name": name, "source": "self_signed", "domains": domains, "days": days})
+        }
+    }
+}
+
+/// An [`AppError`] as a [`NginxResponse`] with the matching status
+fn error_response(error: AppError) -> HttpResponse {
+    use actix_web::ResponseError;
+
+    if matches!(error, AppError::NginxError(_)) {
+        error!("Nginx operation failed: {}", error);
     }
+    HttpResponse::build(error.status_code()).json(NginxResponse {
+        success: false,
+        message: error.to_string(),
+    })
+}
+
+/// Sites whose `ssl_certificate` is certificate `name`
+fn certificate_users(sites_available: &str, name: &str) -> Vec<String> {
+    let Ok(entries) = fs::read_dir(sites_available) else {
+        return Vec::new();
+    };
+
+    let (chain_path, _) = certificate_paths(name);
+    let mut users: Vec<String> = entries
+        .flatten()
+        .filter(|entry| {
+            fs::read_to_string(entry.path())
+                .ok()
+                .and_then(|content| parse(&content).ok())
+                .is_some_and(|config| {
+                    config
+                        .walk()
+                        .into_iter()
+                        .filter(|d| d.name == "ssl_certificate")
+                        .any(|d| d.first_arg() == Some(chain_path.as_str()))
+                })
+        })
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    users.sort();
+    users
 }
@@ -0,0 +1,128 @@
+// Basic auth users of a site, one htpasswd file per site
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::AppError;
+use crate::nginx::models::BasicAuth;
+
+/// Where the htpasswd files of sites with basic auth live
+pub const HTPASSWD_DIR: &str = "/etc/ubuntu-resource-api/htpasswd";
+
+/// The `auth_basic_user_file` nginx is configured with for site `name`
+pub fn htpasswd_path(name: &str) -> String {
+    format!("{}/{}", HTPASSWD_DIR, name)
+}
+
+/// htpasswd files named after their site, holding `user:bcrypt-hash` lines
+pub struct HtpasswdStore {
+    dir: PathBuf,
+    cost: u32,
+}
+
+impl Default for HtpasswdStore {
+    fn default() -> Self {
+        Self::new(HTPASSWD_DIR, bcrypt::DEFAULT_COST)
+    }
+}
+
+impl HtpasswdStore {
+    /// `cost` is the bcrypt work factor of new hashes
+    pub fn new(dir: impl Into<PathBuf>, cost: u32) -> Self {
+        Self { dir: dir.into(), cost }
+    }
+
+    pub fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// The directory the files are written to
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Usernames in the file of site `name`, in file order
+    pub fn users(&self, name: &str) -> Vec<String> {
+        read_entries(&self.path(name))
+            .into_iter()
+            .map(|(username, _)| username)
+            .collect()
+    }
+
+    /// The file content for `auth` on site `name`
+    ///
+    /// Users with a password get a fresh bcrypt hash; users without one keep
+    /// the hash they already have. Hashing is slow on purpose, so this must
+    /// not run on an async worker.
+    pub fn render(&self, name: &str, auth: &BasicAuth) -> Result<String, AppError> {
+        let current = read_entries(&self.path(name));
+        let mut content = String::new();
+        for user in &auth.users {
+            let hash = match &user.password {
+                Some(password) => bcrypt::hash(password, self.cost)
+                    .map_err(|e| AppError::NginxError(format!("Cannot hash password of '{}': {}", user.username, e)))?,
+                None => current
+                    .iter()
+                    .find(|(username, _)| *username == user.username)
+                    .map(|(_, hash)| hash.clone())
+                    .ok_or_else(|| {
+                        AppError::ValidationError(format!("basic_auth: '{}' is a new user and needs a password", user.username))
+                    })?,
+            };
+            content.push_str(&format!("{}:{}\n", user.username, hash));
+        }
+        Ok(content)
+    }
+}
+
+/// `(username, hash)` pairs; a missing file has none
+fn read_entries(path: &Path) -> Vec<(String, String)> {
+    fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .map(|(username, hash)| (username.to_string(), hash.to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nginx::models::BasicAuthUser;
+
+    #[test]
+    fn test_render_hashes_new_passwords_and_keeps_old_ones() {
+        let dir = std::env::temp_dir().join(format!("nginx-htpasswd-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = HtpasswdStore::new(&dir, 4);
+        let user = |username: &str, password: Option<&str>| BasicAuthUser {
+            username: username.to_string(),
+            password: password.map(str::to_string),
+        };
+
+        let auth = BasicAuth {
+            realm: "Admin".to_string(),
+            users: vec![user("alice", Some("wonderland")), user("bob", Some("builder"))],
+        };
+        let content = store.render("app", &auth).unwrap();
+        fs::create_dir_all(store.dir()).unwrap();
+        fs::write(store.path("app"), &content).unwrap();
+        assert_eq!(store.users("app"), vec!["alice", "bob"]);
+        let alice = content.lines().next().unwrap().strip_prefix("alice:").unwrap();
+        assert!(bcrypt::verify("wonderland", alice).unwrap());
+
+        // Alice keeps her hash, bob is dropped, carol is new
+        let auth = BasicAuth {
+            users: vec![user("alice", None), user("carol", Some("secret"))],
+            ..auth
+        };
+        let updated = store.render("app", &auth).unwrap();
+        assert!(updated.starts_with(&format!("alice:{}\ncarol:$2", alice)), "{}", updated);
+
+        let auth = BasicAuth { users: vec![user("dave", None)], ..auth };
+        assert!(matches!(store.render("app", &auth), Err(AppError::ValidationError(_))));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
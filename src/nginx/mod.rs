@@ -1,15 +1,31 @@
 //! Nginx proxy management module
 //!
 //! This module provides functionality for managing nginx reverse proxy configurations:
-//! - Create, read, update, and delete nginx proxy configurations
+//! - Create, read, update, and delete nginx proxy configurations and upstream pools
+//! - Serve static sites, domain redirects and path-based routes besides plain proxies
+//! - Restrict sites with basic auth, IP allow/deny lists and request limits
 //! - Generate nginx configuration files
-//! - Validate nginx configuration syntax
+//! - Parse nginx configuration into a syntax tree and validate it
 //! - Format nginx configuration for readability
+//! - Issue and renew TLS certificates through ACME
+//! - Keep numbered revisions of site configs for diffing and rollback
+//! - Preview changes with a diff and `nginx -t` on a copy of the config tree
+//! - Test and reload nginx through a configurable controller
+//! - Apply changes with atomic renames, one at a time, and undo them if `nginx -t` fails
 //!
 //! # Submodules
 //!
-//! - [`models`]: Data structures for nginx proxy configurations
-//! - [`config`]: Nginx configuration generation and validation utilities
+//! - [`models`]: Data structures for nginx proxies and upstream pools
+//! - [`config`]: Nginx configuration parser, syntax tree, generation and validation
+//! - [`acme`]: ACME client, certificate store and renewal
+//! - [`controller`]: Testing and reloading nginx via systemd, signals or a fake
+//! - [`validation`]: Field-level checks of proxy names, server names and backends
+//! - [`staging`]: Atomic, undoable writes to the live nginx tree
+//! - [`htpasswd`]: bcrypt htpasswd files for basic auth on sites
+//! - [`service`]: Transactional create, update and delete of proxies
+//! - [`preview`]: Dry-run tests on a temporary copy of the config tree
+//! - [`revisions`]: Numbered history of applied site configs
+//! - [`stats`]: Counters for proxy operations, exported as metrics
 //! - [`handlers`]: HTTP request handlers for nginx management endpoints
 //! - [`routes`]: Route registration for nginx management API
 //!
@@ -33,5 +49,14 @@
 
 pub mod models;
 pub mod config;
+pub mod acme;
+pub mod controller;
+pub mod validation;
+pub mod staging;
+pub mod htpasswd;
+pub mod service;
+pub mod preview;
+pub mod revisions;
+pub mod stats;
 pub mod handlers;
 pub mod routes;
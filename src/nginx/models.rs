@@ -1,13 +1,382 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-/// Nginx proxy configuration
+use crate::error::AppError;
+
+/// Nginx site configuration: a reverse proxy, static files, a redirect or path-based routes
 #[derive(Serialize, Deserialize, Clone)]
 pub struct NginxProxy {
     pub name: String,
+    /// One or more space-separated server names
     pub domain: String,
+    /// Where `location /` proxies to; only used by [`SiteKind::Proxy`]
+    #[serde(default)]
     pub backend: String,
+    /// What the site serves; a proxy to `backend` when omitted
+    #[serde(default)]
+    pub kind: SiteKind,
     pub ssl: bool,
     pub extra_config: Option<String>,
+    /// Obtain and renew the certificate through ACME; requires `ssl`
+    #[serde(default)]
+    pub acme: bool,
+    /// Name of an uploaded or self-signed certificate from `/api/nginx/certificates`;
+    /// used with `ssl` instead of `acme`
+    #[serde(default)]
+    pub certificate: Option<String>,
+    /// Add a port-80 server that redirects to HTTPS (and still answers ACME challenges)
+    #[serde(default)]
+    pub redirect_http: bool,
+    /// Send `Strict-Transport-Security`
+    #[serde(default)]
+    pub hsts: bool,
+    /// Staple OCSP responses; requires `acme` or `certificate`
+    #[serde(default)]
+    pub ocsp_stapling: bool,
+    /// Require HTTP basic auth against an htpasswd file the service manages
+    #[serde(default)]
+    pub basic_auth: Option<BasicAuth>,
+    /// Addresses or CIDR ranges let in; everything else is denied when any is given
+    #[serde(default)]
+    pub allow: Vec<String>,
+    /// Addresses or CIDR ranges refused, checked before `allow`
+    #[serde(default)]
+    pub deny: Vec<String>,
+    /// `limit_req` per client address
+    #[serde(default)]
+    pub rate_limit: Option<RateLimit>,
+    /// `limit_conn`: concurrent connections per client address
+    #[serde(default)]
+    pub connection_limit: Option<u32>,
+    /// The site file holds configuration this model cannot represent; it is
+    /// listed read-only and updates are refused
+    #[serde(default)]
+    pub unmanaged: bool,
+}
+
+impl NginxProxy {
+    /// The first server name, used for certificate paths
+    pub fn primary_domain(&self) -> &str {
+        self.domain.split_whitespace().next().unwrap_or(&self.domain)
+    }
+
+    /// All server names
+    pub fn domains(&self) -> Vec<String> {
+        self.domain.split_whitespace().map(str::to_string).collect()
+    }
+
+    /// Check every field before the proxy becomes a file name or config text
+    ///
+    /// See [`crate::nginx::validation`] for the rules.
+    pub fn validate(&self) -> Result<(), AppError> {
+        crate::nginx::validation::validate_proxy(self)
+    }
+}
+
+/// What a site does with its requests, tagged by `type`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Default)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SiteKind {
+    /// Proxy everything to `backend`
+    #[default]
+    Proxy,
+    /// Serve files from a directory
+    Static {
+        /// Absolute directory path
+        root: String,
+        #[serde(default = "default_index")]
+        index: String,
+        /// Arguments of `try_files` in `location /`, e.g. `$uri /index.html` for a single-page app
+        #[serde(default = "default_try_files")]
+        try_files: String,
+        /// Nginx time value for `expires` on assets (CSS, JS, images, fonts); no caching headers when unset
+        #[serde(default)]
+        expires: Option<String>,
+    },
+    /// Send every request to another URL
+    Redirect {
+        /// `http://` or `https://` URL without a query string
+        target: String,
+        /// 301, 302, 307 or 308
+        #[serde(default = "default_redirect_code")]
+        code: u16,
+        /// Append the request URI to `target`
+        #[serde(default = "default_true")]
+        preserve_path: bool,
+    },
+    /// Proxy each path prefix to its own backend
+    Locations { locations: Vec<LocationRoute> },
+}
+
+/// One prefix `location` of a [`SiteKind::Locations`] site
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct LocationRoute {
+    /// Prefix starting with `/`
+    pub path: String,
+    pub backend: String,
+}
+
+fn default_index() -> String {
+    "index.html".to_string()
+}
+
+fn default_try_files() -> String {
+    "$uri $uri/ =404".to_string()
+}
+
+fn default_redirect_code() -> u16 {
+    301
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// HTTP basic auth settings of a site
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BasicAuth {
+    /// Shown in the browser's login prompt
+    #[serde(default = "default_realm")]
+    pub realm: String,
+    /// The complete list of users; anyone left out is removed from the htpasswd file
+    #[serde(default)]
+    pub users: Vec<BasicAuthUser>,
+}
+
+/// One basic auth user
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BasicAuthUser {
+    pub username: String,
+    /// New password, stored as a bcrypt hash; omit it to keep the current one.
+    /// Never serialized, so it stays out of responses and the audit log.
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+}
+
+fn default_realm() -> String {
+    "Restricted".to_string()
+}
+
+/// A `limit_req` zone keyed by client address
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct RateLimit {
+    /// Nginx rate such as `10r/s` or `120r/m`
+    pub rate: String,
+    /// Requests allowed above `rate` before clients get 429
+    #[serde(default)]
+    pub burst: u32,
+    /// Serve the burst at once instead of spacing it out at `rate`
+    #[serde(default)]
+    pub nodelay: bool,
+}
+
+/// How an upstream pool spreads requests over its servers
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum BalanceMethod {
+    #[default]
+    RoundRobin,
+    LeastConn,
+    IpHash,
+}
+
+impl BalanceMethod {
+    /// The nginx directive selecting this method, `None` for the default round-robin
+    pub fn directive(&self) -> Option<&'static str> {
+        match self {
+            BalanceMethod::RoundRobin => None,
+            BalanceMethod::LeastConn => Some("least_conn"),
+            BalanceMethod::IpHash => Some("ip_hash"),
+        }
+    }
+}
+
+/// One `server` entry of an upstream pool
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct UpstreamServer {
+    /// `host:port` or `unix:/path`
+    pub address: String,
+    #[serde(default)]
+    pub weight: Option<u32>,
+    #[serde(default)]
+    pub max_fails: Option<u32>,
+    /// Nginx time value, e.g. `30s`
+    #[serde(default)]
+    pub fail_timeout: Option<String>,
+    /// Only receives requests when all primary servers are unavailable
+    #[serde(default)]
+    pub backup: bool,
+}
+
+/// A named `upstream` block that proxies reference as `http://<name>`
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NginxUpstream {
+    pub name: String,
+    #[serde(default)]
+    pub method: BalanceMethod,
+    pub servers: Vec<UpstreamServer>,
+}
+
+impl NginxUpstream {
+    /// Check the pool before it is written, returning a user-facing message
+    pub fn validate(&self) -> Result<(), String> {
+        if self.name.is_empty()
+            || !self.name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err("Upstream adı yalnız hərf, rəqəm, '-' və '_' ola bilər".to_string());
+        }
+        if self.servers.is_empty() {
+            return Err("Upstream ən azı bir server tələb edir".to_string());
+        }
+        for server in &self.servers {
+            if server.address.is_empty()
+                || server.address.chars().any(|c| c.is_whitespace() || matches!(c, ';' | '{' | '}' | '#'))
+            {
+                return Err(format!("Yanlış server ünvanı: '{}'", server.address));
+            }
+            if server.weight == Some(0) {
+                return Err(format!("{}: weight 1 və ya daha böyük olmalıdır", server.address));
+            }
+            if let Some(timeout) = &server.fail_timeout {
+                if !is_time_value(timeout) {
+                    return Err(format!("{}: yanlış fail_timeout '{}'", server.address, timeout));
+                }
+            }
+            if server.backup && self.method == BalanceMethod::IpHash {
+                return Err("backup serverlər ip_hash ilə istifadə oluna bilməz".to_string());
+            }
+        }
+        if self.servers.iter().all(|s| s.backup) {
+            return Err("Ən azı bir server backup olmamalıdır".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Nginx time value such as `10`, `30s` or `1m`
+pub(crate) fn is_time_value(value: &str) -> bool {
+    let digits = value.trim_end_matches(|c: char| c.is_ascii_alphabetic());
+    let unit = &value[digits.len()..];
+    !digits.is_empty()
+        && digits.chars().all(|c| c.is_ascii_digit())
+        && matches!(unit, "" | "ms" | "s" | "m" | "h" | "d")
+}
+
+/// Body of `POST /api/nginx/certificates` and `PUT /api/nginx/certificates/{name}`
+#[derive(Deserialize)]
+#[serde(tag = "source", rename_all = "snake_case")]
+pub enum CertificateRequest {
+    /// A PEM certificate chain (leaf first) and its private key
+    Uploaded {
+        name: String,
+        certificate: String,
+        private_key: String,
+    },
+    /// Generate a self-signed certificate for `domains`
+    SelfSigned {
+        name: String,
+        domains: Vec<String>,
+        #[serde(default = "default_self_signed_days")]
+        days: u32,
+    },
+}
+
+fn default_self_signed_days() -> u32 {
+    365
+}
+
+impl CertificateRequest {
+    pub fn name(&self) -> &str {
+        match self {
+            CertificateRequest::Uploaded { name, .. } | CertificateRequest::SelfSigned { name, .. } => name,
+        }
+    }
+
+    /// Check the request before anything is generated or stored
+    pub fn validate(&self) -> Result<(), String> {
+        let name = self.name();
+        if name.is_empty()
+            || name.starts_with('.')
+            || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        {
+            return Err("Sertifikat adı yalnız hərf, rəqəm, '-', '_' və '.' ola bilər".to_string());
+        }
+        if let CertificateRequest::SelfSigned { domains, days, .. } = self {
+            if domains.is_empty() {
+                return Err("Ən azı bir domen tələb olunur".to_string());
+            }
+            if let Some(domain) = domains.iter().find(|d| {
+                d.is_empty() || !d.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '*'))
+            }) {
+                return Err(format!("Yanlış domen: '{}'", domain));
+            }
+            if !(1..=3650).contains(days) {
+                return Err("Müddət 1 ilə 3650 gün arasında olmalıdır".to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// One applied version of a site config
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct NginxRevision {
+    /// Increasing per site, starting at 1
+    pub number: u32,
+    pub timestamp: DateTime<Utc>,
+    /// API token name or username
+    pub author: String,
+    /// `create`, `update`, `delete` or `rollback`
+    pub action: String,
+    /// Revision restored by a rollback
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rollback_of: Option<u32>,
+    /// Unified diff against the previous revision
+    pub diff: String,
+}
+
+/// Query parameters for comparing two revisions
+#[derive(Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: u32,
+    pub to: u32,
+}
+
+/// Query parameters accepted by proxy create and update
+#[derive(Deserialize, Default)]
+pub struct ProxyWriteQuery {
+    /// Render and test the change without writing anything
+    #[serde(default)]
+    pub preview: bool,
+}
+
+/// Outcome of `nginx -t`
+#[derive(Serialize, Debug, PartialEq)]
+pub struct NginxTestResult {
+    pub success: bool,
+    pub output: String,
+}
+
+/// Response of `GET /api/nginx/version`
+#[derive(Serialize)]
+pub struct NginxVersion {
+    /// `systemd`, `signal` or `fake`
+    pub controller: &'static str,
+    /// As nginx reports it, e.g. `nginx/1.24.0 (Ubuntu)`
+    pub version: String,
+}
+
+/// What a proxy create or update would do
+#[derive(Serialize)]
+pub struct ProxyPreview {
+    /// The config `generate_nginx_config` renders
+    pub config: String,
+    /// Unified diff from the current file; all additions for a new site
+    pub diff: String,
+    /// Whether the site would be linked into sites-enabled
+    pub enabled: bool,
+    /// `nginx -t` on a temporary copy of the config tree with the change applied
+    pub test: NginxTestResult,
 }
 
 /// Generic nginx operation response
@@ -0,0 +1,229 @@
+// Temporary copies of the nginx config tree for dry-run `nginx -t` checks
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config::NginxConfig;
+use crate::error::AppError;
+use crate::nginx::acme::store::self_signed;
+
+/// Distinguishes trees of concurrent previews
+static NEXT_TREE: AtomicU64 = AtomicU64::new(0);
+
+/// A copy of the directories nginx reads, with every absolute path into them
+/// rewritten to point at the copy; removed on drop
+pub struct PreviewTree {
+    root: PathBuf,
+    /// `(original, copy)` directory pairs, longest original first
+    mappings: Vec<(PathBuf, PathBuf)>,
+}
+
+impl PreviewTree {
+    /// Copy the directory of the main config, sites-available, sites-enabled
+    /// and the upstream directory below `temp_dir`
+    ///
+    /// Paths into the `extra` directories (such as the certificate of a proxy
+    /// that has none yet) are rewritten too, but start out empty; extra
+    /// directories inside a copied one are left to that copy.
+    pub fn new(nginx: &NginxConfig, temp_dir: &Path, extra: &[PathBuf]) -> Result<Self, AppError> {
+        let root = temp_dir.join(format!(
+            "nginx-preview-{}-{}",
+            std::process::id(),
+            NEXT_TREE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&root);
+        create_private_dir(&root)?;
+
+        let main_dir = Path::new(&nginx.main_config_path)
+            .parent()
+            .ok_or_else(|| AppError::ConfigError(format!("Invalid main config path '{}'", nginx.main_config_path)))?;
+        let mut copied: Vec<(PathBuf, PathBuf)> = Vec::new();
+        for dir in [
+            main_dir,
+            Path::new(&nginx.sites_available_path),
+            Path::new(&nginx.sites_enabled_path),
+            Path::new(&nginx.upstreams_path),
+        ] {
+            if !copied.iter().any(|(original, _)| dir.starts_with(original)) {
+                copied.retain(|(original, _)| !original.starts_with(dir));
+                copied.push((dir.to_path_buf(), root.join(format!("tree{}", copied.len()))));
+            }
+        }
+
+        let mut mappings = copied.clone();
+        let extra = extra.iter().filter(|dir| !copied.iter().any(|(original, _)| dir.starts_with(original)));
+        for (index, dir) in extra.enumerate() {
+            mappings.push((dir.clone(), root.join(format!("extra{}", index))));
+        }
+        mappings.sort_by_key(|(original, _)| std::cmp::Reverse(original.as_os_str().len()));
+        let tree = PreviewTree { root, mappings };
+
+        for (original, copy) in &copied {
+            tree.copy_dir(original, copy)?;
+        }
+        Ok(tree)
+    }
+
+    /// Where `path` lives inside the copy
+    pub fn path(&self, path: &Path) -> PathBuf {
+        for (original, copy) in &self.mappings {
+            if let Ok(rest) = path.strip_prefix(original) {
+                return if rest.as_os_str().is_empty() { copy.clone() } else { copy.join(rest) };
+            }
+        }
+        path.to_path_buf()
+    }
+
+    /// Write `content` to the copy of `path`, rewriting the paths inside it
+    pub fn write(&self, path: &Path, content: &str) -> Result<(), AppError> {
+        let target = self.path(path);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::NginxError(format!("Cannot create {}: {}", parent.display(), e)))?;
+        }
+        fs::write(&target, self.rewrite(content))
+            .map_err(|e| AppError::NginxError(format!("Cannot write {}: {}", target.display(), e)))
+    }
+
+    /// Make the copy of `link` a copy of the copy of `target`
+    pub fn link(&self, target: &Path, link: &Path) -> Result<(), AppError> {
+        let (from, to) = (self.path(target), self.path(link));
+        if from == to {
+            return Ok(());
+        }
+        if let Some(parent) = to.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| AppError::NginxError(format!("Cannot create {}: {}", parent.display(), e)))?;
+        }
+        fs::copy(&from, &to)
+            .map(|_| ())
+            .map_err(|e| AppError::NginxError(format!("Cannot copy {}: {}", from.display(), e)))
+    }
+
+    /// Remove the copy of `path` if there is one
+    pub fn remove(&self, path: &Path) {
+        let _ = fs::remove_file(self.path(path));
+    }
+
+    fn rewrite(&self, content: &str) -> String {
+        self.mappings.iter().fold(content.to_string(), |text, (original, copy)| {
+            text.replace(&*original.to_string_lossy(), &copy.to_string_lossy())
+        })
+    }
+
+    /// Copy `from` into `to`; symlinks are copied as the files they point to
+    fn copy_dir(&self, from: &Path, to: &Path) -> Result<(), AppError> {
+        let copy_error = |path: &Path, e: std::io::Error| AppError::NginxError(format!("Cannot copy {}: {}", path.display(), e));
+        fs::create_dir_all(to).map_err(|e| copy_error(to, e))?;
+        let Ok(entries) = fs::read_dir(from) else {
+            // A missing directory is copied as an empty one; nginx -t reports what it lacks
+            return Ok(());
+        };
+        for entry in entries.flatten() {
+            let source = entry.path();
+            let target = to.join(entry.file_name());
+            let is_link = entry.file_type().is_ok_and(|t| t.is_symlink());
+            if source.is_dir() {
+                if !is_link {
+                    self.copy_dir(&source, &target)?;
+                }
+            } else if let Ok(bytes) = fs::read(&source) {
+                let bytes = match String::from_utf8(bytes) {
+                    Ok(text) => self.rewrite(&text).into_bytes(),
+                    Err(e) => e.into_bytes(),
+                };
+                fs::write(&target, bytes).map_err(|e| copy_error(&source, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for PreviewTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+/// Write a self-signed certificate for `domains` into `dir` inside a preview tree
+pub fn write_placeholder(dir: &Path, domains: &[String]) -> Result<(), AppError> {
+    let (certificate, key) = self_signed(domains, 1)?;
+    create_private_dir(dir)?;
+    for (name, pem) in [("fullchain.pem", certificate), ("privkey.pem", key)] {
+        fs::write(dir.join(name), pem)
+            .map_err(|e| AppError::NginxError(format!("Cannot write {}: {}", dir.join(name).display(), e)))?;
+    }
+    Ok(())
+}
+
+/// The copy may hold private keys, so only the owner can read it
+fn create_private_dir(dir: &Path) -> Result<(), AppError> {
+    fs::create_dir_all(dir).map_err(|e| AppError::NginxError(format!("Cannot create {}: {}", dir.display(), e)))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(dir, fs::Permissions::from_mode(0o700))
+            .map_err(|e| AppError::NginxError(format!("Cannot protect {}: {}", dir.display(), e)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tree_copy_rewrites_paths() {
+        let base = std::env::temp_dir().join(format!("nginx-preview-test-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&base);
+        let etc = base.join("etc/nginx");
+        fs::create_dir_all(etc.join("sites-available")).unwrap();
+        fs::create_dir_all(etc.join("sites-enabled")).unwrap();
+        fs::write(
+            etc.join("nginx.conf"),
+            format!("http {{\n    include mime.types;\n    include {}/sites-enabled/*;\n}}\n", etc.display()),
+        )
+        .unwrap();
+        fs::write(etc.join("sites-available/app"), "server { listen 80; }\n").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(etc.join("sites-available/app"), etc.join("sites-enabled/app")).unwrap();
+
+        let nginx = NginxConfig {
+            sites_available_path: etc.join("sites-available").display().to_string(),
+            sites_enabled_path: etc.join("sites-enabled").display().to_string(),
+            upstreams_path: base.join("upstreams").display().to_string(),
+            revisions_path: base.join("revisions").display().to_string(),
+            main_config_path: etc.join("nginx.conf").display().to_string(),
+            controller: crate::config::NginxControllerKind::Fake,
+        };
+        let certs = PathBuf::from("/etc/ubuntu-resource-api/certs/app.example.com");
+        let previews = base.join("previews");
+        let tree = PreviewTree::new(&nginx, &previews, std::slice::from_ref(&certs)).unwrap();
+
+        // The include now points into the copy
+        let main = fs::read_to_string(tree.path(&etc.join("nginx.conf"))).unwrap();
+        let enabled_copy = tree.path(&etc.join("sites-enabled"));
+        assert!(main.contains(&format!("include {}/*;", enabled_copy.display())), "{}", main);
+        assert!(enabled_copy.starts_with(&previews));
+
+        // Symlinked sites are copied as files
+        let site = tree.path(&etc.join("sites-enabled/app"));
+        assert_eq!(fs::read_to_string(&site).unwrap(), "server { listen 80; }\n");
+        assert!(!fs::symlink_metadata(&site).unwrap().file_type().is_symlink());
+
+        tree.write(
+            &etc.join("sites-available/app"),
+            &format!("ssl_certificate {}/fullchain.pem;\n", certs.display()),
+        )
+        .unwrap();
+        let written = fs::read_to_string(tree.path(&etc.join("sites-available/app"))).unwrap();
+        assert_eq!(written, format!("ssl_certificate {}/fullchain.pem;\n", tree.path(&certs).display()));
+        assert!(tree.path(&certs).starts_with(&previews));
+        assert_eq!(fs::read_to_string(etc.join("sites-available/app")).unwrap(), "server { listen 80; }\n");
+
+        drop(tree);
+        assert_eq!(fs::read_dir(&previews).unwrap().count(), 0);
+        let _ = fs::remove_dir_all(base);
+    }
+}
@@ -0,0 +1,175 @@
+// Numbered history of site configs, one directory per site
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::PathBuf;
+
+use similar::TextDiff;
+
+use crate::error::AppError;
+use crate::nginx::models::NginxRevision;
+
+/// `<dir>/<site>/<number>.conf` holds the config as applied and
+/// `<dir>/<site>/<number>.json` its [`NginxRevision`] metadata
+pub struct RevisionStore {
+    dir: PathBuf,
+}
+
+impl RevisionStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        RevisionStore { dir: dir.into() }
+    }
+
+    /// Store `content` as the next revision of `site`
+    ///
+    /// An empty `content` records that the site was deleted. The diff is taken
+    /// against the previous revision.
+    pub fn record(
+        &self,
+        site: &str,
+        content: &str,
+        author: &str,
+        action: &str,
+        rollback_of: Option<u32>,
+    ) -> Result<NginxRevision, AppError> {
+        let dir = self.site_dir(site)?;
+        fs::create_dir_all(&dir)
+            .map_err(|e| AppError::NginxError(format!("Cannot create {}: {}", dir.display(), e)))?;
+
+        let previous = self.list(site).into_iter().next();
+        let (previous_content, mut number) = match &previous {
+            Some(previous) => (self.content(site, previous.number)?, previous.number + 1),
+            None => (String::new(), 1),
+        };
+
+        // `create_new` claims the number, so concurrent writers never share one
+        let mut file = loop {
+            let path = dir.join(format!("{}.conf", number));
+            match OpenOptions::new().write(true).create_new(true).open(&path) {
+                Ok(file) => break file,
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => number += 1,
+                Err(e) => return Err(AppError::NginxError(format!("Cannot write {}: {}", path.display(), e))),
+            }
+        };
+        file.write_all(content.as_bytes())
+            .map_err(|e| AppError::NginxError(format!("Cannot write revision {} of {}: {}", number, site, e)))?;
+
+        let revision = NginxRevision {
+            number,
+            timestamp: chrono::Utc::now(),
+            author: author.to_string(),
+            action: action.to_string(),
+            rollback_of,
+            diff: unified_diff(
+                &previous_content,
+                content,
+                &previous.map_or_else(|| "/dev/null".to_string(), |p| format!("{}@{}", site, p.number)),
+                &format!("{}@{}", site, number),
+            ),
+        };
+        let metadata_path = dir.join(format!("{}.json", number));
+        let metadata = serde_json::to_string_pretty(&revision)
+            .map_err(|e| AppError::NginxError(format!("Cannot encode revision: {}", e)))?;
+        fs::write(&metadata_path, metadata)
+            .map_err(|e| AppError::NginxError(format!("Cannot write {}: {}", metadata_path.display(), e)))?;
+        Ok(revision)
+    }
+
+    /// Revisions of `site`, newest first
+    pub fn list(&self, site: &str) -> Vec<NginxRevision> {
+        let Ok(dir) = self.site_dir(site) else {
+            return Vec::new();
+        };
+        let Ok(entries) = fs::read_dir(&dir) else {
+            return Vec::new();
+        };
+        let mut revisions: Vec<NginxRevision> = entries
+            .flatten()
+            .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|entry| serde_json::from_str(&fs::read_to_string(entry.path()).ok()?).ok())
+            .collect();
+        revisions.sort_by_key(|revision| std::cmp::Reverse(revision.number));
+        revisions
+    }
+
+    /// Revision `number` of `site`
+    pub fn get(&self, site: &str, number: u32) -> Result<NginxRevision, AppError> {
+        let path = self.site_dir(site)?.join(format!("{}.json", number));
+        let metadata = fs::read_to_string(&path).map_err(|_| revision_not_found(site, number))?;
+        serde_json::from_str(&metadata)
+            .map_err(|e| AppError::NginxError(format!("Cannot read {}: {}", path.display(), e)))
+    }
+
+    /// The config text of revision `number`; empty for a deletion
+    pub fn content(&self, site: &str, number: u32) -> Result<String, AppError> {
+        let path = self.site_dir(site)?.join(format!("{}.conf", number));
+        fs::read_to_string(&path).map_err(|_| revision_not_found(site, number))
+    }
+
+    /// Unified diff from revision `from` to revision `to`
+    pub fn diff(&self, site: &str, from: u32, to: u32) -> Result<String, AppError> {
+        let old = self.content(site, from)?;
+        let new = self.content(site, to)?;
+        Ok(unified_diff(&old, &new, &format!("{}@{}", site, from), &format!("{}@{}", site, to)))
+    }
+
+    fn site_dir(&self, site: &str) -> Result<PathBuf, AppError> {
+        if site.is_empty() || site.starts_with('.') || site.contains('/') {
+            return Err(AppError::ValidationError(format!("Invalid site name '{}'", site)));
+        }
+        Ok(self.dir.join(site))
+    }
+}
+
+fn revision_not_found(site: &str, number: u32) -> AppError {
+    AppError::NotFound(format!("Revision {} of '{}' not found", number, site))
+}
+
+/// Line-based unified diff with three lines of context
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_list_and_diff() {
+        let dir = std::env::temp_dir().join(format!("nginx-revisions-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let store = RevisionStore::new(&dir);
+
+        let first = store.record("app", "listen 80;\nserver_name a;\n", "admin", "create", None).unwrap();
+        assert_eq!(first.number, 1);
+        assert!(first.diff.contains("--- /dev/null"));
+        assert!(first.diff.contains("+server_name a;"));
+
+        let second = store.record("app", "listen 80;\nserver_name b;\n", "deploy-bot", "update", None).unwrap();
+        assert_eq!(second.number, 2);
+        assert!(second.diff.contains("-server_name a;\n+server_name b;"));
+
+        let third = store.record("app", "", "admin", "delete", None).unwrap();
+        assert_eq!(third.number, 3);
+        assert_eq!(store.content("app", 3).unwrap(), "");
+
+        let numbers: Vec<u32> = store.list("app").iter().map(|r| r.number).collect();
+        assert_eq!(numbers, vec![3, 2, 1]);
+        assert_eq!(store.get("app", 2).unwrap(), second);
+        assert_eq!(store.get("app", 2).unwrap().author, "deploy-bot");
+
+        let diff = store.diff("app", 1, 2).unwrap();
+        assert!(diff.starts_with("--- app@1\n+++ app@2\n"));
+        assert_eq!(diff, second.diff);
+
+        assert!(matches!(store.get("app", 9), Err(AppError::NotFound(_))));
+        assert!(matches!(store.get("../etc", 1), Err(AppError::ValidationError(_))));
+        assert!(store.list("other").is_empty());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -9,16 +9,41 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         .service(handlers::nginx_admin)
         // API endpoints
         .service(handlers::format_nginx_extra_config)
+        .service(handlers::get_nginx_version)
         .service(handlers::get_nginx_proxies)
         .service(handlers::create_nginx_proxy)
         .service(handlers::delete_nginx_proxy)
-        .service(handlers::update_nginx_proxy);
+        .service(handlers::update_nginx_proxy)
+        .service(handlers::enable_nginx_proxy)
+        .service(handlers::disable_nginx_proxy)
+        .service(handlers::get_nginx_revisions)
+        // Registered before `/revisions/{number}`, which would otherwise match `diff`
+        .service(handlers::diff_nginx_revisions)
+        .service(handlers::get_nginx_revision)
+        .service(handlers::rollback_nginx_proxy)
+        .service(handlers::issue_nginx_certificate)
+        .service(handlers::get_nginx_certificates)
+        .service(handlers::create_nginx_certificate)
+        .service(handlers::update_nginx_certificate)
+        .service(handlers::delete_nginx_certificate)
+        .service(handlers::get_nginx_upstreams)
+        .service(handlers::get_nginx_upstream)
+        .service(handlers::create_nginx_upstream)
+        .service(handlers::update_nginx_upstream)
+        .service(handlers::delete_nginx_upstream);
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use actix_web::{test, App};
+    use std::sync::{Arc, RwLock};
+
+    use crate::nginx::acme::AcmeClient;
+    use crate::nginx::controller::FakeController;
+    use crate::nginx::htpasswd::HtpasswdStore;
+    use crate::config::{AppConfig, NginxConfig, NginxControllerKind};
+    use crate::system::models::AppState;
 
     #[actix_web::test]
     async fn test_configure_routes_compiles() {
@@ -31,7 +56,6 @@ mod tests {
         
         // If we get here, the routes were configured successfully
         drop(app);
-        assert!(true);
     }
 
     #[actix_web::test]
@@ -52,6 +76,563 @@ mod tests {
         assert!(resp.status().is_success());
     }
 
+    fn app_state(sites_available_path: &str) -> web::Data<AppState> {
+        let config = AppConfig::with_defaults();
+        web::Data::new(AppState {
+            nginx_config: RwLock::new(NginxConfig {
+                sites_available_path: sites_available_path.to_string(),
+                sites_enabled_path: sites_available_path.to_string(),
+                upstreams_path: format!("{}/upstreams", sites_available_path),
+                revisions_path: format!("{}/revisions", sites_available_path),
+                main_config_path: format!("{}/nginx.conf", sites_available_path),
+                controller: NginxControllerKind::Fake,
+            }),
+            nginx_controller: RwLock::new(Arc::new(FakeController::new())),
+            htpasswd: HtpasswdStore::new(format!("{}/htpasswd", sites_available_path), 4),
+            acme: Arc::new(
+                AcmeClient::with_dirs(
+                    &config.acme,
+                    format!("{}/certs", sites_available_path),
+                    format!("{}/challenges", sites_available_path),
+                )
+                .unwrap(),
+            ),
+            ..AppState::for_tests()
+        })
+    }
+
+    /// Swap in a fake controller the test can inspect
+    fn fake_controller(state: &AppState) -> Arc<FakeController> {
+        let fake = Arc::new(FakeController::new());
+        *state.nginx_controller.write().unwrap() = fake.clone();
+        fake
+    }
+
+    #[actix_web::test]
+    async fn test_proxy_lifecycle() {
+        use actix_web::http::StatusCode;
+
+        let dir = std::env::temp_dir().join(format!("nginx-lifecycle-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("enabled")).unwrap();
+        let state = app_state(dir.to_str().unwrap());
+        state.nginx_config.write().unwrap().sites_enabled_path = dir.join("enabled").to_str().unwrap().to_string();
+        let fake = fake_controller(&state);
+        let store = crate::nginx::revisions::RevisionStore::new(dir.join("revisions"));
+        let app = test::init_service(App::new().app_data(state.clone()).configure(configure_routes)).await;
+
+        let req = test::TestRequest::get().uri("/api/nginx/version").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body, serde_json::json!({"controller": "fake", "version": "nginx/fake"}));
+
+        // Create writes the site, links it and reloads
+        let mut proxy = serde_json::json!({
+            "name": "app",
+            "domain": "app.example.com",
+            "backend": "http://127.0.0.1:3000",
+            "ssl": false,
+            "extra_config": null
+        });
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&proxy).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert!(std::fs::read_to_string(dir.join("app")).unwrap().contains("proxy_pass http://127.0.0.1:3000;"));
+        assert!(std::fs::symlink_metadata(dir.join("enabled/app")).unwrap().file_type().is_symlink());
+        // nginx -t ran on a staged copy before the files were moved into place
+        let calls = fake.calls();
+        assert!(calls[0].starts_with("test ") && calls[0].contains("nginx-preview-"), "{:?}", calls);
+        assert_eq!(calls[1..], ["reload"]);
+        assert_eq!(store.list("app")[0].action, "create");
+
+        // Names and backends never reach the disk unchecked
+        let mut unsafe_proxy = proxy.clone();
+        unsafe_proxy["name"] = "../escape".into();
+        unsafe_proxy["backend"] = "http://127.0.0.1:3000; include /etc/shadow".into();
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&unsafe_proxy).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        let message = body["message"].as_str().unwrap();
+        assert!(message.contains("name: ") && message.contains("; backend: "), "{}", message);
+        assert!(!dir.parent().unwrap().join("escape").exists());
+        assert_eq!(fake.calls().len(), 2);
+
+        // A rejected update puts the old file back
+        fake.fail_tests(Some("nginx: [emerg] unexpected \"}\""));
+        proxy["backend"] = "http://127.0.0.1:4000".into();
+        let req = test::TestRequest::put().uri("/api/nginx/proxies/app").set_json(&proxy).to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value = test::read_body_json(resp).await;
+        assert!(body["message"].as_str().unwrap().contains("[emerg]"));
+        assert!(std::fs::read_to_string(dir.join("app")).unwrap().contains("127.0.0.1:3000"));
+        assert!(!dir.join("app.backup").exists());
+        assert_eq!(store.list("app").len(), 1);
+
+        fake.fail_tests(None);
+        let req = test::TestRequest::put().uri("/api/nginx/proxies/app").set_json(&proxy).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert!(std::fs::read_to_string(dir.join("app")).unwrap().contains("127.0.0.1:4000"));
+        assert_eq!(store.list("app")[0].action, "update");
+
+        // A rejected create leaves nothing behind
+        fake.fail_tests(Some("nginx: [emerg] duplicate listen"));
+        let mut other = proxy.clone();
+        other["name"] = "other".into();
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&other).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+        assert!(!dir.join("other").exists());
+        assert!(std::fs::symlink_metadata(dir.join("enabled/other")).is_err());
+        fake.fail_tests(None);
+
+        let req = test::TestRequest::delete().uri("/api/nginx/proxies/app").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert!(!dir.join("app").exists());
+        assert!(std::fs::symlink_metadata(dir.join("enabled/app")).is_err());
+        assert_eq!(store.list("app")[0].action, "delete");
+        assert_eq!(fake.calls().last().map(String::as_str), Some("reload"));
+
+        // Other site kinds need no backend and are listed with their kind
+        let redirect = serde_json::json!({
+            "name": "old",
+            "domain": "old.example.com",
+            "ssl": false,
+            "extra_config": null,
+            "kind": {"type": "redirect", "target": "https://app.example.com", "code": 308}
+        });
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&redirect).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert!(std::fs::read_to_string(dir.join("old")).unwrap().contains("return 308 https://app.example.com$request_uri;"));
+        let req = test::TestRequest::get().uri("/api/nginx/proxies").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let listed = body.as_array().unwrap().iter().find(|p| p["name"] == "old").unwrap();
+        assert_eq!(listed["unmanaged"], false);
+        assert_eq!(listed["kind"], serde_json::json!({"type": "redirect", "target": "https://app.example.com", "code": 308, "preserve_path": true}));
+
+        // Basic auth users are listed by name only
+        let guarded = serde_json::json!({
+            "name": "admin",
+            "domain": "admin.example.com",
+            "backend": "http://127.0.0.1:9000",
+            "ssl": false,
+            "extra_config": null,
+            "basic_auth": {"users": [{"username": "alice", "password": "wonderland"}]},
+            "allow": ["10.0.0.0/8"]
+        });
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&guarded).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+        assert!(std::fs::read_to_string(dir.join("htpasswd/admin")).unwrap().starts_with("alice:$2"));
+        let req = test::TestRequest::get().uri("/api/nginx/proxies").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let listed = body.as_array().unwrap().iter().find(|p| p["name"] == "admin").unwrap();
+        assert_eq!(listed["basic_auth"], serde_json::json!({"realm": "Restricted", "users": [{"username": "alice"}]}));
+        assert_eq!(listed["allow"], serde_json::json!(["10.0.0.0/8"]));
+
+        // Changes wait for whoever holds the nginx lock
+        let lock = state.nginx_lock.lock().await;
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&proxy).to_request();
+        let waiting = std::time::Duration::from_millis(200);
+        assert!(tokio::time::timeout(waiting, test::call_service(&app, req)).await.is_err());
+        assert!(!dir.join("app").exists());
+        drop(lock);
+        let req = test::TestRequest::post().uri("/api/nginx/proxies").set_json(&proxy).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[actix_web::test]
+    async fn test_list_proxies_from_parsed_sites() {
+        let dir = std::env::temp_dir().join(format!("nginx-sites-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("shop"),
+            "# server_name decoy.example.com;\nserver {\n    listen 443 ssl;\n    server_name shop.example.com;\n    location / { proxy_pass http://127.0.0.1:4000; }\n}\n",
+        )
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(dir.to_str().unwrap()))
+                .configure(configure_routes)
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/nginx/proxies").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body[0]["domain"], "shop.example.com");
+        assert_eq!(body[0]["backend"], "http://127.0.0.1:4000");
+        assert_eq!(body[0]["ssl"], true);
+        assert_eq!(body[0]["unmanaged"], true);
+
+        // Saving through the proxy model would drop the hand-written parts
+        let req = test::TestRequest::put()
+            .uri("/api/nginx/proxies/shop")
+            .set_json(serde_json::json!({
+                "name": "shop",
+                "domain": "shop.example.com",
+                "backend": "http://127.0.0.1:4000",
+                "ssl": true,
+                "extra_config": null
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+        assert!(std::fs::read_to_string(dir.join("shop")).unwrap().contains("decoy"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[actix_web::test]
+    async fn test_upstream_crud_checks() {
+        let dir = std::env::temp_dir().join(format!("nginx-upstreams-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("upstreams")).unwrap();
+        std::fs::write(
+            dir.join("upstreams/upstream-app_pool.conf"),
+            "upstream app_pool {\n    ip_hash;\n    server 10.0.0.1:3000 weight=2;\n    server 10.0.0.2:3000;\n}\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("app"),
+            "server {\n    listen 80;\n    location / { proxy_pass http://app_pool; }\n}\n",
+        )
+        .unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(dir.to_str().unwrap()))
+                .configure(configure_routes)
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/nginx/upstreams").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body[0]["name"], "app_pool");
+        assert_eq!(body[0]["method"], "ip_hash");
+        assert_eq!(body[0]["servers"][0]["weight"], 2);
+
+        let req = test::TestRequest::get().uri("/api/nginx/upstreams/missing").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        let pool = serde_json::json!({
+            "name": "app_pool",
+            "servers": [{"address": "10.0.0.9:3000"}]
+        });
+        let req = test::TestRequest::post().uri("/api/nginx/upstreams").set_json(&pool).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+
+        let req = test::TestRequest::post()
+            .uri("/api/nginx/upstreams")
+            .set_json(serde_json::json!({"name": "empty", "servers": []}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        // Still referenced by the `app` site
+        let req = test::TestRequest::delete().uri("/api/nginx/upstreams/app_pool").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+        assert!(dir.join("upstreams/upstream-app_pool.conf").exists());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[actix_web::test]
+    async fn test_certificate_endpoints() {
+        let dir = std::env::temp_dir().join(format!("nginx-certificates-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = app_state(dir.to_str().unwrap());
+        let fake = fake_controller(&state);
+
+        let app = test::init_service(
+            App::new()
+                .app_data(state)
+                .configure(configure_routes)
+        ).await;
+
+        let request = serde_json::json!({
+            "source": "self_signed",
+            "name": "internal",
+            "domains": ["grafana.internal", "prometheus.internal"],
+            "days": 90
+        });
+        let req = test::TestRequest::post().uri("/api/nginx/certificates").set_json(&request).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CREATED);
+        let req = test::TestRequest::post().uri("/api/nginx/certificates").set_json(&request).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+
+        // A key that does not belong to the certificate
+        let (certificate, _) = crate::nginx::acme::store::self_signed(&["a.internal".to_string()], 30).unwrap();
+        let (_, private_key) = crate::nginx::acme::store::self_signed(&["a.internal".to_string()], 30).unwrap();
+        let req = test::TestRequest::post()
+            .uri("/api/nginx/certificates")
+            .set_json(serde_json::json!({
+                "source": "uploaded",
+                "name": "uploaded",
+                "certificate": certificate,
+                "private_key": private_key
+            }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        std::fs::write(
+            dir.join("grafana"),
+            "server {\n    listen 443 ssl;\n    ssl_certificate /etc/ubuntu-resource-api/certs/internal/fullchain.pem;\n}\n",
+        )
+        .unwrap();
+
+        let req = test::TestRequest::get().uri("/api/nginx/certificates").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["name"], "internal");
+        assert_eq!(body[0]["source"], "self_signed");
+        assert_eq!(body[0]["subject"], "CN=grafana.internal");
+        assert_eq!(body[0]["sans"], serde_json::json!(["grafana.internal", "prometheus.internal"]));
+        assert_eq!(body[0]["used_by"], serde_json::json!(["grafana"]));
+        assert!(body[0]["not_after"].as_i64().unwrap() > chrono::Utc::now().timestamp() + 89 * 86400);
+
+        // Replacing a certificate in use is tested first and left alone if nginx rejects it
+        let chain = std::fs::read_to_string(dir.join("certs/internal/fullchain.pem")).unwrap();
+        let mut replacement = request.clone();
+        replacement["domains"] = serde_json::json!(["grafana.internal"]);
+        fake.fail_tests(Some("nginx: [emerg] cannot load certificate"));
+        let req = test::TestRequest::put().uri("/api/nginx/certificates/internal").set_json(&replacement).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        assert_eq!(std::fs::read_to_string(dir.join("certs/internal/fullchain.pem")).unwrap(), chain);
+        assert!(fake.calls().iter().all(|call| call != "reload"));
+
+        fake.fail_tests(None);
+        let req = test::TestRequest::put().uri("/api/nginx/certificates/internal").set_json(&replacement).to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["sans"], serde_json::json!(["grafana.internal"]));
+        assert_eq!(fake.calls().last().map(String::as_str), Some("reload"));
+
+        let req = test::TestRequest::delete().uri("/api/nginx/certificates/internal").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+        std::fs::remove_file(dir.join("grafana")).unwrap();
+        let req = test::TestRequest::delete().uri("/api/nginx/certificates/internal").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::OK);
+
+        // Issuing checks the site name before it becomes a path
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/bad%20name/certificate").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/grafana/certificate").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        // An ACME site never takes over a certificate the user provided under its domain
+        let request = serde_json::json!({"source": "self_signed", "name": "shop.example.com", "domains": ["shop.example.com"], "days": 5});
+        let req = test::TestRequest::post().uri("/api/nginx/certificates").set_json(&request).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CREATED);
+        let shop: crate::nginx::models::NginxProxy = serde_json::from_value(serde_json::json!({
+            "name": "shop",
+            "domain": "shop.example.com",
+            "backend": "http://127.0.0.1:3000",
+            "ssl": true,
+            "acme": true,
+            "extra_config": null
+        }))
+        .unwrap();
+        std::fs::write(dir.join("shop"), crate::nginx::config::generate_nginx_config(&shop)).unwrap();
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/shop/certificate").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+        let req = test::TestRequest::get().uri("/api/nginx/certificates").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body[0]["source"], "self_signed");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[actix_web::test]
+    async fn test_enable_disable_checks() {
+        let dir = std::env::temp_dir().join(format!("nginx-enable-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("enabled")).unwrap();
+        for name in ["app", "copied"] {
+            std::fs::write(dir.join(name), "server {\n    listen 80;\n}\n").unwrap();
+        }
+        std::fs::write(dir.join("enabled/copied"), "server {\n    listen 80;\n}\n").unwrap();
+
+        let state = app_state(dir.to_str().unwrap());
+        state.nginx_config.write().unwrap().sites_enabled_path = dir.join("enabled").to_str().unwrap().to_string();
+        let fake = fake_controller(&state);
+        let app = test::init_service(App::new().app_data(state).configure(configure_routes)).await;
+
+        let req = test::TestRequest::get().uri("/api/nginx/proxies").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let enabled = |name: &str| {
+            body.as_array().unwrap().iter().find(|p| p["name"] == name).unwrap()["enabled"].clone()
+        };
+        assert_eq!(enabled("app"), false);
+        assert_eq!(enabled("copied"), true);
+
+        // Already disabled: nothing to do
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/app/disable").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::OK);
+
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/missing/enable").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::NOT_FOUND);
+
+        // A copied file is not a link this service manages
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/copied/disable").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::CONFLICT);
+        assert!(dir.join("enabled/copied").exists());
+
+        // A failing `nginx -t` takes the new link back
+        fake.fail_tests(Some("emerg"));
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/app/enable").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        assert!(std::fs::symlink_metadata(dir.join("enabled/app")).is_err());
+
+        fake.fail_tests(None);
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/app/enable").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::OK);
+        assert!(std::fs::symlink_metadata(dir.join("enabled/app")).unwrap().file_type().is_symlink());
+        let calls = fake.calls();
+        assert!(calls[..2].iter().all(|call| call.contains("nginx-preview-")), "{:?}", calls);
+        assert_eq!(calls[2..], ["reload"]);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[actix_web::test]
+    async fn test_revision_endpoints() {
+        use actix_web::http::StatusCode;
+        use crate::nginx::config::generate_nginx_config;
+
+        let dir = std::env::temp_dir().join(format!("nginx-history-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("enabled")).unwrap();
+        let site = |backend: &str, basic_auth: bool| {
+            let mut proxy = serde_json::json!({
+                "name": "app",
+                "domain": "app.example.com",
+                "backend": backend,
+                "ssl": false,
+                "extra_config": null
+            });
+            if basic_auth {
+                proxy["basic_auth"] = serde_json::json!({"users": [{"username": "alice"}]});
+            }
+            generate_nginx_config(&serde_json::from_value(proxy).unwrap())
+        };
+        let store = crate::nginx::revisions::RevisionStore::new(dir.join("revisions"));
+        store.record("app", &site("http://127.0.0.1:3000", false), "admin", "create", None).unwrap();
+        store.record("app", &site("http://127.0.0.1:4000", false), "admin", "update", None).unwrap();
+        store.record("app", "", "admin", "delete", None).unwrap();
+        store.record("app", "server {\n    listen 80;\n    root /var/www;\n}\n", "admin", "update", None).unwrap();
+        store.record("app", &site("http://127.0.0.1:5000", true), "admin", "update", None).unwrap();
+        std::fs::write(dir.join("app"), site("http://127.0.0.1:4000", false)).unwrap();
+
+        let state = app_state(dir.to_str().unwrap());
+        state.nginx_config.write().unwrap().sites_enabled_path = dir.join("enabled").to_str().unwrap().to_string();
+        let fake = fake_controller(&state);
+        let app = test::init_service(App::new().app_data(state).configure(configure_routes)).await;
+        let rollback = |number: u32| test::TestRequest::post().uri(&format!("/api/nginx/proxies/app/revisions/{}/rollback", number)).to_request();
+
+        let req = test::TestRequest::get().uri("/api/nginx/proxies/app/revisions").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body[0]["number"], 5);
+        assert_eq!(body[4]["action"], "create");
+        assert_eq!(body[4]["author"], "admin");
+
+        let req = test::TestRequest::get().uri("/api/nginx/proxies/app/revisions/diff?from=1&to=2").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let diff = body["diff"].as_str().unwrap();
+        assert!(diff.contains("-    ") && diff.contains("127.0.0.1:3000;") && diff.contains("+    "), "{}", diff);
+
+        let req = test::TestRequest::get().uri("/api/nginx/proxies/app/revisions/1").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["content"], site("http://127.0.0.1:3000", false));
+
+        let req = test::TestRequest::get().uri("/api/nginx/proxies/app/revisions/7").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::post().uri("/api/nginx/proxies/bad%20name/revisions/1/rollback").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+        // A deletion has no config to go back to, and a hand-written one is not restored blindly
+        assert_eq!(test::call_service(&app, rollback(3)).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(test::call_service(&app, rollback(4)).await.status(), StatusCode::CONFLICT);
+
+        // A failing `nginx -t` keeps the current file and adds no revision
+        fake.fail_tests(Some("emerg"));
+        assert_eq!(test::call_service(&app, rollback(1)).await.status(), StatusCode::BAD_REQUEST);
+        assert!(std::fs::read_to_string(dir.join("app")).unwrap().contains("127.0.0.1:4000"));
+        assert_eq!(store.list("app").len(), 5);
+
+        fake.fail_tests(None);
+        assert_eq!(test::call_service(&app, rollback(1)).await.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.join("app")).unwrap(), site("http://127.0.0.1:3000", false));
+        let latest = &store.list("app")[0];
+        assert_eq!((latest.number, latest.action.as_str(), latest.rollback_of), (6, "rollback", Some(1)));
+        // A disabled site stays disabled
+        assert!(std::fs::symlink_metadata(dir.join("enabled/app")).is_err());
+
+        // Basic auth comes back with the users the htpasswd file still has
+        assert_eq!(test::call_service(&app, rollback(5)).await.status(), StatusCode::CONFLICT);
+        let htpasswd = format!("alice:{}\n", bcrypt::hash("wonderland", 4).unwrap());
+        std::fs::create_dir_all(dir.join("htpasswd")).unwrap();
+        std::fs::write(dir.join("htpasswd/app"), &htpasswd).unwrap();
+        assert_eq!(test::call_service(&app, rollback(5)).await.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.join("app")).unwrap(), site("http://127.0.0.1:5000", true));
+        assert_eq!(std::fs::read_to_string(dir.join("htpasswd/app")).unwrap(), htpasswd);
+
+        // Rolling a deleted site back writes it, links it and drops the htpasswd file it no longer uses
+        std::fs::remove_file(dir.join("app")).unwrap();
+        assert_eq!(test::call_service(&app, rollback(2)).await.status(), StatusCode::OK);
+        assert_eq!(std::fs::read_to_string(dir.join("app")).unwrap(), site("http://127.0.0.1:4000", false));
+        assert!(std::fs::symlink_metadata(dir.join("enabled/app")).unwrap().file_type().is_symlink());
+        assert!(!dir.join("htpasswd/app").exists());
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[actix_web::test]
+    async fn test_preview_writes_nothing() {
+        let dir = std::env::temp_dir().join(format!("nginx-preview-routes-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("nginx.conf"), format!("http {{\n    include {}/*;\n}}\n", dir.display())).unwrap();
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(dir.to_str().unwrap()))
+                .configure(configure_routes)
+        ).await;
+
+        let proxy = serde_json::json!({
+            "name": "app",
+            "domain": "app.example.com",
+            "backend": "http://127.0.0.1:3000",
+            "ssl": false,
+            "extra_config": "client_max_body_size 10m;"
+        });
+        let req = test::TestRequest::post().uri("/api/nginx/proxies?preview=true").set_json(&proxy).to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert!(body["config"].as_str().unwrap().contains("server_name app.example.com;"));
+        assert!(body["diff"].as_str().unwrap().starts_with("--- /dev/null\n+++ app (preview)\n"));
+        assert_eq!(body["enabled"], true);
+        assert!(!body["test"]["output"].as_str().unwrap().is_empty());
+        assert!(!dir.join("app").exists());
+
+        // An update is compared with the file on disk
+        let current = crate::nginx::config::generate_nginx_config(&serde_json::from_value(proxy.clone()).unwrap());
+        std::fs::write(dir.join("app"), &current).unwrap();
+        let mut changed = proxy.clone();
+        changed["backend"] = "http://127.0.0.1:4000".into();
+        let req = test::TestRequest::put().uri("/api/nginx/proxies/app?preview=true").set_json(&changed).to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let diff = body["diff"].as_str().unwrap();
+        assert!(diff.contains("-        proxy_pass http://127.0.0.1:3000;\n+        proxy_pass http://127.0.0.1:4000;"), "{}", diff);
+        assert_eq!(std::fs::read_to_string(dir.join("app")).unwrap(), current);
+
+        // The usual validation still applies
+        changed["extra_config"] = "location / {".into();
+        let req = test::TestRequest::put().uri("/api/nginx/proxies/app?preview=true").set_json(&changed).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+        let req = test::TestRequest::put().uri("/api/nginx/proxies/missing?preview=true").set_json(&proxy).to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), actix_web::http::StatusCode::BAD_REQUEST);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
     #[actix_web::test]
     async fn test_get_proxies_route_exists() {
         // Test that the get proxies route is registered
@@ -0,0 +1,473 @@
+// Transactional proxy changes, shared by the HTTP handlers and any other front end
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::{error, info};
+
+use crate::config::NginxConfig;
+use crate::error::AppError;
+use crate::nginx::acme::store::CertificateStore;
+use crate::nginx::config::import::import_site;
+use crate::nginx::config::{generate_nginx_config, validate_nginx_extra_config};
+use crate::nginx::controller::NginxController;
+use crate::nginx::htpasswd::HtpasswdStore;
+use crate::nginx::models::{NginxProxy, NginxTestResult};
+use crate::nginx::preview::PreviewTree;
+use crate::nginx::staging::{Change, Changeset};
+use crate::nginx::validation::validate_site_name;
+
+/// Whether [`ProxyService::apply`] adds a site or replaces one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyWrite {
+    /// Write the site and link it into sites-enabled; an existing managed site is overwritten
+    Create,
+    /// Rewrite an existing site, leaving its sites-enabled link as it is
+    Update,
+}
+
+/// Applies proxy changes to the live nginx tree as one unit
+///
+/// Every change is staged in a [`Changeset`], tested with `nginx -t` on a
+/// copy of the tree, written and reloaded; if any step fails the tree is put
+/// back as it was and the failure is returned as an [`AppError`].
+pub struct ProxyService<'a> {
+    nginx: &'a NginxConfig,
+    controller: &'a dyn NginxController,
+    certificates: &'a CertificateStore,
+    htpasswd: &'a HtpasswdStore,
+}
+
+impl<'a> ProxyService<'a> {
+    pub fn new(
+        nginx: &'a NginxConfig,
+        controller: &'a dyn NginxController,
+        certificates: &'a CertificateStore,
+        htpasswd: &'a HtpasswdStore,
+    ) -> Self {
+        Self { nginx, controller, certificates, htpasswd }
+    }
+
+    /// `sites_available_path/<name>`
+    pub fn site_path(&self, name: &str) -> PathBuf {
+        Path::new(&self.nginx.sites_available_path).join(name)
+    }
+
+    /// `sites_enabled_path/<name>`
+    pub fn enabled_path(&self, name: &str) -> PathBuf {
+        Path::new(&self.nginx.sites_enabled_path).join(name)
+    }
+
+    /// Check that `proxy` may be written over the current tree, returning the current site file
+    ///
+    /// `proxy` must already have been through [`normalize`].
+    pub fn check(&self, proxy: &NginxProxy, write: ProxyWrite) -> Result<Option<String>, AppError> {
+        if !Path::new(&self.nginx.sites_available_path).is_dir() {
+            return Err(AppError::NginxError(format!(
+                "Sites directory {} not found; is nginx installed?",
+                self.nginx.sites_available_path
+            )));
+        }
+        let current = fs::read_to_string(self.site_path(&proxy.name)).ok();
+        match &current {
+            None if write == ProxyWrite::Update => {
+                return Err(AppError::NotFound(format!("Site '{}' not found", proxy.name)));
+            }
+            // Saving would drop whatever the proxy model cannot represent
+            Some(content) if import_site(&proxy.name, content).unmanaged => {
+                return Err(AppError::Conflict(format!(
+                    "'{}' holds configuration the proxy model cannot represent; edit the file by hand",
+                    proxy.name
+                )));
+            }
+            _ => {}
+        }
+        if let Some(certificate) = &proxy.certificate {
+            if self.certificates.info(certificate).is_none() {
+                return Err(AppError::ValidationError(format!("certificate: '{}' is not in the store", certificate)));
+            }
+        }
+        Ok(current)
+    }
+
+    /// Validate, write, test and reload one proxy, returning it as written
+    ///
+    /// An ACME proxy gets a self-signed placeholder certificate until its
+    /// first certificate is issued, so that nginx can load it. The htpasswd
+    /// file of the site is written or removed in the same change; new
+    /// passwords are hashed here, so callers run this on a blocking thread.
+    pub fn apply(&self, proxy: NginxProxy, write: ProxyWrite) -> Result<NginxProxy, AppError> {
+        let proxy = normalize(proxy)?;
+        self.check(&proxy, write)?;
+        let mut changes = Changeset::new();
+        if proxy.acme {
+            self.certificates.stage_placeholder(&mut changes, proxy.primary_domain(), &proxy.domains())?;
+        }
+
+        let site = self.site_path(&proxy.name);
+        let htpasswd = self.htpasswd.path(&proxy.name);
+        match &proxy.basic_auth {
+            Some(auth) => {
+                changes.create_dir(self.htpasswd.dir());
+                changes.write(&htpasswd, &self.htpasswd.render(&proxy.name, auth)?);
+            }
+            None => changes.remove(&htpasswd),
+        }
+        changes.write(&site, &generate_nginx_config(&proxy));
+        // A disabled site stays disabled on update
+        if write == ProxyWrite::Create {
+            changes.link(&site, &self.enabled_path(&proxy.name));
+        }
+        info!("Staged {} ({:?})", site.display(), write);
+
+        commit(self.nginx, self.controller, changes)?;
+        Ok(proxy)
+    }
+
+    /// Remove a site, its sites-enabled link and its htpasswd file, then test and reload nginx
+    pub fn delete(&self, name: &str) -> Result<(), AppError> {
+        validate_site_name(name).map_err(|e| AppError::ValidationError(format!("name: {}", e)))?;
+        let site = self.site_path(name);
+        if !site.is_file() {
+            return Err(AppError::NotFound(format!("Site '{}' not found", name)));
+        }
+
+        let mut changes = Changeset::new();
+        changes.remove(&self.enabled_path(name));
+        changes.remove(&site);
+        changes.remove(&self.htpasswd.path(name));
+        commit(self.nginx, self.controller, changes)
+    }
+}
+
+/// Format the extra config of a proxy request and validate every field
+pub fn normalize(proxy: NginxProxy) -> Result<NginxProxy, AppError> {
+    let proxy = match &proxy.extra_config {
+        Some(extra) if !extra.trim().is_empty() => {
+            let formatted = validate_nginx_extra_config(extra)
+                .map_err(|e| AppError::ValidationError(format!("extra_config: {}", e)))?;
+            NginxProxy {
+                extra_config: Some(formatted),
+                unmanaged: false,
+                ..proxy
+            }
+        }
+        _ => proxy,
+    };
+    proxy.validate()?;
+    Ok(proxy)
+}
+
+/// Run `nginx -t` on a copy of the tree with `changes` made, then make them
+/// in the live tree and reload nginx
+///
+/// Nothing live is touched unless the copy passes; a failed write or reload
+/// rolls `changes` back. A config nginx rejects is a `ValidationError`
+/// carrying its output; a test or reload that cannot run is passed through
+/// as it is.
+pub fn commit(nginx: &NginxConfig, controller: &dyn NginxController, mut changes: Changeset) -> Result<(), AppError> {
+    info!("Testing nginx configuration on a staged copy...");
+    let result = test_staged(nginx, controller, &changes)?;
+    if !result.success {
+        error!("Nginx config test failed, nothing was changed: {}", result.output);
+        return Err(AppError::ValidationError(format!("nginx -t failed and nothing was changed: {}", result.output)));
+    }
+
+    if let Err(e) = changes.apply() {
+        error!("Writing nginx changes failed, rolling back: {}", e);
+        return Err(undo(changes, e));
+    }
+    info!("Reloading nginx...");
+    if let Err(e) = controller.reload() {
+        error!("Nginx reload failed, rolling back: {}", e);
+        return Err(undo(changes, e));
+    }
+    info!("Nginx reloaded successfully");
+    Ok(())
+}
+
+/// `nginx -t` on a [`PreviewTree`] with the staged `changes` made in it
+///
+/// Staged paths outside the copied directories, such as htpasswd files, are
+/// mapped into the copy as well so the test never writes to the live tree.
+fn test_staged(nginx: &NginxConfig, controller: &dyn NginxController, changes: &Changeset) -> Result<NginxTestResult, AppError> {
+    let mut parents: Vec<PathBuf> = changes.staged().iter().filter_map(|(path, _)| path.parent()).map(Path::to_path_buf).collect();
+    parents.sort();
+    parents.dedup();
+    let tree = PreviewTree::new(nginx, &std::env::temp_dir(), &parents)?;
+    for (path, change) in changes.staged() {
+        match change {
+            Change::Write(content) | Change::WritePrivate(content) => tree.write(path, content)?,
+            Change::Link(target) => tree.link(target, path)?,
+            Change::Remove => tree.remove(path),
+        }
+    }
+    controller.test(Some(&tree.path(Path::new(&nginx.main_config_path))))
+}
+
+/// Roll `changes` back after `cause`, reporting a failed restore alongside it
+fn undo(changes: Changeset, cause: AppError) -> AppError {
+    match changes.rollback() {
+        Ok(()) => cause,
+        Err(e) => {
+            error!("Failed to roll back nginx changes: {}", e);
+            AppError::NginxError(format!("{}; the previous config could not be restored: {}", cause, e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::NginxControllerKind;
+    use crate::nginx::controller::FakeController;
+    use crate::nginx::models::{BasicAuth, BasicAuthUser, SiteKind};
+
+    /// A sites-available/sites-enabled pair and a certificate store under a fresh temp dir
+    struct Tree {
+        dir: PathBuf,
+        nginx: NginxConfig,
+        certificates: CertificateStore,
+        htpasswd: HtpasswdStore,
+        controller: FakeController,
+    }
+
+    impl Tree {
+        fn new(label: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("nginx-service-{}-{}", label, std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            fs::create_dir_all(dir.join("available")).unwrap();
+            fs::create_dir_all(dir.join("enabled")).unwrap();
+            let nginx = NginxConfig {
+                sites_available_path: dir.join("available").display().to_string(),
+                sites_enabled_path: dir.join("enabled").display().to_string(),
+                upstreams_path: dir.join("upstreams").display().to_string(),
+                revisions_path: dir.join("revisions").display().to_string(),
+                main_config_path: dir.join("nginx.conf").display().to_string(),
+                controller: NginxControllerKind::Fake,
+            };
+            let certificates = CertificateStore::new(dir.join("certs"));
+            let htpasswd = HtpasswdStore::new(dir.join("htpasswd"), 4);
+            Self { dir, nginx, certificates, htpasswd, controller: FakeController::new() }
+        }
+
+        fn service(&self) -> ProxyService<'_> {
+            ProxyService::new(&self.nginx, &self.controller, &self.certificates, &self.htpasswd)
+        }
+
+        fn site(&self, name: &str) -> Option<String> {
+            fs::read_to_string(self.dir.join("available").join(name)).ok()
+        }
+
+        fn linked(&self, name: &str) -> bool {
+            fs::symlink_metadata(self.dir.join("enabled").join(name)).is_ok()
+        }
+    }
+
+    impl Drop for Tree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.dir);
+        }
+    }
+
+    /// Controller calls, with a test of a staged copy shown as `test copy`
+    fn steps(controller: &FakeController) -> Vec<String> {
+        let copy = |call: &str| call.strip_prefix("test ").is_some_and(|path| path.contains("nginx-preview-"));
+        controller.calls().into_iter().map(|call| if copy(&call) { "test copy".to_string() } else { call }).collect()
+    }
+
+    fn proxy(backend: &str) -> NginxProxy {
+        NginxProxy {
+            name: "app".to_string(),
+            domain: "app.example.com".to_string(),
+            backend: backend.to_string(),
+            kind: SiteKind::Proxy,
+            ssl: false,
+            extra_config: None,
+            acme: false,
+            certificate: None,
+            redirect_http: false,
+            hsts: false,
+            ocsp_stapling: false,
+            basic_auth: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            rate_limit: None,
+            connection_limit: None,
+            unmanaged: false,
+        }
+    }
+
+    #[test]
+    fn test_apply_create_update_and_delete() {
+        let tree = Tree::new("ok");
+        let service = tree.service();
+
+        let mut created = proxy("http://127.0.0.1:3000");
+        created.extra_config = Some("client_max_body_size 10m;".to_string());
+        let written = service.apply(created, ProxyWrite::Create).unwrap();
+        assert_eq!(written.extra_config.as_deref(), Some("client_max_body_size 10m;"));
+        assert!(tree.site("app").unwrap().contains("proxy_pass http://127.0.0.1:3000;"));
+        assert!(tree.linked("app"));
+
+        service.apply(proxy("http://127.0.0.1:4000"), ProxyWrite::Update).unwrap();
+        assert!(tree.site("app").unwrap().contains("127.0.0.1:4000"));
+
+        service.delete("app").unwrap();
+        assert!(tree.site("app").is_none());
+        assert!(!tree.linked("app"));
+        assert_eq!(steps(&tree.controller), vec!["test copy", "reload", "test copy", "reload", "test copy", "reload"]);
+    }
+
+    #[test]
+    fn test_rejected_input_touches_nothing() {
+        let tree = Tree::new("input");
+        let service = tree.service();
+
+        let mut bad_extra = proxy("http://127.0.0.1:3000");
+        bad_extra.extra_config = Some("location / {".to_string());
+        let Err(AppError::ValidationError(message)) = service.apply(bad_extra, ProxyWrite::Create) else {
+            panic!("expected a validation error");
+        };
+        assert!(message.starts_with("extra_config: "), "{}", message);
+
+        let mut bad_name = proxy("http://127.0.0.1:3000");
+        bad_name.name = "../app".to_string();
+        assert!(matches!(service.apply(bad_name, ProxyWrite::Create), Err(AppError::ValidationError(_))));
+
+        let mut missing_certificate = proxy("http://127.0.0.1:3000");
+        missing_certificate.ssl = true;
+        missing_certificate.certificate = Some("internal".to_string());
+        let Err(AppError::ValidationError(message)) = service.apply(missing_certificate, ProxyWrite::Create) else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(message, "certificate: 'internal' is not in the store");
+
+        assert!(matches!(service.delete("../app"), Err(AppError::ValidationError(_))));
+        assert!(tree.site("app").is_none());
+        assert!(tree.controller.calls().is_empty());
+    }
+
+    #[test]
+    fn test_state_of_the_tree_is_checked_first() {
+        let tree = Tree::new("state");
+        let service = tree.service();
+
+        assert!(matches!(service.apply(proxy("http://127.0.0.1:3000"), ProxyWrite::Update), Err(AppError::NotFound(_))));
+        assert!(matches!(service.delete("app"), Err(AppError::NotFound(_))));
+
+        let unmanaged = "server {\n    listen 80;\n    server_name app.example.com;\n    root /var/www;\n}\n";
+        fs::write(tree.dir.join("available/app"), unmanaged).unwrap();
+        assert!(matches!(service.apply(proxy("http://127.0.0.1:3000"), ProxyWrite::Create), Err(AppError::Conflict(_))));
+        assert_eq!(tree.site("app").as_deref(), Some(unmanaged));
+
+        fs::remove_dir_all(tree.dir.join("available")).unwrap();
+        assert!(matches!(service.apply(proxy("http://127.0.0.1:3000"), ProxyWrite::Create), Err(AppError::NginxError(_))));
+        assert!(tree.controller.calls().is_empty());
+    }
+
+    #[test]
+    fn test_failed_placeholder_or_write_rolls_back() {
+        let tree = Tree::new("write");
+        let service = tree.service();
+
+        // The certificate store cannot be created under a regular file
+        fs::write(tree.dir.join("certs"), "").unwrap();
+        let mut acme = proxy("http://127.0.0.1:3000");
+        acme.ssl = true;
+        acme.acme = true;
+        assert!(matches!(service.apply(acme, ProxyWrite::Create), Err(AppError::NginxError(_))));
+        assert!(tree.site("app").is_none());
+
+        // The copy passes and the site is written, but its link cannot be, so the site goes again
+        fs::remove_dir_all(tree.dir.join("enabled")).unwrap();
+        assert!(matches!(service.apply(proxy("http://127.0.0.1:3000"), ProxyWrite::Create), Err(AppError::NginxError(_))));
+        assert!(tree.site("app").is_none());
+        assert_eq!(steps(&tree.controller), vec!["test copy"]);
+    }
+
+    #[test]
+    fn test_htpasswd_follows_the_site() {
+        let tree = Tree::new("htpasswd");
+        let service = tree.service();
+        let htpasswd = tree.dir.join("htpasswd/app");
+        let user = |password: Option<&str>| BasicAuthUser {
+            username: "alice".to_string(),
+            password: password.map(str::to_string),
+        };
+
+        let mut guarded = proxy("http://127.0.0.1:3000");
+        guarded.basic_auth = Some(BasicAuth { realm: "Admin".to_string(), users: vec![user(Some("wonderland"))] });
+        service.apply(guarded.clone(), ProxyWrite::Create).unwrap();
+        let content = fs::read_to_string(&htpasswd).unwrap();
+        assert!(bcrypt::verify("wonderland", content.trim_end().strip_prefix("alice:").unwrap()).unwrap());
+
+        // A rejected config restores the previous users along with the site
+        tree.controller.fail_tests(Some("nginx: [emerg] unknown directive"));
+        guarded.basic_auth = Some(BasicAuth { realm: "Admin".to_string(), users: vec![user(Some("changed"))] });
+        assert!(service.apply(guarded.clone(), ProxyWrite::Update).is_err());
+        assert_eq!(fs::read_to_string(&htpasswd).unwrap(), content);
+        tree.controller.fail_tests(None);
+
+        // Without a password the stored hash is kept
+        guarded.basic_auth = Some(BasicAuth { realm: "Admin".to_string(), users: vec![user(None)] });
+        service.apply(guarded.clone(), ProxyWrite::Update).unwrap();
+        assert_eq!(fs::read_to_string(&htpasswd).unwrap(), content);
+
+        service.apply(proxy("http://127.0.0.1:3000"), ProxyWrite::Update).unwrap();
+        assert!(!htpasswd.exists());
+
+        assert!(matches!(service.apply(guarded, ProxyWrite::Update), Err(AppError::ValidationError(_))));
+        assert!(!tree.site("app").unwrap().contains("auth_basic"));
+        service.delete("app").unwrap();
+        assert!(!htpasswd.exists());
+    }
+
+    #[test]
+    fn test_failed_test_or_reload_rolls_back() {
+        let tree = Tree::new("commit");
+        let service = tree.service();
+        service.apply(proxy("http://127.0.0.1:3000"), ProxyWrite::Create).unwrap();
+        let original = tree.site("app").unwrap();
+
+        tree.controller.fail_tests(Some("nginx: [emerg] host not found in upstream"));
+        let Err(AppError::ValidationError(message)) = service.apply(proxy("http://127.0.0.1:4000"), ProxyWrite::Update) else {
+            panic!("expected a validation error");
+        };
+        assert!(message.contains("[emerg]"), "{}", message);
+        assert_eq!(tree.site("app").unwrap(), original);
+
+        // Nor does a rejected site leave its placeholder certificate or htpasswd directory behind
+        let mut secure = proxy("http://127.0.0.1:6000");
+        secure.name = "secure".to_string();
+        secure.domain = "secure.example.com".to_string();
+        secure.ssl = true;
+        secure.acme = true;
+        secure.basic_auth = Some(BasicAuth {
+            realm: "Admin".to_string(),
+            users: vec![BasicAuthUser { username: "alice".to_string(), password: Some("wonderland".to_string()) }],
+        });
+        assert!(matches!(service.apply(secure.clone(), ProxyWrite::Create), Err(AppError::ValidationError(_))));
+        assert!(!tree.dir.join("certs").exists());
+        assert!(!tree.dir.join("htpasswd").exists());
+        tree.controller.fail_tests(None);
+
+        tree.controller.fail_test_runs(Some("nginx: not found"));
+        assert!(matches!(service.delete("app"), Err(AppError::NginxError(_))));
+        assert_eq!(tree.site("app").unwrap(), original);
+        assert!(tree.linked("app"));
+        tree.controller.fail_test_runs(None);
+
+        tree.controller.fail_reloads(Some("nginx is not running"));
+        let mut other = proxy("http://127.0.0.1:5000");
+        other.name = "other".to_string();
+        assert!(matches!(service.apply(other, ProxyWrite::Create), Err(AppError::NginxError(_))));
+        assert!(tree.site("other").is_none());
+        assert!(!tree.linked("other"));
+        assert!(matches!(service.apply(secure, ProxyWrite::Create), Err(AppError::NginxError(_))));
+        assert!(tree.site("secure").is_none());
+        assert!(!tree.dir.join("certs").exists());
+        assert!(!tree.dir.join("htpasswd").exists());
+        assert_eq!(tree.controller.calls().last().map(String::as_str), Some("reload"));
+    }
+}
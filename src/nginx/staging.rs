@@ -0,0 +1,328 @@
+// Atomic changes to the live nginx tree that can be undone as a unit
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::error::AppError;
+
+/// Distinguishes temp files of concurrent writers
+static NEXT_TEMP: AtomicU64 = AtomicU64::new(0);
+
+/// What a path held before a [`Changeset`] touched it
+enum Previous {
+    File(Vec<u8>),
+    Symlink(PathBuf),
+    Missing,
+    /// A directory the changeset created
+    MissingDir,
+}
+
+/// One change waiting in a [`Changeset`]
+pub enum Change {
+    Write(String),
+    /// Content only the owner may read, such as a private key
+    WritePrivate(String),
+    /// A symlink to the given target
+    Link(PathBuf),
+    Remove,
+}
+
+/// Files and symlinks staged in memory, then replaced through a temp file,
+/// fsync and rename
+///
+/// Nothing touches the disk until [`Changeset::apply`], so the staged changes
+/// can be tested on a copy of the tree first. Every path is either fully old
+/// or fully new at any moment, so a crash can never leave a half-written
+/// config behind. [`Changeset::rollback`] puts the earlier contents back the
+/// same way.
+#[derive(Default)]
+pub struct Changeset {
+    /// Directories to create before any file is written
+    dirs: Vec<PathBuf>,
+    staged: Vec<(PathBuf, Change)>,
+    applied: Vec<(PathBuf, Previous)>,
+}
+
+impl Changeset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stage replacing `path` with `content`
+    pub fn write(&mut self, path: &Path, content: &str) {
+        self.staged.push((path.to_path_buf(), Change::Write(content.to_string())));
+    }
+
+    /// Stage replacing `path` with `content` that only its owner may read
+    pub fn write_private(&mut self, path: &Path, content: &str) {
+        self.staged.push((path.to_path_buf(), Change::WritePrivate(content.to_string())));
+    }
+
+    /// Stage creating directory `path` and its missing parents before any file is written
+    pub fn create_dir(&mut self, path: &Path) {
+        self.dirs.push(path.to_path_buf());
+    }
+
+    /// Stage pointing `link` at `target`, replacing whatever `link` was
+    pub fn link(&mut self, target: &Path, link: &Path) {
+        self.staged.push((link.to_path_buf(), Change::Link(target.to_path_buf())));
+    }
+
+    /// Stage removing `path` if it exists
+    pub fn remove(&mut self, path: &Path) {
+        self.staged.push((path.to_path_buf(), Change::Remove));
+    }
+
+    /// The file changes not applied yet, in order
+    pub fn staged(&self) -> &[(PathBuf, Change)] {
+        &self.staged
+    }
+
+    /// Make the staged changes in order
+    ///
+    /// On failure the changes made so far stay recorded, so
+    /// [`Changeset::rollback`] can take them back.
+    pub fn apply(&mut self) -> Result<(), AppError> {
+        for dir in std::mem::take(&mut self.dirs) {
+            self.create_dirs(&dir)
+                .map_err(|e| AppError::NginxError(format!("Cannot create {}: {}", dir.display(), e)))?;
+        }
+        for (path, change) in std::mem::take(&mut self.staged) {
+            let previous = read_previous(&path)
+                .map_err(|e| AppError::NginxError(format!("Cannot read {}: {}", path.display(), e)))?;
+            let result = match &change {
+                Change::Write(content) => write_atomic(&path, content.as_bytes()),
+                Change::WritePrivate(content) => replace(&path, content.as_bytes(), true),
+                Change::Link(target) => symlink_atomic(target, &path),
+                Change::Remove => remove_synced(&path),
+            };
+            result.map_err(|e| AppError::NginxError(format!("Cannot write {}: {}", path.display(), e)))?;
+            self.applied.push((path, previous));
+        }
+        Ok(())
+    }
+
+    /// Make the staged changes, taking back the ones made so far if one fails
+    pub fn apply_or_undo(mut self) -> Result<(), AppError> {
+        let Err(e) = self.apply() else {
+            return Ok(());
+        };
+        match self.rollback() {
+            Ok(()) => Err(e),
+            Err(restore) => Err(AppError::NginxError(format!("{}; the previous files could not be restored: {}", e, restore))),
+        }
+    }
+
+    /// Create `path` and its missing parents, recording each one
+    fn create_dirs(&mut self, path: &Path) -> io::Result<()> {
+        let missing: Vec<PathBuf> = path
+            .ancestors()
+            .take_while(|dir| !dir.as_os_str().is_empty() && fs::symlink_metadata(dir).is_err())
+            .map(Path::to_path_buf)
+            .collect();
+        for dir in missing.into_iter().rev() {
+            fs::create_dir(&dir)?;
+            sync_parent(&dir)?;
+            self.applied.push((dir, Previous::MissingDir));
+        }
+        Ok(())
+    }
+
+    /// Restore every applied path in reverse order, continuing past failures
+    pub fn rollback(self) -> Result<(), AppError> {
+        let mut first_error = None;
+        for (path, previous) in self.applied.into_iter().rev() {
+            let result = match &previous {
+                Previous::File(content) => write_atomic(&path, content),
+                Previous::Symlink(target) => symlink_atomic(target, &path),
+                Previous::Missing => remove_synced(&path),
+                Previous::MissingDir => fs::remove_dir(&path).and_then(|_| sync_parent(&path)),
+            };
+            if let Err(e) = result {
+                first_error.get_or_insert_with(|| AppError::NginxError(format!("Cannot restore {}: {}", path.display(), e)));
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+fn read_previous(path: &Path) -> io::Result<Previous> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => Ok(Previous::Symlink(fs::read_link(path)?)),
+        Ok(_) => Ok(Previous::File(fs::read(path)?)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Previous::Missing),
+        Err(e) => Err(e),
+    }
+}
+
+/// Write `content` to a temp file next to `path`, fsync it and rename it over `path`
+///
+/// A file that is replaced keeps its permissions.
+pub fn write_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    replace(path, content, false)
+}
+
+/// [`write_atomic`], with a `private` file readable by its owner only
+fn replace(path: &Path, content: &[u8], private: bool) -> io::Result<()> {
+    let temp = temp_path(path)?;
+    let permissions = if private {
+        private_permissions()
+    } else {
+        fs::metadata(path).ok().map(|metadata| metadata.permissions())
+    };
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if private {
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    }
+    let written = options
+        .open(&temp)
+        .and_then(|mut file| {
+            if let Some(permissions) = permissions {
+                file.set_permissions(permissions)?;
+            }
+            file.write_all(content)?;
+            file.sync_all()
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written?;
+    sync_parent(path)
+}
+
+#[cfg(unix)]
+fn private_permissions() -> Option<fs::Permissions> {
+    Some(std::os::unix::fs::PermissionsExt::from_mode(0o600))
+}
+
+#[cfg(not(unix))]
+fn private_permissions() -> Option<fs::Permissions> {
+    None
+}
+
+/// Create `link` pointing at `target` through a temp link and a rename
+fn symlink_atomic(target: &Path, link: &Path) -> io::Result<()> {
+    let temp = temp_path(link)?;
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(target, &temp)?;
+    #[cfg(not(unix))]
+    fs::copy(target, &temp)?;
+    if let Err(e) = fs::rename(&temp, link) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    sync_parent(link)
+}
+
+fn remove_synced(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Ok(()) => sync_parent(path),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+/// `.<name>.<pid>-<n>.tmp` in the same directory, so the rename stays on one
+/// filesystem and include globs such as `*.conf` never pick it up
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let name = path
+        .file_name()
+        .ok_or_else(|| io::Error::new(ErrorKind::InvalidInput, "path has no file name"))?;
+    Ok(path.with_file_name(format!(
+        ".{}.{}-{}.tmp",
+        name.to_string_lossy(),
+        std::process::id(),
+        NEXT_TEMP.fetch_add(1, Ordering::Relaxed)
+    )))
+}
+
+/// Make a rename or removal durable
+fn sync_parent(path: &Path) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::File::open(parent)?.sync_all()?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_apply_and_roll_back() {
+        let dir = std::env::temp_dir().join(format!("nginx-staging-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("enabled")).unwrap();
+        fs::write(dir.join("app"), "old\n").unwrap();
+        fs::write(dir.join("stale"), "stale\n").unwrap();
+
+        let mut changes = Changeset::new();
+        changes.write(&dir.join("app"), "new\n");
+        changes.write(&dir.join("shop"), "shop\n");
+        changes.link(&dir.join("shop"), &dir.join("enabled/shop"));
+        changes.remove(&dir.join("stale"));
+        changes.remove(&dir.join("never-existed"));
+
+        // Staging alone changes nothing
+        assert_eq!(changes.staged().len(), 5);
+        assert_eq!(fs::read_to_string(dir.join("app")).unwrap(), "old\n");
+        assert!(!dir.join("shop").exists());
+
+        changes.apply().unwrap();
+        assert!(changes.staged().is_empty());
+        assert_eq!(fs::read_to_string(dir.join("app")).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(dir.join("enabled/shop")).unwrap(), "shop\n");
+        assert!(fs::symlink_metadata(dir.join("enabled/shop")).unwrap().file_type().is_symlink());
+        assert!(!dir.join("stale").exists());
+
+        changes.rollback().unwrap();
+        assert_eq!(fs::read_to_string(dir.join("app")).unwrap(), "old\n");
+        assert_eq!(fs::read_to_string(dir.join("stale")).unwrap(), "stale\n");
+        assert!(!dir.join("shop").exists());
+        assert!(fs::symlink_metadata(dir.join("enabled/shop")).is_err());
+
+        // No temp files are left behind
+        let leftovers: Vec<_> = fs::read_dir(&dir)
+            .unwrap()
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().ends_with(".tmp"))
+            .collect();
+        assert!(leftovers.is_empty());
+
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_failed_write_leaves_file_untouched() {
+        let dir = std::env::temp_dir().join(format!("nginx-staging-missing-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+
+        let mut changes = Changeset::new();
+        changes.write(&dir.join("app"), "new\n");
+        assert!(matches!(changes.apply(), Err(AppError::NginxError(_))));
+        assert!(changes.rollback().is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replaced_file_keeps_its_mode() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = std::env::temp_dir().join(format!("nginx-staging-mode-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("app"), "old\n").unwrap();
+        fs::set_permissions(dir.join("app"), fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_atomic(&dir.join("app"), b"new\n").unwrap();
+        assert_eq!(fs::metadata(dir.join("app")).unwrap().permissions().mode() & 0o777, 0o640);
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
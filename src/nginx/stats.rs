@@ -0,0 +1,66 @@
+// Counters for nginx proxy operations
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Kinds of proxy operations that are counted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyOperation {
+    Create,
+    Update,
+    Delete,
+    Enable,
+    Disable,
+}
+
+impl ProxyOperation {
+    pub const ALL: [ProxyOperation; 5] = [
+        ProxyOperation::Create,
+        ProxyOperation::Update,
+        ProxyOperation::Delete,
+        ProxyOperation::Enable,
+        ProxyOperation::Disable,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProxyOperation::Create => "create",
+            ProxyOperation::Update => "update",
+            ProxyOperation::Delete => "delete",
+            ProxyOperation::Enable => "enable",
+            ProxyOperation::Disable => "disable",
+        }
+    }
+
+    fn index(&self) -> usize {
+        match self {
+            ProxyOperation::Create => 0,
+            ProxyOperation::Update => 1,
+            ProxyOperation::Delete => 2,
+            ProxyOperation::Enable => 3,
+            ProxyOperation::Disable => 4,
+        }
+    }
+}
+
+/// Success and failure counts per proxy operation since startup
+#[derive(Default)]
+pub struct ProxyOperationStats {
+    succeeded: [AtomicU64; 5],
+    failed: [AtomicU64; 5],
+}
+
+impl ProxyOperationStats {
+    /// Count one finished operation
+    pub fn record(&self, operation: ProxyOperation, success: bool) {
+        let counters = if success { &self.succeeded } else { &self.failed };
+        counters[operation.index()].fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn succeeded(&self, operation: ProxyOperation) -> u64 {
+        self.succeeded[operation.index()].load(Ordering::Relaxed)
+    }
+
+    pub fn failed(&self, operation: ProxyOperation) -> u64 {
+        self.failed[operation.index()].load(Ordering::Relaxed)
+    }
+}
@@ -0,0 +1,543 @@
+// Field-level checks on proxy input before it becomes a file path or config text
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use crate::error::AppError;
+use crate::nginx::models::{is_time_value, BasicAuth, LocationRoute, NginxProxy, SiteKind};
+
+/// Earlier releases kept `<name>.backup` copies next to the sites, which may still be there
+const BACKUP_SUFFIX: &str = ".backup";
+
+/// One rejected field of a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// Every problem with `proxy`, in field order
+pub fn proxy_errors(proxy: &NginxProxy) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut check = |field: &'static str, result: Result<(), String>| {
+        if let Err(message) = result {
+            errors.push(FieldError { field, message });
+        }
+    };
+
+    check("name", validate_site_name(&proxy.name));
+    check("domain", validate_domain_list(&proxy.domain, proxy.acme));
+    match &proxy.kind {
+        SiteKind::Proxy => check("backend", validate_backend(&proxy.backend)),
+        SiteKind::Static { root, index, try_files, expires } => {
+            check("root", validate_root(root));
+            check("index", validate_index(index));
+            check("try_files", validate_try_files(try_files));
+            if let Some(expires) = expires {
+                if expires != "max" && !is_time_value(expires) {
+                    check("expires", Err(format!("'{}' is not a time value such as 30d or max", expires.escape_default())));
+                }
+            }
+        }
+        SiteKind::Redirect { target, code, preserve_path } => {
+            check("target", validate_redirect_target(target, *preserve_path));
+            if !matches!(code, 301 | 302 | 307 | 308) {
+                check("code", Err(format!("{} is not one of 301, 302, 307 or 308", code)));
+            }
+        }
+        SiteKind::Locations { locations } => check("locations", validate_locations(locations)),
+    }
+    if let Some(certificate) = &proxy.certificate {
+        check("certificate", validate_certificate_name(certificate));
+    }
+    if !proxy.ssl {
+        for (field, enabled) in [
+            ("acme", proxy.acme),
+            ("certificate", proxy.certificate.is_some()),
+            ("redirect_http", proxy.redirect_http),
+            ("hsts", proxy.hsts),
+            ("ocsp_stapling", proxy.ocsp_stapling),
+        ] {
+            if enabled {
+                check(field, Err("requires ssl".to_string()));
+            }
+        }
+    } else if proxy.acme && proxy.certificate.is_some() {
+        check("certificate", Err("cannot be combined with acme".to_string()));
+    }
+    if proxy.ssl && proxy.ocsp_stapling && !proxy.acme && proxy.certificate.is_none() {
+        check("ocsp_stapling", Err("requires acme or a stored certificate".to_string()));
+    }
+    if let Some(auth) = &proxy.basic_auth {
+        check("basic_auth", validate_basic_auth(auth));
+    }
+    check("allow", validate_addresses(&proxy.allow));
+    check("deny", validate_addresses(&proxy.deny));
+    if let Some(limit) = &proxy.rate_limit {
+        check("rate_limit", validate_rate(&limit.rate));
+    }
+    if proxy.connection_limit == Some(0) {
+        check("connection_limit", Err("must be at least 1".to_string()));
+    }
+    errors
+}
+
+/// [`proxy_errors`] as one `ValidationError`, e.g. `name: ...; backend: ...`
+pub fn validate_proxy(proxy: &NginxProxy) -> Result<(), AppError> {
+    let errors = proxy_errors(proxy);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(AppError::ValidationError(
+        errors
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect::<Vec<_>>()
+            .join("; "),
+    ))
+}
+
+/// A site file name: letters, digits, `.`, `-` and `_`, starting with a letter or digit
+pub fn validate_site_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > 64 {
+        return Err("must be 1 to 64 characters".to_string());
+    }
+    if !name.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err("must start with a letter or digit".to_string());
+    }
+    if let Some(c) = name.chars().find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))) {
+        return Err(format!("must not contain '{}'; use letters, digits, '.', '-' and '_'", c.escape_default()));
+    }
+    if name.ends_with(BACKUP_SUFFIX) {
+        return Err(format!("must not end with '{}'", BACKUP_SUFFIX));
+    }
+    Ok(())
+}
+
+/// Space-separated server names; wildcards cannot be validated over HTTP-01
+fn validate_domain_list(domain: &str, acme: bool) -> Result<(), String> {
+    let names: Vec<&str> = domain.split_whitespace().collect();
+    if names.is_empty() {
+        return Err("at least one server name is required".to_string());
+    }
+    for name in names {
+        validate_server_name(name).map_err(|e| format!("'{}' {}", name.escape_default(), e))?;
+        if acme && (name == "_" || name.starts_with("*.")) {
+            return Err(format!("'{}' cannot get an ACME certificate over HTTP-01", name));
+        }
+    }
+    Ok(())
+}
+
+/// A host name (RFC 1123), an IP address, a `*.` wildcard or nginx's `_` catch-all
+pub fn validate_server_name(name: &str) -> Result<(), String> {
+    if name == "_" || name.parse::<Ipv4Addr>().is_ok() {
+        return Ok(());
+    }
+    let (host, wildcard) = match name.strip_prefix("*.") {
+        Some(rest) => (rest, true),
+        None => (name, false),
+    };
+    if wildcard && !host.contains('.') {
+        return Err("wildcards need at least two labels after '*.'".to_string());
+    }
+    validate_host_name(host, false)
+}
+
+/// `http://` or `https://`, a host or upstream name with an optional port, and an optional path
+pub fn validate_backend(backend: &str) -> Result<(), String> {
+    let Some(rest) = backend.strip_prefix("http://").or_else(|| backend.strip_prefix("https://")) else {
+        return Err("must start with http:// or https://".to_string());
+    };
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, ""),
+    };
+
+    let (host, port) = if let Some(bracketed) = authority.strip_prefix('[') {
+        let (address, after) = bracketed
+            .split_once(']')
+            .ok_or_else(|| "IPv6 address is missing its closing ']'".to_string())?;
+        address
+            .parse::<Ipv6Addr>()
+            .map_err(|_| format!("'{}' is not an IPv6 address", address.escape_default()))?;
+        match after {
+            "" => (None, None),
+            _ => (None, Some(after.strip_prefix(':').ok_or("expected ':' after the IPv6 address")?)),
+        }
+    } else {
+        match authority.rsplit_once(':') {
+            Some((host, port)) => (Some(host), Some(port)),
+            None => (Some(authority), None),
+        }
+    };
+
+    if let Some(host) = host {
+        if host.parse::<Ipv4Addr>().is_err() {
+            // Upstream names may contain '_'
+            validate_host_name(host, true).map_err(|e| format!("host '{}' {}", host.escape_default(), e))?;
+        }
+    }
+    if let Some(port) = port {
+        if !port.chars().all(|c| c.is_ascii_digit()) || !matches!(port.parse::<u32>(), Ok(1..=65535)) {
+            return Err(format!("port '{}' must be between 1 and 65535", port.escape_default()));
+        }
+    }
+    if let Some(c) = path.chars().find(|c| !is_path_char(*c)) {
+        return Err(format!("path must not contain '{}'", c.escape_default()));
+    }
+    Ok(())
+}
+
+/// An absolute directory to serve files from
+fn validate_root(root: &str) -> Result<(), String> {
+    if !root.starts_with('/') {
+        return Err("must be an absolute path".to_string());
+    }
+    if root.trim_end_matches('/').is_empty() {
+        return Err("must not be the filesystem root".to_string());
+    }
+    if let Some(c) = root.chars().find(|c| !is_path_char(*c)) {
+        return Err(format!("must not contain '{}'", c.escape_default()));
+    }
+    Ok(())
+}
+
+/// Space-separated index file names
+fn validate_index(index: &str) -> Result<(), String> {
+    let names: Vec<&str> = index.split_whitespace().collect();
+    if names.is_empty() {
+        return Err("at least one file name is required".to_string());
+    }
+    match names.iter().find(|name| !name.chars().all(|c| is_path_char(c) && c != '/')) {
+        Some(name) => Err(format!("'{}' is not a file name", name.escape_default())),
+        None => Ok(()),
+    }
+}
+
+/// Paths, variables such as `$uri` and a final fallback such as `=404` or `/index.html`
+fn validate_try_files(try_files: &str) -> Result<(), String> {
+    let args: Vec<&str> = try_files.split_whitespace().collect();
+    if args.len() < 2 {
+        return Err("needs at least one file and a fallback".to_string());
+    }
+    match args.iter().find(|arg| !arg.chars().all(|c| is_path_char(c) || c == '$')) {
+        Some(arg) => Err(format!("'{}' may only use path characters and $variables", arg.escape_default())),
+        None => Ok(()),
+    }
+}
+
+/// A backend-shaped URL; with `preserve_path` the request URI supplies the slash
+fn validate_redirect_target(target: &str, preserve_path: bool) -> Result<(), String> {
+    validate_backend(target)?;
+    if preserve_path && target.ends_with('/') {
+        return Err("must not end with '/' when the path is preserved".to_string());
+    }
+    Ok(())
+}
+
+/// Distinct `/` prefixes, each with a valid backend
+fn validate_locations(locations: &[LocationRoute]) -> Result<(), String> {
+    if locations.is_empty() {
+        return Err("at least one location is required".to_string());
+    }
+    for (index, route) in locations.iter().enumerate() {
+        let path = route.path.escape_default();
+        if !route.path.starts_with('/') {
+            return Err(format!("'{}' must start with '/'", path));
+        }
+        if let Some(c) = route.path.chars().find(|c| !is_path_char(*c)) {
+            return Err(format!("'{}' must not contain '{}'", path, c.escape_default()));
+        }
+        if locations[..index].iter().any(|other| other.path == route.path) {
+            return Err(format!("'{}' is listed twice", path));
+        }
+        validate_backend(&route.backend).map_err(|e| format!("'{}' backend {}", path, e))?;
+    }
+    Ok(())
+}
+
+/// A realm that fits in a quoted string and distinct usernames that fit in an htpasswd line
+fn validate_basic_auth(auth: &BasicAuth) -> Result<(), String> {
+    if auth.realm.is_empty() || auth.realm.len() > 64 {
+        return Err("realm must be 1 to 64 characters".to_string());
+    }
+    if let Some(c) = auth.realm.chars().find(|c| !(c.is_ascii_graphic() || *c == ' ') || matches!(c, '"' | '\\' | '$')) {
+        return Err(format!("realm must not contain '{}'", c.escape_default()));
+    }
+    if auth.users.is_empty() {
+        return Err("at least one user is required".to_string());
+    }
+    for (index, user) in auth.users.iter().enumerate() {
+        let username = user.username.escape_default();
+        if user.username.is_empty()
+            || user.username.len() > 64
+            || !user.username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '@'))
+        {
+            return Err(format!("username '{}' must be 1 to 64 letters, digits, '.', '_', '-' or '@'", username));
+        }
+        if auth.users[..index].iter().any(|other| other.username == user.username) {
+            return Err(format!("'{}' is listed twice", username));
+        }
+        // bcrypt ignores everything after 72 bytes
+        if user.password.as_ref().is_some_and(|password| password.is_empty() || password.len() > 72) {
+            return Err(format!("password of '{}' must be 1 to 72 bytes", username));
+        }
+    }
+    Ok(())
+}
+
+/// IP addresses or CIDR ranges such as `10.0.0.0/8` and `2001:db8::/32`
+fn validate_addresses(addresses: &[String]) -> Result<(), String> {
+    for address in addresses {
+        let (ip, prefix) = match address.split_once('/') {
+            Some((ip, prefix)) => (ip, Some(prefix)),
+            None => (address.as_str(), None),
+        };
+        let bits = match ip.parse::<IpAddr>() {
+            Ok(IpAddr::V4(_)) => 32,
+            Ok(IpAddr::V6(_)) => 128,
+            Err(_) => return Err(format!("'{}' is not an IP address or CIDR range", address.escape_default())),
+        };
+        if let Some(prefix) = prefix {
+            if !prefix.chars().all(|c| c.is_ascii_digit()) || !prefix.parse::<u32>().is_ok_and(|prefix| prefix <= bits) {
+                return Err(format!("'{}' has a prefix length outside 0-{}", address.escape_default(), bits));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// A request rate such as `10r/s` or `120r/m`
+fn validate_rate(rate: &str) -> Result<(), String> {
+    let count = rate.strip_suffix("r/s").or_else(|| rate.strip_suffix("r/m"));
+    match count {
+        Some(count) if !count.is_empty() && count.chars().all(|c| c.is_ascii_digit()) && count.parse::<u32>().is_ok_and(|n| n > 0) => Ok(()),
+        _ => Err(format!("rate '{}' must look like 10r/s or 120r/m", rate.escape_default())),
+    }
+}
+
+/// Same rules as certificate names in the store
+fn validate_certificate_name(name: &str) -> Result<(), String> {
+    if name.is_empty()
+        || name.starts_with('.')
+        || !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err("use letters, digits, '-', '_' and '.', not starting with '.'".to_string());
+    }
+    Ok(())
+}
+
+/// Dot-separated labels of 1-63 letters, digits and inner hyphens, 253 characters at most
+fn validate_host_name(host: &str, allow_underscore: bool) -> Result<(), String> {
+    if host.is_empty() {
+        return Err("is empty".to_string());
+    }
+    if host.len() > 253 {
+        return Err("is longer than 253 characters".to_string());
+    }
+    for label in host.split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err("has a label that is empty or longer than 63 characters".to_string());
+        }
+        if label.starts_with('-') || label.ends_with('-') {
+            return Err(format!("has a label '{}' that starts or ends with '-'", label));
+        }
+        if let Some(c) = label
+            .chars()
+            .find(|c| !(c.is_ascii_alphanumeric() || *c == '-' || (allow_underscore && *c == '_')))
+        {
+            return Err(format!("must not contain '{}'", c.escape_default()));
+        }
+    }
+    Ok(())
+}
+
+/// URI path characters that cannot end or open a directive or block
+fn is_path_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '.' | '_' | '~' | '%' | '!' | '&' | '(' | ')' | '*' | '+' | ',' | '=' | ':' | '@')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn proxy() -> NginxProxy {
+        NginxProxy {
+            name: "app".to_string(),
+            domain: "app.example.com www.example.com".to_string(),
+            backend: "http://127.0.0.1:3000".to_string(),
+            kind: SiteKind::Proxy,
+            ssl: false,
+            extra_config: None,
+            acme: false,
+            certificate: None,
+            redirect_http: false,
+            hsts: false,
+            ocsp_stapling: false,
+            basic_auth: None,
+            allow: Vec::new(),
+            deny: Vec::new(),
+            rate_limit: None,
+            connection_limit: None,
+            unmanaged: false,
+        }
+    }
+
+    #[test]
+    fn test_site_names() {
+        for name in ["app", "shop.example.com", "api_v2-internal", "9lives"] {
+            assert!(validate_site_name(name).is_ok(), "{}", name);
+        }
+        for name in ["", "../../etc/passwd", "a/b", ".hidden", "-x", "app.backup", "app conf", "app\n", &"a".repeat(65)] {
+            assert!(validate_site_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_server_names() {
+        for name in ["example.com", "*.example.com", "localhost", "xn--bcher-kva.example", "_", "10.0.0.1", "API.Example.COM"] {
+            assert!(validate_server_name(name).is_ok(), "{}", name);
+        }
+        for name in ["*.com", "*example.com", "ex_ample.com", "-a.com", "a-.com", "a..com", "a.com;", "example.com.*", &format!("{}.com", "a".repeat(64))] {
+            assert!(validate_server_name(name).is_err(), "{:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_backends() {
+        for backend in [
+            "http://127.0.0.1:3000",
+            "https://api.internal",
+            "http://app_pool",
+            "http://[::1]:8080/api/",
+            "http://localhost:65535/v1/items%20all",
+        ] {
+            assert!(validate_backend(backend).is_ok(), "{}", backend);
+        }
+        for backend in [
+            "127.0.0.1:3000",
+            "ftp://host",
+            "http://",
+            "http://host:0",
+            "http://host:70000",
+            "http://host:80x",
+            "http://[::1",
+            "http://[not-v6]:80",
+            "http://host/$uri",
+            "http://127.0.0.1:3000; return 200 pwned",
+            "http://127.0.0.1:3000;\n    include /etc/passwd",
+            "http://host/a}",
+        ] {
+            assert!(validate_backend(backend).is_err(), "{:?}", backend);
+        }
+    }
+
+    #[test]
+    fn test_proxy_errors_name_each_field() {
+        assert!(validate_proxy(&proxy()).is_ok());
+
+        let mut invalid = proxy();
+        invalid.name = "../../etc/passwd".to_string();
+        invalid.domain = "ok.example.com evil.com;".to_string();
+        invalid.backend = "http://127.0.0.1:3000; return 200".to_string();
+        invalid.hsts = true;
+        let fields: Vec<&str> = proxy_errors(&invalid).iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["name", "domain", "backend", "hsts"]);
+
+        let Err(AppError::ValidationError(message)) = validate_proxy(&invalid) else {
+            panic!("expected a validation error");
+        };
+        assert!(message.starts_with("name: must start with a letter or digit"), "{}", message);
+        assert!(message.contains("; domain: 'evil.com;' must not contain ';'"), "{}", message);
+        assert!(message.ends_with("; hsts: requires ssl"), "{}", message);
+
+        let mut tls = proxy();
+        tls.ssl = true;
+        tls.acme = true;
+        tls.domain = "*.example.com".to_string();
+        assert_eq!(proxy_errors(&tls)[0].field, "domain");
+        tls.domain = "example.com".to_string();
+        tls.certificate = Some("../key".to_string());
+        let fields: Vec<&str> = proxy_errors(&tls).iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["certificate", "certificate"]);
+    }
+
+    #[test]
+    fn test_site_kinds() {
+        let site = |kind: SiteKind| NginxProxy { backend: String::new(), kind, ..proxy() };
+        let fields = |kind: SiteKind| proxy_errors(&site(kind)).iter().map(|e| e.field).collect::<Vec<_>>();
+
+        let files = SiteKind::Static {
+            root: "/var/www/app".to_string(),
+            index: "index.html index.htm".to_string(),
+            try_files: "$uri $uri/ /index.html".to_string(),
+            expires: Some("30d".to_string()),
+        };
+        assert!(validate_proxy(&site(files)).is_ok());
+        let files = SiteKind::Static {
+            root: "/".to_string(),
+            index: "../index.html".to_string(),
+            try_files: "$uri;".to_string(),
+            expires: Some("soon".to_string()),
+        };
+        assert_eq!(fields(files), vec!["root", "index", "try_files", "expires"]);
+
+        let redirect = |target: &str, code: u16, preserve_path: bool| SiteKind::Redirect {
+            target: target.to_string(),
+            code,
+            preserve_path,
+        };
+        assert!(validate_proxy(&site(redirect("https://new.example.com", 308, true))).is_ok());
+        assert!(validate_proxy(&site(redirect("https://new.example.com/landing/", 302, false))).is_ok());
+        assert_eq!(fields(redirect("https://new.example.com/", 200, true)), vec!["target", "code"]);
+        assert_eq!(fields(redirect("https://x.com/$host", 301, false)), vec!["target"]);
+
+        let route = |path: &str, backend: &str| LocationRoute { path: path.to_string(), backend: backend.to_string() };
+        let routes = |routes: Vec<LocationRoute>| SiteKind::Locations { locations: routes };
+        assert!(validate_proxy(&site(routes(vec![route("/", "http://web:3000"), route("/api/", "http://api_pool")]))).is_ok());
+        for invalid in [
+            vec![],
+            vec![route("api", "http://api")],
+            vec![route("/api {", "http://api")],
+            vec![route("/api", "http://a"), route("/api", "http://b")],
+            vec![route("/api", "api:80")],
+        ] {
+            assert_eq!(fields(routes(invalid)), vec!["locations"]);
+        }
+    }
+
+    #[test]
+    fn test_access_control() {
+        let user = |username: &str, password: Option<&str>| crate::nginx::models::BasicAuthUser {
+            username: username.to_string(),
+            password: password.map(str::to_string),
+        };
+        let mut guarded = proxy();
+        guarded.basic_auth = Some(BasicAuth {
+            realm: "Internal tools".to_string(),
+            users: vec![user("alice", Some("wonderland")), user("ops@example.com", None)],
+        });
+        guarded.allow = vec!["10.0.0.0/8".to_string(), "2001:db8::/32".to_string(), "192.168.1.10".to_string()];
+        guarded.deny = vec!["10.0.0.13".to_string()];
+        guarded.rate_limit = Some(crate::nginx::models::RateLimit { rate: "120r/m".to_string(), burst: 20, nodelay: true });
+        guarded.connection_limit = Some(10);
+        assert!(validate_proxy(&guarded).is_ok());
+
+        let mut invalid = guarded.clone();
+        invalid.basic_auth = Some(BasicAuth {
+            realm: "Say \"hi\"".to_string(),
+            users: vec![user("alice", Some("x"))],
+        });
+        invalid.allow = vec!["10.0.0.0/33".to_string()];
+        invalid.deny = vec!["all".to_string()];
+        invalid.rate_limit = Some(crate::nginx::models::RateLimit { rate: "10r/h".to_string(), burst: 0, nodelay: false });
+        invalid.connection_limit = Some(0);
+        let fields: Vec<&str> = proxy_errors(&invalid).iter().map(|e| e.field).collect();
+        assert_eq!(fields, vec!["basic_auth", "allow", "deny", "rate_limit", "connection_limit"]);
+
+        for users in [vec![], vec![user("a:b", None)], vec![user("bob", None), user("bob", None)], vec![user("bob", Some(""))]] {
+            let auth = BasicAuth { realm: "Admin".to_string(), users };
+            assert!(validate_basic_auth(&auth).is_err());
+        }
+    }
+}
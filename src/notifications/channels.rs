@@ -0,0 +1,213 @@
+// Webhook and SMTP delivery
+
+use std::time::Duration;
+
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::Serialize;
+
+use crate::alerts::models::Alert;
+use crate::config::{SmtpConfig, SmtpTls};
+use crate::notifications::models::Notification;
+
+/// Timeout for a single delivery attempt
+const SEND_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A configured notification destination
+pub enum Channel {
+    Webhook(WebhookChannel),
+    Smtp(SmtpChannel),
+}
+
+impl Channel {
+    pub fn name(&self) -> &str {
+        match self {
+            Channel::Webhook(channel) => &channel.name,
+            Channel::Smtp(channel) => &channel.name,
+        }
+    }
+
+    /// Make one delivery attempt
+    pub async fn send(&self, notification: &Notification) -> Result<(), String> {
+        match self {
+            Channel::Webhook(channel) => channel.send(notification).await,
+            Channel::Smtp(channel) => channel.send(notification).await,
+        }
+    }
+}
+
+/// JSON body posted to webhooks
+///
+/// `text` is what Slack and Mattermost incoming webhooks display; other
+/// receivers can use the structured `alert`.
+#[derive(Serialize)]
+struct WebhookPayload<'a> {
+    text: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alert: Option<&'a Alert>,
+}
+
+/// Posts notifications as JSON to an HTTP endpoint
+pub struct WebhookChannel {
+    name: String,
+    url: reqwest::Url,
+    client: reqwest::Client,
+}
+
+impl WebhookChannel {
+    pub fn new(url: &str) -> Result<Self, String> {
+        let url = reqwest::Url::parse(url).map_err(|e| format!("Invalid webhook URL '{}': {}", url, e))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(format!("Webhook URL '{}' must use http or https", url));
+        }
+        let client = reqwest::Client::builder()
+            .timeout(SEND_TIMEOUT)
+            .build()
+            .map_err(|e| format!("Failed to create HTTP client: {}", e))?;
+
+        Ok(WebhookChannel {
+            name: format!("webhook:{}", url.host_str().unwrap_or_default()),
+            url,
+            client,
+        })
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        let payload = WebhookPayload {
+            text: &notification.text,
+            alert: notification.alert.as_ref(),
+        };
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&payload)
+            .send()
+            .await
+            .map_err(|e| format!("Webhook request failed: {}", e))?;
+
+        if response.status().is_success() {
+            Ok(())
+        } else {
+            Err(format!("Webhook returned HTTP {}", response.status()))
+        }
+    }
+}
+
+/// Sends notifications as plain-text email
+pub struct SmtpChannel {
+    name: String,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpChannel {
+    pub fn new(config: &SmtpConfig) -> Result<Self, String> {
+        let from = config
+            .from
+            .parse()
+            .map_err(|e| format!("Invalid sender address '{}': {}", config.from, e))?;
+        let to = config
+            .to
+            .iter()
+            .map(|address| {
+                address
+                    .parse()
+                    .map_err(|e| format!("Invalid recipient address '{}': {}", address, e))
+            })
+            .collect::<Result<Vec<Mailbox>, String>>()?;
+
+        let builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .map_err(|e| format!("Invalid SMTP host '{}': {}", config.host, e))?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .map_err(|e| format!("Invalid SMTP host '{}': {}", config.host, e))?,
+        };
+        let mut builder = builder.port(config.port).timeout(Some(SEND_TIMEOUT));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        Ok(SmtpChannel {
+            name: format!("smtp:{}", config.host),
+            from,
+            to,
+            transport: builder.build(),
+        })
+    }
+
+    async fn send(&self, notification: &Notification) -> Result<(), String> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(&notification.subject);
+        for recipient in &self.to {
+            message = message.to(recipient.clone());
+        }
+        let message = message
+            .body(notification.text.clone())
+            .map_err(|e| format!("Failed to build email: {}", e))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map(|_| ())
+            .map_err(|e| format!("SMTP delivery failed: {}", e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notifications::testing::{HttpStandIn, SmtpStandIn};
+
+    #[actix_web::test]
+    async fn test_webhook_posts_slack_compatible_payload() {
+        let server = HttpStandIn::start(vec![200]).await;
+        let channel = WebhookChannel::new(&server.url).unwrap();
+
+        channel.send(&Notification::test()).await.unwrap();
+
+        let body: serde_json::Value = serde_json::from_str(&server.bodies()[0]).unwrap();
+        assert_eq!(body["text"], "🔔 Test notification from Ubuntu Resource API");
+        assert!(body.get("alert").is_none());
+    }
+
+    #[actix_web::test]
+    async fn test_webhook_error_status() {
+        let server = HttpStandIn::start(vec![500]).await;
+        let channel = WebhookChannel::new(&server.url).unwrap();
+
+        let error = channel.send(&Notification::test()).await.unwrap_err();
+        assert!(error.contains("500"));
+    }
+
+    #[test]
+    fn test_webhook_rejects_bad_url() {
+        assert!(WebhookChannel::new("not a url").is_err());
+        assert!(WebhookChannel::new("ftp://example.com/hook").is_err());
+    }
+
+    #[actix_web::test]
+    async fn test_smtp_delivers_email() {
+        let server = SmtpStandIn::start().await;
+        let channel = SmtpChannel::new(&SmtpConfig {
+            host: "127.0.0.1".to_string(),
+            port: server.port,
+            tls: SmtpTls::None,
+            username: None,
+            password: None,
+            from: "monitor@example.com".to_string(),
+            to: vec!["ops@example.com".to_string()],
+        })
+        .unwrap();
+
+        channel.send(&Notification::test()).await.unwrap();
+
+        let messages = server.messages();
+        assert_eq!(messages.len(), 1);
+        assert!(messages[0].contains("Subject: Test notification"));
+        assert!(messages[0].contains("To: ops@example.com"));
+    }
+}
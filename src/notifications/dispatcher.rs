@@ -0,0 +1,216 @@
+// Delivery with retries, deduplication and rate limiting
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use futures_util::future::join_all;
+use log::{info, warn};
+
+use crate::config::NotificationsConfig;
+use crate::notifications::channels::{Channel, SmtpChannel, WebhookChannel};
+use crate::notifications::models::{DeliveryReport, Notification};
+
+/// Window the rate limit applies to
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Delay before the first retry; doubled for every further attempt
+const RETRY_BACKOFF: Duration = Duration::from_secs(2);
+
+/// Sends notifications to every configured channel
+pub struct Notifier {
+    channels: Vec<Channel>,
+    max_retries: u32,
+    retry_backoff: Duration,
+    rate_limit_per_minute: u32,
+    dedup_window: Duration,
+    /// When each deduplication key was last sent
+    recent: Mutex<HashMap<String, Instant>>,
+    /// Send times inside the current rate window
+    sent: Mutex<VecDeque<Instant>>,
+}
+
+impl Notifier {
+    pub fn new(channels: Vec<Channel>, config: &NotificationsConfig) -> Self {
+        Notifier {
+            channels,
+            max_retries: config.max_retries,
+            retry_backoff: RETRY_BACKOFF,
+            rate_limit_per_minute: config.rate_limit_per_minute,
+            dedup_window: Duration::from_secs(config.dedup_window_secs),
+            recent: Mutex::new(HashMap::new()),
+            sent: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Build the webhook and SMTP channels described by the configuration
+    pub fn from_config(config: &NotificationsConfig) -> Result<Self, String> {
+        let mut channels = Vec::new();
+        for url in &config.webhook_urls {
+            channels.push(Channel::Webhook(WebhookChannel::new(url)?));
+        }
+        if let Some(smtp) = &config.smtp {
+            channels.push(Channel::Smtp(SmtpChannel::new(smtp)?));
+        }
+        Ok(Self::new(channels, config))
+    }
+
+    /// Override the delay before the first retry
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
+    pub fn channel_names(&self) -> Vec<&str> {
+        self.channels.iter().map(Channel::name).collect()
+    }
+
+    /// Deliver to every channel unless the notification is a duplicate or the rate limit is hit
+    ///
+    /// Returns no reports when the notification was suppressed.
+    pub async fn notify(&self, notification: &Notification) -> Vec<DeliveryReport> {
+        if self.channels.is_empty() {
+            return Vec::new();
+        }
+        if !self.admit(&notification.key, Instant::now()) {
+            info!("Notification suppressed: {}", notification.subject);
+            return Vec::new();
+        }
+        self.deliver(notification, None).await
+    }
+
+    /// Deliver to every channel, or only `channel`, retrying failures
+    ///
+    /// Deduplication and rate limiting are not applied.
+    pub async fn deliver(&self, notification: &Notification, channel: Option<&str>) -> Vec<DeliveryReport> {
+        let deliveries = self
+            .channels
+            .iter()
+            .filter(|c| channel.is_none_or(|name| c.name() == name))
+            .map(|c| self.deliver_to(c, notification));
+        join_all(deliveries).await
+    }
+
+    async fn deliver_to(&self, channel: &Channel, notification: &Notification) -> DeliveryReport {
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            match channel.send(notification).await {
+                Ok(()) => {
+                    info!("Notification '{}' sent via {}", notification.subject, channel.name());
+                    return DeliveryReport {
+                        channel: channel.name().to_string(),
+                        success: true,
+                        attempts,
+                        error: None,
+                    };
+                }
+                Err(e) if attempts > self.max_retries => {
+                    warn!("Notification '{}' via {} failed: {}", notification.subject, channel.name(), e);
+                    return DeliveryReport {
+                        channel: channel.name().to_string(),
+                        success: false,
+                        attempts,
+                        error: Some(e),
+                    };
+                }
+                Err(e) => {
+                    warn!("Notification via {} failed (attempt {}), retrying: {}", channel.name(), attempts, e);
+                    tokio::time::sleep(self.retry_backoff * 2u32.pow(attempts - 1)).await;
+                }
+            }
+        }
+    }
+
+    /// Decide whether a notification may be sent now, recording it if so
+    fn admit(&self, key: &str, now: Instant) -> bool {
+        let mut recent = self.recent.lock().unwrap();
+        recent.retain(|_, sent| now.duration_since(*sent) < self.dedup_window);
+        if recent.contains_key(key) {
+            return false;
+        }
+
+        let mut sent = self.sent.lock().unwrap();
+        while sent.front().is_some_and(|t| now.duration_since(*t) >= RATE_WINDOW) {
+            sent.pop_front();
+        }
+        if sent.len() >= self.rate_limit_per_minute as usize {
+            return false;
+        }
+
+        sent.push_back(now);
+        recent.insert(key.to_string(), now);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use crate::notifications::testing::HttpStandIn;
+
+    fn config(rate_limit_per_minute: u32, dedup_window_secs: u64) -> NotificationsConfig {
+        NotificationsConfig {
+            rate_limit_per_minute,
+            dedup_window_secs,
+            ..AppConfig::with_defaults().notifications
+        }
+    }
+
+    #[test]
+    fn test_duplicates_suppressed_within_window() {
+        let notifier = Notifier::new(Vec::new(), &config(100, 60));
+        let start = Instant::now();
+
+        assert!(notifier.admit("disk|/|firing", start));
+        assert!(!notifier.admit("disk|/|firing", start + Duration::from_secs(30)));
+        assert!(notifier.admit("disk|/|resolved", start + Duration::from_secs(30)));
+        assert!(notifier.admit("disk|/|firing", start + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn test_rate_limit() {
+        let notifier = Notifier::new(Vec::new(), &config(2, 0));
+        let start = Instant::now();
+
+        assert!(notifier.admit("a", start));
+        assert!(notifier.admit("b", start));
+        assert!(!notifier.admit("c", start + Duration::from_secs(1)));
+        assert!(notifier.admit("c", start + Duration::from_secs(60)));
+    }
+
+    #[actix_web::test]
+    async fn test_retries_until_success() {
+        let server = HttpStandIn::start(vec![500, 503, 200]).await;
+        let notifier = Notifier::new(
+            vec![Channel::Webhook(WebhookChannel::new(&server.url).unwrap())],
+            &config(100, 60),
+        )
+        .with_retry_backoff(Duration::from_millis(1));
+
+        let reports = notifier.notify(&Notification::test()).await;
+        assert_eq!(reports.len(), 1);
+        assert!(reports[0].success);
+        assert_eq!(reports[0].attempts, 3);
+        assert_eq!(server.bodies().len(), 3);
+
+        // Same key again inside the window: nothing is sent
+        assert!(notifier.notify(&Notification::test()).await.is_empty());
+    }
+
+    #[actix_web::test]
+    async fn test_gives_up_after_max_retries() {
+        let server = HttpStandIn::start(vec![500; 4]).await;
+        let notifier = Notifier::new(
+            vec![Channel::Webhook(WebhookChannel::new(&server.url).unwrap())],
+            &config(100, 60),
+        )
+        .with_retry_backoff(Duration::from_millis(1));
+
+        let reports = notifier.deliver(&Notification::test(), None).await;
+        assert!(!reports[0].success);
+        assert_eq!(reports[0].attempts, 4);
+        assert!(reports[0].error.as_deref().unwrap().contains("500"));
+    }
+}
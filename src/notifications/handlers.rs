@@ -0,0 +1,44 @@
+// Notification HTTP handlers
+
+use actix_web::{post, web, HttpResponse};
+use log::info;
+use serde_json::json;
+
+use crate::error::AppError;
+use crate::notifications::models::{Notification, TestNotificationRequest};
+use crate::system::models::AppState;
+
+/// Send a test message through every channel, or the one named in the body
+///
+/// Bypasses deduplication and rate limiting so it can be repeated while
+/// setting channels up.
+#[post("/api/notifications/test")]
+pub async fn test_notification(
+    data: web::Data<AppState>,
+    request: Option<web::Json<TestNotificationRequest>>,
+) -> Result<HttpResponse, AppError> {
+    info!("POST /api/notifications/test");
+
+    let notifier = &data.notifier;
+    let names = notifier.channel_names();
+    if names.is_empty() {
+        return Err(AppError::ValidationError("No notification channels configured".to_string()));
+    }
+
+    let channel = request.and_then(|r| r.into_inner().channel);
+    if let Some(channel) = &channel {
+        if !names.contains(&channel.as_str()) {
+            return Err(AppError::NotFound(format!(
+                "Unknown channel '{}'. Available: {}",
+                channel,
+                names.join(", ")
+            )));
+        }
+    }
+
+    let results = notifier.deliver(&Notification::test(), channel.as_deref()).await;
+    Ok(HttpResponse::Ok().json(json!({
+        "success": results.iter().all(|r| r.success),
+        "results": results
+    })))
+}
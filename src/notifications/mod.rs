@@ -0,0 +1,39 @@
+//! Notifications module
+//!
+//! This module delivers alert transitions to on-call channels:
+//! - JSON webhooks with a `text` field understood by Slack and Mattermost
+//! - Plain-text email over SMTP
+//! - Failed deliveries are retried with exponential backoff
+//! - Repeats of the same alert state are deduplicated within a window
+//! - A global per-minute rate limit protects against alert storms
+//!
+//! # Submodules
+//!
+//! - [`models`]: Notification and delivery report structures
+//! - [`channels`]: Webhook and SMTP channel implementations
+//! - [`dispatcher`]: Retries, deduplication and rate limiting
+//! - [`handlers`]: HTTP request handler for test notifications
+//! - [`routes`]: Route registration for the notifications API
+//!
+//! # Example
+//!
+//! ```no_run
+//! use ubuntu_resource_api::config::AppConfig;
+//! use ubuntu_resource_api::notifications::dispatcher::Notifier;
+//! use ubuntu_resource_api::notifications::models::Notification;
+//!
+//! # async fn run() {
+//! let config = AppConfig::with_defaults();
+//! let notifier = Notifier::from_config(&config.notifications).expect("invalid channel");
+//! notifier.notify(&Notification::test()).await;
+//! # }
+//! ```
+
+pub mod models;
+pub mod channels;
+pub mod dispatcher;
+pub mod handlers;
+pub mod routes;
+
+#[cfg(test)]
+mod testing;
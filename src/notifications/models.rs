@@ -0,0 +1,78 @@
+use serde::{Deserialize, Serialize};
+
+use crate::alerts::models::{Alert, AlertState};
+
+/// A message delivered to every notification channel
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct Notification {
+    /// Deduplication key; notifications sharing a key within the window are sent once
+    pub key: String,
+    pub subject: String,
+    pub text: String,
+    pub alert: Option<Alert>,
+}
+
+impl Notification {
+    /// Describe an alert that started firing or resolved
+    pub fn from_alert(alert: &Alert) -> Self {
+        let target = match &alert.label {
+            Some(label) => format!("{} ({})", alert.rule_name, label),
+            None => alert.rule_name.clone(),
+        };
+        let (subject, text) = match alert.state {
+            AlertState::Resolved => (
+                format!("[RESOLVED] {}", target),
+                format!("✅ RESOLVED {}: {} = {:.2}", target, alert.metric, alert.value),
+            ),
+            _ => (
+                format!("[{}] {}", alert.severity.as_str().to_uppercase(), target),
+                format!(
+                    "🔥 [{}] {}: {} = {:.2} (threshold {:.2})",
+                    alert.severity.as_str().to_uppercase(),
+                    target,
+                    alert.metric,
+                    alert.value,
+                    alert.threshold
+                ),
+            ),
+        };
+
+        Notification {
+            key: format!(
+                "{}|{}|{}",
+                alert.rule_id,
+                alert.label.as_deref().unwrap_or_default(),
+                alert.state.as_str()
+            ),
+            subject,
+            text,
+            alert: Some(alert.clone()),
+        }
+    }
+
+    /// Message sent by `/api/notifications/test`
+    pub fn test() -> Self {
+        Notification {
+            key: "test".to_string(),
+            subject: "Test notification".to_string(),
+            text: "🔔 Test notification from Ubuntu Resource API".to_string(),
+            alert: None,
+        }
+    }
+}
+
+/// Outcome of delivering one notification to one channel
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct DeliveryReport {
+    pub channel: String,
+    pub success: bool,
+    pub attempts: u32,
+    pub error: Option<String>,
+}
+
+/// Body of `/api/notifications/test`
+#[derive(Deserialize, Default)]
+pub struct TestNotificationRequest {
+    /// Only test this channel (as listed by name); all channels when omitted
+    pub channel: Option<String>,
+}
@@ -0,0 +1,57 @@
+use actix_web::web;
+
+use crate::notifications::handlers;
+
+/// Configure all notification routes
+pub fn configure_routes(cfg: &mut web::ServiceConfig) {
+    cfg.service(handlers::test_notification);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{http::StatusCode, test, App};
+    use serde_json::json;
+    use std::sync::Arc;
+
+    use crate::config::AppConfig;
+    use crate::notifications::channels::{Channel, WebhookChannel};
+    use crate::notifications::dispatcher::Notifier;
+    use crate::notifications::testing::HttpStandIn;
+    use crate::system::models::AppState;
+
+    fn app_state(channels: Vec<Channel>) -> web::Data<AppState> {
+        let config = AppConfig::with_defaults();
+        web::Data::new(AppState {
+            notifier: Arc::new(Notifier::new(channels, &config.notifications)),
+            ..AppState::for_tests()
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_notification_without_channels() {
+        let app = test::init_service(App::new().app_data(app_state(Vec::new())).configure(configure_routes)).await;
+
+        let req = test::TestRequest::post().uri("/api/notifications/test").to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[actix_web::test]
+    async fn test_notification_through_webhook() {
+        let server = HttpStandIn::start(vec![200]).await;
+        let channels = vec![Channel::Webhook(WebhookChannel::new(&server.url).unwrap())];
+        let app = test::init_service(App::new().app_data(app_state(channels)).configure(configure_routes)).await;
+
+        let req = test::TestRequest::post()
+            .uri("/api/notifications/test")
+            .set_json(json!({"channel": "smtp:mail.example.com"}))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::NOT_FOUND);
+
+        let req = test::TestRequest::post().uri("/api/notifications/test").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(body["success"], true);
+        assert_eq!(body["results"][0]["channel"], "webhook:127.0.0.1");
+        assert_eq!(server.bodies().len(), 1);
+    }
+}
@@ -0,0 +1,120 @@
+// Conversions from sysinfo data into API models
+//
+// Shared by the HTTP handlers and the background metrics sampler so that both
+// report identical values.
+
+use sysinfo::{Disks, Networks, System};
+
+use crate::utils::bytes_to_gb;
+use super::models::*;
+
+/// Build CPU usage from an already refreshed `System`
+pub fn collect_cpu_usage(system: &System) -> CpuUsage {
+    let per_core_usage: Vec<f32> = system.cpus().iter().map(|cpu| cpu.cpu_usage()).collect();
+
+    let overall_usage = if !per_core_usage.is_empty() {
+        per_core_usage.iter().sum::<f32>() / per_core_usage.len() as f32
+    } else {
+        0.0
+    };
+
+    CpuUsage {
+        overall_usage_percent: overall_usage,
+        per_core_usage,
+    }
+}
+
+/// Build memory information from an already refreshed `System`
+pub fn collect_memory(system: &System) -> MemoryInfo {
+    let total = system.total_memory();
+    let used = system.used_memory();
+    let free = system.free_memory();
+    let available = system.available_memory();
+
+    MemoryInfo {
+        total_bytes: total,
+        used_bytes: used,
+        free_bytes: free,
+        available_bytes: available,
+        total_gb: bytes_to_gb(total),
+        used_gb: bytes_to_gb(used),
+        free_gb: bytes_to_gb(free),
+        used_percent: if total > 0 { (used as f64 / total as f64) * 100.0 } else { 0.0 },
+    }
+}
+
+/// Build disk usage for every mounted filesystem
+pub fn collect_disks(disks: &Disks) -> Vec<DiskInfo> {
+    disks
+        .iter()
+        .map(|disk| {
+            let total = disk.total_space();
+            let available = disk.available_space();
+            let used = total.saturating_sub(available);
+
+            DiskInfo {
+                name: disk.name().to_string_lossy().to_string(),
+                mount_point: disk.mount_point().to_string_lossy().to_string(),
+                filesystem: disk.file_system().to_string_lossy().to_string(),
+                total_bytes: total,
+                used_bytes: used,
+                available_bytes: available,
+                total_gb: bytes_to_gb(total),
+                used_gb: bytes_to_gb(used),
+                available_gb: bytes_to_gb(available),
+                used_percent: if total > 0 { (used as f64 / total as f64) * 100.0 } else { 0.0 },
+            }
+        })
+        .collect()
+}
+
+/// Build cumulative statistics for every network interface
+pub fn collect_networks(networks: &Networks) -> Vec<NetworkInfo> {
+    networks
+        .iter()
+        .map(|(name, network)| NetworkInfo {
+            interface_name: name.to_string(),
+            mac_address: network.mac_address().to_string(),
+            received_bytes: network.total_received(),
+            transmitted_bytes: network.total_transmitted(),
+            received_packets: network.total_packets_received(),
+            transmitted_packets: network.total_packets_transmitted(),
+            errors_in: network.total_errors_on_received(),
+            errors_out: network.total_errors_on_transmitted(),
+        })
+        .collect()
+}
+
+/// Build the `limit` processes with the highest CPU usage from an already refreshed `System`
+pub fn collect_processes(system: &System, limit: usize) -> Vec<ProcessInfo> {
+    let mut processes: Vec<ProcessInfo> = system
+        .processes()
+        .values()
+        .map(|process| ProcessInfo {
+            pid: process.pid().as_u32(),
+            name: process.name().to_string(),
+            cpu_usage: process.cpu_usage(),
+            memory_bytes: process.memory(),
+            memory_mb: process.memory() as f64 / (1024.0 * 1024.0),
+            parent_pid: process.parent().map(|p| p.as_u32()),
+            status: format!("{:?}", process.status()),
+            runtime_seconds: process.run_time(),
+        })
+        .collect();
+
+    // Sort by CPU usage descending
+    processes.sort_by(|a, b| b.cpu_usage.total_cmp(&a.cpu_usage));
+    processes.truncate(limit);
+    processes
+}
+
+/// Read the current system load average
+pub fn collect_load_average() -> LoadAverage {
+    let load = System::load_average();
+
+    LoadAverage {
+        one_min: load.one,
+        five_min: load.five,
+        fifteen_min: load.fifteen,
+    }
+}
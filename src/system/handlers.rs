@@ -1,8 +1,11 @@
 use actix_web::{delete, get, web, HttpResponse, Responder};
 use sysinfo::{Disks, Networks, Pid, Signal, System};
 use log::{info, warn};
+use serde_json::json;
 
-use crate::utils::{bytes_to_gb, refresh_system};
+use crate::auth::models::Identity;
+use crate::utils::refresh_system;
+use super::collectors::*;
 use super::models::*;
 
 // ==================== API Endpoints ====================
@@ -22,7 +25,15 @@ pub async fn index() -> impl Responder {
             "/api/network",
             "/api/processes",
             "/api/load",
+            "/api/history/{metric}",
+            "/api/stream",
             "/health",
+            "/metrics",
+            "/api/alerts",
+            "/api/alerts/rules",
+            "/api/notifications/test",
+            "/api/auth/me",
+            "/api/audit",
         ],
     })
 }
@@ -68,26 +79,18 @@ pub async fn get_cpu_info(data: web::Data<AppState>) -> impl Responder {
 
 #[get("/api/cpu/usage")]
 pub async fn get_cpu_usage(data: web::Data<AppState>) -> impl Responder {
-    let mut system = data.system.lock().unwrap();
-    system.refresh_cpu_usage();
-    
-    // Wait a moment for CPU usage calculation
-    std::thread::sleep(std::time::Duration::from_millis(200));
-    system.refresh_cpu_usage();
+    // Served from the sampler's deltas so concurrent clients never block
+    if let Some(snapshot) = data.metrics.latest() {
+        return HttpResponse::Ok().json(snapshot.cpu);
+    }
 
-    let cpus = system.cpus();
-    let per_core_usage: Vec<f32> = cpus.iter().map(|cpu| cpu.cpu_usage()).collect();
-    
-    let overall_usage = if !per_core_usage.is_empty() {
-        per_core_usage.iter().sum::<f32>() / per_core_usage.len() as f32
-    } else {
-        0.0
-    };
+    // The sampler has not ticked yet: measure ourselves without holding the lock while waiting
+    data.system.lock().unwrap().refresh_cpu_usage();
+    tokio::time::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL).await;
 
-    let usage = CpuUsage {
-        overall_usage_percent: overall_usage,
-        per_core_usage,
-    };
+    let mut system = data.system.lock().unwrap();
+    system.refresh_cpu_usage();
+    let usage = collect_cpu_usage(&system);
 
     HttpResponse::Ok().json(usage)
 }
@@ -97,21 +100,7 @@ pub async fn get_memory_info(data: web::Data<AppState>) -> impl Responder {
     let mut system = data.system.lock().unwrap();
     system.refresh_memory();
 
-    let total = system.total_memory();
-    let used = system.used_memory();
-    let free = system.free_memory();
-    let available = system.available_memory();
-
-    let info = MemoryInfo {
-        total_bytes: total,
-        used_bytes: used,
-        free_bytes: free,
-        available_bytes: available,
-        total_gb: bytes_to_gb(total),
-        used_gb: bytes_to_gb(used),
-        free_gb: bytes_to_gb(free),
-        used_percent: if total > 0 { (used as f64 / total as f64) * 100.0 } else { 0.0 },
-    };
+    let info = collect_memory(&system);
 
     HttpResponse::Ok().json(info)
 }
@@ -119,28 +108,7 @@ pub async fn get_memory_info(data: web::Data<AppState>) -> impl Responder {
 #[get("/api/disks")]
 pub async fn get_disks_info() -> impl Responder {
     let disks = Disks::new_with_refreshed_list();
-    
-    let disk_info: Vec<DiskInfo> = disks
-        .iter()
-        .map(|disk| {
-            let total = disk.total_space();
-            let available = disk.available_space();
-            let used = total.saturating_sub(available);
-            
-            DiskInfo {
-                name: disk.name().to_string_lossy().to_string(),
-                mount_point: disk.mount_point().to_string_lossy().to_string(),
-                filesystem: disk.file_system().to_string_lossy().to_string(),
-                total_bytes: total,
-                used_bytes: used,
-                available_bytes: available,
-                total_gb: bytes_to_gb(total),
-                used_gb: bytes_to_gb(used),
-                available_gb: bytes_to_gb(available),
-                used_percent: if total > 0 { (used as f64 / total as f64) * 100.0 } else { 0.0 },
-            }
-        })
-        .collect();
+    let disk_info = collect_disks(&disks);
 
     HttpResponse::Ok().json(disk_info)
 }
@@ -148,20 +116,7 @@ pub async fn get_disks_info() -> impl Responder {
 #[get("/api/network")]
 pub async fn get_network_info() -> impl Responder {
     let networks = Networks::new_with_refreshed_list();
-    
-    let network_info: Vec<NetworkInfo> = networks
-        .iter()
-        .map(|(name, network)| NetworkInfo {
-            interface_name: name.to_string(),
-            mac_address: network.mac_address().to_string(),
-            received_bytes: network.total_received(),
-            transmitted_bytes: network.total_transmitted(),
-            received_packets: network.total_packets_received(),
-            transmitted_packets: network.total_packets_transmitted(),
-            errors_in: network.total_errors_on_received(),
-            errors_out: network.total_errors_on_transmitted(),
-        })
-        .collect();
+    let network_info = collect_networks(&networks);
 
     HttpResponse::Ok().json(network_info)
 }
@@ -173,37 +128,14 @@ pub async fn get_processes(data: web::Data<AppState>, query: web::Query<ProcessQ
 
     let limit = query.limit.unwrap_or(50);
 
-    let mut processes: Vec<ProcessInfo> = system
-        .processes()
-        .values()
-        .map(|process| ProcessInfo {
-            pid: process.pid().as_u32(),
-            name: process.name().to_string(),
-            cpu_usage: process.cpu_usage(),
-            memory_bytes: process.memory(),
-            memory_mb: process.memory() as f64 / (1024.0 * 1024.0),
-            parent_pid: process.parent().map(|p| p.as_u32()),
-            status: format!("{:?}", process.status()),
-            runtime_seconds: process.run_time(),
-        })
-        .collect();
-
-    // Sort by CPU usage descending
-    processes.sort_by(|a, b| b.cpu_usage.partial_cmp(&a.cpu_usage).unwrap());
-    processes.truncate(limit);
+    let processes = collect_processes(&system, limit);
 
     HttpResponse::Ok().json(processes)
 }
 
 #[get("/api/load")]
 pub async fn get_load_average() -> impl Responder {
-    let load = System::load_average();
-    
-    let load_avg = LoadAverage {
-        one_min: load.one,
-        five_min: load.five,
-        fifteen_min: load.fifteen,
-    };
+    let load_avg = collect_load_average();
 
     HttpResponse::Ok().json(load_avg)
 }
@@ -217,11 +149,17 @@ pub async fn health_check() -> impl Responder {
 }
 
 #[delete("/api/processes/{pid}")]
-pub async fn kill_process(data: web::Data<AppState>, pid: web::Path<u32>) -> impl Responder {
+pub async fn kill_process(data: web::Data<AppState>, pid: web::Path<u32>, identity: Identity) -> impl Responder {
+    let pid_value = *pid;
+    let response = kill_process_by_pid(&data, pid_value);
+    data.audit.record(&identity, "system", "process.kill", &pid_value.to_string(), json!({"pid": pid_value, "signal": "TERM"}), response.status());
+    response
+}
+
+fn kill_process_by_pid(data: &AppState, pid_value: u32) -> HttpResponse {
     let mut system = data.system.lock().unwrap();
     system.refresh_processes();
     
-    let pid_value = pid.into_inner();
     let process_pid = Pid::from_u32(pid_value);
     
     if let Some(process) = system.process(process_pid) {
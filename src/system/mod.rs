@@ -12,6 +12,7 @@
 //! # Submodules
 //!
 //! - [`models`]: Data structures for system information
+//! - [`collectors`]: Conversions from sysinfo data into the models, shared with the metrics sampler
 //! - [`handlers`]: HTTP request handlers for system monitoring endpoints
 //! - [`routes`]: Route registration for system monitoring API
 //!
@@ -34,6 +35,7 @@
 //! ```
 
 pub mod models;
+pub mod collectors;
 pub mod handlers;
 pub mod routes;
 
@@ -1,13 +1,82 @@
 use serde::{Deserialize, Serialize};
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex, RwLock};
 use sysinfo::System;
-use crate::config::{NginxConfig, DockerConfig};
+use crate::config::{AppConfig, NginxConfig, DockerConfig};
+use crate::alerts::engine::AlertEngine;
+use crate::audit::log::AuditLog;
+use crate::auth::service::AuthService;
+use crate::metrics::store::MetricsStore;
+use crate::nginx::acme::AcmeClient;
+use crate::nginx::controller::{self, NginxController};
+use crate::nginx::htpasswd::HtpasswdStore;
+use crate::nginx::stats::ProxyOperationStats;
+use crate::notifications::dispatcher::Notifier;
 
 // Shared application state
 pub struct AppState {
     pub system: Mutex<System>,
-    pub nginx_config: NginxConfig,
+    /// Replaced when the configuration is reloaded; read it through [`AppState::nginx_config`]
+    pub nginx_config: RwLock<NginxConfig>,
+    /// Follows `nginx_config.controller`; read it through [`AppState::nginx_controller`]
+    pub nginx_controller: RwLock<Arc<dyn NginxController>>,
+    /// Held for every change to the nginx tree and every reload, so they never interleave
+    pub nginx_lock: tokio::sync::Mutex<()>,
     pub docker_config: DockerConfig,
+    pub metrics: Arc<MetricsStore>,
+    pub nginx_stats: ProxyOperationStats,
+    pub alerts: Arc<AlertEngine>,
+    pub notifier: Arc<Notifier>,
+    pub auth: Arc<AuthService>,
+    pub audit: Arc<AuditLog>,
+    pub acme: Arc<AcmeClient>,
+    pub htpasswd: HtpasswdStore,
+}
+
+impl AppState {
+    /// Current nginx directories
+    pub fn nginx_config(&self) -> NginxConfig {
+        self.nginx_config.read().unwrap().clone()
+    }
+
+    /// Current way of testing and reloading nginx
+    pub fn nginx_controller(&self) -> Arc<dyn NginxController> {
+        self.nginx_controller.read().unwrap().clone()
+    }
+
+    /// Apply the parts of a reloaded configuration that can change at runtime
+    pub fn reload(&self, config: &AppConfig) -> Result<(), String> {
+        self.auth.reload(&config.auth)?;
+        let mut nginx_config = self.nginx_config.write().unwrap();
+        if nginx_config.controller != config.nginx.controller {
+            *self.nginx_controller.write().unwrap() = controller::from_kind(config.nginx.controller);
+        }
+        *nginx_config = config.nginx.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+impl AppState {
+    /// State from [`AppConfig::with_defaults`] with authentication, auditing
+    /// and notifications off; tests override the fields they exercise
+    pub fn for_tests() -> Self {
+        let config = AppConfig::with_defaults();
+        AppState {
+            system: Mutex::new(System::new()),
+            nginx_controller: RwLock::new(controller::from_kind(config.nginx.controller)),
+            nginx_config: RwLock::new(config.nginx),
+            nginx_lock: Default::default(),
+            docker_config: config.docker,
+            metrics: Arc::new(MetricsStore::new(&config.retention)),
+            nginx_stats: Default::default(),
+            alerts: Arc::new(AlertEngine::new(Vec::new())),
+            notifier: Arc::new(Notifier::new(Vec::new(), &config.notifications)),
+            auth: Arc::new(AuthService::disabled()),
+            audit: Arc::new(AuditLog::disabled()),
+            acme: Arc::new(AcmeClient::new(&config.acme).unwrap()),
+            htpasswd: Default::default(),
+        }
+    }
 }
 
 // ==================== Response Models ====================
@@ -32,13 +101,13 @@ pub struct CpuInfo {
     pub architecture: String,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct CpuUsage {
     pub overall_usage_percent: f32,
     pub per_core_usage: Vec<f32>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct MemoryInfo {
     pub total_bytes: u64,
     pub used_bytes: u64,
@@ -50,7 +119,7 @@ pub struct MemoryInfo {
     pub used_percent: f64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct DiskInfo {
     pub name: String,
     pub mount_point: String,
@@ -64,7 +133,7 @@ pub struct DiskInfo {
     pub used_percent: f64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct NetworkInfo {
     pub interface_name: String,
     pub mac_address: String,
@@ -76,7 +145,7 @@ pub struct NetworkInfo {
     pub errors_out: u64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct ProcessInfo {
     pub pid: u32,
     pub name: String,
@@ -88,7 +157,7 @@ pub struct ProcessInfo {
     pub runtime_seconds: u64,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone)]
 pub struct LoadAverage {
     pub one_min: f64,
     pub five_min: f64,
@@ -22,3 +22,68 @@ pub fn configure_routes(cfg: &mut web::ServiceConfig) {
         .service(handlers::health_check)
         .service(handlers::kill_process);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use actix_web::{test, App};
+    use std::sync::Arc;
+    use sysinfo::System;
+
+    use crate::config::AppConfig;
+    use crate::metrics::models::MetricsSnapshot;
+    use crate::metrics::store::MetricsStore;
+    use crate::system::collectors::{collect_load_average, collect_memory};
+    use crate::system::models::{AppState, CpuUsage};
+
+    fn app_state(metrics: Arc<MetricsStore>) -> web::Data<AppState> {
+        web::Data::new(AppState { metrics, ..AppState::for_tests() })
+    }
+
+    #[actix_web::test]
+    async fn test_cpu_usage_served_from_sampler_snapshot() {
+        let config = AppConfig::with_defaults();
+        let metrics = Arc::new(MetricsStore::new(&config.retention));
+        metrics.publish(MetricsSnapshot {
+            timestamp: 0,
+            cpu: CpuUsage {
+                overall_usage_percent: 42.0,
+                per_core_usage: vec![40.0, 44.0],
+            },
+            memory: collect_memory(&System::new()),
+            load: collect_load_average(),
+            disks: Vec::new(),
+            networks: Vec::new(),
+            processes: Vec::new(),
+        });
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(metrics))
+                .configure(configure_routes)
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/cpu/usage").to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["overall_usage_percent"], 42.0);
+        assert_eq!(body["per_core_usage"], serde_json::json!([40.0, 44.0]));
+    }
+
+    #[actix_web::test]
+    async fn test_cpu_usage_before_first_sample() {
+        let config = AppConfig::with_defaults();
+        let metrics = Arc::new(MetricsStore::new(&config.retention));
+
+        let app = test::init_service(
+            App::new()
+                .app_data(app_state(metrics))
+                .configure(configure_routes)
+        ).await;
+
+        let req = test::TestRequest::get().uri("/api/cpu/usage").to_request();
+        let resp = test::call_service(&app, req).await;
+
+        assert!(resp.status().is_success());
+    }
+}
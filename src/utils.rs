@@ -7,6 +7,7 @@
 //!
 //! - [`bytes_to_gb`]: Convert bytes to gigabytes for human-readable display
 //! - [`refresh_system`]: Refresh all system information for monitoring
+//! - [`parse_duration_secs`]: Parse human-friendly durations such as `30s`, `10m` or `24h`
 //!
 //! # Example
 //!
@@ -63,3 +64,37 @@ pub fn bytes_to_gb(bytes: u64) -> f64 {
 pub fn refresh_system(system: &mut System) {
     system.refresh_all();
 }
+
+/// Parse a human-friendly duration into seconds
+///
+/// Accepts a number followed by an optional unit: `s`, `m`, `h` or `d`.
+/// A bare number is interpreted as seconds.
+///
+/// # Example
+///
+/// ```
+/// use ubuntu_resource_api::utils::parse_duration_secs;
+///
+/// assert_eq!(parse_duration_secs("90").unwrap(), 90);
+/// assert_eq!(parse_duration_secs("10m").unwrap(), 600);
+/// assert_eq!(parse_duration_secs("24h").unwrap(), 86_400);
+/// assert!(parse_duration_secs("ten minutes").is_err());
+/// assert!(parse_duration_secs("999999999999999999d").is_err());
+/// ```
+pub fn parse_duration_secs(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let (number, multiplier) = match value.chars().last() {
+        Some('s') => (&value[..value.len() - 1], 1),
+        Some('m') => (&value[..value.len() - 1], 60),
+        Some('h') => (&value[..value.len() - 1], 3_600),
+        Some('d') => (&value[..value.len() - 1], 86_400),
+        _ => (value, 1),
+    };
+
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(multiplier))
+        .ok_or_else(|| format!("Invalid duration '{}': expected e.g. 30s, 10m, 24h or 7d", value))
+}